@@ -0,0 +1,107 @@
+use criterion::{criterion_group, criterion_main, Criterion};
+use std::time::Duration;
+use tower::{service_fn, Layer};
+#[cfg(feature = "http")]
+use tower_fault::http::ResponseLayer;
+use tower_fault::{
+    bench, decider::Probability, degrade::DegradeLayer, duplicate::DuplicateLayer,
+    error::ErrorLayer, latency::LatencyLayer, registry::FaultRegistry, saturation::SaturationLayer,
+    stale::StaleLayer,
+};
+
+async fn inner(_: u64) -> Result<(), String> {
+    Ok(())
+}
+
+fn deciders(c: &mut Criterion) {
+    bench::bench_decider(c, "decider/bool", &true, &0u64);
+    bench::bench_decider(c, "decider/f64", &0.5, &0u64);
+    bench::bench_decider(
+        c,
+        "decider/probability",
+        &Probability::new_const(0.5),
+        &0u64,
+    );
+
+    let registry = FaultRegistry::new();
+    let handle = registry.register("error", 0.5);
+    bench::bench_decider(c, "decider/registry", &handle, &0u64);
+}
+
+fn layers(c: &mut Criterion) {
+    bench::bench_layer(
+        c,
+        "error",
+        |state| {
+            ErrorLayer::new(state.probability(), |_: &u64| String::from("error"))
+                .enabled(state.is_enabled())
+                .layer(service_fn(inner))
+        },
+        || 0,
+    );
+    bench::bench_layer(
+        c,
+        "latency",
+        |state| {
+            LatencyLayer::new(state.probability(), Duration::ZERO)
+                .enabled(state.is_enabled())
+                .layer(service_fn(inner))
+        },
+        || 0,
+    );
+    #[cfg(feature = "http")]
+    bench::bench_layer(
+        c,
+        "response",
+        |state| {
+            ResponseLayer::new(state.probability(), |_: &u64| ())
+                .enabled(state.is_enabled())
+                .layer(service_fn(inner))
+        },
+        || 0,
+    );
+    bench::bench_layer(
+        c,
+        "saturation",
+        |state| {
+            SaturationLayer::new(state.probability(), Duration::ZERO, 1)
+                .enabled(state.is_enabled())
+                .layer(service_fn(inner))
+        },
+        || 0,
+    );
+    bench::bench_layer(
+        c,
+        "stale",
+        |state| {
+            // The first call captures the response returned by later calls.
+            StaleLayer::new(state.probability(), |req: &u64| Some(*req))
+                .enabled(state.is_enabled())
+                .layer(service_fn(inner))
+        },
+        || 0,
+    );
+    bench::bench_layer(
+        c,
+        "duplicate",
+        |state| {
+            DuplicateLayer::new(state.probability(), |_: ()| ())
+                .enabled(state.is_enabled())
+                .layer(service_fn(inner))
+        },
+        || 0,
+    );
+    bench::bench_layer(
+        c,
+        "degrade",
+        |state| {
+            DegradeLayer::new(state.probability(), |res: ()| res)
+                .enabled(state.is_enabled())
+                .layer(service_fn(inner))
+        },
+        || 0,
+    );
+}
+
+criterion_group!(benches, deciders, layers);
+criterion_main!(benches);
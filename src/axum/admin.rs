@@ -0,0 +1,166 @@
+use crate::registry::{EffectiveConfig, FaultInfo, FaultRegistry};
+use axum::{
+    extract::{Extension, Path},
+    http::StatusCode,
+    routing::{get, post},
+    Json, Router,
+};
+use serde::Deserialize;
+
+/// Create a router to inspect and control the faults of the given registry.
+///
+/// The router exposes the following endpoints:
+///
+/// * `GET /` - list all the faults.
+/// * `GET /effective` - list the effective configuration of all the faults.
+/// * `GET /:name` - return a single fault.
+/// * `GET /:name/effective` - return the effective configuration of a fault.
+/// * `PUT /:name` - update a fault with a [`FaultUpdate`] JSON payload.
+/// * `POST /:name/enable` - enable a fault.
+/// * `POST /:name/disable` - disable a fault.
+///
+/// Requests for unknown faults return a `404 Not Found` response. `GET
+/// /effective` takes precedence over a fault named `effective`.
+pub fn admin_router(registry: FaultRegistry) -> Router {
+    Router::new()
+        .route("/", get(list))
+        .route("/effective", get(list_effective))
+        .route("/:name", get(show).put(update))
+        .route("/:name/effective", get(show_effective))
+        .route("/:name/enable", post(enable))
+        .route("/:name/disable", post(disable))
+        .layer(Extension(registry))
+}
+
+/// Payload to update the settings of a fault.
+///
+/// Fields that are not set are left untouched.
+#[derive(Clone, Debug, Default, Deserialize)]
+pub struct FaultUpdate {
+    /// Whether the fault is enabled.
+    pub enabled: Option<bool>,
+    /// Probability of injecting the fault.
+    pub probability: Option<f64>,
+}
+
+async fn list(Extension(registry): Extension<FaultRegistry>) -> Json<Vec<FaultInfo>> {
+    Json(registry.faults())
+}
+
+async fn show(
+    Path(name): Path<String>,
+    Extension(registry): Extension<FaultRegistry>,
+) -> Result<Json<FaultInfo>, StatusCode> {
+    let handle = registry.get(&name).ok_or(StatusCode::NOT_FOUND)?;
+    Ok(Json(handle.info()))
+}
+
+async fn list_effective(
+    Extension(registry): Extension<FaultRegistry>,
+) -> Json<Vec<EffectiveConfig>> {
+    Json(registry.effective())
+}
+
+async fn show_effective(
+    Path(name): Path<String>,
+    Extension(registry): Extension<FaultRegistry>,
+) -> Result<Json<EffectiveConfig>, StatusCode> {
+    let handle = registry.get(&name).ok_or(StatusCode::NOT_FOUND)?;
+    Ok(Json(handle.effective()))
+}
+
+async fn update(
+    Path(name): Path<String>,
+    Json(update): Json<FaultUpdate>,
+    Extension(registry): Extension<FaultRegistry>,
+) -> Result<Json<FaultInfo>, StatusCode> {
+    let handle = registry.get(&name).ok_or(StatusCode::NOT_FOUND)?;
+    if let Some(probability) = update.probability {
+        handle.set_probability(probability);
+    }
+    match update.enabled {
+        Some(true) => handle.enable(),
+        Some(false) => handle.disable(),
+        None => (),
+    }
+    Ok(Json(handle.info()))
+}
+
+async fn enable(
+    Path(name): Path<String>,
+    Extension(registry): Extension<FaultRegistry>,
+) -> Result<Json<FaultInfo>, StatusCode> {
+    let handle = registry.get(&name).ok_or(StatusCode::NOT_FOUND)?;
+    handle.enable();
+    Ok(Json(handle.info()))
+}
+
+async fn disable(
+    Path(name): Path<String>,
+    Extension(registry): Extension<FaultRegistry>,
+) -> Result<Json<FaultInfo>, StatusCode> {
+    let handle = registry.get(&name).ok_or(StatusCode::NOT_FOUND)?;
+    handle.disable();
+    Ok(Json(handle.info()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::body::{Body, HttpBody};
+    use http::Request;
+    use tower::ServiceExt;
+
+    #[tokio::test]
+    async fn admin_updates_fault() {
+        let registry = FaultRegistry::new();
+        let handle = registry.register("fault", 0.1);
+
+        let res = admin_router(registry.clone())
+            .oneshot(
+                Request::put("/fault")
+                    .header("content-type", "application/json")
+                    .body(Body::from(r#"{"probability":0.5,"enabled":false}"#))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(res.status(), StatusCode::OK);
+        assert_eq!(handle.probability(), 0.5);
+        assert!(!handle.is_enabled());
+
+        let res = admin_router(registry)
+            .oneshot(Request::get("/unknown").body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        assert_eq!(res.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn admin_effective() {
+        let registry = FaultRegistry::new();
+        registry.register("fault", 0.1).disable();
+
+        let res = admin_router(registry.clone())
+            .oneshot(Request::get("/effective").body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        assert_eq!(res.status(), StatusCode::OK);
+        let (mut body, mut bytes) = (res.into_body(), Vec::new());
+        while let Some(chunk) = body.data().await {
+            bytes.extend_from_slice(&chunk.unwrap());
+        }
+        let effective: Vec<EffectiveConfig> = serde_json::from_slice(&bytes).unwrap();
+        assert_eq!(effective, registry.effective());
+
+        let res = admin_router(registry)
+            .oneshot(
+                Request::get("/fault/effective")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(res.status(), StatusCode::OK);
+    }
+}
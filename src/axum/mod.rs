@@ -0,0 +1,153 @@
+//! # `axum` integration
+//!
+//! This module contains helpers to use the fault layers with [`axum`]:
+//!
+//! * [`RouterExt`] - extension trait to add fault layers to a [`Router`].
+//! * [`Faults`] - extractor returning information about the faults of a
+//!   [`FaultRegistry`].
+//! * [`admin_router`] - router to inspect and control the faults of a
+//!   [`FaultRegistry`] at runtime.
+//! * `FaultWebSocket` - WebSocket with injected message-level faults, with
+//!   the `axum-ws` feature.
+//!
+//! ## Example
+//!
+//! ```rust
+//! use axum::{routing::get, Router};
+//! use tower_fault::{
+//!     axum::{admin_router, Faults, RouterExt},
+//!     registry::FaultRegistry,
+//! };
+//!
+//! async fn handler(Faults(faults): Faults) -> String {
+//!     format!("{} faults registered", faults.len())
+//! }
+//!
+//! let registry = FaultRegistry::new();
+//!
+//! let app: Router = Router::new()
+//!     .route("/", get(handler))
+//!     // Inject 200 to 500 milliseconds of latency, controlled by the
+//!     // "latency" fault in the registry.
+//!     .with_latency(registry.register("latency", 0.1), 200..500)
+//!     .with_fault_registry(registry.clone())
+//!     // Expose the admin endpoints under `/faults`.
+//!     .nest("/faults", admin_router(registry));
+//! ```
+//!
+//! ## WebSockets
+//!
+//! With the `axum-ws` feature, `FaultWebSocket` wraps upgraded WebSockets to
+//! delay, drop, or reorder the received messages, or to close the connection
+//! abruptly.
+//!
+//! ```rust
+//! # #[cfg(feature = "axum-ws")]
+//! # {
+//! use axum::{extract::ws::WebSocketUpgrade, response::IntoResponse};
+//! use tower_fault::axum::FaultWebSocket;
+//! # async fn chat<S>(_socket: S) {}
+//!
+//! async fn handler(ws: WebSocketUpgrade) -> impl IntoResponse {
+//!     ws.on_upgrade(|socket| async move {
+//!         let socket = FaultWebSocket::new(socket)
+//!             // Delay 10% of the messages by 100 to 500 milliseconds.
+//!             .with_delay(0.1, 100..500)
+//!             // Drop 1% of the messages.
+//!             .with_drop(0.01)
+//!             // Swap 1% of the messages with the next one.
+//!             .with_reorder(0.01)
+//!             // Close the connection on 0.1% of the messages.
+//!             .with_close(0.001);
+//!         chat(socket).await;
+//!     })
+//! }
+//! # }
+//! ```
+
+use crate::{
+    decider::Decider,
+    latency::{Distribution, LatencyLayer},
+    registry::{FaultInfo, FaultRegistry},
+};
+use axum::{
+    async_trait,
+    body::{Bytes, HttpBody},
+    extract::{rejection::ExtensionRejection, Extension, FromRequest, RequestParts},
+    routing::Router,
+    BoxError,
+};
+
+mod admin;
+#[cfg(feature = "axum-ws")]
+mod ws;
+pub use admin::{admin_router, FaultUpdate};
+#[cfg(feature = "axum-ws")]
+#[cfg_attr(docsrs, doc(cfg(feature = "axum-ws")))]
+pub use ws::FaultWebSocket;
+
+/// Extension trait to add fault layers to an axum [`Router`].
+pub trait RouterExt<B> {
+    /// Add a [`LatencyLayer`] with the given decider and distribution to
+    /// all the routes of the router.
+    fn with_latency<De, Di>(self, decider: De, distribution: Di) -> Self
+    where
+        De: Decider<http::Request<B>> + Clone + Send + 'static,
+        Di: Distribution<http::Request<B>> + Clone + Send + 'static;
+
+    /// Make the given [`FaultRegistry`] available to the [`Faults`] and
+    /// [`FaultRegistry`] extractors.
+    fn with_fault_registry(self, registry: FaultRegistry) -> Self;
+}
+
+impl<B> RouterExt<B> for Router<B>
+where
+    B: HttpBody<Data = Bytes> + Send + 'static,
+    B::Error: Into<BoxError>,
+{
+    fn with_latency<De, Di>(self, decider: De, distribution: Di) -> Self
+    where
+        De: Decider<http::Request<B>> + Clone + Send + 'static,
+        Di: Distribution<http::Request<B>> + Clone + Send + 'static,
+    {
+        self.layer(LatencyLayer::new(decider, distribution))
+    }
+
+    fn with_fault_registry(self, registry: FaultRegistry) -> Self {
+        self.layer(Extension(registry))
+    }
+}
+
+#[async_trait]
+impl<B> FromRequest<B> for FaultRegistry
+where
+    B: Send,
+{
+    type Rejection = ExtensionRejection;
+
+    async fn from_request(req: &mut RequestParts<B>) -> Result<Self, Self::Rejection> {
+        let Extension(registry) = Extension::<FaultRegistry>::from_request(req).await?;
+        Ok(registry)
+    }
+}
+
+/// Extractor that returns information about all the faults registered in
+/// the [`FaultRegistry`].
+///
+/// This requires the registry to be added to the router with
+/// [`RouterExt::with_fault_registry`].
+#[derive(Clone, Debug)]
+pub struct Faults(pub Vec<FaultInfo>);
+
+#[async_trait]
+impl<B> FromRequest<B> for Faults
+where
+    B: Send,
+{
+    type Rejection = ExtensionRejection;
+
+    async fn from_request(req: &mut RequestParts<B>) -> Result<Self, Self::Rejection> {
+        let registry = FaultRegistry::from_request(req).await?;
+        Ok(Faults(registry.faults()))
+    }
+}
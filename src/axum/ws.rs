@@ -0,0 +1,305 @@
+use crate::{decider::Decider, latency::Distribution};
+use axum::{
+    extract::ws::{Message, WebSocket},
+    Error,
+};
+use futures_core::Stream;
+use futures_sink::Sink;
+use std::{
+    future::Future,
+    pin::Pin,
+    task::{Context, Poll},
+};
+use tokio::time::{self, Sleep};
+
+/// WebSocket with injected message-level faults.
+///
+/// The faults apply to the messages received from the client: they can be
+/// delayed, dropped, reordered with the next message, or the connection can
+/// be closed abruptly, without a close frame. Messages sent to the client
+/// are forwarded as-is.
+///
+/// Deciders and distributions receive the [`Message`]. No fault is injected
+/// until deciders are set with the `with_*` methods.
+#[derive(Debug)]
+pub struct FaultWebSocket<S = WebSocket, De = bool, Di = u64, Dr = bool, Re = bool, Cl = bool> {
+    inner: Option<S>,
+    delay: De,
+    distribution: Di,
+    drop: Dr,
+    reorder: Re,
+    close: Cl,
+    sleep: Option<Pin<Box<Sleep>>>,
+    delayed: Option<Message>,
+    held: Option<Message>,
+    queued: Option<Message>,
+}
+
+impl<S> FaultWebSocket<S> {
+    /// Wrap the given WebSocket.
+    pub fn new(inner: S) -> Self {
+        Self {
+            inner: Some(inner),
+            delay: false,
+            distribution: 0,
+            drop: false,
+            reorder: false,
+            close: false,
+            sleep: None,
+            delayed: None,
+            held: None,
+            queued: None,
+        }
+    }
+}
+
+impl<S, De, Di, Dr, Re, Cl> FaultWebSocket<S, De, Di, Dr, Re, Cl> {
+    /// Delay messages using the given decider and latency distribution.
+    pub fn with_delay<NDe, NDi>(
+        self,
+        decider: NDe,
+        distribution: NDi,
+    ) -> FaultWebSocket<S, NDe, NDi, Dr, Re, Cl> {
+        FaultWebSocket {
+            inner: self.inner,
+            delay: decider,
+            distribution,
+            drop: self.drop,
+            reorder: self.reorder,
+            close: self.close,
+            sleep: self.sleep,
+            delayed: self.delayed,
+            held: self.held,
+            queued: self.queued,
+        }
+    }
+
+    /// Drop messages using the given decider.
+    pub fn with_drop<NDr>(self, decider: NDr) -> FaultWebSocket<S, De, Di, NDr, Re, Cl> {
+        FaultWebSocket {
+            inner: self.inner,
+            delay: self.delay,
+            distribution: self.distribution,
+            drop: decider,
+            reorder: self.reorder,
+            close: self.close,
+            sleep: self.sleep,
+            delayed: self.delayed,
+            held: self.held,
+            queued: self.queued,
+        }
+    }
+
+    /// Swap messages with the next one using the given decider.
+    pub fn with_reorder<NRe>(self, decider: NRe) -> FaultWebSocket<S, De, Di, Dr, NRe, Cl> {
+        FaultWebSocket {
+            inner: self.inner,
+            delay: self.delay,
+            distribution: self.distribution,
+            drop: self.drop,
+            reorder: decider,
+            close: self.close,
+            sleep: self.sleep,
+            delayed: self.delayed,
+            held: self.held,
+            queued: self.queued,
+        }
+    }
+
+    /// Close the connection abruptly when receiving a message, using the
+    /// given decider.
+    pub fn with_close<NCl>(self, decider: NCl) -> FaultWebSocket<S, De, Di, Dr, Re, NCl> {
+        FaultWebSocket {
+            inner: self.inner,
+            delay: self.delay,
+            distribution: self.distribution,
+            drop: self.drop,
+            reorder: self.reorder,
+            close: decider,
+            sleep: self.sleep,
+            delayed: self.delayed,
+            held: self.held,
+            queued: self.queued,
+        }
+    }
+
+    /// Returns `true` if the connection was closed by an injected fault.
+    pub fn is_closed(&self) -> bool {
+        self.inner.is_none()
+    }
+
+    fn closed() -> Error {
+        Error::new("websocket closed by fault injection")
+    }
+}
+
+impl<S, De, Di, Dr, Re, Cl> Stream for FaultWebSocket<S, De, Di, Dr, Re, Cl>
+where
+    S: Stream<Item = Result<Message, Error>> + Unpin,
+    De: Decider<Message> + Unpin,
+    Di: Distribution<Message> + Unpin,
+    Dr: Decider<Message> + Unpin,
+    Re: Decider<Message> + Unpin,
+    Cl: Decider<Message> + Unpin,
+{
+    type Item = Result<Message, Error>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.get_mut();
+
+        if let Some(message) = this.queued.take() {
+            return Poll::Ready(Some(Ok(message)));
+        }
+
+        if let Some(sleep) = this.sleep.as_mut() {
+            if sleep.as_mut().poll(cx).is_pending() {
+                return Poll::Pending;
+            }
+            this.sleep = None;
+            if let Some(message) = this.delayed.take() {
+                this.queued = this.held.take();
+                return Poll::Ready(Some(Ok(message)));
+            }
+        }
+
+        loop {
+            let inner = match this.inner.as_mut() {
+                Some(inner) => inner,
+                None => return Poll::Ready(None),
+            };
+            let message = match Pin::new(inner).poll_next(cx) {
+                Poll::Ready(Some(Ok(message))) => message,
+                Poll::Ready(None) => return Poll::Ready(this.held.take().map(Ok)),
+                other => return other,
+            };
+
+            if this.close.decide(&message) {
+                // Dropping the socket closes the connection without sending
+                // a close frame.
+                this.inner = None;
+                this.held = None;
+                return Poll::Ready(None);
+            }
+
+            if this.drop.decide(&message) {
+                continue;
+            }
+
+            if this.held.is_none() && this.reorder.decide(&message) {
+                this.held = Some(message);
+                continue;
+            }
+
+            if this.delay.decide(&message) {
+                let mut sleep = Box::pin(time::sleep(this.distribution.sample(&message)));
+                if sleep.as_mut().poll(cx).is_pending() {
+                    this.sleep = Some(sleep);
+                    this.delayed = Some(message);
+                    return Poll::Pending;
+                }
+            }
+
+            this.queued = this.held.take();
+            return Poll::Ready(Some(Ok(message)));
+        }
+    }
+}
+
+impl<S, De, Di, Dr, Re, Cl> Sink<Message> for FaultWebSocket<S, De, Di, Dr, Re, Cl>
+where
+    S: Sink<Message, Error = Error> + Unpin,
+    Self: Unpin,
+{
+    type Error = Error;
+
+    fn poll_ready(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Error>> {
+        match self.get_mut().inner.as_mut() {
+            Some(inner) => Pin::new(inner).poll_ready(cx),
+            None => Poll::Ready(Err(Self::closed())),
+        }
+    }
+
+    fn start_send(self: Pin<&mut Self>, item: Message) -> Result<(), Error> {
+        match self.get_mut().inner.as_mut() {
+            Some(inner) => Pin::new(inner).start_send(item),
+            None => Err(Self::closed()),
+        }
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Error>> {
+        match self.get_mut().inner.as_mut() {
+            Some(inner) => Pin::new(inner).poll_flush(cx),
+            None => Poll::Ready(Err(Self::closed())),
+        }
+    }
+
+    fn poll_close(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Error>> {
+        match self.get_mut().inner.as_mut() {
+            Some(inner) => Pin::new(inner).poll_close(cx),
+            None => Poll::Ready(Ok(())),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{future::poll_fn, time::Duration};
+
+    struct Messages(Vec<&'static str>);
+
+    impl Stream for Messages {
+        type Item = Result<Message, Error>;
+
+        fn poll_next(mut self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+            if self.0.is_empty() {
+                Poll::Ready(None)
+            } else {
+                Poll::Ready(Some(Ok(Message::Text(self.0.remove(0).to_string()))))
+            }
+        }
+    }
+
+    async fn collect<St: Stream<Item = Result<Message, Error>> + Unpin>(
+        mut stream: St,
+    ) -> Vec<String> {
+        let mut texts = Vec::new();
+        while let Some(Ok(message)) = poll_fn(|cx| Pin::new(&mut stream).poll_next(cx)).await {
+            if let Message::Text(text) = message {
+                texts.push(text);
+            }
+        }
+        texts
+    }
+
+    fn is(text: &'static str) -> impl Fn(&Message) -> bool {
+        move |message| matches!(message, Message::Text(t) if t == text)
+    }
+
+    #[tokio::test]
+    async fn websocket_drop_and_reorder() {
+        let socket = FaultWebSocket::new(Messages(vec!["a", "b", "c", "d"]))
+            .with_drop(is("b"))
+            .with_reorder(is("a"));
+
+        assert_eq!(collect(socket).await, vec!["c", "a", "d"]);
+    }
+
+    #[tokio::test]
+    async fn websocket_close() {
+        let mut socket = FaultWebSocket::new(Messages(vec!["a", "b", "c"])).with_close(is("b"));
+
+        assert_eq!(collect(&mut socket).await, vec!["a"]);
+        assert!(socket.is_closed());
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn websocket_delay() {
+        let socket =
+            FaultWebSocket::new(Messages(vec!["a", "b"])).with_delay(true, Duration::from_secs(1));
+
+        let start = time::Instant::now();
+        assert_eq!(collect(socket).await, vec!["a", "b"]);
+        assert!(start.elapsed() >= Duration::from_secs(2));
+    }
+}
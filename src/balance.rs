@@ -0,0 +1,301 @@
+//! # Per-endpoint fault injection
+//!
+//! This module contains the [`PerEndpointFaultLayer`], which wraps the
+//! individual endpoints of a load-balanced service, such as the services
+//! behind [`tower::balance::p2c::Balance`]. Faults are controlled through an
+//! [`EndpointFaults`] registry keyed by endpoint identity, so that one
+//! backend out of N can be killed or slowed down to observe how the balancer
+//! reacts.
+//!
+//! ## Example
+//!
+//! ```rust
+//! use std::time::Duration;
+//! use tower::Layer;
+//! use tower_fault::balance::{EndpointFault, EndpointFaults};
+//! # #[derive(Clone)]
+//! # struct Backend;
+//! # let backends = vec![("10.0.0.1:80", Backend), ("10.0.0.2:80", Backend)];
+//!
+//! let faults = EndpointFaults::new();
+//!
+//! let endpoints: Vec<_> = backends
+//!     .into_iter()
+//!     .map(|(addr, backend)| {
+//!         faults
+//!             .layer(addr, |addr: &&str| format!("{} is down", addr))
+//!             .layer(backend)
+//!     })
+//!     .collect();
+//!
+//! // Slow down a single backend.
+//! faults.set("10.0.0.2:80", EndpointFault::Slow(Duration::from_millis(500)));
+//!
+//! // Then restore it.
+//! faults.clear(&"10.0.0.2:80");
+//! ```
+
+use crate::veto::Veto;
+use std::{
+    collections::HashMap,
+    future::Future,
+    hash::Hash,
+    pin::Pin,
+    sync::{Arc, RwLock},
+    task::{Context, Poll},
+    time::Duration,
+};
+use tokio::time;
+use tower::{load::Load, Layer, Service};
+
+/// Fault applied to an endpoint.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum EndpointFault {
+    /// Calls to the endpoint fail immediately with a generated error.
+    Down,
+    /// The endpoint reports an error when polled for readiness. Balancers
+    /// typically remove such endpoints from their ready set.
+    Unready,
+    /// Calls to the endpoint are delayed by the given duration.
+    Slow(Duration),
+}
+
+/// Registry of faults keyed by endpoint identity.
+///
+/// Cloning the registry is cheap, and all clones share the same faults.
+#[derive(Debug)]
+pub struct EndpointFaults<K> {
+    faults: Arc<RwLock<HashMap<K, EndpointFault>>>,
+}
+
+impl<K> Clone for EndpointFaults<K> {
+    fn clone(&self) -> Self {
+        Self {
+            faults: self.faults.clone(),
+        }
+    }
+}
+
+impl<K> EndpointFaults<K>
+where
+    K: Eq + Hash,
+{
+    /// Create a new `EndpointFaults` registry without any fault.
+    pub fn new() -> Self {
+        Self {
+            faults: Arc::new(RwLock::new(HashMap::new())),
+        }
+    }
+
+    /// Apply a fault to the given endpoint.
+    pub fn set(&self, key: K, fault: EndpointFault) {
+        let mut faults = self.faults.write().expect("endpoint faults lock poisoned");
+        faults.insert(key, fault);
+    }
+
+    /// Remove the fault applied to the given endpoint, if any.
+    pub fn clear(&self, key: &K) {
+        let mut faults = self.faults.write().expect("endpoint faults lock poisoned");
+        faults.remove(key);
+    }
+
+    /// Remove all the faults.
+    pub fn clear_all(&self) {
+        let mut faults = self.faults.write().expect("endpoint faults lock poisoned");
+        faults.clear();
+    }
+
+    /// Returns the fault applied to the given endpoint, if any.
+    pub fn get(&self, key: &K) -> Option<EndpointFault> {
+        let faults = self.faults.read().expect("endpoint faults lock poisoned");
+        faults.get(key).copied()
+    }
+
+    /// Create a [`PerEndpointFaultLayer`] for the endpoint with the given
+    /// key.
+    ///
+    /// The generator is used to create errors for the
+    /// [`Down`](EndpointFault::Down) and [`Unready`](EndpointFault::Unready)
+    /// faults.
+    pub fn layer<G>(&self, key: K, generator: G) -> PerEndpointFaultLayer<K, G> {
+        PerEndpointFaultLayer {
+            faults: self.clone(),
+            key,
+            generator,
+            veto: false,
+        }
+    }
+}
+
+impl<K> Default for EndpointFaults<K>
+where
+    K: Eq + Hash,
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Layer that applies the faults of an [`EndpointFaults`] registry to a
+/// single endpoint.
+#[derive(Clone, Debug)]
+pub struct PerEndpointFaultLayer<K, G, V = bool> {
+    faults: EndpointFaults<K>,
+    key: K,
+    generator: G,
+    veto: V,
+}
+
+impl<K, G, V> PerEndpointFaultLayer<K, G, V> {
+    /// Never apply the [`Down`](EndpointFault::Down) and
+    /// [`Slow`](EndpointFault::Slow) faults to the requests vetoed by the
+    /// given veto.
+    ///
+    /// The [`Unready`](EndpointFault::Unready) fault applies to the readiness
+    /// of the endpoint, before any request is known, so it can't be vetoed.
+    ///
+    /// See the [`veto`](crate::veto) module for more information.
+    pub fn with_veto<NV>(self, veto: NV) -> PerEndpointFaultLayer<K, G, NV> {
+        PerEndpointFaultLayer {
+            faults: self.faults,
+            key: self.key,
+            generator: self.generator,
+            veto,
+        }
+    }
+}
+
+impl<K, G, V, S> Layer<S> for PerEndpointFaultLayer<K, G, V>
+where
+    K: Clone,
+    G: Clone,
+    V: Clone,
+{
+    type Service = PerEndpointFaultService<K, G, S, V>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        PerEndpointFaultService {
+            inner,
+            faults: self.faults.clone(),
+            key: self.key.clone(),
+            generator: self.generator.clone(),
+            veto: self.veto.clone(),
+        }
+    }
+}
+
+/// Service that applies the faults of an [`EndpointFaults`] registry to a
+/// single endpoint.
+#[derive(Clone, Debug)]
+pub struct PerEndpointFaultService<K, G, S, V = bool> {
+    inner: S,
+    faults: EndpointFaults<K>,
+    key: K,
+    generator: G,
+    veto: V,
+}
+
+impl<K, G, S, V, R> Service<R> for PerEndpointFaultService<K, G, S, V>
+where
+    K: Eq + Hash,
+    G: Fn(&K) -> S::Error,
+    V: Veto<R>,
+    S: Service<R>,
+    S::Future: Send + 'static,
+    S::Error: Send + 'static,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = PerEndpointFaultFuture<R, S>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        if let Some(EndpointFault::Unready) = self.faults.get(&self.key) {
+            return Poll::Ready(Err((self.generator)(&self.key)));
+        }
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, request: R) -> Self::Future {
+        if self.veto.veto(&request) {
+            return Box::pin(self.inner.call(request));
+        }
+
+        match self.faults.get(&self.key) {
+            Some(EndpointFault::Down) | Some(EndpointFault::Unready) => {
+                let error = (self.generator)(&self.key);
+                Box::pin(async move { Err(error) })
+            }
+            Some(EndpointFault::Slow(latency)) => {
+                let fut = self.inner.call(request);
+                Box::pin(async move {
+                    time::sleep(latency).await;
+                    fut.await
+                })
+            }
+            None => Box::pin(self.inner.call(request)),
+        }
+    }
+}
+
+impl<K, G, S, V> Load for PerEndpointFaultService<K, G, S, V>
+where
+    S: Load,
+{
+    type Metric = S::Metric;
+
+    fn load(&self) -> Self::Metric {
+        self.inner.load()
+    }
+}
+
+type PerEndpointFaultFuture<R, S> = Pin<
+    Box<
+        dyn Future<Output = Result<<S as Service<R>>::Response, <S as Service<R>>::Error>>
+            + Send
+            + 'static,
+    >,
+>;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::*;
+    use tower::ServiceExt;
+
+    #[tokio::test]
+    async fn endpoint_down() {
+        let faults = EndpointFaults::new();
+        let mut a = faults
+            .layer("a", |key: &&str| format!("{} down", key))
+            .layer(DummyService);
+        let mut b = faults
+            .layer("b", |key: &&str| format!("{} down", key))
+            .layer(DummyService);
+
+        faults.set("a", EndpointFault::Down);
+        assert_eq!(
+            a.ready().await.unwrap().call(()).await.unwrap_err(),
+            "a down"
+        );
+        assert_eq!(b.ready().await.unwrap().call(()).await.unwrap(), "ok");
+
+        faults.set("b", EndpointFault::Unready);
+        assert_eq!(b.ready().await.err().unwrap(), "b down");
+
+        faults.clear_all();
+        assert_eq!(a.ready().await.unwrap().call(()).await.unwrap(), "ok");
+        assert_eq!(b.ready().await.unwrap().call(()).await.unwrap(), "ok");
+    }
+
+    #[tokio::test]
+    async fn endpoint_veto() {
+        let faults = EndpointFaults::new();
+        let mut a = faults
+            .layer("a", |key: &&str| format!("{} down", key))
+            .with_veto(true)
+            .layer(DummyService);
+
+        faults.set("a", EndpointFault::Down);
+        assert_eq!(a.ready().await.unwrap().call(()).await.unwrap(), "ok");
+    }
+}
@@ -0,0 +1,124 @@
+//! # Benchmarks
+//!
+//! Helpers to measure the overhead of fault layers and deciders with
+//! [`criterion`], used by the benchmarks of this crate and available to
+//! benchmark custom deciders.
+//!
+//! [`bench_layer`] measures the per-call overhead of a layer in each
+//! [`LayerState`]: disabled, enabled without injecting faults, and injecting
+//! a fault into every request. [`bench_decider`] measures the time taken by
+//! a decider to make a decision.
+//!
+//! ```rust,no_run
+//! use criterion::{criterion_group, criterion_main, Criterion};
+//! use tower::{service_fn, Layer};
+//! use tower_fault::{bench, error::ErrorLayer};
+//!
+//! fn my_decider(req: &u64) -> bool {
+//!     req % 2 == 0
+//! }
+//!
+//! fn benches(c: &mut Criterion) {
+//!     bench::bench_decider(c, "my_decider", &my_decider, &42);
+//!     bench::bench_layer(
+//!         c,
+//!         "error",
+//!         |state| {
+//!             ErrorLayer::new(state.probability(), |_: &u64| String::from("error"))
+//!                 .enabled(state.is_enabled())
+//!                 .layer(service_fn(|_: u64| async { Ok::<_, String>(()) }))
+//!         },
+//!         || 42,
+//!     );
+//! }
+//!
+//! criterion_group!(group, benches);
+//! criterion_main!(group);
+//! ```
+
+use crate::decider::Decider;
+use criterion::{black_box, Criterion};
+use std::fmt;
+use tokio::runtime::{Builder, Runtime};
+use tower::{Service, ServiceExt};
+
+/// State of a layer during a benchmark.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LayerState {
+    /// The layer is disabled.
+    Disabled,
+    /// The layer is enabled, but doesn't inject faults.
+    Passing,
+    /// The layer injects a fault into every request.
+    Faulted,
+}
+
+impl LayerState {
+    /// All the states, in the order they are benchmarked.
+    pub const ALL: [LayerState; 3] = [
+        LayerState::Disabled,
+        LayerState::Passing,
+        LayerState::Faulted,
+    ];
+
+    /// Returns whether the layer should be enabled.
+    pub fn is_enabled(self) -> bool {
+        self != LayerState::Disabled
+    }
+
+    /// Returns the probability of injecting a fault for this state.
+    pub fn probability(self) -> f64 {
+        match self {
+            LayerState::Faulted => 1.0,
+            _ => 0.0,
+        }
+    }
+}
+
+impl fmt::Display for LayerState {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            LayerState::Disabled => "disabled",
+            LayerState::Passing => "passing",
+            LayerState::Faulted => "faulted",
+        })
+    }
+}
+
+/// Measure the time taken by the decider to make a decision for the
+/// request.
+pub fn bench_decider<D, R>(c: &mut Criterion, name: &str, decider: &D, req: &R)
+where
+    D: Decider<R>,
+{
+    c.bench_function(name, |b| b.iter(|| decider.decide(black_box(req))));
+}
+
+/// Measure the per-call overhead of a service in each [`LayerState`].
+///
+/// `make_service` creates the service to benchmark for a state, and
+/// `make_request` creates the request sent for each call.
+pub fn bench_layer<S, R, M, F>(c: &mut Criterion, name: &str, make_service: M, make_request: F)
+where
+    S: Service<R> + Clone,
+    M: Fn(LayerState) -> S,
+    F: Fn() -> R,
+{
+    let runtime = runtime();
+    let mut group = c.benchmark_group(name);
+    for state in LayerState::ALL {
+        let service = make_service(state);
+        group.bench_function(state.to_string(), |b| {
+            b.to_async(&runtime)
+                .iter(|| service.clone().oneshot(make_request()))
+        });
+    }
+    group.finish();
+}
+
+fn runtime() -> Runtime {
+    Builder::new_current_thread()
+        .enable_time()
+        .build()
+        .expect("failed to build the benchmark runtime")
+}
@@ -0,0 +1,85 @@
+//! # Boxed services
+//!
+//! The [`BoxedFaults`] extension trait applies fault layers to a service
+//! and erases its type into a [`BoxService`] or a [`BoxCloneService`], so
+//! services with faults can be stored in struct fields or application state
+//! without naming the types of the fault layers.
+//!
+//! Several layers can be applied at once as a tuple, the first layer of the
+//! tuple being the outermost one.
+//!
+//! ## Example
+//!
+//! ```rust
+//! use tower::{service_fn, util::BoxCloneService};
+//! use tower_fault::{boxed::BoxedFaults, error::ErrorLayer, latency::LatencyLayer};
+//!
+//! struct AppState {
+//!     service: BoxCloneService<u64, (), String>,
+//! }
+//!
+//! let latency_layer = LatencyLayer::new(0.1, 200..500);
+//! let error_layer = ErrorLayer::new(0.1, |_: &u64| String::from("error"));
+//!
+//! let state = AppState {
+//!     service: service_fn(|_: u64| async { Ok::<_, String>(()) })
+//!         .boxed_clone_with_faults((latency_layer, error_layer)),
+//! };
+//! ```
+
+use tower::{
+    util::{BoxCloneService, BoxService},
+    Layer, Service,
+};
+
+/// Extension trait to apply fault layers to a service and box it.
+///
+/// The fault layers must keep the response and error types of the service,
+/// as the layers of this crate do.
+///
+/// This is implemented for all services.
+pub trait BoxedFaults<R>: Service<R> + Sized {
+    /// Apply the layer to the service, and box the resulting service.
+    fn boxed_with_faults<L>(self, layer: L) -> BoxService<R, Self::Response, Self::Error>
+    where
+        L: Layer<Self>,
+        L::Service: Service<R, Response = Self::Response, Error = Self::Error> + Send + 'static,
+        <L::Service as Service<R>>::Future: Send + 'static,
+    {
+        BoxService::new(layer.layer(self))
+    }
+
+    /// Apply the layer to the service, and box the resulting service into a
+    /// service that can be cloned.
+    fn boxed_clone_with_faults<L>(self, layer: L) -> BoxCloneService<R, Self::Response, Self::Error>
+    where
+        L: Layer<Self>,
+        L::Service:
+            Service<R, Response = Self::Response, Error = Self::Error> + Clone + Send + 'static,
+        <L::Service as Service<R>>::Future: Send + 'static,
+    {
+        BoxCloneService::new(layer.layer(self))
+    }
+}
+
+impl<S, R> BoxedFaults<R> for S where S: Service<R> {}
+
+#[cfg(all(test, feature = "error", feature = "latency"))]
+mod tests {
+    use super::*;
+    use crate::{error::ErrorLayer, latency::LatencyLayer};
+    use tower::{service_fn, ServiceExt};
+
+    #[tokio::test]
+    async fn boxed_services() {
+        let inner = service_fn(|_: u64| async { Ok::<_, String>("ok") });
+        let error_layer = ErrorLayer::new(|req: &u64| *req == 0, |_: &u64| String::from("error"));
+
+        let service = inner.boxed_with_faults(error_layer.clone());
+        assert_eq!(service.oneshot(0).await.unwrap_err(), "error");
+
+        let service = inner.boxed_clone_with_faults((LatencyLayer::new(1.0, 0), error_layer));
+        assert_eq!(service.clone().oneshot(0).await.unwrap_err(), "error");
+        assert_eq!(service.oneshot(1).await.unwrap(), "ok");
+    }
+}
@@ -0,0 +1,268 @@
+//! # Cascading faults
+//!
+//! This module expresses cascading-failure scenarios between the named
+//! faults of a [`FaultRegistry`].
+//!
+//! A [`Rule`] declares that a target fault depends on a source fault: when
+//! the source fault is active above a given probability for long enough,
+//! the target fault is activated too. When the source fault recovers, the
+//! target fault is restored to its previous settings. The [`Cascade`]
+//! engine evaluates the rules periodically until the registry's
+//! [`KillSwitch`](crate::registry::KillSwitch) is engaged.
+//!
+//! ## Example
+//!
+//! ```rust
+//! use std::time::Duration;
+//! use tower_fault::{
+//!     cascade::{Cascade, Rule},
+//!     registry::FaultRegistry,
+//! };
+//! # async fn run() {
+//!
+//! let registry = FaultRegistry::new();
+//! registry.register("db-errors", 0.0);
+//! registry.register("cache-latency", 0.0).disable();
+//!
+//! // If db-errors is active at more than 50%, also activate cache-latency
+//! // after 30 seconds.
+//! let cascade = Cascade::new().rule(
+//!     Rule::new("db-errors", "cache-latency")
+//!         .above(0.5)
+//!         .after(Duration::from_secs(30)),
+//! );
+//!
+//! tokio::spawn(async move { cascade.run(&registry).await });
+//! # }
+//! ```
+
+use crate::{
+    registry::{FaultHandle, FaultInfo, FaultRegistry},
+    Error,
+};
+use std::time::Duration;
+use tokio::time::{self, Instant};
+
+/// Dependency between two named faults.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Rule {
+    source: String,
+    target: String,
+    threshold: f64,
+    delay: Duration,
+    probability: f64,
+}
+
+impl Rule {
+    /// Create a new `Rule` activating the target fault as soon as the source
+    /// fault is active.
+    pub fn new(source: impl Into<String>, target: impl Into<String>) -> Self {
+        Self {
+            source: source.into(),
+            target: target.into(),
+            threshold: 0.0,
+            delay: Duration::ZERO,
+            probability: 1.0,
+        }
+    }
+
+    /// Only consider the source fault active when its probability is above
+    /// the given threshold.
+    ///
+    /// Defaults to 0.0, meaning any enabled source fault with a non-zero
+    /// probability.
+    pub fn above(mut self, threshold: f64) -> Self {
+        self.threshold = threshold;
+        self
+    }
+
+    /// Wait for the source fault to be active for the given duration before
+    /// activating the target fault.
+    pub fn after(mut self, delay: Duration) -> Self {
+        self.delay = delay;
+        self
+    }
+
+    /// Set the probability of the target fault while activated.
+    ///
+    /// Defaults to 1.0.
+    pub fn probability(mut self, probability: f64) -> Self {
+        self.probability = probability;
+        self
+    }
+
+    fn is_triggered(&self, source: &FaultHandle) -> bool {
+        source.is_enabled() && source.probability() > self.threshold
+    }
+}
+
+/// Engine evaluating cascading rules against a registry.
+#[derive(Clone, Debug)]
+pub struct Cascade {
+    rules: Vec<Rule>,
+    check_interval: Duration,
+}
+
+impl Default for Cascade {
+    fn default() -> Self {
+        Self {
+            rules: Vec::new(),
+            check_interval: Duration::from_secs(1),
+        }
+    }
+}
+
+/// Evaluation state of a rule.
+struct RuleState {
+    source: FaultHandle,
+    target: FaultHandle,
+    /// When the source fault became active.
+    since: Option<Instant>,
+    /// Settings of the target fault before it was activated.
+    saved: Option<FaultInfo>,
+}
+
+impl Cascade {
+    /// Create a new `Cascade` without rules.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a rule.
+    ///
+    /// Rules are evaluated in order, so chained rules can propagate within a
+    /// single evaluation.
+    pub fn rule(mut self, rule: Rule) -> Self {
+        self.rules.push(rule);
+        self
+    }
+
+    /// Set the interval at which the rules are evaluated.
+    ///
+    /// Defaults to 1 second.
+    pub fn check_interval(mut self, interval: Duration) -> Self {
+        self.check_interval = interval;
+        self
+    }
+
+    /// Evaluate the rules against the registry until its kill switch is
+    /// engaged.
+    ///
+    /// The activated target faults are restored to their previous settings
+    /// before returning. Returns an error if a rule references a fault that
+    /// isn't registered.
+    pub async fn run(&self, registry: &FaultRegistry) -> Result<(), Error> {
+        let handle = |name: &str| {
+            registry.get(name).ok_or_else(|| {
+                Error::InvalidConfig(format!("rule references unknown fault '{}'", name))
+            })
+        };
+        let mut states = self
+            .rules
+            .iter()
+            .map(|rule| {
+                Ok(RuleState {
+                    source: handle(&rule.source)?,
+                    target: handle(&rule.target)?,
+                    since: None,
+                    saved: None,
+                })
+            })
+            .collect::<Result<Vec<_>, Error>>()?;
+
+        let kill_switch = registry.kill_switch();
+        while !kill_switch.is_engaged() {
+            let now = Instant::now();
+            for (rule, state) in self.rules.iter().zip(states.iter_mut()) {
+                if rule.is_triggered(&state.source) {
+                    let since = *state.since.get_or_insert(now);
+                    if state.saved.is_none() && now.duration_since(since) >= rule.delay {
+                        state.saved = Some(state.target.info());
+                        state.target.set_probability(rule.probability);
+                        state.target.enable();
+                    }
+                } else {
+                    state.since = None;
+                    if let Some(saved) = state.saved.take() {
+                        restore(&state.target, &saved);
+                    }
+                }
+            }
+            time::sleep(self.check_interval).await;
+        }
+
+        // Restore in reverse order, so a fault targeted by several rules ends
+        // up with its original settings.
+        for state in states.iter_mut().rev() {
+            if let Some(saved) = state.saved.take() {
+                restore(&state.target, &saved);
+            }
+        }
+        Ok(())
+    }
+}
+
+fn restore(handle: &FaultHandle, info: &FaultInfo) {
+    handle.set_probability(info.probability);
+    if info.enabled {
+        handle.enable();
+    } else {
+        handle.disable();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test(start_paused = true)]
+    async fn cascade_activates_after_delay() {
+        let registry = FaultRegistry::new();
+        let db = registry.register("db-errors", 0.0);
+        let cache = registry.register("cache-latency", 0.1);
+        cache.disable();
+
+        let cascade = Cascade::new().rule(
+            Rule::new("db-errors", "cache-latency")
+                .above(0.5)
+                .after(Duration::from_secs(30)),
+        );
+        let task = tokio::spawn({
+            let registry = registry.clone();
+            async move { cascade.run(&registry).await }
+        });
+
+        db.set_probability(0.8);
+        time::sleep(Duration::from_secs(10)).await;
+        assert!(!cache.is_enabled());
+        time::sleep(Duration::from_secs(30)).await;
+        assert!(cache.is_enabled());
+        assert_eq!(cache.probability(), 1.0);
+
+        db.set_probability(0.2);
+        time::sleep(Duration::from_secs(2)).await;
+        assert!(!cache.is_enabled());
+        assert_eq!(cache.probability(), 0.1);
+
+        db.set_probability(1.0);
+        time::sleep(Duration::from_secs(40)).await;
+        registry.kill_switch().engage();
+        assert_eq!(task.await.unwrap(), Ok(()));
+        assert!(!cache.is_enabled());
+    }
+
+    #[tokio::test]
+    async fn cascade_unknown_fault() {
+        let registry = FaultRegistry::new();
+        registry.register("db-errors", 0.0);
+        let err = Cascade::new()
+            .rule(Rule::new("db-errors", "missing"))
+            .run(&registry)
+            .await
+            .unwrap_err();
+        assert_eq!(
+            err,
+            Error::InvalidConfig("rule references unknown fault 'missing'".to_string())
+        );
+    }
+}
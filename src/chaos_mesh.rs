@@ -0,0 +1,661 @@
+//! # Chaos Mesh compatibility
+//!
+//! This module contains a serde schema matching a subset of the
+//! [Chaos Mesh](https://chaos-mesh.org) `HTTPChaos` and `NetworkChaos`
+//! specifications, and maps them onto a [`ChaosLayer`]. This allows using
+//! the same experiment definitions for mesh-level and in-process chaos.
+//!
+//! As the layer runs inside a single process, pod selectors and modes are
+//! ignored: the experiment applies to all requests going through the layer.
+//!
+//! The following fields are supported:
+//!
+//! * `HTTPChaos`: `target`, `method`, `path` (with `*` wildcards),
+//!   `request_headers`, `code`, `delay` and `abort`. `replace` and `patch`
+//!   return an error.
+//! * `NetworkChaos`: the `delay` action (`latency` and `jitter`) and the
+//!   `loss` action. Other actions return an error.
+//!
+//! The types implement `Deserialize`, so experiments can be loaded from YAML
+//! with any serde-compatible YAML library.
+//!
+//! ## Example
+//!
+//! ```rust
+//! use tower_fault::chaos_mesh::ChaosExperiment;
+//!
+//! let experiment = ChaosExperiment::from_json(r#"{
+//!     "apiVersion": "chaos-mesh.org/v1alpha1",
+//!     "kind": "HTTPChaos",
+//!     "metadata": { "name": "slow-users" },
+//!     "spec": {
+//!         "target": "Request",
+//!         "port": 80,
+//!         "method": "GET",
+//!         "path": "/users/*",
+//!         "delay": "500ms"
+//!     }
+//! }"#).unwrap();
+//!
+//! let layer = experiment.layer(|_: &http::Request<()>| String::from("aborted")).unwrap();
+//! ```
+
+use http::{HeaderMap, Method, Request};
+use serde::Deserialize;
+use std::{
+    collections::BTreeMap,
+    future::Future,
+    pin::Pin,
+    task::{Context, Poll},
+    time::Duration,
+};
+use tokio::time;
+use tower::{Layer, Service};
+
+use crate::{decider::Decider, latency::Distribution, veto::Veto, Error};
+
+/// Chaos Mesh experiment.
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+#[serde(tag = "kind")]
+pub enum ChaosExperiment {
+    /// `HTTPChaos` experiment.
+    #[serde(rename = "HTTPChaos")]
+    Http {
+        /// Metadata of the experiment.
+        #[serde(default)]
+        metadata: Metadata,
+        /// Specification of the experiment.
+        spec: HttpChaosSpec,
+    },
+    /// `NetworkChaos` experiment.
+    #[serde(rename = "NetworkChaos")]
+    Network {
+        /// Metadata of the experiment.
+        #[serde(default)]
+        metadata: Metadata,
+        /// Specification of the experiment.
+        spec: NetworkChaosSpec,
+    },
+}
+
+/// Metadata of a Chaos Mesh experiment.
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Eq)]
+pub struct Metadata {
+    /// Name of the experiment.
+    #[serde(default)]
+    pub name: String,
+}
+
+/// Phase of the HTTP exchange targeted by an `HTTPChaos` experiment.
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq, Eq)]
+pub enum Target {
+    /// Inject faults before calling the service.
+    #[default]
+    Request,
+    /// Inject faults after the service returned a response.
+    Response,
+}
+
+/// Subset of the `HTTPChaos` specification.
+#[derive(Clone, Debug, Default, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct HttpChaosSpec {
+    /// Phase of the HTTP exchange to inject faults into.
+    pub target: Target,
+    /// Only inject faults for requests with this method.
+    pub method: Option<String>,
+    /// Only inject faults for requests matching this path.
+    ///
+    /// `*` matches any sequence of characters.
+    pub path: Option<String>,
+    /// Only inject faults for requests with these headers.
+    pub request_headers: BTreeMap<String, String>,
+    /// Only inject faults for responses with this status code.
+    ///
+    /// This only applies to the `Response` target.
+    pub code: Option<u16>,
+    /// Delay to inject, as a Go duration such as `"1s"` or `"500ms"`.
+    pub delay: Option<String>,
+    /// Abort the request.
+    pub abort: Option<bool>,
+    /// Replace parts of the request or response. Not supported.
+    pub replace: Option<serde::de::IgnoredAny>,
+    /// Patch parts of the request or response. Not supported.
+    pub patch: Option<serde::de::IgnoredAny>,
+}
+
+/// Action of a `NetworkChaos` experiment.
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum NetworkAction {
+    /// Add latency.
+    Delay,
+    /// Drop packets.
+    Loss,
+    /// Duplicate packets. Not supported.
+    Duplicate,
+    /// Corrupt packets. Not supported.
+    Corrupt,
+    /// Limit the bandwidth. Not supported.
+    Bandwidth,
+    /// Partition the network. Not supported.
+    Partition,
+}
+
+/// Subset of the `NetworkChaos` specification.
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+pub struct NetworkChaosSpec {
+    /// Action of the experiment.
+    pub action: NetworkAction,
+    /// Settings of the `delay` action.
+    #[serde(default)]
+    pub delay: Option<NetworkDelay>,
+    /// Settings of the `loss` action.
+    #[serde(default)]
+    pub loss: Option<NetworkLoss>,
+}
+
+/// Settings of the `delay` action of a `NetworkChaos` experiment.
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Eq)]
+#[serde(default)]
+pub struct NetworkDelay {
+    /// Base latency, as a Go duration.
+    pub latency: String,
+    /// Maximum deviation from the base latency, as a Go duration.
+    pub jitter: Option<String>,
+}
+
+/// Settings of the `loss` action of a `NetworkChaos` experiment.
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Eq)]
+#[serde(default)]
+pub struct NetworkLoss {
+    /// Percentage of packets to drop, such as `"25"`.
+    pub loss: String,
+}
+
+impl ChaosExperiment {
+    /// Parse an experiment from its JSON definition.
+    pub fn from_json(json: &str) -> Result<Self, Error> {
+        serde_json::from_str(json)
+            .map_err(|err| Error::InvalidConfig(format!("invalid experiment definition: {}", err)))
+    }
+
+    /// Returns the name of the experiment.
+    pub fn name(&self) -> &str {
+        match self {
+            ChaosExperiment::Http { metadata, .. } | ChaosExperiment::Network { metadata, .. } => {
+                &metadata.name
+            }
+        }
+    }
+
+    /// Build a [`ChaosLayer`] equivalent to this experiment.
+    ///
+    /// The generator is used to create errors when the experiment aborts
+    /// requests or drops packets.
+    pub fn layer<G>(&self, generator: G) -> Result<ChaosLayer<G>, Error> {
+        match self {
+            ChaosExperiment::Http { spec, .. } => spec.layer(generator),
+            ChaosExperiment::Network { spec, .. } => spec.layer(generator),
+        }
+    }
+}
+
+impl HttpChaosSpec {
+    fn layer<G>(&self, generator: G) -> Result<ChaosLayer<G>, Error> {
+        if self.replace.is_some() {
+            return Err(unsupported("replace"));
+        }
+        if self.patch.is_some() {
+            return Err(unsupported("patch"));
+        }
+
+        let method = self
+            .method
+            .as_deref()
+            .map(|method| {
+                Method::from_bytes(method.as_bytes()).map_err(|_| invalid("method", method))
+            })
+            .transpose()?;
+        let delay = match &self.delay {
+            Some(delay) => Delay::fixed(parse_duration("delay", delay)?),
+            None => Delay::NONE,
+        };
+
+        Ok(ChaosLayer {
+            filter: Filter {
+                method,
+                path: self.path.clone(),
+                headers: self.request_headers.clone(),
+            },
+            target: self.target,
+            code: self.code,
+            probability: 1.0,
+            delay,
+            abort: self.abort.unwrap_or(false),
+            generator,
+            veto: false,
+        })
+    }
+}
+
+impl NetworkChaosSpec {
+    fn layer<G>(&self, generator: G) -> Result<ChaosLayer<G>, Error> {
+        let (probability, delay, abort) = match self.action {
+            NetworkAction::Delay => {
+                let settings = self.delay.clone().unwrap_or_default();
+                let delay = Delay {
+                    latency: parse_duration("latency", &settings.latency)?,
+                    jitter: match &settings.jitter {
+                        Some(jitter) => parse_duration("jitter", jitter)?,
+                        None => Duration::ZERO,
+                    },
+                };
+                (1.0, delay, false)
+            }
+            NetworkAction::Loss => {
+                let settings = self.loss.clone().unwrap_or_default();
+                let loss = settings
+                    .loss
+                    .trim()
+                    .parse::<f64>()
+                    .ok()
+                    .filter(|loss| (0.0..=100.0).contains(loss))
+                    .ok_or_else(|| invalid("loss", &settings.loss))?;
+                (loss / 100.0, Delay::NONE, true)
+            }
+            NetworkAction::Duplicate => return Err(unsupported("duplicate")),
+            NetworkAction::Corrupt => return Err(unsupported("corrupt")),
+            NetworkAction::Bandwidth => return Err(unsupported("bandwidth")),
+            NetworkAction::Partition => return Err(unsupported("partition")),
+        };
+
+        Ok(ChaosLayer {
+            filter: Filter::default(),
+            target: Target::Request,
+            code: None,
+            probability,
+            delay,
+            abort,
+            generator,
+            veto: false,
+        })
+    }
+}
+
+fn unsupported(feature: &str) -> Error {
+    Error::InvalidConfig(format!("unsupported chaos feature: {}", feature))
+}
+
+fn invalid(field: &str, value: &str) -> Error {
+    Error::InvalidConfig(format!("invalid value for '{}': {}", field, value))
+}
+
+/// Parse a Go duration, such as `"1h30m"`, `"1.5s"` or `"100ms"`.
+fn parse_duration(field: &'static str, value: &str) -> Result<Duration, Error> {
+    let invalid = || invalid(field, value);
+
+    let mut rest = value.trim();
+    if rest == "0" {
+        return Ok(Duration::ZERO);
+    }
+    if rest.is_empty() {
+        return Err(invalid());
+    }
+
+    let mut secs = 0.0;
+    while !rest.is_empty() {
+        let split = rest
+            .find(|c: char| !c.is_ascii_digit() && c != '.')
+            .ok_or_else(invalid)?;
+        let (number, tail) = rest.split_at(split);
+        let number: f64 = number.parse().map_err(|_| invalid())?;
+        let split = tail
+            .find(|c: char| c.is_ascii_digit() || c == '.')
+            .unwrap_or(tail.len());
+        let (unit, tail) = tail.split_at(split);
+        secs += number
+            * match unit {
+                "ns" => 1e-9,
+                "us" | "µs" => 1e-6,
+                "ms" => 1e-3,
+                "s" => 1.0,
+                "m" => 60.0,
+                "h" => 3600.0,
+                _ => return Err(invalid()),
+            };
+        rest = tail;
+    }
+
+    Duration::try_from_secs_f64(secs).map_err(|_| invalid())
+}
+
+/// Latency of `latency` ± `jitter`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+struct Delay {
+    latency: Duration,
+    jitter: Duration,
+}
+
+impl Delay {
+    const NONE: Delay = Delay::fixed(Duration::ZERO);
+
+    const fn fixed(latency: Duration) -> Self {
+        Self {
+            latency,
+            jitter: Duration::ZERO,
+        }
+    }
+}
+
+impl<R> Distribution<R> for Delay {
+    fn sample(&self, req: &R) -> Duration {
+        if self.jitter.is_zero() {
+            return self.latency;
+        }
+        let low = self.latency.saturating_sub(self.jitter);
+        let high = self.latency.saturating_add(self.jitter);
+        (low..=high).sample(req)
+    }
+}
+
+/// Request filters of an `HTTPChaos` experiment.
+#[derive(Clone, Debug, Default)]
+struct Filter {
+    method: Option<Method>,
+    path: Option<String>,
+    headers: BTreeMap<String, String>,
+}
+
+impl Filter {
+    fn matches<B>(&self, req: &Request<B>) -> bool {
+        self.method.as_ref().is_none_or(|m| m == req.method())
+            && self
+                .path
+                .as_deref()
+                .is_none_or(|p| glob(p, req.uri().path()))
+            && self
+                .headers
+                .iter()
+                .all(|(name, value)| has_header(req.headers(), name, value))
+    }
+}
+
+fn has_header(headers: &HeaderMap, name: &str, value: &str) -> bool {
+    headers
+        .get_all(name)
+        .iter()
+        .any(|v| v.to_str().is_ok_and(|v| v == value))
+}
+
+/// Match `value` against a pattern where `*` matches any sequence of
+/// characters.
+fn glob(pattern: &str, value: &str) -> bool {
+    match pattern.split_once('*') {
+        None => pattern == value,
+        Some((prefix, rest)) => {
+            let Some(value) = value.strip_prefix(prefix) else {
+                return false;
+            };
+            (0..=value.len())
+                .filter(|i| value.is_char_boundary(*i))
+                .any(|i| glob(rest, &value[i..]))
+        }
+    }
+}
+
+/// Layer equivalent to a Chaos Mesh experiment.
+#[derive(Clone, Debug)]
+pub struct ChaosLayer<G, V = bool> {
+    filter: Filter,
+    target: Target,
+    code: Option<u16>,
+    probability: f64,
+    delay: Delay,
+    abort: bool,
+    generator: G,
+    veto: V,
+}
+
+impl<G, V> ChaosLayer<G, V> {
+    /// Never apply the experiment to the requests vetoed by the given veto,
+    /// even if they match its filter.
+    ///
+    /// See the [`veto`](crate::veto) module for more information.
+    pub fn with_veto<NV>(self, veto: NV) -> ChaosLayer<G, NV> {
+        ChaosLayer {
+            filter: self.filter,
+            target: self.target,
+            code: self.code,
+            probability: self.probability,
+            delay: self.delay,
+            abort: self.abort,
+            generator: self.generator,
+            veto,
+        }
+    }
+}
+
+impl<G, V, S> Layer<S> for ChaosLayer<G, V>
+where
+    G: Clone,
+    V: Clone,
+{
+    type Service = ChaosService<G, S, V>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        ChaosService {
+            inner,
+            layer: self.clone(),
+        }
+    }
+}
+
+/// Service that applies a Chaos Mesh experiment.
+#[derive(Clone, Debug)]
+pub struct ChaosService<G, S, V = bool> {
+    inner: S,
+    layer: ChaosLayer<G, V>,
+}
+
+impl<G, S, V, ReqB, ResB> Service<Request<ReqB>> for ChaosService<G, S, V>
+where
+    G: Fn(&Request<ReqB>) -> S::Error,
+    V: Veto<Request<ReqB>>,
+    S: Service<Request<ReqB>, Response = http::Response<ResB>>,
+    S::Future: Send + 'static,
+    S::Error: Send + 'static,
+    ResB: Send + 'static,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = ChaosFuture<Request<ReqB>, S>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, request: Request<ReqB>) -> Self::Future {
+        let layer = &self.layer;
+        if !layer.filter.matches(&request)
+            || layer.veto.veto(&request)
+            || !layer.probability.decide(&request)
+        {
+            return Box::pin(self.inner.call(request));
+        }
+
+        let delay = layer.delay.sample(&request);
+        let error = layer.abort.then(|| (layer.generator)(&request));
+
+        match layer.target {
+            Target::Request => {
+                if let Some(error) = error {
+                    return Box::pin(async move {
+                        time::sleep(delay).await;
+                        Err(error)
+                    });
+                }
+                let fut = self.inner.call(request);
+                Box::pin(async move {
+                    time::sleep(delay).await;
+                    fut.await
+                })
+            }
+            Target::Response => {
+                let code = layer.code;
+                let fut = self.inner.call(request);
+                Box::pin(async move {
+                    let res = fut.await?;
+                    if code.is_some_and(|code| res.status().as_u16() != code) {
+                        return Ok(res);
+                    }
+                    time::sleep(delay).await;
+                    match error {
+                        Some(error) => Err(error),
+                        None => Ok(res),
+                    }
+                })
+            }
+        }
+    }
+}
+
+type ChaosFuture<R, S> = Pin<
+    Box<
+        dyn Future<Output = Result<<S as Service<R>>::Response, <S as Service<R>>::Error>>
+            + Send
+            + 'static,
+    >,
+>;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use http::{Response, StatusCode};
+
+    #[derive(Clone)]
+    struct StatusService(StatusCode);
+
+    impl Service<Request<()>> for StatusService {
+        type Response = Response<()>;
+        type Error = String;
+        type Future = std::future::Ready<Result<Self::Response, Self::Error>>;
+
+        fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+            Poll::Ready(Ok(()))
+        }
+
+        fn call(&mut self, _req: Request<()>) -> Self::Future {
+            let mut res = Response::new(());
+            *res.status_mut() = self.0;
+            std::future::ready(Ok(res))
+        }
+    }
+
+    fn request(method: &str, path: &str) -> Request<()> {
+        Request::builder()
+            .method(method)
+            .uri(path)
+            .body(())
+            .unwrap()
+    }
+
+    #[test]
+    fn chaos_parse_duration() {
+        assert_eq!(
+            parse_duration("d", "1h30m").unwrap(),
+            Duration::from_secs(5400)
+        );
+        assert_eq!(
+            parse_duration("d", "1.5s").unwrap(),
+            Duration::from_millis(1500)
+        );
+        assert_eq!(
+            parse_duration("d", "100ms").unwrap(),
+            Duration::from_millis(100)
+        );
+        assert!(parse_duration("d", "10").is_err());
+        assert!(parse_duration("d", "10x").is_err());
+    }
+
+    #[test]
+    fn chaos_glob() {
+        assert!(glob("/api/*", "/api/users/1"));
+        assert!(glob("/api/*/items", "/api/users/items"));
+        assert!(!glob("/api/*", "/other"));
+        assert!(glob("/exact", "/exact"));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn chaos_http_abort() {
+        let experiment = ChaosExperiment::from_json(
+            r#"{
+                "kind": "HTTPChaos",
+                "metadata": { "name": "abort-users" },
+                "spec": { "method": "POST", "path": "/users/*", "delay": "1s", "abort": true }
+            }"#,
+        )
+        .unwrap();
+        assert_eq!(experiment.name(), "abort-users");
+
+        let mut service = experiment
+            .layer(|_: &Request<()>| String::from("aborted"))
+            .unwrap()
+            .layer(StatusService(StatusCode::OK));
+
+        assert!(service.call(request("GET", "/users/1")).await.is_ok());
+
+        let start = time::Instant::now();
+        let err = service.call(request("POST", "/users/1")).await.unwrap_err();
+        assert_eq!(err, "aborted");
+        assert_eq!(start.elapsed(), Duration::from_secs(1));
+    }
+
+    #[tokio::test]
+    async fn chaos_http_response_code() {
+        let experiment = ChaosExperiment::from_json(
+            r#"{ "kind": "HTTPChaos", "spec": { "target": "Response", "code": 500, "abort": true } }"#,
+        )
+        .unwrap();
+        let layer = experiment
+            .layer(|_: &Request<()>| String::from("aborted"))
+            .unwrap();
+
+        let mut service = layer.layer(StatusService(StatusCode::OK));
+        assert!(service.call(request("GET", "/")).await.is_ok());
+
+        let mut service = layer.layer(StatusService(StatusCode::INTERNAL_SERVER_ERROR));
+        assert!(service.call(request("GET", "/")).await.is_err());
+    }
+
+    #[test]
+    fn chaos_network() {
+        let experiment = ChaosExperiment::from_json(
+            r#"{
+                "kind": "NetworkChaos",
+                "spec": { "action": "delay", "delay": { "latency": "10ms", "jitter": "5ms" } }
+            }"#,
+        )
+        .unwrap();
+        let layer = experiment.layer(()).unwrap();
+        assert_eq!(layer.delay.latency, Duration::from_millis(10));
+        assert_eq!(layer.delay.jitter, Duration::from_millis(5));
+
+        let experiment = ChaosExperiment::from_json(
+            r#"{ "kind": "NetworkChaos", "spec": { "action": "loss", "loss": { "loss": "25" } } }"#,
+        )
+        .unwrap();
+        let layer = experiment.layer(()).unwrap();
+        assert_eq!(layer.probability, 0.25);
+        assert!(layer.abort);
+
+        let experiment = ChaosExperiment::from_json(
+            r#"{ "kind": "NetworkChaos", "spec": { "action": "partition" } }"#,
+        )
+        .unwrap();
+        assert_eq!(
+            experiment.layer(()).err().unwrap(),
+            unsupported("partition")
+        );
+    }
+}
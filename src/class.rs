@@ -0,0 +1,285 @@
+//! Settings keyed by request classification.
+
+use crate::{
+    decider::{Decider, ExplainStep, Explanation, Verdict},
+    describe::{DeciderDescription, DescribeDecider},
+    validate::ValidateDecider,
+    Error,
+};
+use std::fmt;
+
+/// Settings keyed by a classification of the request.
+///
+/// The classifier returns the class of the request, such as reads and
+/// writes, or small and large payloads, and the settings of that class are
+/// used. If no class matches, the fallback is used, if any. Otherwise, no
+/// fault is injected.
+///
+/// `ByClass` implements [`Decider`],
+/// [`Distribution`](crate::latency::Distribution) when the `latency` feature
+/// is enabled, and [`Generator`](crate::generator::Generator), so a single
+/// layer can model heterogeneous traffic.
+///
+/// A generator must return a value for every request, so `ByClass` only
+/// implements [`Generator`](crate::generator::Generator) once a fallback is
+/// set, which the `FALLBACK` parameter tracks.
+///
+/// ```rust,compile_fail
+/// use tower_fault::{error::ByClass, generator::Generator};
+///
+/// // Without a fallback, reads wouldn't have an error.
+/// let generator = ByClass::new(|write: &bool| *write).class(true, |_: &bool| 429);
+/// generator.generate(&false);
+/// ```
+#[derive(Clone)]
+pub struct ByClass<F, K, T, const FALLBACK: bool = false> {
+    classify: F,
+    classes: Vec<(K, T)>,
+    fallback: Option<T>,
+}
+
+impl<F, K, T> ByClass<F, K, T> {
+    /// Create a new `ByClass` with the given classifier.
+    pub fn new(classify: F) -> Self {
+        Self {
+            classify,
+            classes: Vec::new(),
+            fallback: None,
+        }
+    }
+}
+
+impl<F, K, T, const FALLBACK: bool> ByClass<F, K, T, FALLBACK> {
+    /// Add settings for the given class.
+    pub fn class(mut self, class: K, settings: T) -> Self {
+        self.classes.push((class, settings));
+        self
+    }
+
+    /// Set the settings used when no class matches.
+    pub fn fallback(self, settings: T) -> ByClass<F, K, T, true> {
+        ByClass {
+            classify: self.classify,
+            classes: self.classes,
+            fallback: Some(settings),
+        }
+    }
+
+    /// Returns the settings for the given request, if any.
+    pub fn find<R>(&self, req: &R) -> Option<&T>
+    where
+        F: Fn(&R) -> K,
+        K: PartialEq,
+    {
+        let class = (self.classify)(req);
+        self.classes
+            .iter()
+            .find(|(k, _)| *k == class)
+            .map(|(_, settings)| settings)
+            .or(self.fallback.as_ref())
+    }
+
+    fn settings(&self) -> impl Iterator<Item = &T> {
+        self.classes
+            .iter()
+            .map(|(_, settings)| settings)
+            .chain(self.fallback.as_ref())
+    }
+
+    fn describe(&self, f: impl Fn(&T) -> String) -> String
+    where
+        K: fmt::Debug,
+    {
+        let classes = self
+            .classes
+            .iter()
+            .map(|(class, settings)| format!("{:?} => {}", class, f(settings)))
+            .chain(
+                self.fallback
+                    .iter()
+                    .map(|settings| format!("_ => {}", f(settings))),
+            )
+            .collect::<Vec<_>>();
+        format!("by class [{}]", classes.join(", "))
+    }
+}
+
+impl<F, K, T, const FALLBACK: bool> fmt::Debug for ByClass<F, K, T, FALLBACK>
+where
+    K: fmt::Debug,
+    T: fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ByClass")
+            .field("classes", &self.classes)
+            .field("fallback", &self.fallback)
+            .finish()
+    }
+}
+
+impl<F, K, T, R, const FALLBACK: bool> Decider<R> for ByClass<F, K, T, FALLBACK>
+where
+    F: Fn(&R) -> K,
+    K: PartialEq,
+    T: Decider<R>,
+{
+    fn decide(&self, req: &R) -> bool {
+        self.find(req).is_some_and(|settings| settings.decide(req))
+    }
+
+    fn verdict(&self, req: &R) -> Verdict {
+        self.find(req)
+            .map_or(Verdict::Pass, |settings| settings.verdict(req))
+    }
+
+    fn explain(&self, req: &R, explanation: &mut Explanation) -> Verdict {
+        let (verdict, detail) = match self.find(req) {
+            Some(settings) => (settings.explain(req, explanation), "class matched"),
+            None => (Verdict::Pass, "no class matched"),
+        };
+        explanation.record(ExplainStep::new("by class", verdict).with_detail(detail));
+        verdict
+    }
+}
+
+impl<F, K, T, const FALLBACK: bool> ValidateDecider for ByClass<F, K, T, FALLBACK>
+where
+    T: ValidateDecider,
+{
+    fn validate_decider(&self) -> Result<(), Error> {
+        self.settings().try_for_each(T::validate_decider)
+    }
+}
+
+impl<F, K, T, const FALLBACK: bool> DescribeDecider for ByClass<F, K, T, FALLBACK>
+where
+    K: fmt::Debug,
+    T: DescribeDecider,
+{
+    fn describe_decider(&self) -> DeciderDescription {
+        DeciderDescription::new(
+            self.describe(|settings| settings.describe_decider().to_string()),
+            None,
+        )
+    }
+}
+
+#[cfg(any(feature = "error", feature = "http"))]
+mod generator {
+    use super::ByClass;
+    use crate::generator::Generator;
+
+    impl<F, K, T, R, E> Generator<R, E> for ByClass<F, K, T, true>
+    where
+        F: Fn(&R) -> K,
+        K: PartialEq,
+        T: Generator<R, E>,
+    {
+        fn generate(&self, req: &R) -> E {
+            self.find(req)
+                .expect("the fallback is set by `ByClass::fallback`")
+                .generate(req)
+        }
+    }
+}
+
+#[cfg(feature = "latency")]
+mod latency {
+    use super::ByClass;
+    use crate::{
+        describe::DescribeDistribution, latency::Distribution, validate::ValidateDistribution,
+        Error,
+    };
+    use std::{fmt, time::Duration};
+
+    impl<F, K, T, R, const FALLBACK: bool> Distribution<R> for ByClass<F, K, T, FALLBACK>
+    where
+        F: Fn(&R) -> K,
+        K: PartialEq,
+        T: Distribution<R>,
+    {
+        fn sample(&self, req: &R) -> Duration {
+            self.find(req)
+                .map_or(Duration::ZERO, |settings| settings.sample(req))
+        }
+    }
+
+    impl<F, K, T, const FALLBACK: bool> ValidateDistribution for ByClass<F, K, T, FALLBACK>
+    where
+        T: ValidateDistribution,
+    {
+        fn validate_distribution(&self) -> Result<(), Error> {
+            self.settings().try_for_each(T::validate_distribution)
+        }
+    }
+
+    impl<F, K, T, const FALLBACK: bool> DescribeDistribution for ByClass<F, K, T, FALLBACK>
+    where
+        K: fmt::Debug,
+        T: DescribeDistribution,
+    {
+        fn describe_distribution(&self) -> String {
+            self.describe(T::describe_distribution)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Debug, PartialEq)]
+    enum Kind {
+        Read,
+        Write,
+    }
+
+    fn classify(req: &&str) -> Kind {
+        if req.starts_with("GET") {
+            Kind::Read
+        } else {
+            Kind::Write
+        }
+    }
+
+    #[test]
+    fn by_class_decider() {
+        let decider = ByClass::new(classify).class(Kind::Write, true);
+        assert!(decider.decide(&"PUT /"));
+        assert!(!decider.decide(&"GET /"));
+        assert_eq!(
+            decider.describe_decider().to_string(),
+            "by class [Write => always (100%)]"
+        );
+    }
+
+    #[cfg(feature = "error")]
+    #[test]
+    fn by_class_generator() {
+        use crate::generator::Generator;
+
+        fn status(code: u16) -> impl Fn(&&str) -> u16 {
+            move |_| code
+        }
+
+        let generator = ByClass::new(classify)
+            .class(Kind::Write, status(429))
+            .fallback(status(503));
+        assert_eq!(generator.generate(&"PUT /"), 429);
+        assert_eq!(generator.generate(&"GET /"), 503);
+    }
+
+    #[cfg(feature = "latency")]
+    #[test]
+    fn by_class_distribution() {
+        use crate::{latency::Distribution, validate::ValidateDistribution};
+        use std::time::Duration;
+
+        let distribution = ByClass::new(classify).class(Kind::Read, 10).fallback(200);
+        assert_eq!(distribution.sample(&"GET /"), Duration::from_millis(10));
+        assert_eq!(distribution.sample(&"PUT /"), Duration::from_millis(200));
+
+        let invalid = ByClass::new(classify).class(Kind::Read, 200..200);
+        assert!(invalid.validate_distribution().is_err());
+    }
+}
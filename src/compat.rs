@@ -0,0 +1,127 @@
+//! # Compatibility with `tower-fault-injector`
+//!
+//! Constructors matching the probabilistic API of `tower-fault-injector`,
+//! built on top of the decider-based layers of this crate, so that code
+//! using that API can migrate incrementally.
+//!
+//! `LatencyLayer::new` and `ErrorLayer::new` take a probability and return
+//! an error if it isn't between 0.0 and 1.0, and `new_with_bernoulli` takes
+//! a [`Bernoulli`] distribution. They return the layers of the
+//! [`latency`](crate::latency) and [`error`](crate::error) modules, which
+//! can then be configured with their usual methods.
+//!
+//! Migrating only requires replacing the imports:
+//!
+//! ```rust
+//! // Before: use tower_fault_injector::{error::ErrorLayer, latency::LatencyLayer};
+//! use tower_fault::compat::{ErrorLayer, LatencyLayer};
+//! # struct MyRequest;
+//! # fn main() -> Result<(), tower_fault::Error> {
+//!
+//! let latency_layer = LatencyLayer::new(0.1, 200..500)?;
+//! let error_layer = ErrorLayer::new(0.1, |_: &MyRequest| String::from("error"))?;
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! New code should use the layers of the [`latency`](crate::latency) and
+//! [`error`](crate::error) modules directly, which accept any decider.
+
+use crate::Error;
+use rand::distributions::Bernoulli;
+
+fn bernoulli(probability: f64) -> Result<Bernoulli, Error> {
+    Bernoulli::new(probability).map_err(|_| Error::InvalidProbability(probability))
+}
+
+/// Constructors for a [`LatencyLayer`](crate::latency::LatencyLayer)
+/// deciding with a Bernoulli distribution.
+#[cfg(any(feature = "latency", feature = "wasi"))]
+#[cfg_attr(docsrs, doc(cfg(any(feature = "latency", feature = "wasi"))))]
+#[derive(Debug)]
+pub enum LatencyLayer {}
+
+// The constructors return the layers of this crate, not the namespace.
+#[cfg(any(feature = "latency", feature = "wasi"))]
+#[allow(clippy::new_ret_no_self)]
+impl LatencyLayer {
+    /// Create a new `LatencyLayer` adding latency with the given
+    /// probability.
+    ///
+    /// Returns an [`Error`] if the probability is not between 0.0 and 1.0.
+    pub fn new<Di>(
+        probability: f64,
+        distribution: Di,
+    ) -> Result<crate::latency::LatencyLayer<Bernoulli, Di>, Error> {
+        Ok(Self::new_with_bernoulli(
+            bernoulli(probability)?,
+            distribution,
+        ))
+    }
+
+    /// Create a new `LatencyLayer` adding latency according to the given
+    /// Bernoulli distribution.
+    pub fn new_with_bernoulli<Di>(
+        bernoulli: Bernoulli,
+        distribution: Di,
+    ) -> crate::latency::LatencyLayer<Bernoulli, Di> {
+        crate::latency::LatencyLayer::new(bernoulli, distribution)
+    }
+}
+
+/// Constructors for an [`ErrorLayer`](crate::error::ErrorLayer) deciding
+/// with a Bernoulli distribution.
+#[cfg(feature = "error")]
+#[cfg_attr(docsrs, doc(cfg(feature = "error")))]
+#[derive(Debug)]
+pub enum ErrorLayer {}
+
+#[cfg(feature = "error")]
+#[allow(clippy::new_ret_no_self)]
+impl ErrorLayer {
+    /// Create a new `ErrorLayer` injecting errors with the given
+    /// probability.
+    ///
+    /// Returns an [`Error`] if the probability is not between 0.0 and 1.0.
+    pub fn new<G>(
+        probability: f64,
+        generator: G,
+    ) -> Result<crate::error::ErrorLayer<Bernoulli, G>, Error> {
+        Ok(Self::new_with_bernoulli(bernoulli(probability)?, generator))
+    }
+
+    /// Create a new `ErrorLayer` injecting errors according to the given
+    /// Bernoulli distribution.
+    pub fn new_with_bernoulli<G>(
+        bernoulli: Bernoulli,
+        generator: G,
+    ) -> crate::error::ErrorLayer<Bernoulli, G> {
+        crate::error::ErrorLayer::new(bernoulli, generator)
+    }
+}
+
+#[cfg(all(test, feature = "error", feature = "latency"))]
+mod tests {
+    use super::*;
+    use crate::test_utils::*;
+    use tower::{Layer, Service};
+
+    #[tokio::test]
+    async fn compat_constructors() {
+        assert_eq!(
+            LatencyLayer::new(1.5, 200..500).err(),
+            Some(Error::InvalidProbability(1.5))
+        );
+        assert!(ErrorLayer::new(-0.1, |_: &()| String::from("error")).is_err());
+
+        let layer = ErrorLayer::new(1.0, |_: &()| String::from("error")).unwrap();
+        assert_eq!(
+            layer.layer(DummyService).call(()).await.unwrap_err(),
+            "error"
+        );
+
+        let bernoulli = Bernoulli::new(0.0).unwrap();
+        let layer = LatencyLayer::new_with_bernoulli(bernoulli, 200..500);
+        assert_eq!(layer.layer(DummyService).call(()).await.unwrap(), "ok");
+    }
+}
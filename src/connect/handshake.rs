@@ -0,0 +1,270 @@
+use super::SelectFault;
+use crate::{decider::Decider, veto::Vetoed};
+use http::Uri;
+use std::{
+    future::Future,
+    io,
+    pin::Pin,
+    task::{ready, Context, Poll},
+    time::Duration,
+};
+use tokio::{
+    io::{AsyncRead, AsyncWrite, ReadBuf},
+    time::{self, Sleep},
+};
+use tower::{BoxError, Layer, Service};
+
+/// Fault injected between the connection being established and its first
+/// byte, where TLS handshakes happen.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum HandshakeFault {
+    /// The first read or write on the connection is delayed by the given
+    /// duration.
+    Delay(Duration),
+    /// The first read or write on the connection fails, as if the handshake
+    /// was aborted by the peer.
+    Abort,
+}
+
+impl SelectFault<HandshakeFault> for HandshakeFault {
+    fn select(&self, _uri: &Uri) -> HandshakeFault {
+        *self
+    }
+}
+
+/// Layer that randomly injects handshake faults into the connections
+/// returned by a connector.
+#[derive(Clone, Debug)]
+pub struct HandshakeFaultLayer<D, F> {
+    decider: D,
+    fault: F,
+}
+
+impl<D, F> HandshakeFaultLayer<D, F> {
+    /// Create a new `HandshakeFaultLayer` with the given decider and fault.
+    pub fn new(decider: D, fault: F) -> Self {
+        Self { decider, fault }
+    }
+
+    /// Never inject handshake faults into the connections to the URIs
+    /// vetoed by the given veto, regardless of the decider.
+    ///
+    /// See the [`veto`](crate::veto) module for more information.
+    pub fn with_veto<V>(self, veto: V) -> HandshakeFaultLayer<Vetoed<D, V>, F> {
+        HandshakeFaultLayer {
+            decider: Vetoed::new(self.decider, veto),
+            fault: self.fault,
+        }
+    }
+}
+
+impl<D, F, S> Layer<S> for HandshakeFaultLayer<D, F>
+where
+    D: Clone,
+    F: Clone,
+{
+    type Service = HandshakeFaultService<D, F, S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        HandshakeFaultService {
+            inner,
+            decider: self.decider.clone(),
+            fault: self.fault.clone(),
+        }
+    }
+}
+
+/// Connector that randomly injects handshake faults into its connections.
+#[derive(Clone, Debug)]
+pub struct HandshakeFaultService<D, F, S> {
+    inner: S,
+    decider: D,
+    fault: F,
+}
+
+impl<D, F, S> Service<Uri> for HandshakeFaultService<D, F, S>
+where
+    D: Decider<Uri>,
+    F: SelectFault<HandshakeFault>,
+    S: Service<Uri>,
+    S::Error: Into<BoxError>,
+    S::Future: Send + 'static,
+{
+    type Response = FaultConnection<S::Response>;
+    type Error = BoxError;
+    type Future = HandshakeFaultFuture<S::Response>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx).map_err(Into::into)
+    }
+
+    fn call(&mut self, uri: Uri) -> Self::Future {
+        let fault = if self.decider.decide(&uri) {
+            Some(self.fault.select(&uri))
+        } else {
+            None
+        };
+
+        let fut = self.inner.call(uri);
+        Box::pin(async move {
+            let inner = fut.await.map_err(Into::into)?;
+            Ok(FaultConnection::new(inner, fault))
+        })
+    }
+}
+
+type HandshakeFaultFuture<T> =
+    Pin<Box<dyn Future<Output = Result<FaultConnection<T>, BoxError>> + Send + 'static>>;
+
+/// Connection that injects a [`HandshakeFault`] on its first read or write.
+#[derive(Debug)]
+pub struct FaultConnection<T> {
+    inner: T,
+    state: State,
+}
+
+#[derive(Debug)]
+enum State {
+    Done,
+    Pending(Duration),
+    Delay(Pin<Box<Sleep>>),
+    Abort,
+}
+
+impl<T> FaultConnection<T> {
+    fn new(inner: T, fault: Option<HandshakeFault>) -> Self {
+        let state = match fault {
+            Some(HandshakeFault::Delay(delay)) => State::Pending(delay),
+            Some(HandshakeFault::Abort) => State::Abort,
+            None => State::Done,
+        };
+        Self { inner, state }
+    }
+
+    /// Returns a reference to the underlying connection.
+    pub fn get_ref(&self) -> &T {
+        &self.inner
+    }
+
+    fn poll_fault(&mut self, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        loop {
+            match &mut self.state {
+                State::Done => return Poll::Ready(Ok(())),
+                State::Pending(delay) => {
+                    // The delay starts on the first I/O operation.
+                    self.state = State::Delay(Box::pin(time::sleep(*delay)));
+                }
+                State::Delay(sleep) => {
+                    if sleep.as_mut().poll(cx).is_pending() {
+                        return Poll::Pending;
+                    }
+                    self.state = State::Done;
+                }
+                State::Abort => {
+                    return Poll::Ready(Err(io::Error::new(
+                        io::ErrorKind::ConnectionReset,
+                        "injected fault: handshake aborted",
+                    )))
+                }
+            }
+        }
+    }
+}
+
+impl<T> AsyncRead for FaultConnection<T>
+where
+    T: AsyncRead + Unpin,
+{
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        ready!(self.poll_fault(cx))?;
+        Pin::new(&mut self.inner).poll_read(cx, buf)
+    }
+}
+
+impl<T> AsyncWrite for FaultConnection<T>
+where
+    T: AsyncWrite + Unpin,
+{
+    fn poll_write(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        ready!(self.poll_fault(cx))?;
+        Pin::new(&mut self.inner).poll_write(cx, buf)
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.inner).poll_flush(cx)
+    }
+
+    fn poll_shutdown(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.inner).poll_shutdown(cx)
+    }
+}
+
+#[cfg(feature = "hyper")]
+impl<T> hyper::client::connect::Connection for FaultConnection<T>
+where
+    T: hyper::client::connect::Connection,
+{
+    fn connected(&self) -> hyper::client::connect::Connected {
+        self.inner.connected()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::io::{duplex, AsyncReadExt, AsyncWriteExt};
+    use tower::{service_fn, ServiceExt};
+
+    #[tokio::test]
+    async fn handshake_abort() {
+        let connector = HandshakeFaultLayer::new(true, HandshakeFault::Abort).layer(service_fn(
+            |_: Uri| async { Ok::<_, io::Error>(duplex(64).0) },
+        ));
+
+        let mut conn = connector
+            .oneshot(Uri::from_static("https://example.com"))
+            .await
+            .unwrap();
+        let err = conn.write_all(b"hello").await.unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::ConnectionReset);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn handshake_delay() {
+        let (client, mut server) = duplex(64);
+        let mut client = Some(client);
+        let connector =
+            HandshakeFaultLayer::new(true, HandshakeFault::Delay(Duration::from_secs(1))).layer(
+                service_fn(move |_: Uri| {
+                    let client = client.take();
+                    async move { client.ok_or_else(|| io::Error::from(io::ErrorKind::Other)) }
+                }),
+            );
+
+        let mut conn = connector
+            .oneshot(Uri::from_static("https://example.com"))
+            .await
+            .unwrap();
+
+        let start = time::Instant::now();
+        conn.write_all(b"hello").await.unwrap();
+        assert!(start.elapsed() >= Duration::from_secs(1));
+
+        // Only the first operation is delayed.
+        let start = time::Instant::now();
+        conn.write_all(b" world").await.unwrap();
+        assert_eq!(start.elapsed(), Duration::ZERO);
+
+        let mut buf = [0; 11];
+        server.read_exact(&mut buf).await.unwrap();
+        assert_eq!(&buf, b"hello world");
+    }
+}
@@ -0,0 +1,299 @@
+//! # Connector fault injection
+//!
+//! This module contains the [`ConnectFaultLayer`], which wraps connectors
+//! implementing `Service<Uri>`, such as `hyper`'s `HttpConnector`. It can
+//! inject faults that request-level layers can't express, such as name
+//! resolution failures, refused connections, connect timeouts, and slow
+//! connects.
+//!
+//! The [`HandshakeFaultLayer`] injects faults between the connection being
+//! established and its first byte, to emulate slow or failed TLS handshakes.
+//! With the `hyper` feature, the wrapped connections implement `hyper`'s
+//! `Connection` trait.
+//!
+//! The [`TrickleLayer`] makes connections write extremely slowly, in the
+//! style of slow-loris attacks, to verify the read timeouts of a server from
+//! its clients.
+//!
+//! Errors returned by the connectors are boxed, which is what `hyper`
+//! expects from connectors.
+//!
+//! ## Usage
+//!
+//! ```rust
+//! use std::time::Duration;
+//! use tower::Layer;
+//! use tower_fault::connect::{ConnectFault, ConnectFaultLayer};
+//! # #[derive(Clone)]
+//! # struct HttpConnector;
+//! # let connector = HttpConnector;
+//!
+//! // Fail 1% of the name resolutions.
+//! let connector = ConnectFaultLayer::new(0.01, ConnectFault::Resolve).layer(connector);
+//!
+//! // Slow down connections to a specific host.
+//! let connector = ConnectFaultLayer::new(
+//!     |uri: &http::Uri| uri.host() == Some("db.internal"),
+//!     ConnectFault::Slow(Duration::from_millis(300)),
+//! )
+//! .layer(connector);
+//! ```
+//!
+//! ### Handshake faults
+//!
+//! ```rust
+//! use std::time::Duration;
+//! use tower::Layer;
+//! use tower_fault::connect::{HandshakeFault, HandshakeFaultLayer};
+//! # #[derive(Clone)]
+//! # struct HttpsConnector;
+//! # let connector = HttpsConnector;
+//!
+//! // Delay the first byte of 10% of the connections by 200 milliseconds.
+//! let connector = HandshakeFaultLayer::new(0.1, HandshakeFault::Delay(Duration::from_millis(200)))
+//!     .layer(connector);
+//! ```
+//!
+//! ### Slow requests
+//!
+//! ```rust
+//! use std::time::Duration;
+//! use tower::Layer;
+//! use tower_fault::connect::{Trickle, TrickleLayer};
+//! # #[derive(Clone)]
+//! # struct HttpConnector;
+//! # let connector = HttpConnector;
+//!
+//! // Send the first 200 bytes at full speed, then 1 byte every 5 seconds.
+//! let connector = TrickleLayer::new(true, Trickle::new(1, Duration::from_secs(5)).after(200))
+//!     .layer(connector);
+//! ```
+
+use crate::{decider::Decider, veto::Vetoed};
+use http::Uri;
+use std::{
+    future::Future,
+    io,
+    pin::Pin,
+    task::{Context, Poll},
+    time::Duration,
+};
+use tokio::time;
+use tower::{BoxError, Layer, Service};
+
+mod handshake;
+mod trickle;
+pub use handshake::{FaultConnection, HandshakeFault, HandshakeFaultLayer, HandshakeFaultService};
+pub use trickle::{Trickle, TrickleConnection, TrickleLayer, TrickleService};
+
+/// Fault injected when establishing a connection.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ConnectFault {
+    /// The name resolution fails immediately.
+    Resolve,
+    /// The connection is refused immediately.
+    Refused,
+    /// The connection attempt times out after the given duration.
+    Timeout(Duration),
+    /// The connection is established after an additional delay.
+    Slow(Duration),
+}
+
+impl ConnectFault {
+    fn error(&self) -> Option<io::Error> {
+        match self {
+            ConnectFault::Resolve => Some(io::Error::new(
+                io::ErrorKind::NotFound,
+                "injected fault: failed to lookup address information",
+            )),
+            ConnectFault::Refused => Some(io::Error::new(
+                io::ErrorKind::ConnectionRefused,
+                "injected fault: connection refused",
+            )),
+            ConnectFault::Timeout(_) => Some(io::Error::new(
+                io::ErrorKind::TimedOut,
+                "injected fault: connection timed out",
+            )),
+            ConnectFault::Slow(_) => None,
+        }
+    }
+
+    fn delay(&self) -> Duration {
+        match self {
+            ConnectFault::Timeout(delay) | ConnectFault::Slow(delay) => *delay,
+            ConnectFault::Resolve | ConnectFault::Refused => Duration::ZERO,
+        }
+    }
+}
+
+/// Trait that selects the fault to inject for a given URI.
+///
+/// This is implemented for [`ConnectFault`] and [`HandshakeFault`]
+/// themselves, and for closures returning a fault.
+pub trait SelectFault<T> {
+    /// Returns the fault to inject when connecting to the given URI.
+    fn select(&self, uri: &Uri) -> T;
+}
+
+impl SelectFault<ConnectFault> for ConnectFault {
+    fn select(&self, _uri: &Uri) -> ConnectFault {
+        *self
+    }
+}
+
+impl<F, T> SelectFault<T> for F
+where
+    F: Fn(&Uri) -> T,
+{
+    fn select(&self, uri: &Uri) -> T {
+        self(uri)
+    }
+}
+
+/// Layer that randomly injects faults into a connector.
+#[derive(Clone, Debug)]
+pub struct ConnectFaultLayer<D, F> {
+    decider: D,
+    fault: F,
+}
+
+impl<D, F> ConnectFaultLayer<D, F> {
+    /// Create a new `ConnectFaultLayer` with the given decider and fault.
+    pub fn new(decider: D, fault: F) -> Self {
+        Self { decider, fault }
+    }
+
+    /// Never inject faults into the connections to the URIs vetoed by the
+    /// given veto, regardless of the decider.
+    ///
+    /// See the [`veto`](crate::veto) module for more information.
+    pub fn with_veto<V>(self, veto: V) -> ConnectFaultLayer<Vetoed<D, V>, F> {
+        ConnectFaultLayer {
+            decider: Vetoed::new(self.decider, veto),
+            fault: self.fault,
+        }
+    }
+}
+
+impl<D, F, S> Layer<S> for ConnectFaultLayer<D, F>
+where
+    D: Clone,
+    F: Clone,
+{
+    type Service = ConnectFaultService<D, F, S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        ConnectFaultService {
+            inner,
+            decider: self.decider.clone(),
+            fault: self.fault.clone(),
+        }
+    }
+}
+
+/// Connector that randomly injects connection faults.
+#[derive(Clone, Debug)]
+pub struct ConnectFaultService<D, F, S> {
+    inner: S,
+    decider: D,
+    fault: F,
+}
+
+impl<D, F, S> Service<Uri> for ConnectFaultService<D, F, S>
+where
+    D: Decider<Uri>,
+    F: SelectFault<ConnectFault>,
+    S: Service<Uri>,
+    S::Error: Into<BoxError>,
+    S::Future: Send + 'static,
+{
+    type Response = S::Response;
+    type Error = BoxError;
+    type Future = ConnectFaultFuture<S::Response>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx).map_err(Into::into)
+    }
+
+    fn call(&mut self, uri: Uri) -> Self::Future {
+        if !self.decider.decide(&uri) {
+            let fut = self.inner.call(uri);
+            return Box::pin(async move { fut.await.map_err(Into::into) });
+        }
+
+        let fault = self.fault.select(&uri);
+        match fault.error() {
+            Some(error) => {
+                let delay = fault.delay();
+                Box::pin(async move {
+                    time::sleep(delay).await;
+                    Err(error.into())
+                })
+            }
+            None => {
+                let delay = fault.delay();
+                let fut = self.inner.call(uri);
+                Box::pin(async move {
+                    time::sleep(delay).await;
+                    fut.await.map_err(Into::into)
+                })
+            }
+        }
+    }
+}
+
+type ConnectFaultFuture<T> = Pin<Box<dyn Future<Output = Result<T, BoxError>> + Send + 'static>>;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tower::{service_fn, ServiceExt};
+
+    fn connector(
+    ) -> impl Service<Uri, Response = &'static str, Error = io::Error, Future = impl Send> {
+        service_fn(|_: Uri| async { Ok::<_, io::Error>("connection") })
+    }
+
+    #[tokio::test]
+    async fn connect_resolve_failure() {
+        let connector = ConnectFaultLayer::new(true, ConnectFault::Resolve).layer(connector());
+
+        let err = connector
+            .oneshot(Uri::from_static("http://example.com"))
+            .await
+            .unwrap_err();
+        let err = err.downcast::<io::Error>().unwrap();
+        assert_eq!(err.kind(), io::ErrorKind::NotFound);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn connect_slow() {
+        let connector = ConnectFaultLayer::new(
+            |uri: &Uri| uri.host() == Some("slow.example.com"),
+            ConnectFault::Slow(Duration::from_secs(1)),
+        )
+        .layer(connector());
+
+        let mut connector = connector;
+        let start = time::Instant::now();
+        let conn = connector
+            .ready()
+            .await
+            .unwrap()
+            .call(Uri::from_static("http://fast.example.com"))
+            .await
+            .unwrap();
+        assert_eq!(conn, "connection");
+        assert!(start.elapsed() < Duration::from_secs(1));
+
+        let conn = connector
+            .ready()
+            .await
+            .unwrap()
+            .call(Uri::from_static("http://slow.example.com"))
+            .await
+            .unwrap();
+        assert_eq!(conn, "connection");
+        assert!(start.elapsed() >= Duration::from_secs(1));
+    }
+}
@@ -0,0 +1,248 @@
+use crate::{decider::Decider, veto::Vetoed};
+use http::Uri;
+use std::{
+    future::Future,
+    io,
+    pin::Pin,
+    task::{Context, Poll},
+    time::Duration,
+};
+use tokio::{
+    io::{AsyncRead, AsyncWrite, ReadBuf},
+    time::{self, Sleep},
+};
+use tower::{BoxError, Layer, Service};
+
+/// Rate at which a connection writes its bytes.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Trickle {
+    bytes: usize,
+    interval: Duration,
+    after: u64,
+}
+
+impl Trickle {
+    /// Write at most the given number of bytes, then wait for the interval
+    /// before the next write.
+    pub fn new(bytes: usize, interval: Duration) -> Self {
+        Self {
+            bytes: bytes.max(1),
+            interval,
+            after: 0,
+        }
+    }
+
+    /// Write the given number of bytes at full speed before trickling.
+    ///
+    /// This can skip the request line and headers, to only slow down the
+    /// body.
+    pub fn after(mut self, bytes: u64) -> Self {
+        self.after = bytes;
+        self
+    }
+}
+
+/// Layer that makes the connections returned by a connector write
+/// extremely slowly, in the style of slow-loris attacks.
+///
+/// This is meant for clients testing servers: it verifies that the server
+/// enforces its header and body read timeouts.
+#[derive(Clone, Debug)]
+pub struct TrickleLayer<D> {
+    decider: D,
+    trickle: Trickle,
+}
+
+impl<D> TrickleLayer<D> {
+    /// Create a new `TrickleLayer` with the given decider and rate.
+    pub fn new(decider: D, trickle: Trickle) -> Self {
+        Self { decider, trickle }
+    }
+
+    /// Never trickle the connections to the URIs vetoed by the given veto,
+    /// regardless of the decider.
+    ///
+    /// See the [`veto`](crate::veto) module for more information.
+    pub fn with_veto<V>(self, veto: V) -> TrickleLayer<Vetoed<D, V>> {
+        TrickleLayer {
+            decider: Vetoed::new(self.decider, veto),
+            trickle: self.trickle,
+        }
+    }
+}
+
+impl<D, S> Layer<S> for TrickleLayer<D>
+where
+    D: Clone,
+{
+    type Service = TrickleService<D, S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        TrickleService {
+            inner,
+            decider: self.decider.clone(),
+            trickle: self.trickle,
+        }
+    }
+}
+
+/// Connector that makes some of its connections write extremely slowly.
+#[derive(Clone, Debug)]
+pub struct TrickleService<D, S> {
+    inner: S,
+    decider: D,
+    trickle: Trickle,
+}
+
+impl<D, S> Service<Uri> for TrickleService<D, S>
+where
+    D: Decider<Uri>,
+    S: Service<Uri>,
+    S::Error: Into<BoxError>,
+    S::Future: Send + 'static,
+{
+    type Response = TrickleConnection<S::Response>;
+    type Error = BoxError;
+    type Future = TrickleFuture<S::Response>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx).map_err(Into::into)
+    }
+
+    fn call(&mut self, uri: Uri) -> Self::Future {
+        let trickle = self.decider.decide(&uri).then_some(self.trickle);
+
+        let fut = self.inner.call(uri);
+        Box::pin(async move {
+            let inner = fut.await.map_err(Into::into)?;
+            Ok(TrickleConnection {
+                inner,
+                trickle,
+                written: 0,
+                sleep: None,
+            })
+        })
+    }
+}
+
+type TrickleFuture<T> =
+    Pin<Box<dyn Future<Output = Result<TrickleConnection<T>, BoxError>> + Send + 'static>>;
+
+/// Connection that writes its bytes at a [`Trickle`] rate.
+#[derive(Debug)]
+pub struct TrickleConnection<T> {
+    inner: T,
+    trickle: Option<Trickle>,
+    written: u64,
+    sleep: Option<Pin<Box<Sleep>>>,
+}
+
+impl<T> TrickleConnection<T> {
+    /// Returns a reference to the underlying connection.
+    pub fn get_ref(&self) -> &T {
+        &self.inner
+    }
+}
+
+impl<T> AsyncRead for TrickleConnection<T>
+where
+    T: AsyncRead + Unpin,
+{
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.inner).poll_read(cx, buf)
+    }
+}
+
+impl<T> AsyncWrite for TrickleConnection<T>
+where
+    T: AsyncWrite + Unpin,
+{
+    fn poll_write(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        let this = &mut *self;
+        let trickle = match this.trickle {
+            Some(trickle) => trickle,
+            None => return Pin::new(&mut this.inner).poll_write(cx, buf),
+        };
+
+        let trickling = this.written >= trickle.after;
+        let len = if trickling {
+            if let Some(sleep) = this.sleep.as_mut() {
+                if sleep.as_mut().poll(cx).is_pending() {
+                    return Poll::Pending;
+                }
+                this.sleep = None;
+            }
+            buf.len().min(trickle.bytes)
+        } else {
+            let remaining = usize::try_from(trickle.after - this.written).unwrap_or(usize::MAX);
+            buf.len().min(remaining)
+        };
+
+        let res = Pin::new(&mut this.inner).poll_write(cx, &buf[..len]);
+        if let Poll::Ready(Ok(written)) = res {
+            this.written += written as u64;
+            if trickling {
+                this.sleep = Some(Box::pin(time::sleep(trickle.interval)));
+            }
+        }
+        res
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.inner).poll_flush(cx)
+    }
+
+    fn poll_shutdown(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.inner).poll_shutdown(cx)
+    }
+}
+
+#[cfg(feature = "hyper")]
+impl<T> hyper::client::connect::Connection for TrickleConnection<T>
+where
+    T: hyper::client::connect::Connection,
+{
+    fn connected(&self) -> hyper::client::connect::Connected {
+        self.inner.connected()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::io::{duplex, AsyncReadExt, AsyncWriteExt};
+    use tower::{service_fn, ServiceExt};
+
+    #[tokio::test(start_paused = true)]
+    async fn trickle() {
+        let (client, mut server) = duplex(64);
+        let mut client = Some(client);
+        let connector = TrickleLayer::new(true, Trickle::new(2, Duration::from_secs(1)).after(3))
+            .layer(service_fn(move |_: Uri| {
+                let client = client.take();
+                async move { client.ok_or_else(|| io::Error::from(io::ErrorKind::Other)) }
+            }));
+
+        let mut conn = connector
+            .oneshot(Uri::from_static("http://example.com"))
+            .await
+            .unwrap();
+
+        // The first 3 bytes are written immediately, then 2 bytes per second.
+        let start = time::Instant::now();
+        conn.write_all(b"hello world").await.unwrap();
+        assert_eq!(start.elapsed(), Duration::from_secs(3));
+
+        let mut buf = [0; 11];
+        server.read_exact(&mut buf).await.unwrap();
+        assert_eq!(&buf, b"hello world");
+    }
+}
@@ -0,0 +1,155 @@
+//! # Fleet-wide control
+//!
+//! A [`FaultRegistry`](crate::registry::FaultRegistry) only controls the
+//! faults of the current process. For a horizontally scaled service, a
+//! [`ControlTransport`] broadcasts the changes made on one replica, such as
+//! a new probability, to all the other replicas, keeping experiments
+//! consistent fleet-wide.
+//!
+//! Once a transport is set with
+//! [`FaultRegistry::set_transport`](crate::registry::FaultRegistry::set_transport),
+//! the registry publishes a [`ControlMessage`] every time a fault is enabled,
+//! disabled, paused, resumed, or its probability or severity changes, and
+//! every time the kill switch or the maximum severity changes. The transport delivers the messages
+//! published by the other replicas to
+//! [`FaultRegistry::apply`](crate::registry::FaultRegistry::apply).
+//!
+//! Applying a message is idempotent, so transports can deliver the messages
+//! published by the current replica back to it.
+//!
+//! ## Custom transport
+//!
+//! ```rust
+//! use tower_fault::{
+//!     control::{ControlMessage, ControlTransport},
+//!     registry::FaultRegistry,
+//! };
+//!
+//! // Transport forwarding the messages to another registry in the same
+//! // process.
+//! struct Mirror(FaultRegistry);
+//!
+//! impl ControlTransport for Mirror {
+//!     fn publish(&self, message: &ControlMessage) {
+//!         self.0.apply(message);
+//!     }
+//! }
+//!
+//! let (registry, replica) = (FaultRegistry::new(), FaultRegistry::new());
+//! registry.register("db-latency", 0.1);
+//! replica.register("db-latency", 0.1);
+//! registry.set_transport(Mirror(replica.clone()));
+//!
+//! registry.get("db-latency").unwrap().set_probability(0.5);
+//! assert_eq!(replica.get("db-latency").unwrap().probability(), 0.5);
+//! ```
+//!
+//! ## Redis
+//!
+//! With the `redis` feature, [`RedisTransport`] broadcasts the messages
+//! through Redis pub/sub.
+
+use crate::registry::Severity;
+use std::{
+    fmt,
+    sync::{Arc, RwLock},
+};
+
+#[cfg(feature = "redis")]
+#[cfg_attr(docsrs, doc(cfg(feature = "redis")))]
+mod redis;
+#[cfg(feature = "redis")]
+#[cfg_attr(docsrs, doc(cfg(feature = "redis")))]
+pub use self::redis::RedisTransport;
+
+/// Change made to a [`FaultRegistry`](crate::registry::FaultRegistry),
+/// broadcast to the other replicas.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(tag = "type", rename_all = "snake_case"))]
+#[non_exhaustive]
+pub enum ControlMessage {
+    /// Enable a fault.
+    Enable {
+        /// Name of the fault.
+        fault: String,
+    },
+    /// Disable a fault.
+    Disable {
+        /// Name of the fault.
+        fault: String,
+    },
+    /// Pause a fault.
+    Pause {
+        /// Name of the fault.
+        fault: String,
+    },
+    /// Resume a paused fault.
+    Resume {
+        /// Name of the fault.
+        fault: String,
+    },
+    /// Set the probability of injecting a fault.
+    SetProbability {
+        /// Name of the fault.
+        fault: String,
+        /// New probability.
+        probability: f64,
+    },
+    /// Engage or release the kill switch.
+    KillSwitch {
+        /// Whether the kill switch is engaged.
+        engaged: bool,
+    },
+    /// Set the severity of a fault.
+    SetSeverity {
+        /// Name of the fault.
+        fault: String,
+        /// New severity.
+        severity: Severity,
+    },
+    /// Set the maximum severity of the faults allowed to inject.
+    MaxSeverity {
+        /// New maximum severity.
+        severity: Severity,
+    },
+}
+
+/// Transport broadcasting [`ControlMessage`]s to the other replicas.
+///
+/// Implementations deliver the messages they receive to
+/// [`FaultRegistry::apply`](crate::registry::FaultRegistry::apply).
+pub trait ControlTransport: Send + Sync {
+    /// Publish a message to the other replicas.
+    ///
+    /// This is called while changing the settings of a fault, so it should
+    /// not block.
+    fn publish(&self, message: &ControlMessage);
+}
+
+/// Transport shared by a registry, its faults and its kill switch.
+#[derive(Clone, Default)]
+pub(crate) struct SharedTransport(Arc<RwLock<Option<Arc<dyn ControlTransport>>>>);
+
+impl SharedTransport {
+    pub(crate) fn set(&self, transport: Arc<dyn ControlTransport>) {
+        let mut current = self.0.write().unwrap_or_else(|e| e.into_inner());
+        *current = Some(transport);
+    }
+
+    pub(crate) fn publish(&self, message: ControlMessage) {
+        let current = self.0.read().unwrap_or_else(|e| e.into_inner());
+        if let Some(transport) = current.as_ref() {
+            transport.publish(&message);
+        }
+    }
+}
+
+impl fmt::Debug for SharedTransport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let current = self.0.read().unwrap_or_else(|e| e.into_inner());
+        f.debug_tuple("SharedTransport")
+            .field(&current.is_some())
+            .finish()
+    }
+}
@@ -0,0 +1,100 @@
+use super::{ControlMessage, ControlTransport};
+use crate::registry::FaultRegistry;
+use ::redis::{aio::MultiplexedConnection, Client, RedisResult};
+use futures_core::Stream;
+use std::{fmt, future::poll_fn};
+use tokio::sync::mpsc;
+
+/// [`ControlTransport`] broadcasting the changes through Redis pub/sub.
+///
+/// Messages are published as JSON on a Redis channel, and the messages
+/// received on that channel are applied to the registry. All the replicas
+/// must use the same channel.
+///
+/// Publishing and receiving run in background tasks, so this requires a
+/// Tokio runtime.
+///
+/// ```rust,no_run
+/// use tower_fault::{control::RedisTransport, registry::FaultRegistry};
+///
+/// # async fn run() -> Result<(), redis::RedisError> {
+/// let registry = FaultRegistry::new();
+/// let transport =
+///     RedisTransport::connect("redis://127.0.0.1/", "tower-fault", registry.clone()).await?;
+/// registry.set_transport(transport);
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone, Debug)]
+pub struct RedisTransport {
+    sender: mpsc::UnboundedSender<String>,
+}
+
+impl RedisTransport {
+    /// Connect to the Redis server at the given URL, and apply the messages
+    /// published on the channel to the registry.
+    pub async fn connect(
+        url: &str,
+        channel: impl Into<String>,
+        registry: FaultRegistry,
+    ) -> RedisResult<Self> {
+        let client = Client::open(url)?;
+        let channel = channel.into();
+        let publisher = client.get_multiplexed_tokio_connection().await?;
+        let mut pubsub = client.get_async_connection().await?.into_pubsub();
+        pubsub.subscribe(&channel).await?;
+
+        let (sender, receiver) = mpsc::unbounded_channel();
+        tokio::spawn(publish(publisher, channel, receiver));
+        tokio::spawn(async move {
+            let mut messages = Box::pin(pubsub.into_on_message());
+            while let Some(msg) = poll_fn(|cx| messages.as_mut().poll_next(cx)).await {
+                match serde_json::from_slice::<ControlMessage>(msg.get_payload_bytes()) {
+                    Ok(message) => registry.apply(&message),
+                    Err(err) => report(err),
+                }
+            }
+        });
+
+        Ok(Self { sender })
+    }
+}
+
+impl ControlTransport for RedisTransport {
+    fn publish(&self, message: &ControlMessage) {
+        match serde_json::to_string(message) {
+            // The publishing task only stops if the connection is closed.
+            Ok(payload) => {
+                let _ = self.sender.send(payload);
+            }
+            Err(err) => report(err),
+        }
+    }
+}
+
+async fn publish(
+    mut connection: MultiplexedConnection,
+    channel: String,
+    mut receiver: mpsc::UnboundedReceiver<String>,
+) {
+    while let Some(payload) = receiver.recv().await {
+        let result: RedisResult<()> = ::redis::cmd("PUBLISH")
+            .arg(&channel)
+            .arg(payload)
+            .query_async(&mut connection)
+            .await;
+        if let Err(err) = result {
+            report(err);
+        }
+    }
+}
+
+/// Report an error of the transport.
+fn report(_error: impl fmt::Display) {
+    #[cfg(feature = "tracing")]
+    tracing::warn!(
+        target: "tower_fault",
+        error = %_error,
+        "control transport error"
+    );
+}
@@ -0,0 +1,339 @@
+//! # Fault coverage
+//!
+//! A [`Coverage`] tracks which classes of requests, such as routes or
+//! request kinds, received each kind of fault. Its report proves that a
+//! chaos suite exercised every critical route at least once.
+//!
+//! The classes are returned by a classification closure. Deciders wrapped
+//! with [`Coverage::track`] record the requests they evaluated and the
+//! faults they approved, keyed by the class of the request and the kind of
+//! fault. A single `Coverage` can track the deciders of several layers.
+//!
+//! Classes that must be covered are declared with [`Coverage::expect`], and
+//! reported by [`CoverageReport::uncovered`] until they received a fault.
+//!
+//! ## Example
+//!
+//! ```rust
+//! use tower_fault::{coverage::Coverage, error::ErrorLayer, latency::LatencyLayer};
+//! # struct MyRequest { path: String }
+//!
+//! let coverage = Coverage::new(|req: &MyRequest| req.path.clone())
+//!     .expect(String::from("/checkout"))
+//!     .expect(String::from("/search"));
+//!
+//! let latency_layer = LatencyLayer::new(coverage.track("latency", 0.1), 200..500);
+//! let error_layer = ErrorLayer::new(
+//!     coverage.track("error", 0.1),
+//!     |_: &MyRequest| String::from("error"),
+//! );
+//!
+//! // ... run the chaos suite ...
+//!
+//! let report = coverage.report();
+//! assert!(report.uncovered().contains(&&String::from("/checkout")));
+//! ```
+//!
+//! With the `serde` feature, the [`CoverageReport`] can be serialized to
+//! export it.
+//!
+//! ## Approved faults
+//!
+//! The coverage records the faults approved by the decider. Layers don't
+//! call their decider when they are disabled or not armed, but do in
+//! dry-run and shadow modes, where approved faults are reported without
+//! being applied.
+
+use crate::{
+    decider::{Decider, Explanation, Verdict},
+    describe::{DeciderDescription, DescribeDecider},
+    validate::ValidateDecider,
+    Error,
+};
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fmt,
+    sync::{Arc, Mutex, MutexGuard},
+};
+
+/// Tracker of the classes of requests that received each kind of fault.
+///
+/// Cloning the tracker is cheap, and clones share the same coverage.
+pub struct Coverage<F, K> {
+    classify: Arc<F>,
+    state: Arc<Mutex<CoverageReport<K>>>,
+}
+
+impl<F, K> Coverage<F, K>
+where
+    K: Ord,
+{
+    /// Create a new `Coverage` with the given classifier.
+    pub fn new(classify: F) -> Self {
+        Self {
+            classify: Arc::new(classify),
+            state: Arc::new(Mutex::new(CoverageReport::default())),
+        }
+    }
+
+    /// Declare a class of requests that must receive a fault.
+    pub fn expect(self, class: K) -> Self {
+        self.lock().expected.insert(class);
+        self
+    }
+
+    /// Wrap the decider of a layer injecting the given kind of fault, to
+    /// record the requests it evaluates and the faults it approves.
+    pub fn track<D>(&self, fault: &'static str, decider: D) -> Covered<D, F, K> {
+        Covered {
+            decider,
+            fault,
+            coverage: self.clone(),
+        }
+    }
+
+    /// Returns the coverage recorded so far.
+    pub fn report(&self) -> CoverageReport<K>
+    where
+        K: Clone,
+    {
+        self.lock().clone()
+    }
+
+    /// Reset the recorded coverage.
+    ///
+    /// The expected classes are kept.
+    pub fn reset(&self) {
+        self.lock().classes.clear();
+    }
+
+    fn record<R>(&self, fault: &'static str, req: &R, injected: bool)
+    where
+        F: Fn(&R) -> K,
+    {
+        let class = (self.classify)(req);
+        let mut state = self.lock();
+        let coverage = state
+            .classes
+            .entry(class)
+            .or_default()
+            .faults
+            .entry(fault)
+            .or_default();
+        coverage.evaluated += 1;
+        if injected {
+            coverage.injected += 1;
+        }
+    }
+
+    fn lock(&self) -> MutexGuard<'_, CoverageReport<K>> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+impl<F, K> Clone for Coverage<F, K> {
+    fn clone(&self) -> Self {
+        Self {
+            classify: self.classify.clone(),
+            state: self.state.clone(),
+        }
+    }
+}
+
+impl<F, K> fmt::Debug for Coverage<F, K>
+where
+    K: fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Coverage")
+            .field("state", &self.state)
+            .finish()
+    }
+}
+
+/// Decider recording its decisions into a [`Coverage`].
+///
+/// Created with [`Coverage::track`].
+#[derive(Clone, Debug)]
+pub struct Covered<D, F, K> {
+    decider: D,
+    fault: &'static str,
+    coverage: Coverage<F, K>,
+}
+
+impl<D, F, K, R> Decider<R> for Covered<D, F, K>
+where
+    D: Decider<R>,
+    F: Fn(&R) -> K,
+    K: Ord,
+{
+    fn decide(&self, req: &R) -> bool {
+        let decision = self.decider.decide(req);
+        self.coverage.record(self.fault, req, decision);
+        decision
+    }
+
+    fn verdict(&self, req: &R) -> Verdict {
+        let verdict = self.decider.verdict(req);
+        self.coverage
+            .record(self.fault, req, verdict == Verdict::Inject);
+        verdict
+    }
+
+    fn explain(&self, req: &R, explanation: &mut Explanation) -> Verdict {
+        let verdict = self.decider.explain(req, explanation);
+        self.coverage
+            .record(self.fault, req, verdict == Verdict::Inject);
+        verdict
+    }
+}
+
+impl<D, F, K> ValidateDecider for Covered<D, F, K>
+where
+    D: ValidateDecider,
+{
+    fn validate_decider(&self) -> Result<(), Error> {
+        self.decider.validate_decider()
+    }
+}
+
+impl<D, F, K> DescribeDecider for Covered<D, F, K>
+where
+    D: DescribeDecider,
+{
+    fn describe_decider(&self) -> DeciderDescription {
+        self.decider.describe_decider()
+    }
+}
+
+/// Coverage recorded by a [`Coverage`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct CoverageReport<K> {
+    classes: BTreeMap<K, ClassCoverage>,
+    expected: BTreeSet<K>,
+}
+
+impl<K> Default for CoverageReport<K> {
+    fn default() -> Self {
+        Self {
+            classes: BTreeMap::new(),
+            expected: BTreeSet::new(),
+        }
+    }
+}
+
+impl<K> CoverageReport<K>
+where
+    K: Ord,
+{
+    /// Returns the coverage of each class of requests that was evaluated.
+    pub fn classes(&self) -> impl Iterator<Item = (&K, &ClassCoverage)> {
+        self.classes.iter()
+    }
+
+    /// Returns the coverage of the given class of requests, if it was
+    /// evaluated.
+    pub fn class(&self, class: &K) -> Option<&ClassCoverage> {
+        self.classes.get(class)
+    }
+
+    /// Returns whether the given class of requests received any fault.
+    pub fn is_covered(&self, class: &K) -> bool {
+        self.class(class).is_some_and(ClassCoverage::is_covered)
+    }
+
+    /// Returns the expected or evaluated classes of requests that never
+    /// received a fault.
+    pub fn uncovered(&self) -> Vec<&K> {
+        let mut uncovered = self
+            .expected
+            .iter()
+            .chain(self.classes.keys())
+            .filter(|class| !self.is_covered(class))
+            .collect::<Vec<_>>();
+        uncovered.sort();
+        uncovered.dedup();
+        uncovered
+    }
+
+    /// Returns whether every expected class of requests received a fault.
+    pub fn is_complete(&self) -> bool {
+        self.expected.iter().all(|class| self.is_covered(class))
+    }
+}
+
+/// Coverage of a class of requests, in a [`CoverageReport`].
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct ClassCoverage {
+    faults: BTreeMap<&'static str, FaultCoverage>,
+}
+
+impl ClassCoverage {
+    /// Returns the coverage of each kind of fault for this class.
+    pub fn faults(&self) -> impl Iterator<Item = (&'static str, FaultCoverage)> + '_ {
+        self.faults
+            .iter()
+            .map(|(fault, coverage)| (*fault, *coverage))
+    }
+
+    /// Returns the coverage of the given kind of fault for this class.
+    pub fn fault(&self, fault: &str) -> FaultCoverage {
+        self.faults.get(fault).copied().unwrap_or_default()
+    }
+
+    /// Returns whether this class received any fault.
+    pub fn is_covered(&self) -> bool {
+        self.faults.values().any(|coverage| coverage.injected > 0)
+    }
+}
+
+/// Coverage of a kind of fault for a class of requests, in a
+/// [`CoverageReport`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct FaultCoverage {
+    /// Number of requests evaluated by the decider.
+    pub evaluated: u64,
+    /// Number of requests for which the decider approved the fault.
+    pub injected: u64,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn coverage_classes() {
+        let coverage = Coverage::new(|req: &u32| req % 3).expect(0).expect(1);
+        let error = coverage.track("error", |req: &u32| *req == 3);
+        let latency = coverage.track("latency", false);
+
+        for req in 0..6 {
+            error.decide(&req);
+            assert_eq!(latency.verdict(&req), Verdict::Pass);
+        }
+
+        let report = coverage.report();
+        let class = report.class(&0).unwrap();
+        assert_eq!(
+            class.fault("error"),
+            FaultCoverage {
+                evaluated: 2,
+                injected: 1
+            }
+        );
+        assert_eq!(class.fault("latency").injected, 0);
+        assert_eq!(class.fault("other"), FaultCoverage::default());
+        assert_eq!(class.faults().count(), 2);
+        assert!(report.is_covered(&0));
+        assert_eq!(report.uncovered(), vec![&1, &2]);
+        assert!(!report.is_complete());
+
+        coverage.reset();
+        let report = coverage.report();
+        assert_eq!(report.classes().count(), 0);
+        assert_eq!(report.uncovered(), vec![&0, &1]);
+    }
+}
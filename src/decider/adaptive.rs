@@ -0,0 +1,89 @@
+use super::Decider;
+use crate::{
+    describe::{percent, DeciderDescription, DescribeDecider},
+    validate::ValidateDecider,
+    Error,
+};
+use rand::Rng;
+use tokio::sync::watch;
+
+/// Decider that adapts its probability to the health of the service.
+///
+/// The decider reads the real error rate of the service, between 0.0 and
+/// 1.0, from a watch channel fed by the user, for example from metrics. It
+/// injects faults so that the combined failure rate stays near the target,
+/// and stops injecting once the real error rate reaches the target. This
+/// makes it safe to run continuous low-grade chaos in staging.
+#[derive(Clone, Debug)]
+pub struct Adaptive {
+    target: f64,
+    error_rate: watch::Receiver<f64>,
+}
+
+impl Adaptive {
+    /// Create a new `Adaptive` decider keeping the combined failure rate near
+    /// the given target.
+    pub fn new(target: f64, error_rate: watch::Receiver<f64>) -> Self {
+        Self { target, error_rate }
+    }
+
+    /// Returns the current probability of injecting a fault.
+    pub fn probability(&self) -> f64 {
+        let observed = *self.error_rate.borrow();
+        if observed.is_nan() || observed >= self.target || observed >= 1.0 {
+            return 0.0;
+        }
+        // Faults are injected in requests that would have succeeded, so the
+        // combined rate is `observed + (1 - observed) * probability`.
+        ((self.target - observed.max(0.0)) / (1.0 - observed.max(0.0))).clamp(0.0, 1.0)
+    }
+}
+
+impl<R> Decider<R> for Adaptive {
+    fn decide(&self, _req: &R) -> bool {
+        crate::seed::rng().gen_bool(self.probability())
+    }
+}
+
+impl ValidateDecider for Adaptive {
+    fn validate_decider(&self) -> Result<(), Error> {
+        if (0.0..=1.0).contains(&self.target) {
+            Ok(())
+        } else {
+            Err(Error::InvalidProbability(self.target))
+        }
+    }
+}
+
+impl DescribeDecider for Adaptive {
+    fn describe_decider(&self) -> DeciderDescription {
+        DeciderDescription::new(
+            format!("adaptive (target {}%)", percent(self.target)),
+            Some(self.probability()),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn adaptive_backs_off() {
+        let (sender, receiver) = watch::channel(0.0);
+        let decider = Adaptive::new(0.1, receiver);
+        assert_eq!(decider.probability(), 0.1);
+
+        sender.send(0.05).unwrap();
+        let combined = 0.05 + 0.95 * decider.probability();
+        assert!((combined - 0.1).abs() < 1e-9);
+
+        sender.send(0.2).unwrap();
+        assert_eq!(decider.probability(), 0.0);
+        assert!(!decider.decide(&()));
+
+        assert!(Adaptive::new(1.5, sender.subscribe())
+            .validate_decider()
+            .is_err());
+    }
+}
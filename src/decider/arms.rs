@@ -0,0 +1,349 @@
+use super::{Decider, ExplainStep, Explanation, KeyExtractor, Verdict};
+use crate::{
+    describe::{DeciderDescription, DescribeDecider},
+    validate::ValidateDecider,
+    Error,
+};
+use std::{
+    hash::{Hash, Hasher},
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+};
+
+/// Decider that splits traffic into labeled arms, for A/B chaos experiments.
+///
+/// The key extracted from the request, such as a user or tenant identifier,
+/// is hashed to assign the request to an arm in proportion to the arm
+/// weights. The same key always lands in the same arm, so each user sees a
+/// consistent experience for the whole experiment.
+///
+/// Control arms never receive faults, while treatment arms delegate to their
+/// own decider. Requests without a key are not part of the experiment, and
+/// are never faulted.
+///
+/// The number of requests and faults decided for each arm is recorded, and
+/// available through [`Arms::stats`]. Clones share the same statistics.
+#[derive(Clone, Debug)]
+pub struct Arms<F, D> {
+    extractor: F,
+    arms: Vec<Arm<D>>,
+    salt: u64,
+}
+
+#[derive(Clone, Debug)]
+struct Arm<D> {
+    label: String,
+    weight: u32,
+    decider: Option<D>,
+    counters: Arc<Counters>,
+}
+
+#[derive(Debug, Default)]
+struct Counters {
+    requests: AtomicU64,
+    faulted: AtomicU64,
+}
+
+/// Statistics of an arm of an [`Arms`] decider.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct ArmStats {
+    /// Label of the arm.
+    pub label: String,
+    /// Whether the arm receives faults.
+    pub treatment: bool,
+    /// Number of requests assigned to the arm.
+    pub requests: u64,
+    /// Number of requests for which the arm decided to inject a fault.
+    pub faulted: u64,
+}
+
+impl<F, D> Arms<F, D> {
+    /// Create a new `Arms` decider without any arm, using the given closure
+    /// to extract the key from the request.
+    pub fn new(extractor: F) -> Self {
+        Self {
+            extractor,
+            arms: Vec::new(),
+            salt: 0,
+        }
+    }
+
+    /// Add a control arm with the given label and weight, which never
+    /// receives faults.
+    pub fn control(self, label: impl Into<String>, weight: u32) -> Self {
+        self.push(label.into(), weight, None)
+    }
+
+    /// Add a treatment arm with the given label and weight, which receives
+    /// faults according to the given decider.
+    pub fn treatment(self, label: impl Into<String>, weight: u32, decider: D) -> Self {
+        self.push(label.into(), weight, Some(decider))
+    }
+
+    /// Set a salt to assign the keys to different arms for the same weights.
+    pub fn with_salt(mut self, salt: u64) -> Self {
+        self.salt = salt;
+        self
+    }
+
+    /// Returns the statistics of each arm, in the order they were added.
+    pub fn stats(&self) -> Vec<ArmStats> {
+        self.arms
+            .iter()
+            .map(|arm| ArmStats {
+                label: arm.label.clone(),
+                treatment: arm.decider.is_some(),
+                requests: arm.counters.requests.load(Ordering::Relaxed),
+                faulted: arm.counters.faulted.load(Ordering::Relaxed),
+            })
+            .collect()
+    }
+
+    /// Returns the label of the arm the given request is assigned to, if
+    /// any.
+    ///
+    /// This can be used to tag the metrics of the request with its arm.
+    pub fn arm<R>(&self, req: &R) -> Option<&str>
+    where
+        F: KeyExtractor<R>,
+    {
+        self.find(req).map(|arm| arm.label.as_str())
+    }
+
+    fn push(mut self, label: String, weight: u32, decider: Option<D>) -> Self {
+        self.arms.push(Arm {
+            label,
+            weight,
+            decider,
+            counters: Arc::default(),
+        });
+        self
+    }
+
+    /// Evaluate the decider of the arm the request is assigned to, and
+    /// record the outcome in the statistics of the arm.
+    fn evaluate<R>(&self, req: &R, f: impl FnOnce(&D) -> Verdict) -> (Option<&Arm<D>>, Verdict)
+    where
+        F: KeyExtractor<R>,
+    {
+        let arm = match self.find(req) {
+            Some(arm) => arm,
+            None => return (None, Verdict::Pass),
+        };
+        arm.counters.requests.fetch_add(1, Ordering::Relaxed);
+        let verdict = arm.decider.as_ref().map_or(Verdict::Pass, f);
+        if verdict == Verdict::Inject {
+            arm.counters.faulted.fetch_add(1, Ordering::Relaxed);
+        }
+        (Some(arm), verdict)
+    }
+
+    fn find<R>(&self, req: &R) -> Option<&Arm<D>>
+    where
+        F: KeyExtractor<R>,
+    {
+        let total = self.arms.iter().map(|arm| arm.weight as u64).sum::<u64>();
+        if total == 0 {
+            return None;
+        }
+
+        let key = self.extractor.extract(req)?;
+        // FNV-1a, so that arms are stable across processes.
+        let mut hasher = Fnv::with_salt(self.salt);
+        key.hash(&mut hasher);
+        let mut bucket = hasher.finish() % total;
+
+        self.arms.iter().find(|arm| {
+            if bucket < arm.weight as u64 {
+                true
+            } else {
+                bucket -= arm.weight as u64;
+                false
+            }
+        })
+    }
+}
+
+impl<F, D, R> Decider<R> for Arms<F, D>
+where
+    F: KeyExtractor<R>,
+    D: Decider<R>,
+{
+    fn decide(&self, req: &R) -> bool {
+        let (_, verdict) = self.evaluate(req, |decider| {
+            if decider.decide(req) {
+                Verdict::Inject
+            } else {
+                Verdict::Pass
+            }
+        });
+        verdict == Verdict::Inject
+    }
+
+    fn verdict(&self, req: &R) -> Verdict {
+        self.evaluate(req, |decider| decider.verdict(req)).1
+    }
+
+    fn explain(&self, req: &R, explanation: &mut Explanation) -> Verdict {
+        let (arm, verdict) = self.evaluate(req, |decider| decider.explain(req, explanation));
+        let detail = match arm {
+            Some(arm) if arm.decider.is_some() => format!("treatment arm '{}'", arm.label),
+            Some(arm) => format!("control arm '{}'", arm.label),
+            None => String::from("no arm"),
+        };
+        explanation.record(ExplainStep::new("arms", verdict).with_detail(detail));
+        verdict
+    }
+}
+
+impl<F, D> ValidateDecider for Arms<F, D>
+where
+    D: ValidateDecider,
+{
+    fn validate_decider(&self) -> Result<(), Error> {
+        if self.arms.iter().all(|arm| arm.weight == 0) {
+            return Err(Error::InvalidConfig(
+                "arms must have at least one arm with a non-zero weight".to_string(),
+            ));
+        }
+        self.arms
+            .iter()
+            .filter_map(|arm| arm.decider.as_ref())
+            .try_for_each(D::validate_decider)
+    }
+}
+
+impl<F, D> DescribeDecider for Arms<F, D>
+where
+    D: DescribeDecider,
+{
+    fn describe_decider(&self) -> DeciderDescription {
+        let total = self.arms.iter().map(|arm| arm.weight as u64).sum::<u64>();
+        let arms = self
+            .arms
+            .iter()
+            .map(|arm| {
+                let share = arm.weight as f64 / total.max(1) as f64 * 100.0;
+                match &arm.decider {
+                    Some(decider) => {
+                        format!("{} {}%: {}", arm.label, share, decider.describe_decider())
+                    }
+                    None => format!("{} {}%: control", arm.label, share),
+                }
+            })
+            .collect::<Vec<_>>();
+        DeciderDescription::new(format!("arms [{}]", arms.join(", ")), None)
+    }
+}
+
+/// FNV-1a hasher, which is stable across processes and builds.
+pub(super) struct Fnv(u64);
+
+impl Fnv {
+    pub(super) fn with_salt(salt: u64) -> Self {
+        Self(0xcbf2_9ce4_8422_2325 ^ salt)
+    }
+}
+
+impl Hasher for Fnv {
+    fn finish(&self) -> u64 {
+        self.0
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        for byte in bytes {
+            self.0 ^= *byte as u64;
+            self.0 = self.0.wrapping_mul(0x0100_0000_01b3);
+        }
+    }
+
+    // Integers are hashed as little-endian bytes, and sizes as 64-bit
+    // integers, so that all the replicas hash keys the same way regardless
+    // of their architecture.
+    fn write_u16(&mut self, i: u16) {
+        self.write(&i.to_le_bytes());
+    }
+
+    fn write_u32(&mut self, i: u32) {
+        self.write(&i.to_le_bytes());
+    }
+
+    fn write_u64(&mut self, i: u64) {
+        self.write(&i.to_le_bytes());
+    }
+
+    fn write_u128(&mut self, i: u128) {
+        self.write(&i.to_le_bytes());
+    }
+
+    fn write_usize(&mut self, i: usize) {
+        self.write_u64(i as u64);
+    }
+
+    fn write_i16(&mut self, i: i16) {
+        self.write_u16(i as u16);
+    }
+
+    fn write_i32(&mut self, i: i32) {
+        self.write_u32(i as u32);
+    }
+
+    fn write_i64(&mut self, i: i64) {
+        self.write_u64(i as u64);
+    }
+
+    fn write_i128(&mut self, i: i128) {
+        self.write_u128(i as u128);
+    }
+
+    fn write_isize(&mut self, i: isize) {
+        self.write_u64(i as i64 as u64);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn user(id: &u32) -> Option<u32> {
+        Some(*id)
+    }
+
+    #[test]
+    fn arms_split_traffic() {
+        let arms = Arms::new(user)
+            .control("control", 1)
+            .treatment("treatment", 1, true);
+
+        for id in 0..10_000u32 {
+            // The same key always lands in the same arm.
+            assert_eq!(arms.arm(&id), arms.arm(&id));
+            assert_eq!(arms.decide(&id), arms.arm(&id) == Some("treatment"));
+        }
+
+        let stats = arms.stats();
+        assert_eq!(stats[0].faulted, 0);
+        assert_eq!(stats[1].faulted, stats[1].requests);
+        assert_eq!(stats[0].requests + stats[1].requests, 10_000);
+        assert!(
+            (4_500..5_500).contains(&stats[1].requests),
+            "treatment: {}",
+            stats[1].requests
+        );
+    }
+
+    #[test]
+    fn arms_validation() {
+        let arms: Arms<_, bool> = Arms::new(user).control("control", 0);
+        assert!(arms.validate_decider().is_err());
+        assert!(!arms.decide(&1));
+
+        let arms = Arms::new(user)
+            .control("control", 1)
+            .treatment("treatment", 1, 1.5);
+        assert_eq!(arms.validate_decider(), Err(Error::InvalidProbability(1.5)));
+    }
+}
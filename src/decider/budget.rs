@@ -0,0 +1,186 @@
+use super::{Decider, ExplainStep, Explanation, Suppression, Verdict};
+use crate::{
+    describe::{DeciderDescription, DescribeDecider},
+    sync::{Arc, AtomicU64, Ordering},
+    validate::ValidateDecider,
+    Error,
+};
+
+/// Decider that caps the total number of faults approved by the inner
+/// decider.
+///
+/// Once the budget is exhausted, no more faults are injected, and the faults
+/// approved by the inner decider are reported as suppressed by
+/// [`Decider::verdict`].
+///
+/// Clones share the same budget.
+#[derive(Clone, Debug)]
+pub struct Budget<D> {
+    decider: D,
+    max: u64,
+    remaining: Arc<AtomicU64>,
+}
+
+impl<D> Budget<D> {
+    /// Create a new `Budget` allowing at most `max` faults.
+    pub fn new(decider: D, max: u64) -> Self {
+        Self {
+            decider,
+            max,
+            remaining: Arc::new(AtomicU64::new(max)),
+        }
+    }
+
+    /// Returns the number of faults left in the budget.
+    pub fn remaining(&self) -> u64 {
+        self.remaining.load(Ordering::Relaxed)
+    }
+
+    /// Refill the budget.
+    pub fn reset(&self) {
+        self.remaining.store(self.max, Ordering::Relaxed);
+    }
+
+    fn spend(&self) -> bool {
+        self.remaining
+            .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |n| n.checked_sub(1))
+            .is_ok()
+    }
+}
+
+impl<D, R> Decider<R> for Budget<D>
+where
+    D: Decider<R>,
+{
+    fn decide(&self, req: &R) -> bool {
+        self.remaining() > 0 && self.decider.decide(req) && self.spend()
+    }
+
+    fn verdict(&self, req: &R) -> Verdict {
+        match self.decider.verdict(req) {
+            Verdict::Inject if !self.spend() => Verdict::Suppressed(Suppression::Budget),
+            verdict => verdict,
+        }
+    }
+
+    fn explain(&self, req: &R, explanation: &mut Explanation) -> Verdict {
+        let verdict = match self.decider.explain(req, explanation) {
+            Verdict::Inject if !self.spend() => Verdict::Suppressed(Suppression::Budget),
+            verdict => verdict,
+        };
+        explanation.record(ExplainStep::new("budget", verdict).with_detail(format!(
+            "{}/{} remaining",
+            self.remaining(),
+            self.max
+        )));
+        verdict
+    }
+}
+
+impl<D> ValidateDecider for Budget<D>
+where
+    D: ValidateDecider,
+{
+    fn validate_decider(&self) -> Result<(), Error> {
+        self.decider.validate_decider()
+    }
+}
+
+impl<D> DescribeDecider for Budget<D>
+where
+    D: DescribeDecider,
+{
+    fn describe_decider(&self) -> DeciderDescription {
+        let inner = self.decider.describe_decider();
+        let kind = format!("{} (budget {}/{})", inner.kind, self.remaining(), self.max);
+        DeciderDescription::new(kind, inner.probability)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn budget() {
+        let budget = Budget::new(true, 2);
+        assert!(budget.decide(&()));
+        assert_eq!(budget.verdict(&()), Verdict::Inject);
+        assert_eq!(budget.remaining(), 0);
+
+        assert!(!budget.decide(&()));
+        assert_eq!(
+            budget.verdict(&()),
+            Verdict::Suppressed(Suppression::Budget)
+        );
+        assert_eq!(Budget::new(false, 0).verdict(&()), Verdict::Pass);
+
+        budget.reset();
+        assert!(budget.clone().decide(&()));
+        assert_eq!(budget.remaining(), 1);
+    }
+
+    #[test]
+    fn budget_explain() {
+        let budget = Budget::new(true, 1);
+        let mut explanation = Explanation::new();
+        assert_eq!(budget.explain(&(), &mut explanation), Verdict::Inject);
+        assert_eq!(
+            explanation.steps()[1],
+            ExplainStep::new("budget", Verdict::Inject).with_detail("0/1 remaining")
+        );
+    }
+
+    #[test]
+    fn budget_nested() {
+        use crate::{
+            class::ByClass,
+            decider::{Arms, FaultGroup, OnlyDuring},
+        };
+        use std::time::Duration;
+
+        // Wrappers report the budget suppression of their inner decider.
+        fn check<D: Decider<()>>(decider: D) {
+            assert_eq!(decider.verdict(&()), Verdict::Inject);
+            assert_eq!(
+                decider.verdict(&()),
+                Verdict::Suppressed(Suppression::Budget)
+            );
+
+            let mut explanation = Explanation::new();
+            assert_eq!(
+                decider.explain(&(), &mut explanation),
+                Verdict::Suppressed(Suppression::Budget)
+            );
+            assert!(explanation
+                .steps()
+                .iter()
+                .any(|step| step.decider == "budget"));
+        }
+
+        check(OnlyDuring::first(Duration::from_secs(3600)).with_decider(Budget::new(true, 1)));
+        let group = FaultGroup::new("group");
+        group.activate();
+        check(group.with_decider(Budget::new(true, 1)));
+        check(Arms::new(|_: &()| Some(0)).treatment("treatment", 1, Budget::new(true, 1)));
+        check(ByClass::new(|_: &()| true).class(true, Budget::new(true, 1)));
+    }
+}
+
+#[cfg(all(test, tower_fault_loom, feature = "test-determinism"))]
+mod loom_tests {
+    use super::*;
+    use loom::thread;
+
+    #[test]
+    fn budget_concurrent() {
+        loom::model(|| {
+            let budget = Budget::new(true, 1);
+            let other = budget.clone();
+            let handle = thread::spawn(move || other.decide(&()));
+            let injected = budget.decide(&());
+            assert!(injected ^ handle.join().unwrap());
+            assert_eq!(budget.remaining(), 0);
+        });
+    }
+}
@@ -0,0 +1,106 @@
+use super::{Decider, Probability};
+use crate::{
+    describe::{DeciderDescription, DescribeDecider},
+    validate::ValidateDecider,
+    Error,
+};
+use rand::Rng;
+use std::sync::{
+    atomic::{AtomicBool, Ordering},
+    Arc,
+};
+
+/// Decider that injects faults in bursts.
+///
+/// This follows a two-state (Gilbert) model: outside of a burst, each
+/// request has an `enter` probability of starting a burst. During a burst,
+/// all requests are faulted, and each request has an `exit` probability of
+/// ending the burst. The average burst length is `1 / exit` requests.
+///
+/// Real failures, such as a saturated connection pool or a flapping link,
+/// tend to affect consecutive requests rather than independent ones.
+///
+/// Clones share the same state, so all the services created by a layer
+/// enter and leave bursts together.
+#[derive(Clone, Debug)]
+pub struct Bursty {
+    enter: f64,
+    exit: f64,
+    in_burst: Arc<AtomicBool>,
+}
+
+impl Bursty {
+    /// Create a new `Bursty` decider with the probabilities of entering and
+    /// leaving a burst.
+    pub fn new(enter: f64, exit: f64) -> Self {
+        Self {
+            enter,
+            exit,
+            in_burst: Arc::new(AtomicBool::new(false)),
+        }
+    }
+
+    /// Returns the long-run ratio of faulted requests.
+    pub fn ratio(&self) -> f64 {
+        if self.enter + self.exit == 0.0 {
+            0.0
+        } else {
+            self.enter / (self.enter + self.exit)
+        }
+    }
+}
+
+impl<R> Decider<R> for Bursty {
+    fn decide(&self, _: &R) -> bool {
+        let mut rng = crate::seed::rng();
+        let in_burst = self.in_burst.load(Ordering::Relaxed);
+        let next = if in_burst {
+            !rng.gen_bool(self.exit)
+        } else {
+            rng.gen_bool(self.enter)
+        };
+        if next != in_burst {
+            self.in_burst.store(next, Ordering::Relaxed);
+        }
+        next
+    }
+}
+
+impl ValidateDecider for Bursty {
+    fn validate_decider(&self) -> Result<(), Error> {
+        Probability::new(self.enter)?;
+        Probability::new(self.exit)?;
+        Ok(())
+    }
+}
+
+impl DescribeDecider for Bursty {
+    fn describe_decider(&self) -> DeciderDescription {
+        let kind = format!("bursty (enter {}, exit {})", self.enter, self.exit);
+        DeciderDescription::new(kind, Some(self.ratio()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bursty_stays_in_burst() {
+        let decider = Bursty::new(1.0, 0.0);
+        for _ in 0..100 {
+            assert!(decider.decide(&()));
+        }
+
+        let decider = Bursty::new(0.0, 1.0);
+        for _ in 0..100 {
+            assert!(!decider.decide(&()));
+        }
+    }
+
+    #[test]
+    fn bursty_validation() {
+        assert!(Bursty::new(0.01, 0.2).validate_decider().is_ok());
+        assert!(Bursty::new(1.5, 0.2).validate_decider().is_err());
+    }
+}
@@ -0,0 +1,147 @@
+use super::{arms::Fnv, Decider, ExplainStep, Explanation, KeyExtractor, Probability, Verdict};
+use crate::{
+    describe::{DeciderDescription, DescribeDecider},
+    validate::ValidateDecider,
+    Error,
+};
+use std::hash::{Hash, Hasher};
+
+/// Decider that makes the same decision for the same logical request on all
+/// the replicas of a service.
+///
+/// The key extracted from the request, such as a request or idempotency
+/// identifier, is hashed with the [experiment seed](crate::seed) into one of
+/// 1,000,000 buckets. Integers in the key are hashed as little-endian bytes,
+/// so replicas on different architectures agree. Replicas sharing the same seed fault the same requests, so a
+/// retry sent to another replica can't dodge the fault. Requests without a
+/// key are never faulted.
+///
+/// Changing the seed selects a different set of requests for the same
+/// probability.
+#[derive(Clone, Debug)]
+pub struct Consistent<F> {
+    probability: f64,
+    seed: u64,
+    extractor: F,
+}
+
+const BUCKETS: u64 = 1_000_000;
+
+impl<F> Consistent<F> {
+    /// Create a new `Consistent` decider faulting the given probability of
+    /// the requests, with the current [experiment seed](crate::seed).
+    pub fn new(probability: f64, extractor: F) -> Self {
+        Self {
+            probability,
+            seed: crate::seed::seed(),
+            extractor,
+        }
+    }
+
+    /// Set the seed of the experiment, shared by all the replicas.
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.seed = seed;
+        self
+    }
+
+    fn threshold(&self) -> u64 {
+        (self.probability.clamp(0.0, 1.0) * BUCKETS as f64).round() as u64
+    }
+
+    fn bucket<R>(&self, req: &R) -> Option<u64>
+    where
+        F: KeyExtractor<R>,
+    {
+        let key = self.extractor.extract(req)?;
+        let mut hasher = Fnv::with_salt(self.seed);
+        key.hash(&mut hasher);
+        Some(hasher.finish() % BUCKETS)
+    }
+}
+
+impl<F, R> Decider<R> for Consistent<F>
+where
+    F: KeyExtractor<R>,
+{
+    fn decide(&self, req: &R) -> bool {
+        self.bucket(req)
+            .is_some_and(|bucket| bucket < self.threshold())
+    }
+
+    fn explain(&self, req: &R, explanation: &mut Explanation) -> Verdict {
+        let bucket = self.bucket(req);
+        let verdict = match bucket {
+            Some(bucket) if bucket < self.threshold() => Verdict::Inject,
+            _ => Verdict::Pass,
+        };
+        let detail = match bucket {
+            Some(bucket) => format!("bucket {} against {}", bucket, self.threshold()),
+            None => String::from("no key"),
+        };
+        explanation.record(ExplainStep::new("consistent", verdict).with_detail(detail));
+        verdict
+    }
+}
+
+impl<F> ValidateDecider for Consistent<F> {
+    fn validate_decider(&self) -> Result<(), Error> {
+        Probability::new(self.probability).map(|_| ())
+    }
+}
+
+impl<F> DescribeDecider for Consistent<F> {
+    fn describe_decider(&self) -> DeciderDescription {
+        DeciderDescription::new(
+            format!("consistent (seed {})", self.seed),
+            Some(self.probability),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn request_id(id: &u64) -> Option<u64> {
+        Some(*id)
+    }
+
+    #[test]
+    fn consistent_across_replicas() {
+        let replica = Consistent::new(0.3, request_id).with_seed(42);
+        let other = Consistent::new(0.3, request_id).with_seed(42);
+
+        let faulted = (0..10_000).filter(|id| replica.decide(id)).count();
+        assert!((2_500..3_500).contains(&faulted), "faulted: {}", faulted);
+        assert!((0..10_000).all(|id| replica.decide(&id) == other.decide(&id)));
+
+        let reseeded = Consistent::new(0.3, request_id).with_seed(7);
+        assert!((0..10_000).any(|id| replica.decide(&id) != reseeded.decide(&id)));
+        assert!(!Consistent::new(1.0, |_: &()| None::<u64>).decide(&()));
+    }
+
+    #[test]
+    fn consistent_defaults() {
+        let decider = Consistent::new(0.3, request_id);
+        assert_eq!(decider.seed, crate::seed::seed());
+    }
+
+    #[test]
+    fn consistent_canonical_keys() {
+        fn hash(key: impl Hash) -> u64 {
+            let mut hasher = Fnv::with_salt(42);
+            key.hash(&mut hasher);
+            hasher.finish()
+        }
+        fn bytes(bytes: &[u8]) -> u64 {
+            let mut hasher = Fnv::with_salt(42);
+            hasher.write(bytes);
+            hasher.finish()
+        }
+
+        assert_eq!(hash(0x0102_0304u32), bytes(&[4, 3, 2, 1]));
+        assert_eq!(hash(-2i16), bytes(&[0xfe, 0xff]));
+        // Lengths are hashed as 64-bit integers on all architectures.
+        assert_eq!(hash([1u8].as_slice()), bytes(&[1, 0, 0, 0, 0, 0, 0, 0, 1]));
+    }
+}
@@ -0,0 +1,95 @@
+use super::{Decider, Explanation, Verdict};
+use crate::{
+    describe::{DeciderDescription, DescribeDecider},
+    validate::ValidateDecider,
+    Error,
+};
+use core::fmt;
+
+/// Decider that enriches the request with ambient context.
+///
+/// The extractor returns context for the request, such as the peer address
+/// or the authenticated identity, and the inner decider is called with a
+/// `(request, context)` tuple. This way, deciders don't need the request type
+/// itself to carry that data.
+#[derive(Clone)]
+pub struct WithContext<D, X> {
+    decider: D,
+    extractor: X,
+}
+
+impl<D, X> WithContext<D, X> {
+    /// Create a new `WithContext` decider.
+    pub fn new(decider: D, extractor: X) -> Self {
+        Self { decider, extractor }
+    }
+}
+
+impl<D, X> fmt::Debug for WithContext<D, X>
+where
+    D: fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("WithContext")
+            .field("decider", &self.decider)
+            .finish()
+    }
+}
+
+impl<D, X, R, C> Decider<R> for WithContext<D, X>
+where
+    X: Fn(&R) -> C,
+    D: for<'r> Decider<(&'r R, C)>,
+{
+    fn decide(&self, req: &R) -> bool {
+        self.decider.decide(&(req, (self.extractor)(req)))
+    }
+
+    fn verdict(&self, req: &R) -> Verdict {
+        self.decider.verdict(&(req, (self.extractor)(req)))
+    }
+
+    fn explain(&self, req: &R, explanation: &mut Explanation) -> Verdict {
+        self.decider
+            .explain(&(req, (self.extractor)(req)), explanation)
+    }
+}
+
+impl<D, X> ValidateDecider for WithContext<D, X>
+where
+    D: ValidateDecider,
+{
+    fn validate_decider(&self) -> Result<(), Error> {
+        self.decider.validate_decider()
+    }
+}
+
+impl<D, X> DescribeDecider for WithContext<D, X>
+where
+    D: DescribeDecider,
+{
+    fn describe_decider(&self) -> DeciderDescription {
+        self.decider.describe_decider()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct Request {
+        user: &'static str,
+    }
+
+    #[test]
+    fn with_context_decider() {
+        let decider = WithContext::new(
+            |(req, admin): &(&Request, bool)| !admin && req.user.starts_with("test-"),
+            |req: &Request| req.user == "test-admin",
+        );
+
+        assert!(decider.decide(&Request { user: "test-user" }));
+        assert!(!decider.decide(&Request { user: "test-admin" }));
+        assert!(!decider.decide(&Request { user: "user" }));
+    }
+}
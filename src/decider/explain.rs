@@ -0,0 +1,64 @@
+use super::Verdict;
+use alloc::{string::String, vec::Vec};
+
+/// Chain of decider evaluations that led to a verdict, recorded by
+/// [`Decider::explain`](super::Decider::explain).
+///
+/// Steps are recorded in the order the deciders finished their evaluation:
+/// deciders wrapping other deciders record their step after the steps of
+/// the deciders they wrap.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Explanation {
+    steps: Vec<ExplainStep>,
+}
+
+impl Explanation {
+    /// Create a new empty `Explanation`.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Record the evaluation of a decider.
+    pub fn record(&mut self, step: ExplainStep) {
+        self.steps.push(step);
+    }
+
+    /// Returns the recorded steps.
+    pub fn steps(&self) -> &[ExplainStep] {
+        &self.steps
+    }
+
+    /// Returns the recorded steps, consuming the explanation.
+    pub fn into_steps(self) -> Vec<ExplainStep> {
+        self.steps
+    }
+}
+
+/// Evaluation of a single decider in an [`Explanation`].
+#[derive(Clone, Debug, PartialEq)]
+pub struct ExplainStep {
+    /// Kind of decider, such as `probability` or `budget`.
+    pub decider: String,
+    /// Verdict of the decider.
+    pub verdict: Verdict,
+    /// Details about the evaluation, such as the matched rule or the
+    /// probability draw.
+    pub detail: Option<String>,
+}
+
+impl ExplainStep {
+    /// Create a new `ExplainStep` without details.
+    pub fn new(decider: impl Into<String>, verdict: Verdict) -> Self {
+        Self {
+            decider: decider.into(),
+            verdict,
+            detail: None,
+        }
+    }
+
+    /// Set the details about the evaluation.
+    pub fn with_detail(mut self, detail: impl Into<String>) -> Self {
+        self.detail = Some(detail.into());
+        self
+    }
+}
@@ -0,0 +1,221 @@
+use super::{Decider, ExplainStep, Explanation, Verdict};
+use crate::{
+    describe::{DeciderDescription, DescribeDecider},
+    validate::ValidateDecider,
+    Error,
+};
+use rand::Rng;
+use std::sync::{
+    atomic::{AtomicU64, Ordering},
+    Arc,
+};
+
+/// Group of faults that trigger together.
+///
+/// All the layers using the same group, or one of its members, inject faults
+/// while the group is active. This simulates a shared failure domain, such as
+/// an availability zone outage, instead of independent random faults.
+///
+/// The group starts inactive. Besides being fully active or inactive, the
+/// group has an intensity between 0.0 and 1.0, which is the probability of
+/// the members injecting a fault.
+///
+/// Clones share the same state.
+#[derive(Clone, Debug)]
+pub struct FaultGroup {
+    state: Arc<GroupState>,
+}
+
+#[derive(Debug)]
+struct GroupState {
+    name: String,
+    intensity: AtomicU64,
+}
+
+impl FaultGroup {
+    /// Create a new inactive `FaultGroup` with the given name.
+    pub fn new(name: impl Into<String>) -> Self {
+        Self {
+            state: Arc::new(GroupState {
+                name: name.into(),
+                intensity: AtomicU64::new(0.0f64.to_bits()),
+            }),
+        }
+    }
+
+    /// Returns the name of the group.
+    pub fn name(&self) -> &str {
+        &self.state.name
+    }
+
+    /// Activate the group, making all its members inject faults.
+    pub fn activate(&self) {
+        self.set_intensity(1.0);
+    }
+
+    /// Deactivate the group.
+    pub fn deactivate(&self) {
+        self.set_intensity(0.0);
+    }
+
+    /// Returns `true` if the members of the group can inject faults.
+    pub fn is_active(&self) -> bool {
+        self.intensity() > 0.0
+    }
+
+    /// Set the probability of the members injecting a fault.
+    ///
+    /// The intensity is clamped between 0.0 and 1.0.
+    pub fn set_intensity(&self, intensity: f64) {
+        let intensity = if intensity.is_nan() {
+            0.0
+        } else {
+            intensity.clamp(0.0, 1.0)
+        };
+        self.state
+            .intensity
+            .store(intensity.to_bits(), Ordering::Relaxed);
+    }
+
+    /// Returns the probability of the members injecting a fault.
+    pub fn intensity(&self) -> f64 {
+        f64::from_bits(self.state.intensity.load(Ordering::Relaxed))
+    }
+
+    /// Returns a member of the group that also requires the given decider to
+    /// inject a fault.
+    pub fn with_decider<D>(&self, decider: D) -> GroupMember<D> {
+        GroupMember {
+            group: self.clone(),
+            inner: decider,
+        }
+    }
+
+    fn triggers(&self) -> bool {
+        let intensity = self.intensity();
+        intensity >= 1.0 || (intensity > 0.0 && crate::seed::rng().gen_bool(intensity))
+    }
+}
+
+impl<R> Decider<R> for FaultGroup {
+    fn decide(&self, _req: &R) -> bool {
+        self.triggers()
+    }
+}
+
+impl ValidateDecider for FaultGroup {
+    fn validate_decider(&self) -> Result<(), Error> {
+        Ok(())
+    }
+}
+
+impl DescribeDecider for FaultGroup {
+    fn describe_decider(&self) -> DeciderDescription {
+        DeciderDescription::new(format!("group '{}'", self.name()), Some(self.intensity()))
+    }
+}
+
+/// Member of a [`FaultGroup`] with its own decider.
+///
+/// The member injects a fault when the group triggers and its decider
+/// decides to inject a fault.
+#[derive(Clone, Debug)]
+pub struct GroupMember<D> {
+    group: FaultGroup,
+    inner: D,
+}
+
+impl<D> GroupMember<D> {
+    /// Returns the group of the member.
+    pub fn group(&self) -> &FaultGroup {
+        &self.group
+    }
+}
+
+impl<D, R> Decider<R> for GroupMember<D>
+where
+    D: Decider<R>,
+{
+    fn decide(&self, req: &R) -> bool {
+        self.group.triggers() && self.inner.decide(req)
+    }
+
+    fn verdict(&self, req: &R) -> Verdict {
+        if self.group.triggers() {
+            self.inner.verdict(req)
+        } else {
+            Verdict::Pass
+        }
+    }
+
+    fn explain(&self, req: &R, explanation: &mut Explanation) -> Verdict {
+        let triggered = self.group.triggers();
+        let verdict = if triggered {
+            self.inner.explain(req, explanation)
+        } else {
+            Verdict::Pass
+        };
+        let detail = if triggered {
+            format!("group '{}' triggered", self.group.name())
+        } else {
+            format!("group '{}' didn't trigger", self.group.name())
+        };
+        explanation.record(ExplainStep::new("group", verdict).with_detail(detail));
+        verdict
+    }
+}
+
+impl<D> ValidateDecider for GroupMember<D>
+where
+    D: ValidateDecider,
+{
+    fn validate_decider(&self) -> Result<(), Error> {
+        self.inner.validate_decider()
+    }
+}
+
+impl<D> DescribeDecider for GroupMember<D>
+where
+    D: DescribeDecider,
+{
+    fn describe_decider(&self) -> DeciderDescription {
+        let inner = self.inner.describe_decider();
+        let kind = format!("{} in group '{}'", inner.kind, self.group.name());
+        let probability = inner.probability.map(|p| p * self.group.intensity());
+        DeciderDescription::new(kind, probability)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn group_triggers_together() {
+        let group = FaultGroup::new("us-east-1a");
+        let db = group.clone();
+        let cache = group.with_decider(true);
+
+        assert!(!db.decide(&()));
+        assert!(!cache.decide(&()));
+
+        group.activate();
+        assert!(db.decide(&()));
+        assert!(cache.decide(&()));
+        assert!(!group.with_decider(false).decide(&()));
+
+        group.deactivate();
+        assert!(!cache.decide(&()));
+    }
+
+    #[test]
+    fn group_describe() {
+        let group = FaultGroup::new("az");
+        group.set_intensity(0.5);
+        let member = group.with_decider(0.5);
+        assert_eq!(
+            member.describe_decider().to_string(),
+            "probability in group 'az' (25%)"
+        );
+    }
+}
@@ -0,0 +1,27 @@
+use core::hash::Hash;
+
+/// Extracts the key identifying a request, for the deciders that make
+/// stable decisions per key, such as [`Arms`](super::Arms) and
+/// [`Consistent`](super::Consistent).
+///
+/// This is implemented for closures returning an optional key. Requests
+/// without a key are not part of the experiment.
+pub trait KeyExtractor<R> {
+    /// Type of the key.
+    type Key: Hash;
+
+    /// Returns the key of the request, if any.
+    fn extract(&self, req: &R) -> Option<Self::Key>;
+}
+
+impl<F, R, K> KeyExtractor<R> for F
+where
+    F: Fn(&R) -> Option<K>,
+    K: Hash,
+{
+    type Key = K;
+
+    fn extract(&self, req: &R) -> Option<K> {
+        self(req)
+    }
+}
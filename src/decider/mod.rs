@@ -0,0 +1,557 @@
+//! # Decider
+//!
+//! This module contains the [`Decider`] trait, which decides if a fault should
+//! be injected for a given request or response. It adds a `decide` method that
+//! returns a boolean based on the request.
+//!
+//! For primitive that implements this trait, such as `f64` and `bool`, it
+//! doesn't look at the request.
+//!
+//! ## Example
+//!
+//! ```rust
+//! use tower_fault::decider::Decider;
+//! # struct MyRequest { value: u64 };
+//! # impl MyRequest {
+//! #     fn new(value: u64) -> Self {
+//! #         Self { value }
+//! #     }
+//! # }
+//!
+//! let my_request = MyRequest::new(6);
+//!
+//! // Always.
+//! assert_eq!(true, true.decide(&my_request));
+//!
+//! // Never.
+//! assert_eq!(false, false.decide(&my_request));
+//!
+//! // 30% of the time.
+//! let decision = (0.3).decide(&my_request);
+//!
+//! // Based on the request, using a closure as decider.
+//! let decision = (|req: &MyRequest| req.value % 2 == 0).decide(&my_request);
+//! ```
+//!
+//! ## Peer address
+//!
+//! The [`PeerDecider`] targets a stable percentage of client IP addresses,
+//! which can be used to simulate an outage for a subset of clients.
+//!
+//! ```rust
+//! use std::net::SocketAddr;
+//! use tower_fault::decider::{Decider, PeerDecider};
+//! # struct MyRequest { peer: SocketAddr };
+//!
+//! // Inject faults for 10% of the client IP addresses.
+//! let decider = PeerDecider::new(0.1, |req: &MyRequest| Some(req.peer));
+//! ```
+//!
+//! ## Rates
+//!
+//! Instead of a raw probability, fault frequency can be expressed as a
+//! number of faults per number of requests, or per duration.
+//!
+//! ```rust
+//! use std::time::Duration;
+//! use tower_fault::decider::{per_duration, per_requests};
+//!
+//! // One fault every 1,000 requests.
+//! let decider = per_requests(1, 1_000);
+//!
+//! // Up to 3 faults per minute.
+//! let decider = per_duration(3, Duration::from_secs(60));
+//! ```
+//!
+//! ## Startup window
+//!
+//! The [`OnlyDuring`] decider only injects faults during the first moments
+//! after the service starts, to simulate cold-start flakiness.
+//!
+//! ```rust
+//! use std::time::Duration;
+//! use tower_fault::decider::OnlyDuring;
+//!
+//! // Fault 20% of the requests during the first 30 seconds.
+//! let decider = OnlyDuring::first(Duration::from_secs(30)).with_decider(0.2);
+//! ```
+//!
+//! ## Bursts
+//!
+//! The [`Bursty`] decider injects faults for consecutive requests, like
+//! real outages, instead of independent ones.
+//!
+//! ```rust
+//! use tower_fault::decider::Bursty;
+//!
+//! // Start a burst for 1% of the requests, lasting 10 requests on average.
+//! let decider = Bursty::new(0.01, 0.1);
+//! ```
+//!
+//! ## Timelines
+//!
+//! The [`ErrorRateTimeline`] decider replays the error rates of a recorded
+//! outage, minute by minute over the course of the experiment.
+//!
+//! ```rust,no_run
+//! use tower_fault::decider::ErrorRateTimeline;
+//!
+//! let decider = ErrorRateTimeline::from_file("outage.timeline").unwrap();
+//! ```
+//!
+//! ## Payload size
+//!
+//! The [`ProbabilityBySize`] decider scales the probability with the size of
+//! the request, as bigger payloads fail more often.
+//!
+//! ```rust
+//! use tower_fault::decider::{ProbabilityBySize, SizeCurve};
+//! # struct MyRequest { body: Vec<u8> };
+//!
+//! // 1% of the requests, plus 1% every time the body size doubles above 1 KiB.
+//! let decider = ProbabilityBySize::new(|req: &MyRequest| req.body.len(), SizeCurve::log(0.01, 0.01));
+//! ```
+//!
+//! ## Groups
+//!
+//! A [`FaultGroup`] links faults across layers, so they trigger together
+//! when the group is activated, like a shared failure domain.
+//!
+//! ```rust
+//! use tower_fault::{decider::FaultGroup, error::ErrorLayer, latency::LatencyLayer};
+//! # struct DbRequest;
+//! # struct CacheRequest;
+//!
+//! let group = FaultGroup::new("us-east-1a");
+//!
+//! // Latency on the database client, errors on half of the cache requests.
+//! let db_layer = LatencyLayer::new(group.clone(), 200..500);
+//! let cache_layer = ErrorLayer::new(group.with_decider(0.5), |_: &CacheRequest| "error");
+//!
+//! // Later on, simulate the outage.
+//! group.activate();
+//! ```
+//!
+//! ## Adaptive chaos
+//!
+//! The [`Adaptive`] decider reduces the injected faults as the real error
+//! rate of the service rises, keeping the combined failure rate near a
+//! target.
+//!
+//! ```rust
+//! use tokio::sync::watch;
+//! use tower_fault::decider::Adaptive;
+//!
+//! // Updated from the service metrics.
+//! let (error_rate, receiver) = watch::channel(0.0);
+//!
+//! // Keep the combined failure rate near 2%.
+//! let decider = Adaptive::new(0.02, receiver);
+//! error_rate.send(0.01).unwrap();
+//! ```
+//!
+//! The [`ErrorPacer`] decider converges to the target with a feedback loop
+//! over the outcomes observed by the
+//! [`ErrorLayer`](crate::error::ErrorLayer) instead.
+//!
+//! ## A/B experiments
+//!
+//! The [`Arms`] decider splits the traffic into labeled arms by hash of a
+//! key, such as a user identifier. Only the treatment arms receive faults,
+//! and the decisions are counted for each arm, so the resilience of the
+//! treatment can be compared with the control.
+//!
+//! ```rust
+//! use tower_fault::decider::{Arms, Decider};
+//! # struct MyRequest { user_id: u64 };
+//!
+//! // Fault 10% of the requests for half of the users.
+//! let arms = Arms::new(|req: &MyRequest| Some(req.user_id))
+//!     .control("control", 50)
+//!     .treatment("treatment", 50, 0.1);
+//!
+//! arms.decide(&MyRequest { user_id: 42 });
+//! for stats in arms.stats() {
+//!     println!("{}: {} faults in {} requests", stats.label, stats.faulted, stats.requests);
+//! }
+//! ```
+//!
+//! ## Consistent sampling
+//!
+//! The [`Consistent`] decider hashes a request key with an experiment seed,
+//! so that all the replicas of a service sharing the seed make the same
+//! decision for the same logical request, and retries to another replica
+//! can't dodge the fault.
+//!
+//! ```rust
+//! use tower_fault::decider::Consistent;
+//! # struct MyRequest { request_id: String };
+//!
+//! // Fault 5% of the requests, consistently across replicas.
+//! let decider = Consistent::new(0.05, |req: &MyRequest| Some(req.request_id.clone()))
+//!     .with_seed(2024);
+//! ```
+//!
+//! ## Context
+//!
+//! The [`WithContext`] decider enriches the request with ambient context,
+//! such as the peer address or the authenticated identity, so that the inner
+//! decider receives a `(request, context)` tuple. The layers of this crate
+//! also have a `with_context()` method.
+//!
+//! ```rust
+//! use tower_fault::decider::WithContext;
+//! # struct MyRequest;
+//! # fn identity(_: &MyRequest) -> Option<String> { None }
+//!
+//! // Only inject faults for test accounts.
+//! let decider = WithContext::new(
+//!     |(_, identity): &(&MyRequest, Option<String>)| {
+//!         identity.as_deref().is_some_and(|id| id.starts_with("test-"))
+//!     },
+//!     identity,
+//! );
+//! ```
+//!
+//! ## Budgets
+//!
+//! The [`Budget`] decider caps the total number of faults approved by an
+//! inner decider. Along with vetoes and kill switches, it is a safety
+//! mechanism: the faults it suppresses are reported to the observers of the
+//! layers as [`Suppression`]s.
+//!
+//! ```rust
+//! use tower_fault::decider::Budget;
+//!
+//! // Inject at most 1000 faults.
+//! let decider = Budget::new(0.1, 1000);
+//! ```
+//!
+//! ## Probability
+//!
+//! Using a `f64` as decider panics at request time if the value is not
+//! between 0.0 and 1.0. The [`Probability`] type checks the value when it is
+//! created instead.
+//!
+//! ```rust
+//! use tower_fault::decider::Probability;
+//!
+//! let probability = Probability::new(0.3).unwrap();
+//! assert!(Probability::new(1.5).is_err());
+//!
+//! // Checked at compile time.
+//! const TEN_PERCENT: Probability = Probability::new_const(0.1);
+//! ```
+
+use crate::{
+    describe::{DeciderDescription, DescribeDecider},
+    validate::ValidateDecider,
+    Error,
+};
+use rand::distributions::Bernoulli;
+#[cfg(feature = "std")]
+use rand::{distributions::Distribution, Rng};
+
+#[cfg(feature = "tokio")]
+mod adaptive;
+mod context;
+mod explain;
+mod key;
+#[cfg(feature = "tokio")]
+#[cfg_attr(docsrs, doc(cfg(feature = "tokio")))]
+pub use adaptive::Adaptive;
+pub use context::WithContext;
+pub use explain::{ExplainStep, Explanation};
+pub use key::KeyExtractor;
+
+// Stateful and random deciders require `std`.
+#[cfg(feature = "std")]
+mod arms;
+#[cfg(feature = "std")]
+mod budget;
+#[cfg(feature = "std")]
+mod bursty;
+#[cfg(feature = "std")]
+mod consistent;
+#[cfg(feature = "std")]
+mod group;
+#[cfg(feature = "std")]
+mod pacing;
+#[cfg(feature = "std")]
+mod peer;
+#[cfg(feature = "std")]
+mod rate;
+#[cfg(feature = "std")]
+mod size;
+#[cfg(feature = "std")]
+mod timeline;
+#[cfg(feature = "std")]
+mod window;
+#[cfg(feature = "std")]
+#[cfg_attr(docsrs, doc(cfg(feature = "std")))]
+pub use self::{
+    arms::{ArmStats, Arms},
+    budget::Budget,
+    bursty::Bursty,
+    consistent::Consistent,
+    group::{FaultGroup, GroupMember},
+    pacing::ErrorPacer,
+    peer::PeerDecider,
+    rate::{per_duration, per_requests, PerDuration, PerRequests},
+    size::{ProbabilityBySize, SizeCurve},
+    timeline::ErrorRateTimeline,
+    window::OnlyDuring,
+};
+
+/// Trait for deciding if a fault should be injected for a given request or
+/// response.
+pub trait Decider<R> {
+    /// Decide if a fault should be injected for a given request or response.
+    fn decide(&self, req: &R) -> bool;
+
+    /// Decide if a fault should be injected, and report if a safety
+    /// mechanism suppressed a fault that the decider approved.
+    ///
+    /// Safety mechanisms, such as vetoes, kill switches and budgets,
+    /// override this method. Layers call it instead of
+    /// [`decide`](Decider::decide) when they have an observer.
+    fn verdict(&self, req: &R) -> Verdict {
+        if self.decide(req) {
+            Verdict::Inject
+        } else {
+            Verdict::Pass
+        }
+    }
+
+    /// Decide if a fault should be injected like
+    /// [`verdict`](Decider::verdict), and record the evaluations that led
+    /// to the verdict.
+    ///
+    /// Deciders wrapping other deciders call `explain` on them, and record
+    /// their own step afterwards. By default, this records a single step
+    /// named after the type of the decider. Layers call this for a sample of
+    /// the requests in explain mode, see the [`observe`](crate::observe)
+    /// module.
+    fn explain(&self, req: &R, explanation: &mut Explanation) -> Verdict {
+        let verdict = self.verdict(req);
+        explanation.record(ExplainStep::new(core::any::type_name::<Self>(), verdict));
+        verdict
+    }
+}
+
+/// Outcome of [`Decider::verdict`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Verdict {
+    /// The fault must be injected.
+    Inject,
+    /// The decider didn't approve the fault.
+    Pass,
+    /// A safety mechanism suppressed the fault.
+    ///
+    /// Vetoes, and the kill switches of fault policies, don't evaluate the
+    /// decider, so that they don't affect stateful deciders. They report
+    /// every request they suppress.
+    Suppressed(Suppression),
+}
+
+/// Safety mechanism that suppressed a fault.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Suppression {
+    /// The request was vetoed.
+    Veto,
+    /// The kill switch was engaged.
+    KillSwitch,
+    /// The fault budget was exhausted.
+    Budget,
+    /// The severity of the fault is above the maximum allowed by its
+    /// registry.
+    Severity,
+}
+
+impl<R> Decider<R> for bool {
+    fn decide(&self, _: &R) -> bool {
+        *self
+    }
+}
+
+#[cfg(feature = "std")]
+#[cfg_attr(docsrs, doc(cfg(feature = "std")))]
+impl<R> Decider<R> for Bernoulli {
+    fn decide(&self, _: &R) -> bool {
+        self.sample(&mut crate::seed::rng())
+    }
+}
+
+#[cfg(feature = "std")]
+#[cfg_attr(docsrs, doc(cfg(feature = "std")))]
+impl<R> Decider<R> for f64 {
+    fn decide(&self, _: &R) -> bool {
+        crate::seed::rng().gen_bool(*self)
+    }
+
+    fn explain(&self, _: &R, explanation: &mut Explanation) -> Verdict {
+        explain_draw(*self, explanation)
+    }
+}
+
+/// Draw a random number to decide with the probability, and record the
+/// draw.
+#[cfg(feature = "std")]
+fn explain_draw(probability: f64, explanation: &mut Explanation) -> Verdict {
+    let draw: f64 = crate::seed::rng().gen();
+    let verdict = if draw < probability {
+        Verdict::Inject
+    } else {
+        Verdict::Pass
+    };
+    explanation.record(
+        ExplainStep::new("probability", verdict)
+            .with_detail(format!("draw {:.4} against {}", draw, probability)),
+    );
+    verdict
+}
+
+impl<F, R> Decider<R> for F
+where
+    F: Fn(&R) -> bool,
+{
+    fn decide(&self, req: &R) -> bool {
+        self(req)
+    }
+}
+
+/// Probability between 0.0 and 1.0.
+#[derive(Clone, Copy, Debug, Default, PartialEq, PartialOrd)]
+pub struct Probability(f64);
+
+impl Probability {
+    /// Probability of never injecting a fault.
+    pub const NEVER: Probability = Probability(0.0);
+    /// Probability of always injecting a fault.
+    pub const ALWAYS: Probability = Probability(1.0);
+
+    /// Create a new `Probability`, returning an error if the value is not
+    /// between 0.0 and 1.0.
+    pub fn new(probability: f64) -> Result<Self, Error> {
+        if (0.0..=1.0).contains(&probability) {
+            Ok(Self(probability))
+        } else {
+            Err(Error::InvalidProbability(probability))
+        }
+    }
+
+    /// Create a new `Probability` in a const context.
+    ///
+    /// ## Panics
+    ///
+    /// Panics if the value is not between 0.0 and 1.0. When used to
+    /// initialize a constant, this fails at compile time.
+    pub const fn new_const(probability: f64) -> Self {
+        assert!(
+            probability >= 0.0 && probability <= 1.0,
+            "probability must be between 0.0 and 1.0"
+        );
+        Self(probability)
+    }
+
+    /// Returns the probability as a `f64`.
+    pub fn get(self) -> f64 {
+        self.0
+    }
+}
+
+impl TryFrom<f64> for Probability {
+    type Error = Error;
+
+    fn try_from(probability: f64) -> Result<Self, Self::Error> {
+        Self::new(probability)
+    }
+}
+
+impl From<Probability> for f64 {
+    fn from(probability: Probability) -> Self {
+        probability.0
+    }
+}
+
+#[cfg(feature = "std")]
+#[cfg_attr(docsrs, doc(cfg(feature = "std")))]
+impl<R> Decider<R> for Probability {
+    fn decide(&self, _: &R) -> bool {
+        crate::seed::rng().gen_bool(self.0)
+    }
+
+    fn explain(&self, _: &R, explanation: &mut Explanation) -> Verdict {
+        explain_draw(self.0, explanation)
+    }
+}
+
+impl ValidateDecider for bool {
+    fn validate_decider(&self) -> Result<(), Error> {
+        Ok(())
+    }
+}
+
+impl ValidateDecider for Bernoulli {
+    fn validate_decider(&self) -> Result<(), Error> {
+        Ok(())
+    }
+}
+
+impl ValidateDecider for f64 {
+    fn validate_decider(&self) -> Result<(), Error> {
+        Probability::new(*self).map(|_| ())
+    }
+}
+
+impl ValidateDecider for Probability {
+    fn validate_decider(&self) -> Result<(), Error> {
+        Ok(())
+    }
+}
+
+impl DescribeDecider for bool {
+    fn describe_decider(&self) -> DeciderDescription {
+        let kind = if *self { "always" } else { "never" };
+        DeciderDescription::new(kind, Some(if *self { 1.0 } else { 0.0 }))
+    }
+}
+
+impl DescribeDecider for Bernoulli {
+    fn describe_decider(&self) -> DeciderDescription {
+        DeciderDescription::new("bernoulli", None)
+    }
+}
+
+impl DescribeDecider for f64 {
+    fn describe_decider(&self) -> DeciderDescription {
+        DeciderDescription::new("probability", Some(*self))
+    }
+}
+
+impl DescribeDecider for Probability {
+    fn describe_decider(&self) -> DeciderDescription {
+        DeciderDescription::new("probability", Some(self.0))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn probability_checked() {
+        assert_eq!(Probability::new(0.5).unwrap().get(), 0.5);
+        assert_eq!(Probability::new(1.5), Err(Error::InvalidProbability(1.5)));
+        assert!(Probability::new(f64::NAN).is_err());
+        #[cfg(feature = "std")]
+        assert!(Probability::ALWAYS.decide(&()));
+    }
+
+    #[test]
+    fn f64_validation() {
+        assert!(0.3.validate_decider().is_ok());
+        assert!((-0.1).validate_decider().is_err());
+    }
+}
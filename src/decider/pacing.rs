@@ -0,0 +1,171 @@
+use super::Decider;
+use crate::{
+    describe::{percent, DeciderDescription, DescribeDecider},
+    validate::ValidateDecider,
+    Error,
+};
+use rand::Rng;
+use std::sync::{Arc, Mutex};
+
+/// Decider that adjusts its probability so that the combined error rate,
+/// including the real errors of the service, converges to a target.
+///
+/// Unlike [`Adaptive`](super::Adaptive), which relies on an external error
+/// rate, the pacer uses a feedback loop over the outcomes it observes: the
+/// [`ErrorLayer`](crate::error::ErrorLayer) records the outcome of each
+/// request when configured with `paced()`, and after each window of
+/// requests, the pacer corrects its probability by the difference between
+/// the target and the observed error rate. This is useful to burn an error
+/// budget at a controlled rate.
+///
+/// Clones share the same state.
+#[derive(Clone, Debug)]
+pub struct ErrorPacer {
+    target: f64,
+    window: u64,
+    gain: f64,
+    state: Arc<Mutex<PacerState>>,
+}
+
+#[derive(Debug, Default)]
+struct PacerState {
+    requests: u64,
+    errors: u64,
+    probability: f64,
+}
+
+impl ErrorPacer {
+    /// Create a new `ErrorPacer` targeting the given combined error rate,
+    /// between 0.0 and 1.0.
+    ///
+    /// By default, the probability is adjusted every 1,000 requests, by half
+    /// of the difference between the target and the observed error rate.
+    pub fn new(target: f64) -> Self {
+        Self {
+            target,
+            window: 1_000,
+            gain: 0.5,
+            state: Arc::default(),
+        }
+    }
+
+    /// Set the number of requests observed before adjusting the
+    /// probability.
+    pub fn window(mut self, window: u64) -> Self {
+        self.window = window;
+        self
+    }
+
+    /// Set the ratio of the error corrected at each adjustment, between 0.0
+    /// and 1.0.
+    ///
+    /// Lower values converge slower, but are less sensitive to noise.
+    pub fn gain(mut self, gain: f64) -> Self {
+        self.gain = gain;
+        self
+    }
+
+    /// Returns the current probability of injecting a fault.
+    pub fn probability(&self) -> f64 {
+        self.lock().probability
+    }
+
+    /// Record the outcome of a request, including the injected errors.
+    ///
+    /// This is called by the [`ErrorLayer`](crate::error::ErrorLayer) when
+    /// configured with `paced()`.
+    pub fn record(&self, is_err: bool) {
+        let mut state = self.lock();
+        state.requests += 1;
+        state.errors += is_err as u64;
+        if state.requests < self.window.max(1) {
+            return;
+        }
+
+        let observed = state.errors as f64 / state.requests as f64;
+        state.requests = 0;
+        state.errors = 0;
+
+        let probability = state.probability + (self.target - observed) * self.gain;
+        state.probability = if probability.is_nan() {
+            0.0
+        } else {
+            probability.clamp(0.0, 1.0)
+        };
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, PacerState> {
+        self.state.lock().unwrap_or_else(|err| err.into_inner())
+    }
+}
+
+impl<R> Decider<R> for ErrorPacer {
+    fn decide(&self, _req: &R) -> bool {
+        crate::seed::rng().gen_bool(self.probability())
+    }
+}
+
+impl ValidateDecider for ErrorPacer {
+    fn validate_decider(&self) -> Result<(), Error> {
+        if !(0.0..=1.0).contains(&self.target) {
+            return Err(Error::InvalidProbability(self.target));
+        }
+        if !(self.gain > 0.0 && self.gain <= 1.0) {
+            return Err(Error::InvalidConfig(format!(
+                "pacer gain must be between 0.0 and 1.0, got {}",
+                self.gain
+            )));
+        }
+        if self.window == 0 {
+            return Err(Error::InvalidConfig(
+                "pacer window must contain at least one request".to_string(),
+            ));
+        }
+        Ok(())
+    }
+}
+
+impl DescribeDecider for ErrorPacer {
+    fn describe_decider(&self) -> DeciderDescription {
+        DeciderDescription::new(
+            format!("paced (target {}%)", percent(self.target)),
+            Some(self.probability()),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn error_pacer_converges() {
+        let pacer = ErrorPacer::new(0.1);
+
+        // The service fails 1 request out of 20 on its own, so the pacer
+        // should inject faults in about 5.3% of the other requests.
+        for _ in 0..30 {
+            for i in 0..1_000 {
+                let is_err = i % 20 == 0 || pacer.decide(&());
+                pacer.record(is_err);
+            }
+        }
+
+        let probability = pacer.probability();
+        assert!(
+            (0.02..0.09).contains(&probability),
+            "probability: {}",
+            probability
+        );
+    }
+
+    #[test]
+    fn error_pacer_validation() {
+        assert!(ErrorPacer::new(0.1).validate_decider().is_ok());
+        assert_eq!(
+            ErrorPacer::new(1.5).validate_decider(),
+            Err(Error::InvalidProbability(1.5))
+        );
+        assert!(ErrorPacer::new(0.1).window(0).validate_decider().is_err());
+    }
+}
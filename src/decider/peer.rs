@@ -0,0 +1,121 @@
+use super::{Decider, ExplainStep, Explanation, Verdict};
+use crate::{
+    describe::{DeciderDescription, DescribeDecider},
+    validate::ValidateDecider,
+    Error,
+};
+use std::net::{IpAddr, SocketAddr};
+
+/// Decider that targets a stable percentage of client IP addresses.
+///
+/// The IP address is extracted from the request using the given closure and
+/// hashed into one of 10,000 buckets. The same client always lands in the
+/// same bucket, so the same subset of clients is faulted for the whole
+/// experiment. Requests without a peer address are never faulted.
+#[derive(Clone, Debug)]
+pub struct PeerDecider<F> {
+    threshold: u64,
+    salt: u64,
+    extractor: F,
+}
+
+const BUCKETS: u64 = 10_000;
+
+impl<F> PeerDecider<F> {
+    /// Create a new `PeerDecider` targeting the given ratio (between 0.0 and
+    /// 1.0) of client IP addresses.
+    pub fn new(ratio: f64, extractor: F) -> Self {
+        Self {
+            threshold: (ratio.clamp(0.0, 1.0) * BUCKETS as f64).round() as u64,
+            salt: 0,
+            extractor,
+        }
+    }
+
+    /// Set a salt to select a different subset of clients for the same
+    /// ratio.
+    pub fn with_salt(mut self, salt: u64) -> Self {
+        self.salt = salt;
+        self
+    }
+
+    fn bucket(&self, ip: IpAddr) -> u64 {
+        // FNV-1a, so that buckets are stable across processes and builds.
+        let mut hash: u64 = 0xcbf2_9ce4_8422_2325 ^ self.salt;
+        let octets = match ip {
+            IpAddr::V4(ip) => ip.to_ipv6_mapped().octets(),
+            IpAddr::V6(ip) => ip.octets(),
+        };
+        for byte in octets {
+            hash ^= byte as u64;
+            hash = hash.wrapping_mul(0x0100_0000_01b3);
+        }
+        hash % BUCKETS
+    }
+}
+
+impl<F, R> Decider<R> for PeerDecider<F>
+where
+    F: Fn(&R) -> Option<SocketAddr>,
+{
+    fn decide(&self, req: &R) -> bool {
+        match (self.extractor)(req) {
+            Some(addr) => self.bucket(addr.ip()) < self.threshold,
+            None => false,
+        }
+    }
+
+    fn explain(&self, req: &R, explanation: &mut Explanation) -> Verdict {
+        let bucket = (self.extractor)(req).map(|addr| self.bucket(addr.ip()));
+        let verdict = match bucket {
+            Some(bucket) if bucket < self.threshold => Verdict::Inject,
+            _ => Verdict::Pass,
+        };
+        let detail = match bucket {
+            Some(bucket) => format!("bucket {} against {}", bucket, self.threshold),
+            None => String::from("no peer address"),
+        };
+        explanation.record(ExplainStep::new("peer", verdict).with_detail(detail));
+        verdict
+    }
+}
+
+impl<F> ValidateDecider for PeerDecider<F> {
+    fn validate_decider(&self) -> Result<(), Error> {
+        Ok(())
+    }
+}
+
+impl<F> DescribeDecider for PeerDecider<F> {
+    fn describe_decider(&self) -> DeciderDescription {
+        let ratio = self.threshold as f64 / BUCKETS as f64;
+        DeciderDescription::new(format!("peer ({}% of clients)", ratio * 100.0), None)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn peer_is_stable() {
+        let decider = PeerDecider::new(0.5, |addr: &SocketAddr| Some(*addr));
+
+        for i in 0..=255 {
+            let addr = SocketAddr::from(([10, 0, 0, i], 1234));
+            let other_port = SocketAddr::from(([10, 0, 0, i], 4321));
+            assert_eq!(decider.decide(&addr), decider.decide(&other_port));
+        }
+    }
+
+    #[test]
+    fn peer_ratio() {
+        let decider = PeerDecider::new(0.3, |addr: &SocketAddr| Some(*addr));
+
+        let faulted = (0..10_000u32)
+            .map(|i| SocketAddr::from((i.to_be_bytes(), 80)))
+            .filter(|addr| decider.decide(addr))
+            .count();
+        assert!((2_500..3_500).contains(&faulted), "faulted: {}", faulted);
+    }
+}
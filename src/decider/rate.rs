@@ -0,0 +1,172 @@
+use super::Decider;
+use crate::{
+    describe::{DeciderDescription, DescribeDecider},
+    validate::ValidateDecider,
+    Error,
+};
+use std::{
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, Mutex,
+    },
+    time::{Duration, Instant},
+};
+
+/// Create a decider injecting `count` faults every `total` requests.
+///
+/// See [`PerRequests`] for more information.
+pub fn per_requests(count: u64, total: u64) -> PerRequests {
+    PerRequests::new(count, total)
+}
+
+/// Create a decider injecting up to `count` faults per `period`.
+///
+/// See [`PerDuration`] for more information.
+pub fn per_duration(count: u32, period: Duration) -> PerDuration {
+    PerDuration::new(count, period)
+}
+
+/// Decider that injects `count` faults every `total` requests.
+///
+/// Unlike a probability, the number of faults is exact, and faults are
+/// evenly spread across the requests. For example, 2 faults every 10
+/// requests fault the 5th and 10th requests of each group of 10.
+///
+/// Clones share the same request counter.
+#[derive(Clone, Debug)]
+pub struct PerRequests {
+    count: u64,
+    total: u64,
+    seen: Arc<AtomicU64>,
+}
+
+impl PerRequests {
+    /// Create a new `PerRequests` decider.
+    pub fn new(count: u64, total: u64) -> Self {
+        Self {
+            count,
+            total,
+            seen: Arc::new(AtomicU64::new(0)),
+        }
+    }
+}
+
+impl<R> Decider<R> for PerRequests {
+    fn decide(&self, _: &R) -> bool {
+        if self.total == 0 {
+            return false;
+        }
+        let n = self.seen.fetch_add(1, Ordering::Relaxed) % self.total;
+        // Inject when the number of expected faults crosses an integer.
+        (n + 1) * self.count / self.total > n * self.count / self.total
+    }
+}
+
+impl ValidateDecider for PerRequests {
+    fn validate_decider(&self) -> Result<(), Error> {
+        if self.total == 0 || self.count > self.total {
+            return Err(Error::InvalidConfig(format!(
+                "invalid rate: {} per {} requests",
+                self.count, self.total
+            )));
+        }
+        Ok(())
+    }
+}
+
+impl DescribeDecider for PerRequests {
+    fn describe_decider(&self) -> DeciderDescription {
+        let kind = format!("{} per {} requests", self.count, self.total);
+        let ratio = (self.total > 0).then(|| self.count as f64 / self.total as f64);
+        DeciderDescription::new(kind, ratio)
+    }
+}
+
+/// Decider that injects up to `count` faults per `period`.
+///
+/// Faults are spaced by at least `period / count`: the first request after
+/// that interval is faulted. If there are fewer requests than `count` in a
+/// period, fewer faults are injected.
+///
+/// Clones share the same state.
+#[derive(Clone, Debug)]
+pub struct PerDuration {
+    count: u32,
+    period: Duration,
+    next: Arc<Mutex<Option<Instant>>>,
+}
+
+impl PerDuration {
+    /// Create a new `PerDuration` decider.
+    pub fn new(count: u32, period: Duration) -> Self {
+        Self {
+            count,
+            period,
+            next: Arc::new(Mutex::new(None)),
+        }
+    }
+}
+
+impl<R> Decider<R> for PerDuration {
+    fn decide(&self, _: &R) -> bool {
+        if self.count == 0 {
+            return false;
+        }
+        let now = Instant::now();
+        let mut next = self.next.lock().expect("rate lock poisoned");
+        match *next {
+            Some(instant) if now < instant => false,
+            _ => {
+                *next = Some(now + self.period / self.count);
+                true
+            }
+        }
+    }
+}
+
+impl ValidateDecider for PerDuration {
+    fn validate_decider(&self) -> Result<(), Error> {
+        if self.period.is_zero() {
+            return Err(Error::InvalidConfig(
+                "invalid rate: period must be greater than zero".to_string(),
+            ));
+        }
+        Ok(())
+    }
+}
+
+impl DescribeDecider for PerDuration {
+    fn describe_decider(&self) -> DeciderDescription {
+        DeciderDescription::new(format!("{} per {:?}", self.count, self.period), None)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn per_requests_exact() {
+        let decider = per_requests(2, 10);
+        let decisions = (0..20).map(|_| decider.decide(&())).collect::<Vec<_>>();
+        let faulted = decisions
+            .iter()
+            .enumerate()
+            .filter(|(_, d)| **d)
+            .map(|(i, _)| i)
+            .collect::<Vec<_>>();
+        assert_eq!(faulted, vec![4, 9, 14, 19]);
+
+        assert!(per_requests(11, 10).validate_decider().is_err());
+    }
+
+    #[test]
+    fn per_duration_spacing() {
+        let decider = per_duration(1, Duration::from_secs(3600));
+        assert!(decider.decide(&()));
+        assert!(!decider.decide(&()));
+        assert!(!decider.clone().decide(&()));
+
+        assert!(per_duration(1, Duration::ZERO).validate_decider().is_err());
+    }
+}
@@ -0,0 +1,185 @@
+use super::Decider;
+use crate::{
+    describe::{DeciderDescription, DescribeDecider},
+    validate::ValidateDecider,
+    Error,
+};
+use rand::Rng;
+use std::fmt;
+
+/// Curve mapping a payload size, in bytes, to a value.
+///
+/// This is used by [`ProbabilityBySize`], and by
+/// [`LatencyBySize`](crate::latency::LatencyBySize) with values in
+/// milliseconds, to model bigger payloads being slower and failing more
+/// often.
+#[derive(Clone, Debug, PartialEq)]
+pub enum SizeCurve {
+    /// `base + per_kib * size / 1024`.
+    Linear {
+        /// Value for an empty payload.
+        base: f64,
+        /// Increase for each KiB of payload.
+        per_kib: f64,
+    },
+    /// Value increasing by `per_doubling` every time the size doubles above
+    /// 1 KiB, starting from `base`.
+    Log {
+        /// Value for payloads up to 1 KiB.
+        base: f64,
+        /// Increase every time the size doubles.
+        per_doubling: f64,
+    },
+    /// Value of the largest step whose size is lower than or equal to the
+    /// payload size, or 0.0 below the first step.
+    Steps(Vec<(usize, f64)>),
+}
+
+impl SizeCurve {
+    /// Create a linear curve.
+    pub fn linear(base: f64, per_kib: f64) -> Self {
+        SizeCurve::Linear { base, per_kib }
+    }
+
+    /// Create a logarithmic curve.
+    pub fn log(base: f64, per_doubling: f64) -> Self {
+        SizeCurve::Log { base, per_doubling }
+    }
+
+    /// Create a step curve from `(size, value)` pairs.
+    pub fn steps(steps: impl IntoIterator<Item = (usize, f64)>) -> Self {
+        let mut steps = steps.into_iter().collect::<Vec<_>>();
+        steps.sort_by_key(|(size, _)| *size);
+        SizeCurve::Steps(steps)
+    }
+
+    /// Returns the value for the given size in bytes.
+    pub fn value(&self, size: usize) -> f64 {
+        let kib = size as f64 / 1024.0;
+        match self {
+            SizeCurve::Linear { base, per_kib } => base + per_kib * kib,
+            SizeCurve::Log { base, per_doubling } => base + per_doubling * kib.max(1.0).log2(),
+            SizeCurve::Steps(steps) => steps
+                .iter()
+                .rev()
+                .find(|(min, _)| *min <= size)
+                .map_or(0.0, |(_, value)| *value),
+        }
+    }
+
+    pub(crate) fn validate(&self) -> Result<(), Error> {
+        let params = match self {
+            SizeCurve::Linear { base, per_kib } => vec![*base, *per_kib],
+            SizeCurve::Log { base, per_doubling } => vec![*base, *per_doubling],
+            SizeCurve::Steps(steps) => steps.iter().map(|(_, value)| *value).collect(),
+        };
+        match params.into_iter().find(|value| !value.is_finite()) {
+            Some(value) => Err(Error::InvalidConfig(format!(
+                "invalid size curve parameter: {}",
+                value
+            ))),
+            None => Ok(()),
+        }
+    }
+}
+
+impl fmt::Display for SizeCurve {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SizeCurve::Linear { base, per_kib } => write!(f, "{} + {}/KiB", base, per_kib),
+            SizeCurve::Log { base, per_doubling } => {
+                write!(f, "{} + {}/doubling", base, per_doubling)
+            }
+            SizeCurve::Steps(steps) => {
+                let steps = steps
+                    .iter()
+                    .map(|(size, value)| format!("{}B: {}", size, value))
+                    .collect::<Vec<_>>();
+                write!(f, "steps [{}]", steps.join(", "))
+            }
+        }
+    }
+}
+
+/// Decider whose probability scales with the size of the request.
+///
+/// The size is extracted from the request with a closure, such as the
+/// length of the body or the value of a `Content-Length` header, and mapped
+/// to a probability with a [`SizeCurve`]. Probabilities are clamped between
+/// 0.0 and 1.0.
+#[derive(Clone)]
+pub struct ProbabilityBySize<F> {
+    size: F,
+    curve: SizeCurve,
+}
+
+impl<F> ProbabilityBySize<F> {
+    /// Create a new `ProbabilityBySize` decider.
+    pub fn new(size: F, curve: SizeCurve) -> Self {
+        Self { size, curve }
+    }
+}
+
+impl<F> fmt::Debug for ProbabilityBySize<F> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ProbabilityBySize")
+            .field("curve", &self.curve)
+            .finish()
+    }
+}
+
+impl<F, R> Decider<R> for ProbabilityBySize<F>
+where
+    F: Fn(&R) -> usize,
+{
+    fn decide(&self, req: &R) -> bool {
+        let probability = self.curve.value((self.size)(req));
+        if probability.is_nan() {
+            return false;
+        }
+        crate::seed::rng().gen_bool(probability.clamp(0.0, 1.0))
+    }
+}
+
+impl<F> ValidateDecider for ProbabilityBySize<F> {
+    fn validate_decider(&self) -> Result<(), Error> {
+        self.curve.validate()
+    }
+}
+
+impl<F> DescribeDecider for ProbabilityBySize<F> {
+    fn describe_decider(&self) -> DeciderDescription {
+        DeciderDescription::new(format!("probability by size ({})", self.curve), None)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn size_curve_values() {
+        assert_eq!(SizeCurve::linear(0.5, 0.25).value(2048), 1.0);
+        assert_eq!(SizeCurve::log(0.1, 0.1).value(512), 0.1);
+        assert_eq!(SizeCurve::log(1.0, 1.0).value(4096), 3.0);
+
+        let steps = SizeCurve::steps([(1_000_000, 0.5), (1_000, 0.1)]);
+        assert_eq!(steps.value(10), 0.0);
+        assert_eq!(steps.value(10_000), 0.1);
+        assert_eq!(steps.value(10_000_000), 0.5);
+    }
+
+    #[test]
+    fn probability_by_size() {
+        let decider =
+            ProbabilityBySize::new(|req: &Vec<u8>| req.len(), SizeCurve::linear(0.0, 1.0));
+        assert!(!decider.decide(&Vec::new()));
+        assert!(decider.decide(&vec![0; 2048]));
+
+        assert!(
+            ProbabilityBySize::new(|_: &()| 0, SizeCurve::linear(f64::NAN, 0.0))
+                .validate_decider()
+                .is_err()
+        );
+    }
+}
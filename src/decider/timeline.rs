@@ -0,0 +1,120 @@
+use super::Decider;
+use crate::{
+    describe::{DeciderDescription, DescribeDecider},
+    timeline::Timeline,
+    validate::ValidateDecider,
+    Error,
+};
+use rand::Rng;
+use std::{
+    path::Path,
+    time::{Duration, Instant},
+};
+
+/// Decider replaying a recorded error profile, such as the error rates of a
+/// past outage, over the elapsed time of the experiment.
+///
+/// The timeline has one `offset,rate` point per line, where the offset is a
+/// number of seconds, or a `MM:SS` or `HH:MM:SS` time since the start of the
+/// experiment, and the rate is a probability between 0.0 and 1.0, or a
+/// percentage such as `5%`. Points can also be separated by whitespace, and
+/// lines starting with `#` are ignored.
+///
+/// Each rate applies until the offset of the next point, and the last rate
+/// applies until the end of the experiment, so timelines usually end with a
+/// rate of `0`. No faults are injected before the first point.
+///
+/// ```text
+/// # Per-minute error rates of the 2024-03-12 outage.
+/// 00:00, 0.5%
+/// 01:00, 12%
+/// 02:00, 40%
+/// 06:00, 0
+/// ```
+///
+/// The experiment starts when the timeline is created, unless set with
+/// [`ErrorRateTimeline::starting_at`].
+#[derive(Clone, Debug)]
+pub struct ErrorRateTimeline {
+    timeline: Timeline<f64>,
+}
+
+impl ErrorRateTimeline {
+    /// Parse an error rate timeline.
+    pub fn parse(input: &str) -> Result<Self, Error> {
+        Timeline::parse(input, parse_rate).map(|timeline| Self { timeline })
+    }
+
+    /// Read and parse an error rate timeline file.
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Self, Error> {
+        Timeline::read(path.as_ref(), parse_rate).map(|timeline| Self { timeline })
+    }
+
+    /// Set the start of the experiment.
+    pub fn starting_at(self, start: Instant) -> Self {
+        Self {
+            timeline: self.timeline.starting_at(start),
+        }
+    }
+
+    /// Returns the error rate at the given elapsed time since the start of
+    /// the experiment.
+    pub fn rate_at(&self, elapsed: Duration) -> f64 {
+        self.timeline.at(elapsed).unwrap_or(0.0)
+    }
+}
+
+fn parse_rate(value: &str) -> Option<f64> {
+    let rate = match value.strip_suffix('%') {
+        Some(percent) => percent.trim().parse::<f64>().ok()? / 100.0,
+        None => value.parse::<f64>().ok()?,
+    };
+    (0.0..=1.0).contains(&rate).then_some(rate)
+}
+
+impl<R> Decider<R> for ErrorRateTimeline {
+    fn decide(&self, _req: &R) -> bool {
+        let rate = self.timeline.current().unwrap_or(0.0);
+        rate > 0.0 && crate::seed::rng().gen_bool(rate)
+    }
+}
+
+impl ValidateDecider for ErrorRateTimeline {
+    fn validate_decider(&self) -> Result<(), Error> {
+        Ok(())
+    }
+}
+
+impl DescribeDecider for ErrorRateTimeline {
+    fn describe_decider(&self) -> DeciderDescription {
+        DeciderDescription::new(
+            format!(
+                "timeline ({} points over {:?})",
+                self.timeline.len(),
+                self.timeline.duration()
+            ),
+            Some(self.timeline.current().unwrap_or(0.0)),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn error_rate_timeline() {
+        let timeline = ErrorRateTimeline::parse("0,0.5%\n1:00,1\n2:00,0").unwrap();
+        assert_eq!(timeline.rate_at(Duration::from_secs(30)), 0.005);
+        assert_eq!(timeline.rate_at(Duration::from_secs(90)), 1.0);
+        assert_eq!(timeline.rate_at(Duration::from_secs(600)), 0.0);
+
+        let start = Instant::now() - Duration::from_secs(90);
+        assert!(timeline.clone().starting_at(start).decide(&()));
+        let start = Instant::now() - Duration::from_secs(600);
+        assert!(!timeline.starting_at(start).decide(&()));
+
+        assert!(ErrorRateTimeline::parse("0,150%").is_err());
+        assert!(ErrorRateTimeline::parse("0,-0.1").is_err());
+    }
+}
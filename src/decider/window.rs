@@ -0,0 +1,140 @@
+use super::{Decider, ExplainStep, Explanation, Verdict};
+use crate::{
+    describe::{DeciderDescription, DescribeDecider},
+    validate::ValidateDecider,
+    Error,
+};
+use std::{
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::{Duration, Instant},
+};
+
+/// Decider that only injects faults during a time window.
+///
+/// This can simulate cold-start flakiness, such as warming caches or slowly
+/// filling connection pools, by only faulting requests during the first
+/// seconds after the service starts. Once the window is over, the decider
+/// disables itself permanently.
+///
+/// Clones share the same window.
+#[derive(Clone, Debug)]
+pub struct OnlyDuring<D = bool> {
+    inner: D,
+    until: Instant,
+    expired: Arc<AtomicBool>,
+}
+
+impl OnlyDuring {
+    /// Create a new `OnlyDuring` decider that faults all the requests during
+    /// the given duration, starting now.
+    ///
+    /// Use [`OnlyDuring::with_decider`] to only fault some of the requests
+    /// during that window.
+    pub fn first(duration: Duration) -> Self {
+        Self {
+            inner: true,
+            until: Instant::now() + duration,
+            expired: Arc::new(AtomicBool::new(false)),
+        }
+    }
+}
+
+impl<D> OnlyDuring<D> {
+    /// Set the decider used during the window.
+    pub fn with_decider<ND>(self, decider: ND) -> OnlyDuring<ND> {
+        OnlyDuring {
+            inner: decider,
+            until: self.until,
+            expired: self.expired,
+        }
+    }
+
+    /// Returns `true` if the window is over.
+    pub fn is_expired(&self) -> bool {
+        if self.expired.load(Ordering::Relaxed) {
+            return true;
+        }
+        if Instant::now() >= self.until {
+            self.expired.store(true, Ordering::Relaxed);
+            return true;
+        }
+        false
+    }
+}
+
+impl<D, R> Decider<R> for OnlyDuring<D>
+where
+    D: Decider<R>,
+{
+    fn decide(&self, req: &R) -> bool {
+        !self.is_expired() && self.inner.decide(req)
+    }
+
+    fn verdict(&self, req: &R) -> Verdict {
+        if self.is_expired() {
+            Verdict::Pass
+        } else {
+            self.inner.verdict(req)
+        }
+    }
+
+    fn explain(&self, req: &R, explanation: &mut Explanation) -> Verdict {
+        let expired = self.is_expired();
+        let verdict = if expired {
+            Verdict::Pass
+        } else {
+            self.inner.explain(req, explanation)
+        };
+        let detail = if expired {
+            "window expired"
+        } else {
+            "within window"
+        };
+        explanation.record(ExplainStep::new("only during", verdict).with_detail(detail));
+        verdict
+    }
+}
+
+impl<D> ValidateDecider for OnlyDuring<D>
+where
+    D: ValidateDecider,
+{
+    fn validate_decider(&self) -> Result<(), Error> {
+        self.inner.validate_decider()
+    }
+}
+
+impl<D> DescribeDecider for OnlyDuring<D>
+where
+    D: DescribeDecider,
+{
+    fn describe_decider(&self) -> DeciderDescription {
+        let inner = self.inner.describe_decider();
+        if self.is_expired() {
+            DeciderDescription::new(format!("{} (window expired)", inner.kind), Some(0.0))
+        } else {
+            let remaining = self.until.saturating_duration_since(Instant::now());
+            let kind = format!("{} (for {:?})", inner.kind, remaining);
+            DeciderDescription::new(kind, inner.probability)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_during_window() {
+        let decider = OnlyDuring::first(Duration::from_secs(3600));
+        assert!(decider.decide(&()));
+        assert!(!decider.clone().with_decider(false).decide(&()));
+
+        let decider = OnlyDuring::first(Duration::ZERO);
+        assert!(!decider.decide(&()));
+        assert!(decider.is_expired());
+    }
+}
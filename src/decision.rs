@@ -0,0 +1,278 @@
+//! # Fault decisions for custom layers
+//!
+//! This module exposes the decide, sample and apply flow of the layers of
+//! this crate, so that downstream crates can build their own fault layers,
+//! such as Kafka producer faults, while reusing the deciders, the kill
+//! switch, dry-run mode and observers.
+//!
+//! A [`FaultPolicy`] returns a [`FaultDecision`] for each request. Only
+//! [`FaultDecision::Inject`] should alter the behavior of the service.
+//!
+//! ## Example
+//!
+//! ```rust
+//! use tower_fault::{
+//!     decision::{FaultDecision, FaultPolicy},
+//!     registry::FaultRegistry,
+//! };
+//! # struct Record;
+//!
+//! let registry = FaultRegistry::new();
+//! let policy = FaultPolicy::new("kafka-produce", 0.1).with_kill_switch(registry.kill_switch());
+//!
+//! # let record = Record;
+//! match policy.decide(&record) {
+//!     FaultDecision::Inject(()) => { /* fail the produce request */ }
+//!     FaultDecision::Report(()) | FaultDecision::Pass => { /* send the record */ }
+//! }
+//! ```
+
+use crate::{
+    decider::{Decider, Suppression},
+    describe::{DescribeDecider, FaultDescription},
+    observe::FaultEvent,
+    options::FaultOptions,
+    registry::KillSwitch,
+    validate::ValidateDecider,
+    veto::Vetoed,
+    Error,
+};
+#[cfg(feature = "latency")]
+use std::time::Duration;
+
+/// Outcome of a [`FaultPolicy`] for a request.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FaultDecision<T = ()> {
+    /// No fault for this request.
+    Pass,
+    /// A fault was decided, but must only be reported, in dry-run or shadow
+    /// mode.
+    Report(T),
+    /// A fault must be injected.
+    Inject(T),
+}
+
+impl<T> FaultDecision<T> {
+    /// Returns `true` if a fault must be injected.
+    pub fn is_inject(&self) -> bool {
+        matches!(self, FaultDecision::Inject(_))
+    }
+
+    /// Returns the value of the fault to inject, if any.
+    pub fn injected(self) -> Option<T> {
+        match self {
+            FaultDecision::Inject(value) => Some(value),
+            _ => None,
+        }
+    }
+
+    /// Maps the value of the fault.
+    pub fn map<U>(self, f: impl FnOnce(T) -> U) -> FaultDecision<U> {
+        match self {
+            FaultDecision::Pass => FaultDecision::Pass,
+            FaultDecision::Report(value) => FaultDecision::Report(f(value)),
+            FaultDecision::Inject(value) => FaultDecision::Inject(f(value)),
+        }
+    }
+}
+
+/// Decides whether to inject a fault, with the same options as the layers
+/// of this crate.
+#[derive(Clone, Debug)]
+pub struct FaultPolicy<D> {
+    fault: &'static str,
+    decider: D,
+    options: FaultOptions,
+    kill_switch: Option<KillSwitch>,
+}
+
+impl<D> FaultPolicy<D> {
+    /// Create a new `FaultPolicy` for the given kind of fault, such as
+    /// `kafka-produce`, using the given decider.
+    pub fn new(fault: &'static str, decider: D) -> Self {
+        Self {
+            fault,
+            decider,
+            options: FaultOptions::default(),
+            kill_switch: None,
+        }
+    }
+
+    crate::options::impl_fault_options! { "policy" }
+
+    /// Never inject faults while the given kill switch is engaged.
+    pub fn with_kill_switch(mut self, kill_switch: KillSwitch) -> Self {
+        self.kill_switch = Some(kill_switch);
+        self
+    }
+
+    /// Never inject faults into the requests vetoed by the given veto.
+    ///
+    /// See the [`veto`](crate::veto) module for more information.
+    pub fn with_veto<V>(self, veto: V) -> FaultPolicy<Vetoed<D, V>> {
+        FaultPolicy {
+            fault: self.fault,
+            decider: Vetoed::new(self.decider, veto),
+            options: self.options,
+            kill_switch: self.kill_switch,
+        }
+    }
+
+    /// Returns whether a fault must be injected into the request.
+    pub fn decide<R>(&self, req: &R) -> FaultDecision
+    where
+        D: Decider<R>,
+    {
+        self.decide_with(req, |_| (), |event, _| event)
+    }
+
+    /// Returns whether a fault must be injected into the request, along with
+    /// the latency sampled from the given distribution.
+    #[cfg(feature = "latency")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "latency")))]
+    pub fn sample<R, Di>(&self, req: &R, distribution: &Di) -> FaultDecision<Duration>
+    where
+        D: Decider<R>,
+        Di: crate::latency::Distribution<R>,
+    {
+        self.decide_with(
+            req,
+            |req| distribution.sample(req),
+            |event, latency| event.with_latency(*latency),
+        )
+    }
+
+    fn decide_with<R, T>(
+        &self,
+        req: &R,
+        value: impl FnOnce(&R) -> T,
+        event: impl FnOnce(FaultEvent, &T) -> FaultEvent,
+    ) -> FaultDecision<T>
+    where
+        D: Decider<R>,
+    {
+        if !self.options.is_active(self.fault) {
+            return FaultDecision::Pass;
+        }
+        if self
+            .kill_switch
+            .as_ref()
+            .is_some_and(KillSwitch::is_engaged)
+        {
+            // The decider isn't evaluated, so that stateful deciders are
+            // left untouched while the kill switch is engaged.
+            if self.options.observer.is_some() {
+                self.options.suppress(self.fault, Suppression::KillSwitch);
+            }
+            return FaultDecision::Pass;
+        }
+        if !self.options.decide(self.fault, &self.decider, req) {
+            return FaultDecision::Pass;
+        }
+        let value = value(req);
+        if self
+            .options
+            .inject(event(FaultEvent::new(self.fault), &value))
+        {
+            FaultDecision::Inject(value)
+        } else {
+            FaultDecision::Report(value)
+        }
+    }
+}
+
+impl<D> FaultPolicy<D>
+where
+    D: ValidateDecider,
+{
+    /// Validate the configuration of the policy.
+    pub fn build(self) -> Result<Self, Error> {
+        self.decider.validate_decider()?;
+        Ok(self)
+    }
+}
+
+impl<D> FaultPolicy<D>
+where
+    D: DescribeDecider,
+{
+    /// Returns a description of the configured fault.
+    pub fn describe(&self) -> FaultDescription {
+        let enabled = self.options.enabled
+            && !self
+                .kill_switch
+                .as_ref()
+                .is_some_and(KillSwitch::is_engaged);
+        FaultDescription::new(self.fault, self.decider.describe_decider()).with_enabled(enabled)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{decider::Budget, observe::FaultObserver};
+    use std::sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    };
+
+    #[test]
+    fn policy_decisions() {
+        let kill_switch = KillSwitch::default();
+        let policy = FaultPolicy::new("custom", true).with_kill_switch(kill_switch.clone());
+        assert_eq!(policy.decide(&()), FaultDecision::Inject(()));
+
+        kill_switch.engage();
+        assert_eq!(policy.decide(&()), FaultDecision::Pass);
+        assert_eq!(
+            policy.describe().to_string(),
+            "custom (disabled): always (100%)"
+        );
+
+        let policy = FaultPolicy::new("custom", true).dry_run(true);
+        assert_eq!(policy.decide(&()), FaultDecision::Report(()));
+        assert_eq!(
+            FaultPolicy::new("custom", 1.5).build().err(),
+            Some(Error::InvalidProbability(1.5))
+        );
+    }
+
+    #[test]
+    fn policy_kill_switch_budget() {
+        struct Recorder(Arc<AtomicUsize>);
+        impl FaultObserver for Recorder {
+            fn on_fault(&self, _event: &FaultEvent) {}
+            fn on_suppressed(&self, _event: &crate::observe::SuppressedEvent) {
+                self.0.fetch_add(1, Ordering::Relaxed);
+            }
+        }
+
+        let kill_switch = KillSwitch::default();
+        let budget = Budget::new(true, 1);
+        let suppressed = Arc::new(AtomicUsize::new(0));
+        let policy = FaultPolicy::new("custom", budget.clone())
+            .with_kill_switch(kill_switch.clone())
+            .with_observer(Recorder(suppressed.clone()));
+
+        // The budget isn't spent while the kill switch is engaged.
+        kill_switch.engage();
+        assert_eq!(policy.decide(&()), FaultDecision::Pass);
+        assert_eq!(budget.remaining(), 1);
+        assert_eq!(suppressed.load(Ordering::Relaxed), 1);
+
+        kill_switch.release();
+        assert_eq!(policy.decide(&()), FaultDecision::Inject(()));
+        assert_eq!(budget.remaining(), 0);
+    }
+
+    #[cfg(feature = "latency")]
+    #[test]
+    fn policy_sample() {
+        let policy = FaultPolicy::new("custom", true);
+        let decision = policy.sample(&(), &200);
+        assert_eq!(
+            decision.injected(),
+            Some(std::time::Duration::from_millis(200))
+        );
+    }
+}
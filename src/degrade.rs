@@ -0,0 +1,282 @@
+//! # Graceful degradation
+//!
+//! Layer that calls the inner service normally, but randomly swaps its
+//! responses for a degraded variant, such as cached or partial data. This
+//! models the fallback paths of a service, to verify that clients tolerate
+//! degraded responses.
+//!
+//! Errors returned by the inner service are left untouched.
+//!
+//! ## Usage
+//!
+//! ```rust
+//! use tower::{service_fn, ServiceBuilder};
+//! use tower_fault::degrade::DegradeLayer;
+//! # struct MyRequest;
+//! # struct MyResponse { items: Vec<u64>, partial: bool }
+//! # async fn my_service(_req: MyRequest) -> Result<MyResponse, String> {
+//! #     Ok(MyResponse { items: Vec::new(), partial: false })
+//! # }
+//!
+//! // Only return the first item for 10% of the requests.
+//! let degrade_layer = DegradeLayer::new(0.1, |mut res: MyResponse| {
+//!     res.items.truncate(1);
+//!     res.partial = true;
+//!     res
+//! });
+//!
+//! let service = ServiceBuilder::new()
+//!     .layer(degrade_layer)
+//!     .service(service_fn(my_service));
+//! ```
+//!
+//! ### Degrader
+//!
+//! The __degrader__ turns the response of the inner service into its
+//! degraded variant. This can be a closure, or a custom implementation of
+//! the [`Degrader`] trait.
+
+use crate::{
+    decider::{Decider, WithContext},
+    describe::{DescribeDecider, FaultDescription},
+    observe::FaultEvent,
+    options::FaultOptions,
+    validate::ValidateDecider,
+    veto::Vetoed,
+    Error,
+};
+use std::{
+    fmt,
+    future::Future,
+    pin::Pin,
+    task::{Context, Poll},
+};
+use tower::{Layer, Service};
+
+/// Trait to turn a response into its degraded variant.
+pub trait Degrader<T> {
+    /// Returns the degraded variant of the response.
+    fn degrade(&self, response: T) -> T;
+}
+
+impl<F, T> Degrader<T> for F
+where
+    F: Fn(T) -> T,
+{
+    fn degrade(&self, response: T) -> T {
+        self(response)
+    }
+}
+
+/// Layer that randomly swaps the responses of the service for a degraded
+/// variant.
+#[derive(Clone, Debug)]
+pub struct DegradeLayer<D, G> {
+    decider: D,
+    degrader: G,
+    options: FaultOptions,
+}
+
+impl<D, G> DegradeLayer<D, G> {
+    /// Create a new `DegradeLayer` with the given decider and degrader.
+    pub fn new(decider: D, degrader: G) -> Self {
+        Self {
+            decider,
+            degrader,
+            options: FaultOptions::default(),
+        }
+    }
+
+    /// Set the given decider to be used to determine if a response should
+    /// be degraded.
+    pub fn with_decider<ND>(self, decider: ND) -> DegradeLayer<ND, G> {
+        DegradeLayer {
+            decider,
+            degrader: self.degrader,
+            options: self.options,
+        }
+    }
+
+    /// Set the given degrader to degrade responses.
+    pub fn with_degrader<NG>(self, degrader: NG) -> DegradeLayer<D, NG> {
+        DegradeLayer {
+            decider: self.decider,
+            degrader,
+            options: self.options,
+        }
+    }
+
+    /// Call the decider with a `(request, context)` tuple, where the context
+    /// is returned by the given extractor.
+    ///
+    /// See [`WithContext`] for more information.
+    pub fn with_context<X>(self, extractor: X) -> DegradeLayer<WithContext<D, X>, G> {
+        DegradeLayer {
+            decider: WithContext::new(self.decider, extractor),
+            degrader: self.degrader,
+            options: self.options,
+        }
+    }
+
+    /// Never degrade the responses to the requests vetoed by the given veto,
+    /// regardless of the decider.
+    ///
+    /// See the [`veto`](crate::veto) module for more information.
+    pub fn with_veto<V>(self, veto: V) -> DegradeLayer<Vetoed<D, V>, G> {
+        DegradeLayer {
+            decider: Vetoed::new(self.decider, veto),
+            degrader: self.degrader,
+            options: self.options,
+        }
+    }
+
+    crate::options::impl_fault_options! { "layer", shadow }
+}
+
+impl<D, G> DegradeLayer<D, G>
+where
+    D: ValidateDecider,
+{
+    /// Validate the configuration of the layer.
+    ///
+    /// Returns an [`Error`] if the decider is misconfigured, instead of
+    /// panicking at request time.
+    pub fn build(self) -> Result<Self, Error> {
+        self.decider.validate_decider()?;
+        Ok(self)
+    }
+}
+
+impl<D, G> DegradeLayer<D, G>
+where
+    D: DescribeDecider,
+{
+    /// Returns a description of the configured fault.
+    pub fn describe(&self) -> FaultDescription {
+        FaultDescription::new("degrade", self.decider.describe_decider())
+            .with_enabled(self.options.enabled)
+    }
+}
+
+impl<D, G> fmt::Display for DegradeLayer<D, G>
+where
+    D: DescribeDecider,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.describe().fmt(f)
+    }
+}
+
+impl<D, G, S> Layer<S> for DegradeLayer<D, G>
+where
+    D: Clone,
+    G: Clone,
+{
+    type Service = DegradeService<D, G, S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        DegradeService {
+            inner,
+            decider: self.decider.clone(),
+            degrader: self.degrader.clone(),
+            options: self.options.clone(),
+        }
+    }
+}
+
+/// Service that randomly swaps the responses of the underlying service for
+/// a degraded variant.
+#[derive(Clone, Debug)]
+pub struct DegradeService<D, G, S> {
+    inner: S,
+    decider: D,
+    degrader: G,
+    options: FaultOptions,
+}
+
+impl<D, G, S> DegradeService<D, G, S>
+where
+    D: DescribeDecider,
+{
+    /// Returns a description of the configured fault.
+    pub fn describe(&self) -> FaultDescription {
+        FaultDescription::new("degrade", self.decider.describe_decider())
+            .with_enabled(self.options.enabled)
+    }
+}
+
+impl<D, G, S> fmt::Display for DegradeService<D, G, S>
+where
+    D: DescribeDecider,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.describe().fmt(f)
+    }
+}
+
+impl<D, G, S, R> Service<R> for DegradeService<D, G, S>
+where
+    D: Decider<R>,
+    G: Degrader<S::Response> + Clone + Send + 'static,
+    S: Service<R>,
+    S::Future: Send + 'static,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = DegradeFuture<S::Response, S::Error>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, request: R) -> Self::Future {
+        if self.options.is_active("degrade")
+            && self.options.decide("degrade", &self.decider, &request)
+        {
+            let fut = self.inner.call(request);
+            if self.options.shadow {
+                // Degraded responses are still successful.
+                return Box::pin(self.options.shadow_call(
+                    FaultEvent::new("degrade"),
+                    fut,
+                    |actual| actual,
+                ));
+            }
+            if self.options.inject(FaultEvent::new("degrade")) {
+                let degrader = self.degrader.clone();
+                return Box::pin(
+                    async move { fut.await.map(|response| degrader.degrade(response)) },
+                );
+            }
+            return Box::pin(fut);
+        }
+
+        Box::pin(self.inner.call(request))
+    }
+}
+
+/// Future returned by [`DegradeService`].
+pub type DegradeFuture<T, E> = Pin<Box<dyn Future<Output = Result<T, E>> + Send>>;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::*;
+
+    #[tokio::test]
+    async fn degrade_response() {
+        let layer = DegradeLayer::new(true, |res: String| format!("{} (cached)", res));
+        let mut service = layer.layer(DummyService);
+        assert_eq!(service.call(()).await.unwrap(), "ok (cached)");
+
+        let mut service = layer.enabled(false).layer(DummyService);
+        assert_eq!(service.call(()).await.unwrap(), "ok");
+    }
+
+    #[tokio::test]
+    async fn degrade_dry_run() {
+        let layer = DegradeLayer::new(true, |_: String| String::new()).dry_run(true);
+        let mut service = layer.layer(DummyService);
+        assert_eq!(service.call(()).await.unwrap(), "ok");
+    }
+}
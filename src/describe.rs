@@ -0,0 +1,163 @@
+//! # Introspection
+//!
+//! Layers and services have a `describe()` method, and implement `Display`,
+//! to report the faults they are configured with. This can be used to log
+//! at startup exactly which faults are armed in a process.
+//!
+//! Descriptions rely on the [`DescribeDecider`] and [`DescribeDistribution`]
+//! traits, which are implemented for the deciders and distributions of this
+//! crate. Like for [validation](crate::validate), closures cannot be
+//! described.
+//!
+//! ```rust
+//! use tower_fault::latency::LatencyLayer;
+//!
+//! let latency_layer = LatencyLayer::new(0.1, 200..500);
+//! assert_eq!(
+//!     latency_layer.to_string(),
+//!     "latency: probability (10%), distribution 200ms..500ms"
+//! );
+//! ```
+//!
+//! Descriptions also carry the [experiment seed](crate::seed), so that the
+//! faults logged at startup can be reproduced.
+//!
+//! ```rust
+//! use tower_fault::{latency::LatencyLayer, seed};
+//!
+//! let latency_layer = LatencyLayer::new(0.1, 200..500);
+//! assert_eq!(latency_layer.describe().seed, Some(seed::seed()));
+//! ```
+
+use alloc::{format, string::String};
+use core::fmt;
+
+/// Description of a configured fault.
+#[derive(Clone, Debug, PartialEq)]
+pub struct FaultDescription {
+    /// Kind of fault, such as `latency` or `error`.
+    pub fault: &'static str,
+    /// Whether the layer is enabled.
+    pub enabled: bool,
+    /// Description of the decider.
+    pub decider: DeciderDescription,
+    /// Description of the distribution, for faults that have one.
+    pub distribution: Option<String>,
+    /// [Experiment seed](crate::seed) used for the random decisions, if
+    /// the `std` feature is enabled.
+    pub seed: Option<u64>,
+}
+
+impl FaultDescription {
+    /// Create a new `FaultDescription` for an enabled fault, with the
+    /// current experiment seed.
+    pub fn new(fault: &'static str, decider: DeciderDescription) -> Self {
+        Self {
+            fault,
+            enabled: true,
+            decider,
+            distribution: None,
+            #[cfg(feature = "std")]
+            seed: Some(crate::seed::seed()),
+            #[cfg(not(feature = "std"))]
+            seed: None,
+        }
+    }
+
+    /// Set whether the fault is enabled.
+    pub fn with_enabled(mut self, enabled: bool) -> Self {
+        self.enabled = enabled;
+        self
+    }
+
+    /// Set the description of the distribution.
+    pub fn with_distribution(mut self, distribution: String) -> Self {
+        self.distribution = Some(distribution);
+        self
+    }
+}
+
+impl fmt::Display for FaultDescription {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.fault)?;
+        if !self.enabled {
+            f.write_str(" (disabled)")?;
+        }
+        write!(f, ": {}", self.decider)?;
+        if let Some(distribution) = &self.distribution {
+            write!(f, ", distribution {}", distribution)?;
+        }
+        Ok(())
+    }
+}
+
+/// Description of a decider.
+#[derive(Clone, Debug, PartialEq)]
+pub struct DeciderDescription {
+    /// Kind of decider, such as `probability` or `baggage`.
+    pub kind: String,
+    /// Probability of injecting a fault, if it doesn't depend on the
+    /// request.
+    pub probability: Option<f64>,
+}
+
+impl DeciderDescription {
+    /// Create a new `DeciderDescription`.
+    pub fn new(kind: impl Into<String>, probability: Option<f64>) -> Self {
+        Self {
+            kind: kind.into(),
+            probability,
+        }
+    }
+}
+
+impl fmt::Display for DeciderDescription {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.kind)?;
+        if let Some(probability) = self.probability {
+            write!(f, " ({}%)", percent(probability))?;
+        }
+        Ok(())
+    }
+}
+
+/// Format a probability as a percentage, with at most four decimals and
+/// without trailing zeros, so that `0.07` is `7` and not `7.000000000000001`.
+pub(crate) fn percent(probability: f64) -> String {
+    let formatted = format!("{:.4}", probability * 100.0);
+    let trimmed = formatted.trim_end_matches('0').trim_end_matches('.');
+    match trimmed {
+        "-0" => String::from("0"),
+        trimmed => String::from(trimmed),
+    }
+}
+
+/// Trait for deciders that can be described.
+pub trait DescribeDecider {
+    /// Returns a description of the decider.
+    fn describe_decider(&self) -> DeciderDescription;
+}
+
+/// Trait for latency distributions that can be described.
+pub trait DescribeDistribution {
+    /// Returns a description of the distribution parameters.
+    fn describe_distribution(&self) -> String;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn describe_percent() {
+        assert_eq!(percent(0.07), "7");
+        assert_eq!(percent(0.25), "25");
+        assert_eq!(percent(1.0), "100");
+        assert_eq!(percent(0.0), "0");
+        assert_eq!(percent(0.00012345), "0.0123");
+        assert_eq!(
+            DeciderDescription::new("probability", Some(0.07)).to_string(),
+            "probability (7%)"
+        );
+    }
+}
@@ -0,0 +1,283 @@
+//! # Discovery fault injection
+//!
+//! This module contains [`FaultDiscover`], which wraps a
+//! [`Discover`](tower::discover::Discover) stream and randomly delays, drops,
+//! or duplicates its service insertion and removal events. This simulates a
+//! flapping service discovery, which is a different injection point than
+//! request-level faults.
+//!
+//! Deciders and distributions receive the [`Change`] event.
+//!
+//! ## Example
+//!
+//! ```rust
+//! use tower::discover::ServiceList;
+//! use tower_fault::discover::FaultDiscover;
+//! # #[derive(Clone)]
+//! # struct Backend;
+//! # impl tower::Service<()> for Backend {
+//! #     type Response = ();
+//! #     type Error = ();
+//! #     type Future = std::future::Ready<Result<(), ()>>;
+//! #     fn poll_ready(&mut self, _: &mut std::task::Context<'_>) -> std::task::Poll<Result<(), ()>> {
+//! #         std::task::Poll::Ready(Ok(()))
+//! #     }
+//! #     fn call(&mut self, _: ()) -> Self::Future {
+//! #         std::future::ready(Ok(()))
+//! #     }
+//! # }
+//!
+//! let discover = ServiceList::new::<()>(vec![Backend, Backend]);
+//!
+//! let discover = FaultDiscover::new(discover)
+//!     // Delay 10% of the events by 1 to 5 seconds.
+//!     .with_delay(0.1, 1_000..5_000)
+//!     // Drop 5% of the events.
+//!     .with_drop(0.05)
+//!     // Duplicate 5% of the events.
+//!     .with_duplicate(0.05);
+//! ```
+
+use crate::{decider::Decider, latency::Distribution};
+use futures_core::Stream;
+use pin_project_lite::pin_project;
+use std::{
+    future::Future,
+    pin::Pin,
+    task::{Context, Poll},
+};
+use tokio::time::{self, Sleep};
+use tower::discover::Change;
+
+pin_project! {
+    /// Stream of discovery changes with injected faults.
+    ///
+    /// Duplicating an event requires both the key and the service to be
+    /// [`Clone`].
+    #[derive(Debug)]
+    pub struct FaultDiscover<D, K, S, De = bool, Di = u64, Dr = bool, Du = bool> {
+        #[pin]
+        inner: D,
+        delay: De,
+        distribution: Di,
+        drop: Dr,
+        duplicate: Du,
+        sleep: Option<Pin<Box<Sleep>>>,
+        delayed: Option<Change<K, S>>,
+        duplicated: Option<Change<K, S>>,
+    }
+}
+
+impl<D, K, S> FaultDiscover<D, K, S> {
+    /// Wrap the given discover stream. No fault is injected until deciders
+    /// are set with the `with_*` methods.
+    pub fn new<E>(inner: D) -> Self
+    where
+        D: Stream<Item = Result<Change<K, S>, E>>,
+    {
+        Self {
+            inner,
+            delay: false,
+            distribution: 0,
+            drop: false,
+            duplicate: false,
+            sleep: None,
+            delayed: None,
+            duplicated: None,
+        }
+    }
+}
+
+impl<D, K, S, De, Di, Dr, Du> FaultDiscover<D, K, S, De, Di, Dr, Du> {
+    /// Delay events using the given decider and latency distribution.
+    pub fn with_delay<NDe, NDi>(
+        self,
+        decider: NDe,
+        distribution: NDi,
+    ) -> FaultDiscover<D, K, S, NDe, NDi, Dr, Du> {
+        FaultDiscover {
+            inner: self.inner,
+            delay: decider,
+            distribution,
+            drop: self.drop,
+            duplicate: self.duplicate,
+            sleep: self.sleep,
+            delayed: self.delayed,
+            duplicated: self.duplicated,
+        }
+    }
+
+    /// Drop events using the given decider.
+    pub fn with_drop<NDr>(self, decider: NDr) -> FaultDiscover<D, K, S, De, Di, NDr, Du> {
+        FaultDiscover {
+            inner: self.inner,
+            delay: self.delay,
+            distribution: self.distribution,
+            drop: decider,
+            duplicate: self.duplicate,
+            sleep: self.sleep,
+            delayed: self.delayed,
+            duplicated: self.duplicated,
+        }
+    }
+
+    /// Duplicate events using the given decider.
+    pub fn with_duplicate<NDu>(self, decider: NDu) -> FaultDiscover<D, K, S, De, Di, Dr, NDu> {
+        FaultDiscover {
+            inner: self.inner,
+            delay: self.delay,
+            distribution: self.distribution,
+            drop: self.drop,
+            duplicate: decider,
+            sleep: self.sleep,
+            delayed: self.delayed,
+            duplicated: self.duplicated,
+        }
+    }
+}
+
+impl<D, K, S, E, De, Di, Dr, Du> Stream for FaultDiscover<D, K, S, De, Di, Dr, Du>
+where
+    D: Stream<Item = Result<Change<K, S>, E>>,
+    K: Clone,
+    S: Clone,
+    De: Decider<Change<K, S>>,
+    Di: Distribution<Change<K, S>>,
+    Dr: Decider<Change<K, S>>,
+    Du: Decider<Change<K, S>>,
+{
+    type Item = Result<Change<K, S>, E>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let mut this = self.project();
+
+        // A delayed event is emitted before its duplicate.
+        if let Some(sleep) = this.sleep.as_mut() {
+            if sleep.as_mut().poll(cx).is_pending() {
+                return Poll::Pending;
+            }
+            *this.sleep = None;
+            if let Some(change) = this.delayed.take() {
+                return Poll::Ready(Some(Ok(change)));
+            }
+        }
+
+        if let Some(change) = this.duplicated.take() {
+            return Poll::Ready(Some(Ok(change)));
+        }
+
+        loop {
+            let change = match this.inner.as_mut().poll_next(cx) {
+                Poll::Ready(Some(Ok(change))) => change,
+                other => return other,
+            };
+
+            if this.drop.decide(&change) {
+                continue;
+            }
+
+            if this.duplicate.decide(&change) {
+                *this.duplicated = Some(clone_change(&change));
+            }
+
+            if this.delay.decide(&change) {
+                let mut sleep = Box::pin(time::sleep(this.distribution.sample(&change)));
+                if sleep.as_mut().poll(cx).is_pending() {
+                    *this.sleep = Some(sleep);
+                    *this.delayed = Some(change);
+                    return Poll::Pending;
+                }
+            }
+
+            return Poll::Ready(Some(Ok(change)));
+        }
+    }
+}
+
+fn clone_change<K: Clone, S: Clone>(change: &Change<K, S>) -> Change<K, S> {
+    match change {
+        Change::Insert(key, service) => Change::Insert(key.clone(), service.clone()),
+        Change::Remove(key) => Change::Remove(key.clone()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{future::poll_fn, time::Duration};
+
+    type Event = Result<Change<usize, &'static str>, ()>;
+
+    struct Events(Vec<Event>);
+
+    impl Stream for Events {
+        type Item = Event;
+
+        fn poll_next(mut self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<Option<Event>> {
+            if self.0.is_empty() {
+                Poll::Ready(None)
+            } else {
+                Poll::Ready(Some(self.0.remove(0)))
+            }
+        }
+    }
+
+    async fn collect<St: Stream<Item = Event>>(stream: St) -> Vec<usize> {
+        let mut stream = Box::pin(stream);
+        let mut keys = Vec::new();
+        while let Some(Ok(change)) = poll_fn(|cx| stream.as_mut().poll_next(cx)).await {
+            match change {
+                Change::Insert(key, _) | Change::Remove(key) => keys.push(key),
+            }
+        }
+        keys
+    }
+
+    fn events() -> Events {
+        Events(vec![
+            Ok(Change::Insert(1, "a")),
+            Ok(Change::Insert(2, "b")),
+            Ok(Change::Remove(1)),
+        ])
+    }
+
+    #[tokio::test]
+    async fn discover_drop_and_duplicate() {
+        let discover = FaultDiscover::new(events())
+            .with_drop(|change: &Change<usize, &str>| matches!(change, Change::Remove(_)))
+            .with_duplicate(|change: &Change<usize, &str>| matches!(change, Change::Insert(2, _)));
+
+        assert_eq!(collect(discover).await, vec![1, 2, 2]);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn discover_delay() {
+        let discover = FaultDiscover::new(events()).with_delay(true, Duration::from_secs(1));
+
+        let start = time::Instant::now();
+        assert_eq!(collect(discover).await, vec![1, 2, 1]);
+        assert!(start.elapsed() >= Duration::from_secs(3));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn discover_delay_and_duplicate() {
+        let discover = FaultDiscover::new(events())
+            .with_delay(
+                |change: &Change<usize, &str>| matches!(change, Change::Insert(2, _)),
+                Duration::from_secs(1),
+            )
+            .with_duplicate(|change: &Change<usize, &str>| matches!(change, Change::Insert(2, _)));
+
+        let start = time::Instant::now();
+        let mut discover = Box::pin(discover);
+        let mut keys = Vec::new();
+        while let Some(Ok(change)) = poll_fn(|cx| discover.as_mut().poll_next(cx)).await {
+            let delayed = start.elapsed() >= Duration::from_secs(1);
+            match change {
+                Change::Insert(key, _) | Change::Remove(key) => keys.push((key, delayed)),
+            }
+        }
+        // The duplicate isn't emitted before the delayed original.
+        assert_eq!(keys, vec![(1, false), (2, true), (2, true), (1, true)]);
+    }
+}
@@ -0,0 +1,273 @@
+//! # Latency distributions
+//!
+//! A [`Distribution`] returns the latency to inject for a request. This is
+//! implemented for fixed latencies, such as `200` milliseconds or a
+//! [`Duration`], for ranges of latencies, and for closures.
+//!
+//! This module doesn't require `std`, except for sampling ranges of
+//! latencies. The layers using distributions are in the
+//! [`latency`](crate::latency) module.
+//!
+//! ```rust
+//! use std::time::Duration;
+//! use tower_fault::distribution::Distribution;
+//!
+//! assert_eq!(200.sample(&()), Duration::from_millis(200));
+//! assert_eq!(Duration::from_secs(1).sample(&()), Duration::from_secs(1));
+//! ```
+
+use crate::{describe::DescribeDistribution, validate::ValidateDistribution, Error};
+use alloc::{format, string::String};
+use core::{fmt, ops, time::Duration};
+#[cfg(feature = "std")]
+use rand::Rng;
+
+/// Trait that returns a random latency.
+pub trait Distribution<R> {
+    /// Returns a random latency.
+    fn sample(&self, req: &R) -> Duration;
+}
+
+/// Convert milliseconds to a `Duration`, saturating negative and `NaN` values
+/// to zero and values that are too large to `Duration::MAX`.
+pub(crate) fn from_millis_f64(value: f64) -> Duration {
+    if value.is_nan() || value <= 0.0 {
+        Duration::ZERO
+    } else {
+        Duration::try_from_secs_f64(value / 1000.0).unwrap_or(Duration::MAX)
+    }
+}
+
+macro_rules! impl_distribution_fixed {
+    ($t:ty, $ret:tt) => {
+        impl<R> Distribution<R> for $t {
+            fn sample(&self, _req: &R) -> Duration {
+                #[allow(clippy::redundant_closure_call)]
+                $ret(*self)
+            }
+        }
+    };
+}
+impl_distribution_fixed! { f64, from_millis_f64 }
+impl_distribution_fixed! { u64, (Duration::from_millis) }
+impl_distribution_fixed! { Duration, (|value| value) }
+
+// Empty ranges, such as `200..200` or `500..=200`, sample their start value
+// instead of panicking, and infinite bounds are clamped to a finite value
+// first. Use `build()` on the layers to reject them upfront.
+macro_rules! impl_distribution_range {
+    ($t:ty, $ret:tt) => {
+        #[cfg(feature = "std")]
+        #[cfg_attr(docsrs, doc(cfg(feature = "std")))]
+        impl<R> Distribution<R> for ops::Range<$t> {
+            fn sample(&self, _req: &R) -> Duration {
+                let range = self.start.saturate()..self.end.saturate();
+                let value = if range.is_empty() {
+                    range.start
+                } else {
+                    crate::seed::rng().gen_range(range)
+                };
+                #[allow(clippy::redundant_closure_call)]
+                $ret(value)
+            }
+        }
+
+        #[cfg(feature = "std")]
+        #[cfg_attr(docsrs, doc(cfg(feature = "std")))]
+        impl<R> Distribution<R> for ops::RangeInclusive<$t> {
+            fn sample(&self, _req: &R) -> Duration {
+                let range = self.start().saturate()..=self.end().saturate();
+                let value = if range.is_empty() {
+                    *range.start()
+                } else {
+                    crate::seed::rng().gen_range(range)
+                };
+                #[allow(clippy::redundant_closure_call)]
+                $ret(value)
+            }
+        }
+    };
+}
+impl_distribution_range! { f64, from_millis_f64 }
+impl_distribution_range! { u64, (Duration::from_millis) }
+impl_distribution_range! { Duration, (|value| value) }
+
+impl<F, R> Distribution<R> for F
+where
+    F: Fn(&R) -> Duration,
+{
+    fn sample(&self, req: &R) -> Duration {
+        self(req)
+    }
+}
+
+/// Trait for the values of the latency distributions.
+trait Latency: Copy + fmt::Debug {
+    /// Returns `true` if the value is a valid latency.
+    fn is_valid(self) -> bool {
+        true
+    }
+
+    /// Returns the value as a bound that can be sampled from without
+    /// overflowing.
+    #[cfg_attr(not(feature = "std"), allow(dead_code))]
+    fn saturate(self) -> Self {
+        self
+    }
+
+    /// Returns a human-readable representation of the latency.
+    fn describe(self) -> String;
+}
+
+impl Latency for u64 {
+    fn describe(self) -> String {
+        format!("{}ms", self)
+    }
+}
+
+impl Latency for Duration {
+    fn describe(self) -> String {
+        format!("{:?}", self)
+    }
+}
+
+impl Latency for f64 {
+    fn is_valid(self) -> bool {
+        self.is_finite() && self >= 0.0
+    }
+
+    fn saturate(self) -> Self {
+        // A quarter of the largest value, so the width of any range between
+        // two bounds, as scaled by the sampler, is still finite.
+        self.clamp(f64::MIN / 4.0, f64::MAX / 4.0)
+    }
+
+    fn describe(self) -> String {
+        format!("{}ms", self)
+    }
+}
+
+fn validate_range<T: Latency>(
+    range: &impl fmt::Debug,
+    bounds: [T; 2],
+    empty: bool,
+) -> Result<(), Error> {
+    if empty || !bounds.iter().all(|bound| bound.is_valid()) {
+        return Err(Error::InvalidRange(format!("{:?}", range)));
+    }
+    Ok(())
+}
+
+macro_rules! impl_validate_distribution {
+    ($($t:ty),*) => {
+        $(
+            impl ValidateDistribution for $t {
+                fn validate_distribution(&self) -> Result<(), Error> {
+                    if !self.is_valid() {
+                        return Err(Error::InvalidConfig(format!("invalid latency: {:?}", self)));
+                    }
+                    Ok(())
+                }
+            }
+
+            impl ValidateDistribution for ops::Range<$t> {
+                fn validate_distribution(&self) -> Result<(), Error> {
+                    validate_range(self, [self.start, self.end], self.is_empty())
+                }
+            }
+
+            impl ValidateDistribution for ops::RangeInclusive<$t> {
+                fn validate_distribution(&self) -> Result<(), Error> {
+                    validate_range(self, [*self.start(), *self.end()], self.is_empty())
+                }
+            }
+        )*
+    };
+}
+impl_validate_distribution! { f64, u64, Duration }
+
+macro_rules! impl_describe_distribution {
+    ($($t:ty),*) => {
+        $(
+            impl DescribeDistribution for $t {
+                fn describe_distribution(&self) -> String {
+                    self.describe()
+                }
+            }
+
+            impl DescribeDistribution for ops::Range<$t> {
+                fn describe_distribution(&self) -> String {
+                    format!("{}..{}", self.start.describe(), self.end.describe())
+                }
+            }
+
+            impl DescribeDistribution for ops::RangeInclusive<$t> {
+                fn describe_distribution(&self) -> String {
+                    format!("{}..={}", self.start().describe(), self.end().describe())
+                }
+            }
+        )*
+    };
+}
+impl_describe_distribution! { f64, u64, Duration }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn distribution_empty_ranges() {
+        assert_eq!((200..200).sample(&()), Duration::from_millis(200));
+        assert_eq!(
+            ops::RangeInclusive::new(500, 200).sample(&()),
+            Duration::from_millis(500)
+        );
+        assert_eq!((-5.0..-1.0).sample(&()), Duration::ZERO);
+        assert_eq!(f64::NAN.sample(&()), Duration::ZERO);
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn distribution_infinite_ranges() {
+        assert_eq!((f64::INFINITY..f64::INFINITY).sample(&()), Duration::MAX);
+        assert_eq!((f64::MAX..f64::INFINITY).sample(&()), Duration::MAX);
+        assert_eq!(
+            (f64::NEG_INFINITY..=f64::NEG_INFINITY).sample(&()),
+            Duration::ZERO
+        );
+        for _ in 0..100 {
+            (0.0..f64::INFINITY).sample(&());
+            (f64::NEG_INFINITY..=f64::INFINITY).sample(&());
+            (-f64::MAX..f64::MAX).sample(&());
+            (0..=u64::MAX).sample(&());
+            (Duration::ZERO..=Duration::MAX).sample(&());
+        }
+    }
+
+    #[test]
+    fn distribution_validation() {
+        assert!((200..500).validate_distribution().is_ok());
+        assert!((200..=200).validate_distribution().is_ok());
+        assert!(matches!(
+            (200..200).validate_distribution(),
+            Err(Error::InvalidRange(_))
+        ));
+        assert!(matches!(
+            ops::RangeInclusive::new(500, 200).validate_distribution(),
+            Err(Error::InvalidRange(_))
+        ));
+        assert!(matches!(
+            (-5.0..10.0).validate_distribution(),
+            Err(Error::InvalidRange(_))
+        ));
+        assert!(matches!(
+            (0.0..f64::INFINITY).validate_distribution(),
+            Err(Error::InvalidRange(_))
+        ));
+        assert!(matches!(
+            (-1.0).validate_distribution(),
+            Err(Error::InvalidConfig(_))
+        ));
+    }
+}
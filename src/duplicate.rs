@@ -0,0 +1,310 @@
+//! # Duplicate delivery
+//!
+//! Layer that completes the request normally, but randomly delivers the
+//! response a second time to a sink, such as a callback re-invoking the
+//! consumer of an event. This simulates duplicate deliveries in
+//! event-driven systems, to verify that their consumers are idempotent.
+//!
+//! The duplicates are delivered once the inner service returns a successful
+//! response, before the response is returned. Errors are never duplicated.
+//!
+//! ## Usage
+//!
+//! ```rust
+//! use std::sync::mpsc;
+//! use tower::{service_fn, ServiceBuilder};
+//! use tower_fault::duplicate::DuplicateLayer;
+//! # struct MyEvent;
+//! # #[derive(Clone)]
+//! # struct MyAck { id: u64 }
+//! # async fn my_consumer(_event: MyEvent) -> Result<MyAck, String> {
+//! #     Ok(MyAck { id: 1 })
+//! # }
+//!
+//! // Deliver the acknowledgement twice for 10% of the events.
+//! let (sender, duplicates) = mpsc::channel();
+//! let duplicate_layer = DuplicateLayer::new(0.1, move |ack: MyAck| {
+//!     let _ = sender.send(ack);
+//! });
+//!
+//! let service = ServiceBuilder::new()
+//!     .layer(duplicate_layer)
+//!     .service(service_fn(my_consumer));
+//! ```
+//!
+//! ### Sink
+//!
+//! The __sink__ receives a clone of each duplicated response. This can be a
+//! closure, or a custom implementation of the [`Sink`] trait. With
+//! [`DuplicateLayer::copies`], each duplicated response is delivered several
+//! times.
+
+use crate::{
+    decider::{Decider, WithContext},
+    describe::{DescribeDecider, FaultDescription},
+    observe::FaultEvent,
+    options::FaultOptions,
+    validate::ValidateDecider,
+    veto::Vetoed,
+    Error,
+};
+use std::{
+    fmt,
+    future::Future,
+    pin::Pin,
+    task::{Context, Poll},
+};
+use tower::{Layer, Service};
+
+/// Trait to deliver duplicated responses.
+pub trait Sink<T> {
+    /// Deliver a duplicate of the response.
+    fn deliver(&self, response: T);
+}
+
+impl<F, T> Sink<T> for F
+where
+    F: Fn(T),
+{
+    fn deliver(&self, response: T) {
+        self(response)
+    }
+}
+
+/// Layer that randomly delivers the responses of the service a second time
+/// to a sink.
+#[derive(Clone, Debug)]
+pub struct DuplicateLayer<D, G> {
+    decider: D,
+    sink: G,
+    copies: usize,
+    options: FaultOptions,
+}
+
+impl<D, G> DuplicateLayer<D, G> {
+    /// Create a new `DuplicateLayer` with the given decider and sink.
+    pub fn new(decider: D, sink: G) -> Self {
+        Self {
+            decider,
+            sink,
+            copies: 1,
+            options: FaultOptions::default(),
+        }
+    }
+
+    /// Set the number of times each duplicated response is delivered to the
+    /// sink.
+    ///
+    /// Defaults to 1.
+    pub fn copies(mut self, copies: usize) -> Self {
+        self.copies = copies;
+        self
+    }
+
+    /// Set the given decider to be used to determine if a response should
+    /// be duplicated.
+    pub fn with_decider<ND>(self, decider: ND) -> DuplicateLayer<ND, G> {
+        DuplicateLayer {
+            decider,
+            sink: self.sink,
+            copies: self.copies,
+            options: self.options,
+        }
+    }
+
+    /// Set the given sink to deliver the duplicated responses.
+    pub fn with_sink<NG>(self, sink: NG) -> DuplicateLayer<D, NG> {
+        DuplicateLayer {
+            decider: self.decider,
+            sink,
+            copies: self.copies,
+            options: self.options,
+        }
+    }
+
+    /// Call the decider with a `(request, context)` tuple, where the context
+    /// is returned by the given extractor.
+    ///
+    /// See [`WithContext`] for more information.
+    pub fn with_context<X>(self, extractor: X) -> DuplicateLayer<WithContext<D, X>, G> {
+        DuplicateLayer {
+            decider: WithContext::new(self.decider, extractor),
+            sink: self.sink,
+            copies: self.copies,
+            options: self.options,
+        }
+    }
+
+    /// Never duplicate the responses to the requests vetoed by the given veto,
+    /// regardless of the decider.
+    ///
+    /// See the [`veto`](crate::veto) module for more information.
+    pub fn with_veto<V>(self, veto: V) -> DuplicateLayer<Vetoed<D, V>, G> {
+        DuplicateLayer {
+            decider: Vetoed::new(self.decider, veto),
+            sink: self.sink,
+            copies: self.copies,
+            options: self.options,
+        }
+    }
+
+    crate::options::impl_fault_options! { "layer", shadow }
+}
+
+impl<D, G> DuplicateLayer<D, G>
+where
+    D: ValidateDecider,
+{
+    /// Validate the configuration of the layer.
+    ///
+    /// Returns an [`Error`] if the decider is misconfigured, instead of
+    /// panicking at request time.
+    pub fn build(self) -> Result<Self, Error> {
+        self.decider.validate_decider()?;
+        Ok(self)
+    }
+}
+
+impl<D, G> DuplicateLayer<D, G>
+where
+    D: DescribeDecider,
+{
+    /// Returns a description of the configured fault.
+    pub fn describe(&self) -> FaultDescription {
+        FaultDescription::new("duplicate", self.decider.describe_decider())
+            .with_enabled(self.options.enabled)
+    }
+}
+
+impl<D, G> fmt::Display for DuplicateLayer<D, G>
+where
+    D: DescribeDecider,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.describe().fmt(f)
+    }
+}
+
+impl<D, G, S> Layer<S> for DuplicateLayer<D, G>
+where
+    D: Clone,
+    G: Clone,
+{
+    type Service = DuplicateService<D, G, S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        DuplicateService {
+            inner,
+            decider: self.decider.clone(),
+            sink: self.sink.clone(),
+            copies: self.copies,
+            options: self.options.clone(),
+        }
+    }
+}
+
+/// Service that randomly delivers the responses of the underlying service a
+/// second time to a sink.
+#[derive(Clone, Debug)]
+pub struct DuplicateService<D, G, S> {
+    inner: S,
+    decider: D,
+    sink: G,
+    copies: usize,
+    options: FaultOptions,
+}
+
+impl<D, G, S> DuplicateService<D, G, S>
+where
+    D: DescribeDecider,
+{
+    /// Returns a description of the configured fault.
+    pub fn describe(&self) -> FaultDescription {
+        FaultDescription::new("duplicate", self.decider.describe_decider())
+            .with_enabled(self.options.enabled)
+    }
+}
+
+impl<D, G, S> fmt::Display for DuplicateService<D, G, S>
+where
+    D: DescribeDecider,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.describe().fmt(f)
+    }
+}
+
+impl<D, G, S, R> Service<R> for DuplicateService<D, G, S>
+where
+    D: Decider<R>,
+    G: Sink<S::Response> + Clone + Send + 'static,
+    S: Service<R>,
+    S::Response: Clone,
+    S::Future: Send + 'static,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = DuplicateFuture<S::Response, S::Error>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, request: R) -> Self::Future {
+        if self.options.is_active("duplicate")
+            && self.options.decide("duplicate", &self.decider, &request)
+        {
+            let fut = self.inner.call(request);
+            if self.options.shadow {
+                // Duplicates don't change the response.
+                return Box::pin(self.options.shadow_call(
+                    FaultEvent::new("duplicate"),
+                    fut,
+                    |actual| actual,
+                ));
+            }
+            if self.options.inject(FaultEvent::new("duplicate")) {
+                let (sink, copies) = (self.sink.clone(), self.copies);
+                return Box::pin(async move {
+                    let res = fut.await;
+                    if let Ok(response) = &res {
+                        for _ in 0..copies {
+                            sink.deliver(response.clone());
+                        }
+                    }
+                    res
+                });
+            }
+            return Box::pin(fut);
+        }
+
+        Box::pin(self.inner.call(request))
+    }
+}
+
+/// Future returned by [`DuplicateService`].
+pub type DuplicateFuture<T, E> = Pin<Box<dyn Future<Output = Result<T, E>> + Send>>;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::*;
+    use std::sync::{Arc, Mutex};
+
+    #[tokio::test]
+    async fn duplicate_response() {
+        let delivered = Arc::new(Mutex::new(Vec::new()));
+        let sink = {
+            let delivered = delivered.clone();
+            move |res: String| delivered.lock().unwrap().push(res)
+        };
+        let layer = DuplicateLayer::new(true, sink).copies(2);
+        let mut service = layer.layer(DummyService);
+        assert_eq!(service.call(()).await.unwrap(), "ok");
+        assert_eq!(*delivered.lock().unwrap(), ["ok", "ok"]);
+
+        let mut service = layer.dry_run(true).layer(DummyService);
+        assert_eq!(service.call(()).await.unwrap(), "ok");
+        assert_eq!(delivered.lock().unwrap().len(), 2);
+    }
+}
@@ -59,82 +59,368 @@
 //! ErrorLayer::new(false, |req: &MyRequest| format!("value: {}", req.value));
 //! ```
 //!
+//! [`ByClass`] returns different errors for each class of requests.
+//!
+//! ```rust
+//! use tower_fault::error::{ByClass, ErrorLayer};
+//! # struct MyRequest { write: bool };
+//! fn status(code: u16) -> impl Fn(&MyRequest) -> u16 + Clone {
+//!     move |_| code
+//! }
+//!
+//! // Throttle writes, and return a 503 error for reads.
+//! let generator = ByClass::new(|req: &MyRequest| req.write)
+//!     .class(true, status(429))
+//!     .fallback(status(503));
+//! let error_layer = ErrorLayer::new(0.1, generator);
+//! ```
+//!
+//! The generator can also be any type implementing the
+//! [`Generator`](crate::generator::Generator) trait.
+//!
+//! ### Boxed errors
+//!
+//! For services whose error type is `Box<dyn Error + Send + Sync>`, such as
+//! in `hyper` and `axum` stacks, [`ErrorLayer::boxed`] injects an
+//! [`InjectedError`] with the given message, without writing a generator.
+//!
+//! ```rust
+//! use tower::{service_fn, BoxError, ServiceBuilder};
+//! use tower_fault::error::ErrorLayer;
+//! # struct MyRequest;
+//! # async fn my_service(_req: MyRequest) -> Result<(), BoxError> {
+//! #     Ok(())
+//! # }
+//!
+//! let service = ServiceBuilder::new()
+//!     .layer(ErrorLayer::boxed(0.1, "injected error"))
+//!     .service(service_fn(my_service));
+//! ```
+//!
+//! ### Magnitude
+//!
+//! [`ErrorLayer::with_magnitude`] samples a magnitude for each injected
+//! error, such as a severity, and passes it to the generator, to produce
+//! errors of varying severity from a single configuration.
+//!
+//! ```rust
+//! use tower_fault::error::ErrorLayer;
+//! # struct MyRequest;
+//!
+//! // 10% of the errors are fatal, the others are transient.
+//! let error_layer = ErrorLayer::new(0.1, |_: &MyRequest, severity: f64| {
+//!     if severity > 0.9 {
+//!         String::from("fatal")
+//!     } else {
+//!         String::from("transient")
+//!     }
+//! })
+//! .with_magnitude(0.0..1.0);
+//! ```
+//!
+//! ### Context
+//!
+//! [`ErrorLayer::with_context_generator`] passes a
+//! [`GenerateContext`](crate::generator::GenerateContext) to the generator
+//! along with the request, for errors that need metadata that can't be
+//! derived from the request, such as a correlation ID.
+//!
+//! ```rust
+//! use rand::Rng;
+//! use tower_fault::{error::ErrorLayer, generator::GenerateContext};
+//! # struct MyRequest;
+//! # enum MyError { Unavailable { correlation_id: u64 } }
+//!
+//! let error_layer = ErrorLayer::new(0.1, |_: &MyRequest| ())
+//!     .with_context_generator(|_: &MyRequest, ctx: &GenerateContext| {
+//!         MyError::Unavailable {
+//!             correlation_id: ctx.rng().gen(),
+//!         }
+//!     });
+//! ```
+//!
+//! ### Pacing
+//!
+//! With an [`ErrorPacer`], the layer adjusts the probability of injecting
+//! errors with a feedback loop, so that the combined error rate, including
+//! the real errors of the service, converges to a target.
+//!
+//! ```rust
+//! use tower_fault::{decider::ErrorPacer, error::ErrorLayer};
+//! # struct MyRequest;
+//!
+//! // Keep the combined error rate near 5%.
+//! let error_layer = ErrorLayer::new(false, |_: &MyRequest| String::from("error"))
+//!     .paced(ErrorPacer::new(0.05));
+//! ```
+//!
+//! ### Default
+//!
+//! `ErrorLayer::default()` never injects errors, so it can be added to a
+//! service stack and armed later with a decider.
+//!
+//! ```rust
+//! use tower_fault::error::ErrorLayer;
+//!
+//! let error_layer = ErrorLayer::default().with_decider(0.01);
+//! ```
+//!
+//! ### Validation
+//!
+//! The `build()` method validates the decider, returning an error for
+//! invalid probabilities instead of panicking at request time, and checks
+//! that the generator was set on a builder. See the
+//! [`validate`](crate::validate) module for more information.
+//!
+//! ```rust
+//! use tower_fault::{decider::Probability, error::ErrorLayer};
+//! # struct MyRequest { value: u64 };
+//!
+//! let error_layer = ErrorLayer::new(0.1, |_: &MyRequest| String::from("error"))
+//!     .build()
+//!     .unwrap();
+//!
+//! // Or check the probability up front.
+//! let probability = Probability::new(0.1).unwrap();
+//! let error_layer = ErrorLayer::new(probability, |_: &MyRequest| String::from("error"));
+//! ```
+//!
 
-use crate::decider::Decider;
+use crate::{
+    decider::{Decider, ErrorPacer, Probability, WithContext},
+    describe::{DescribeDecider, FaultDescription},
+    generator::{Contextual, DefaultGenerator, Generator, WithMagnitude},
+    observe::{FaultEvent, Outcome},
+    options::FaultOptions,
+    validate::{self, Unset, ValidateDecider, ValidateGenerator},
+    veto::Vetoed,
+    Error,
+};
 use std::{
+    borrow::Cow,
+    error, fmt,
     future::Future,
-    marker::PhantomData,
     pin::Pin,
     task::{Context, Poll},
+    time::Duration,
 };
-use tower::{Layer, Service};
+use tower::{BoxError, Layer, Service};
+
+pub use crate::class::ByClass;
 
 /// Layer that randomly trigger errors for the service.
 ///
 /// This trigger errors based on the given probability and using
 /// a function to generate errors.
 #[derive(Clone, Debug)]
-pub struct ErrorLayer<'a, D, G> {
+pub struct ErrorLayer<D, G> {
     decider: D,
     generator: G,
-    _phantom: PhantomData<&'a ()>,
+    options: FaultOptions,
+    pacer: Option<ErrorPacer>,
 }
 
-impl<'a> ErrorLayer<'a, (), ()> {
+impl ErrorLayer<(), Unset> {
     /// Create a new `ErrorLayer` builder.
     pub fn builder() -> Self {
         Self {
             decider: (),
-            generator: (),
-            _phantom: PhantomData,
+            generator: Unset,
+            options: FaultOptions::default(),
+            pacer: None,
         }
     }
 }
 
-impl<'a, D, G> ErrorLayer<'a, D, G> {
+impl Default for ErrorLayer<Probability, DefaultGenerator> {
+    /// Create a new `ErrorLayer` that never injects errors, and returns the
+    /// default value of the error type once armed with
+    /// [`ErrorLayer::with_decider`].
+    fn default() -> Self {
+        Self::new(Probability::NEVER, DefaultGenerator)
+    }
+}
+
+impl<D> ErrorLayer<D, BoxedErrors> {
+    /// Create a new `ErrorLayer` for services whose error type is
+    /// `Box<dyn Error + Send + Sync>`, injecting [`InjectedError`]s with the
+    /// given message.
+    pub fn boxed(decider: D, message: impl Into<Cow<'static, str>>) -> Self {
+        Self::new(decider, BoxedErrors::new(message))
+    }
+}
+
+impl<D, G> ErrorLayer<D, G> {
     /// Create a new `ErrorLayer` builder with the given probability
     /// and error generator.
     pub fn new(decider: D, generator: G) -> Self {
         Self {
             decider,
             generator,
-            _phantom: PhantomData,
+            options: FaultOptions::default(),
+            pacer: None,
         }
     }
 
     /// Set the given decider to be used to determine if an error
     /// should be injected.
-    pub fn with_decider<ND>(self, decider: ND) -> ErrorLayer<'a, ND, G> {
+    pub fn with_decider<ND>(self, decider: ND) -> ErrorLayer<ND, G> {
         ErrorLayer {
             decider,
             generator: self.generator,
-            _phantom: PhantomData,
+            options: self.options,
+            pacer: None,
+        }
+    }
+
+    /// Call the decider with a `(request, context)` tuple, where the context
+    /// is returned by the given extractor.
+    ///
+    /// See [`WithContext`] for more information.
+    pub fn with_context<X>(self, extractor: X) -> ErrorLayer<WithContext<D, X>, G> {
+        ErrorLayer {
+            decider: WithContext::new(self.decider, extractor),
+            generator: self.generator,
+            options: self.options,
+            pacer: self.pacer,
+        }
+    }
+
+    /// Never inject errors into the requests vetoed by the given veto,
+    /// regardless of the decider.
+    ///
+    /// See the [`veto`](crate::veto) module for more information.
+    pub fn with_veto<V>(self, veto: V) -> ErrorLayer<Vetoed<D, V>, G> {
+        ErrorLayer {
+            decider: Vetoed::new(self.decider, veto),
+            generator: self.generator,
+            options: self.options,
+            pacer: self.pacer,
+        }
+    }
+
+    /// Use the given pacer as the decider, and feed it with the outcome of
+    /// each request, including the real errors of the service.
+    ///
+    /// See [`ErrorPacer`] for more information.
+    pub fn paced(self, pacer: ErrorPacer) -> ErrorLayer<ErrorPacer, G> {
+        ErrorLayer {
+            decider: pacer.clone(),
+            generator: self.generator,
+            options: self.options,
+            pacer: Some(pacer),
         }
     }
 
     /// Set the given error generator to generate errors.
-    pub fn with_generator<NG>(self, generator: NG) -> ErrorLayer<'a, D, NG> {
+    pub fn with_generator<NG>(self, generator: NG) -> ErrorLayer<D, NG> {
         ErrorLayer {
             decider: self.decider,
             generator,
-            _phantom: PhantomData,
+            options: self.options,
+            pacer: self.pacer,
+        }
+    }
+
+    /// Set the given generator to generate errors from the request and a
+    /// [`GenerateContext`](crate::generator::GenerateContext).
+    ///
+    /// The generator must implement
+    /// [`ContextGenerator`](crate::generator::ContextGenerator), such as a
+    /// closure taking the request and the context.
+    pub fn with_context_generator<NG>(self, generator: NG) -> ErrorLayer<D, Contextual<NG>> {
+        self.with_generator(Contextual::new("error", generator))
+    }
+
+    /// Sample a magnitude for each injected error, and pass it to the
+    /// generator with the request.
+    ///
+    /// The generator must then implement
+    /// [`MagnitudeGenerator`](crate::generator::MagnitudeGenerator), such as
+    /// a closure taking the request and the magnitude.
+    pub fn with_magnitude<M>(self, magnitude: M) -> ErrorLayer<D, WithMagnitude<M, G>> {
+        ErrorLayer {
+            decider: self.decider,
+            generator: WithMagnitude::new(magnitude, self.generator),
+            options: self.options,
+            pacer: self.pacer,
         }
     }
 }
 
-impl<'a, D, G, S> Layer<S> for ErrorLayer<'a, D, G>
+impl<D, G> ErrorLayer<D, G> {
+    crate::options::impl_fault_options! { "layer", shadow }
+}
+
+impl<D, G> ErrorLayer<D, G>
+where
+    D: ValidateDecider,
+    G: ValidateGenerator,
+{
+    /// Create a new `ErrorLayer`, returning an error if the configuration is
+    /// invalid.
+    pub fn try_new(decider: D, generator: G) -> Result<Self, Error> {
+        Self::new(decider, generator).build()
+    }
+
+    /// Validate the configuration of the layer.
+    ///
+    /// Returns an [`Error`] if the decider is misconfigured, such as a
+    /// probability above 1.0, instead of panicking at request time, or
+    /// [`Error::Missing`] if the decider or the generator was not set on an
+    /// [`ErrorLayer::builder`].
+    pub fn build(self) -> Result<Self, Error> {
+        validate::all([
+            self.decider.validate_decider(),
+            self.generator.validate_generator(),
+        ])?;
+        Ok(self)
+    }
+
+    /// Validate the configuration of the layer, and wrap the given service.
+    pub fn build_service<S>(self, inner: S) -> Result<ErrorService<D, G, S>, Error>
+    where
+        D: Clone,
+        G: Clone,
+    {
+        Ok(self.build()?.layer(inner))
+    }
+}
+
+impl<D, G> ErrorLayer<D, G>
+where
+    D: DescribeDecider,
+{
+    /// Returns a description of the configured fault.
+    pub fn describe(&self) -> FaultDescription {
+        FaultDescription::new("error", self.decider.describe_decider())
+            .with_enabled(self.options.enabled)
+    }
+}
+
+impl<D, G> fmt::Display for ErrorLayer<D, G>
+where
+    D: DescribeDecider,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.describe().fmt(f)
+    }
+}
+
+impl<D, G, S> Layer<S> for ErrorLayer<D, G>
 where
     D: Clone,
     G: Clone,
 {
-    type Service = ErrorService<'a, D, G, S>;
+    type Service = ErrorService<D, G, S>;
 
     fn layer(&self, inner: S) -> Self::Service {
         ErrorService {
             inner,
             decider: self.decider.clone(),
             generator: self.generator.clone(),
-            _phantom: PhantomData,
+            options: self.options.clone(),
+            pacer: self.pacer.clone(),
         }
     }
 }
@@ -142,51 +428,147 @@ where
 /// Service that randomly trigger errors instead of calling the underlying
 /// service.
 #[derive(Clone, Debug)]
-pub struct ErrorService<'a, D, G, S> {
+pub struct ErrorService<D, G, S> {
     inner: S,
     decider: D,
     generator: G,
-    _phantom: PhantomData<&'a ()>,
+    options: FaultOptions,
+    pacer: Option<ErrorPacer>,
+}
+
+impl<D, G, S> ErrorService<D, G, S>
+where
+    D: DescribeDecider,
+{
+    /// Returns a description of the configured fault.
+    pub fn describe(&self) -> FaultDescription {
+        FaultDescription::new("error", self.decider.describe_decider())
+            .with_enabled(self.options.enabled)
+    }
+}
+
+impl<D, G, S> fmt::Display for ErrorService<D, G, S>
+where
+    D: DescribeDecider,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.describe().fmt(f)
+    }
 }
 
-impl<'a, D, G, S, R> Service<R> for ErrorService<'a, D, G, S>
+impl<D, G, S, R> Service<R> for ErrorService<D, G, S>
 where
     D: Decider<R> + Clone,
-    G: Fn(&R) -> S::Error + Clone,
+    G: Generator<R, S::Error> + Clone,
     S: Service<R> + Send,
-    S::Future: Send + 'a,
-    S::Error: Send + 'a,
+    S::Future: Send + 'static,
+    S::Error: Send + 'static,
 {
     type Response = S::Response;
     type Error = S::Error;
-    type Future = ErrorFuture<'a, R, S>;
+    type Future = ErrorFuture<S::Response, S::Error>;
 
     fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
         self.inner.poll_ready(cx)
     }
 
     fn call(&mut self, request: R) -> Self::Future {
-        if self.decider.decide(&request) {
-            let error = (self.generator)(&request);
-            return Box::pin(async move { Err(error) });
+        if self.options.is_active("error") && self.options.decide("error", &self.decider, &request)
+        {
+            if self.options.shadow {
+                let fut = self.inner.call(request);
+                return Box::pin(
+                    self.options
+                        .shadow_call(FaultEvent::new("error"), fut, |_| {
+                            Outcome::new(false, Duration::ZERO)
+                        }),
+                );
+            }
+            if self.options.inject(FaultEvent::new("error")) {
+                let error = self.generator.generate(&request);
+                if let Some(pacer) = &self.pacer {
+                    pacer.record(true);
+                }
+                return Box::pin(async move { Err(error) });
+            }
         }
 
-        Box::pin(self.inner.call(request))
+        let fut = self.inner.call(request);
+        match self.pacer.clone() {
+            Some(pacer) => Box::pin(async move {
+                let res = fut.await;
+                pacer.record(res.is_err());
+                res
+            }),
+            None => Box::pin(fut),
+        }
+    }
+}
+
+/// Error injected by the layers created with [`ErrorLayer::boxed`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct InjectedError {
+    message: Cow<'static, str>,
+}
+
+impl InjectedError {
+    /// Create a new `InjectedError` with the given message.
+    pub fn new(message: impl Into<Cow<'static, str>>) -> Self {
+        Self {
+            message: message.into(),
+        }
+    }
+
+    /// Returns the message of the error.
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+}
+
+impl fmt::Display for InjectedError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+impl error::Error for InjectedError {}
+
+/// Generator of boxed [`InjectedError`]s, used by [`ErrorLayer::boxed`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct BoxedErrors {
+    error: InjectedError,
+}
+
+impl BoxedErrors {
+    /// Create a new `BoxedErrors` generator with the given message.
+    pub fn new(message: impl Into<Cow<'static, str>>) -> Self {
+        Self {
+            error: InjectedError::new(message),
+        }
     }
 }
 
-type ErrorFuture<'a, R, S> = Pin<
-    Box<
-        dyn Future<Output = Result<<S as Service<R>>::Response, <S as Service<R>>::Error>>
-            + Send
-            + 'a,
-    >,
->;
+impl<R> Generator<R, BoxError> for BoxedErrors {
+    fn generate(&self, _req: &R) -> BoxError {
+        Box::new(self.error.clone())
+    }
+}
+
+/// Future returned by [`ErrorService`].
+pub type ErrorFuture<T, E> = Pin<Box<dyn Future<Output = Result<T, E>> + Send>>;
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::test_utils::*;
+    use crate::{
+        decider::{Budget, Suppression},
+        observe::FaultObserver,
+        test_utils::*,
+    };
+    use std::sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    };
 
     #[tokio::test]
     async fn error_success() {
@@ -199,6 +581,281 @@ mod tests {
         }
     }
 
+    #[test]
+    fn error_build() {
+        let generator = |_: &()| String::from("error");
+        assert!(ErrorLayer::new(0.5, generator).build().is_ok());
+        assert_eq!(
+            ErrorLayer::new(1.5, generator).build().err(),
+            Some(Error::InvalidProbability(1.5))
+        );
+    }
+
+    #[tokio::test]
+    async fn error_paced() {
+        let pacer = ErrorPacer::new(1.0).window(10);
+        let layer = ErrorLayer::new(false, |_: &()| String::from("error")).paced(pacer.clone());
+        let mut service = layer.layer(DummyService);
+
+        for _ in 0..10 {
+            assert!(service.call(()).await.is_ok());
+        }
+        assert_eq!(pacer.probability(), 0.5);
+    }
+
+    #[tokio::test]
+    async fn error_disabled() {
+        let layer = ErrorLayer::new(1.0, |_: &()| String::from("error")).enabled(false);
+        assert_eq!(layer.to_string(), "error (disabled): probability (100%)");
+
+        let mut service = layer.layer(DummyService);
+        assert_eq!(service.call(()).await.unwrap(), String::from("ok"));
+    }
+
+    #[tokio::test]
+    async fn error_magnitude() {
+        let generator = |_: &(), severity: f64| match severity > 0.5 {
+            true => String::from("fatal"),
+            false => String::from("transient"),
+        };
+        let layer = ErrorLayer::new(true, generator).with_magnitude(0.9);
+        let mut service = layer.layer(DummyService);
+        assert_eq!(service.call(()).await, Err(String::from("fatal")));
+
+        let layer = ErrorLayer::new(true, generator).with_magnitude(0.1);
+        let mut service = layer.layer(DummyService);
+        assert_eq!(service.call(()).await, Err(String::from("transient")));
+    }
+
+    #[tokio::test]
+    async fn error_context_generator() {
+        let layer = ErrorLayer::new(true, DefaultGenerator).with_context_generator(
+            |_: &(), ctx: &crate::generator::GenerateContext| {
+                format!("{} #{}", ctx.fault, ctx.attempt)
+            },
+        );
+        let mut service = layer.layer(DummyService);
+        assert_eq!(service.call(()).await, Err(String::from("error #1")));
+        assert_eq!(service.call(()).await, Err(String::from("error #2")));
+    }
+
+    #[tokio::test]
+    async fn error_boxed() {
+        let layer = ErrorLayer::boxed(true, "injected");
+        let service = layer.layer(tower::service_fn(|_: ()| async { Ok::<_, BoxError>(()) }));
+
+        let err = tower::ServiceExt::oneshot(service, ()).await.unwrap_err();
+        assert_eq!(
+            err.downcast_ref::<InjectedError>(),
+            Some(&InjectedError::new("injected"))
+        );
+    }
+
+    #[tokio::test]
+    async fn error_arm_after() {
+        let layer =
+            ErrorLayer::new(1.0, |_: &()| String::from("error")).arm_after(Duration::from_secs(60));
+        let mut service = layer.layer(DummyService);
+        assert_eq!(service.call(()).await.unwrap(), String::from("ok"));
+
+        let layer = layer.arm_after(Duration::ZERO);
+        let mut service = layer.layer(DummyService);
+        assert!(service.call(()).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn error_disarm_after() {
+        let disarmed = Arc::new(std::sync::atomic::AtomicUsize::new(0));
+        struct Recorder(Arc<std::sync::atomic::AtomicUsize>);
+        impl FaultObserver for Recorder {
+            fn on_fault(&self, _event: &FaultEvent) {}
+            fn on_disarmed(&self, event: &crate::observe::DisarmedEvent) {
+                assert_eq!(event.fault, "error");
+                self.0.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+            }
+        }
+
+        let layer = ErrorLayer::new(1.0, |_: &()| String::from("error"))
+            .with_observer(Recorder(disarmed.clone()))
+            .disarm_after(Duration::from_secs(60));
+        let mut service = layer.clone().layer(DummyService);
+        assert!(service.call(()).await.is_err());
+
+        let mut service = layer.disarm_after(Duration::ZERO).layer(DummyService);
+        for _ in 0..2 {
+            assert_eq!(service.call(()).await.unwrap(), String::from("ok"));
+        }
+        assert_eq!(disarmed.load(std::sync::atomic::Ordering::Relaxed), 1);
+    }
+
+    #[tokio::test]
+    async fn error_dry_run() {
+        let injected = Arc::new(std::sync::atomic::AtomicUsize::new(0));
+        let layer = ErrorLayer::new(1.0, |_: &()| String::from("error"))
+            .dry_run(true)
+            .with_observer({
+                let injected = injected.clone();
+                move |event: &FaultEvent| {
+                    assert!(event.dry_run);
+                    injected.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+                }
+            });
+
+        let mut service = layer.layer(DummyService);
+        assert_eq!(service.call(()).await.unwrap(), String::from("ok"));
+        assert_eq!(injected.load(std::sync::atomic::Ordering::Relaxed), 1);
+    }
+
+    #[tokio::test]
+    async fn error_shadow() {
+        let faulted = Arc::new(std::sync::Mutex::new(None));
+        let dry_runs = Arc::new(std::sync::Mutex::new(Vec::new()));
+        struct Recorder(
+            Arc<std::sync::Mutex<Option<(bool, bool)>>>,
+            Arc<std::sync::Mutex<Vec<bool>>>,
+        );
+        impl FaultObserver for Recorder {
+            fn on_fault(&self, event: &FaultEvent) {
+                self.1.lock().unwrap().push(event.dry_run);
+            }
+            fn on_shadow(&self, event: &crate::observe::ShadowEvent) {
+                *self.0.lock().unwrap() = Some((event.actual.is_ok, event.faulted.is_ok));
+            }
+        }
+
+        let layer = ErrorLayer::new(1.0, |_: &()| String::from("error"))
+            .shadow(true)
+            .with_observer(Recorder(faulted.clone(), dry_runs.clone()));
+
+        let mut service = layer.layer(DummyService);
+        assert_eq!(service.call(()).await.unwrap(), String::from("ok"));
+        assert_eq!(*faulted.lock().unwrap(), Some((true, false)));
+        assert_eq!(*dry_runs.lock().unwrap(), vec![true]);
+    }
+
+    #[tokio::test]
+    async fn error_veto() {
+        let layer = ErrorLayer::new(1.0, |_: &()| String::from("error")).with_veto(|_: &()| true);
+        let mut service = layer.layer(DummyService);
+        assert_eq!(service.call(()).await.unwrap(), String::from("ok"));
+    }
+
+    #[tokio::test]
+    async fn error_suppressed() {
+        let suppressed = Arc::new(std::sync::Mutex::new(Vec::new()));
+        struct Recorder(Arc<std::sync::Mutex<Vec<Suppression>>>);
+        impl FaultObserver for Recorder {
+            fn on_fault(&self, _event: &FaultEvent) {}
+            fn on_suppressed(&self, event: &crate::observe::SuppressedEvent) {
+                self.0.lock().unwrap().push(event.reason);
+            }
+        }
+
+        let layer = ErrorLayer::new(Budget::new(1.0, 1), |_: &()| String::from("error"))
+            .with_veto(|_: &()| false)
+            .with_observer(Recorder(suppressed.clone()));
+        let mut service = layer.layer(DummyService);
+        assert_eq!(service.call(()).await.err(), Some(String::from("error")));
+        assert_eq!(service.call(()).await.unwrap(), String::from("ok"));
+
+        let layer = ErrorLayer::new(1.0, |_: &()| String::from("error"))
+            .with_veto(|_: &()| true)
+            .with_observer(Recorder(suppressed.clone()));
+        let mut service = layer.layer(DummyService);
+        assert_eq!(service.call(()).await.unwrap(), String::from("ok"));
+
+        assert_eq!(
+            *suppressed.lock().unwrap(),
+            vec![Suppression::Budget, Suppression::Veto]
+        );
+    }
+
+    #[tokio::test]
+    async fn error_suppressed_budget() {
+        let suppressed = Arc::new(std::sync::Mutex::new(Vec::new()));
+        struct Recorder(Arc<std::sync::Mutex<Vec<Suppression>>>);
+        impl FaultObserver for Recorder {
+            fn on_fault(&self, _event: &FaultEvent) {}
+            fn on_suppressed(&self, event: &crate::observe::SuppressedEvent) {
+                self.0.lock().unwrap().push(event.reason);
+            }
+        }
+
+        // Vetoed requests don't spend the budget, even with an observer.
+        let budget = Budget::new(1.0, 1);
+        let vetoed = Arc::new(AtomicBool::new(true));
+        let veto = {
+            let vetoed = vetoed.clone();
+            move |_: &()| vetoed.load(Ordering::Relaxed)
+        };
+        let layer = ErrorLayer::new(budget.clone(), |_: &()| String::from("error"))
+            .with_veto(veto)
+            .with_observer(Recorder(suppressed.clone()));
+        let mut service = layer.layer(DummyService);
+        assert_eq!(service.call(()).await.unwrap(), String::from("ok"));
+        assert_eq!(service.call(()).await.unwrap(), String::from("ok"));
+        assert_eq!(budget.remaining(), 1);
+
+        vetoed.store(false, Ordering::Relaxed);
+        assert_eq!(service.call(()).await.err(), Some(String::from("error")));
+        assert_eq!(budget.remaining(), 0);
+        assert_eq!(
+            *suppressed.lock().unwrap(),
+            vec![Suppression::Veto, Suppression::Veto]
+        );
+    }
+
+    #[tokio::test]
+    async fn error_with_context() {
+        let layer = ErrorLayer::new(
+            |(_, admin): &(&(), bool)| !admin,
+            |_: &()| String::from("error"),
+        )
+        .with_context(|_: &()| true);
+        let mut service = layer.layer(DummyService);
+        assert_eq!(service.call(()).await.unwrap(), String::from("ok"));
+    }
+
+    #[test]
+    fn error_builder_missing() {
+        let res = ErrorLayer::builder().build();
+        assert_eq!(
+            res.err(),
+            Some(Error::Missing(vec!["decider", "generator"]))
+        );
+
+        let res = ErrorLayer::builder().with_decider(0.5).build();
+        assert_eq!(res.err(), Some(Error::Missing(vec!["generator"])));
+
+        let res = ErrorLayer::builder().with_decider(1.5).build();
+        assert_eq!(res.err(), Some(Error::InvalidProbability(1.5)));
+
+        let res = ErrorLayer::builder()
+            .with_decider(0.5)
+            .with_generator(|_: &()| String::from("error"))
+            .build_service(DummyService);
+        assert!(res.is_ok());
+    }
+
+    #[tokio::test]
+    async fn error_default() {
+        let layer = ErrorLayer::default();
+        assert_eq!(layer.to_string(), "error: probability (0%)");
+
+        let mut service = layer.with_decider(1.0).layer(DummyService);
+        assert_eq!(service.call(()).await.unwrap_err(), String::new());
+    }
+
+    #[test]
+    fn error_describe() {
+        let layer = ErrorLayer::new(0.25, |_: &()| String::from("error"));
+        assert_eq!(layer.to_string(), "error: probability (25%)");
+        assert_eq!(
+            layer.layer(DummyService).describe().decider.probability,
+            Some(0.25)
+        );
+    }
+
     #[tokio::test]
     async fn error_fail() {
         let layer = ErrorLayer::new(1.0, |_: &()| String::from("error"));
@@ -0,0 +1,402 @@
+//! # Experiment templates
+//!
+//! This module runs experiment templates against a [`FaultRegistry`], in
+//! the same way as AWS Fault Injection Simulator (FIS) runs infrastructure
+//! experiments.
+//!
+//! An [`ExperimentTemplate`] contains a list of [`Action`]s. Each action
+//! targets a fault by name, and enables it after a start offset for a given
+//! duration. The [`Experiment`] drives the registry accordingly, and
+//! checks its stop conditions while running. When a stop condition is met,
+//! or when the registry's [`KillSwitch`](crate::registry::KillSwitch) is
+//! engaged, the experiment stops, disables all the targeted faults, and
+//! engages the kill switch.
+//!
+//! ## Example
+//!
+//! ```rust
+//! use std::time::Duration;
+//! use tower_fault::{
+//!     experiment::{Action, Experiment, ExperimentOutcome, ExperimentTemplate},
+//!     registry::FaultRegistry,
+//! };
+//! # #[tokio::main(flavor = "current_thread", start_paused = true)]
+//! # async fn main() {
+//!
+//! let registry = FaultRegistry::new();
+//! registry.register("db-latency", 0.0);
+//! registry.register("db-errors", 0.0);
+//!
+//! let template = ExperimentTemplate::new("database degradation")
+//!     .action(Action::new("slow-db", "db-latency", Duration::from_secs(60)).probability(0.5))
+//!     .action(
+//!         Action::new("failing-db", "db-errors", Duration::from_secs(30))
+//!             .start_after(Duration::from_secs(60))
+//!             .probability(0.1),
+//!     );
+//!
+//! let outcome = Experiment::new(template)
+//!     .stop_condition("error-rate", || false)
+//!     .run(&registry)
+//!     .await
+//!     .unwrap();
+//! assert_eq!(outcome, ExperimentOutcome::Completed);
+//! # }
+//! ```
+
+use crate::{
+    registry::{FaultHandle, FaultRegistry},
+    Error,
+};
+use std::{fmt, time::Duration};
+use tokio::time::{self, Instant};
+
+/// Template of an experiment.
+#[derive(Clone, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Deserialize))]
+pub struct ExperimentTemplate {
+    /// Description of the experiment.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub description: String,
+    /// Actions of the experiment.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub actions: Vec<Action>,
+}
+
+impl ExperimentTemplate {
+    /// Create a new `ExperimentTemplate` without any action.
+    pub fn new(description: impl Into<String>) -> Self {
+        Self {
+            description: description.into(),
+            actions: Vec::new(),
+        }
+    }
+
+    /// Add an action to the template.
+    pub fn action(mut self, action: Action) -> Self {
+        self.actions.push(action);
+        self
+    }
+}
+
+/// Action of an experiment, enabling a fault for a duration.
+///
+/// With the `serde` feature, durations are deserialized from ISO-8601
+/// durations such as `"PT5M"`, as used by FIS templates.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "camelCase"))]
+pub struct Action {
+    /// Name of the action.
+    pub name: String,
+    /// Name of the targeted fault in the registry.
+    pub target: String,
+    /// Offset from the start of the experiment.
+    #[cfg_attr(feature = "serde", serde(default, deserialize_with = "de::duration"))]
+    pub start_after: Duration,
+    /// Duration of the action.
+    #[cfg_attr(feature = "serde", serde(deserialize_with = "de::duration"))]
+    pub duration: Duration,
+    /// Probability set on the fault when the action starts.
+    ///
+    /// If not set, the probability of the fault is left untouched.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub probability: Option<f64>,
+}
+
+impl Action {
+    /// Create a new `Action` enabling the `target` fault for `duration`,
+    /// from the start of the experiment.
+    pub fn new(name: impl Into<String>, target: impl Into<String>, duration: Duration) -> Self {
+        Self {
+            name: name.into(),
+            target: target.into(),
+            start_after: Duration::ZERO,
+            duration,
+            probability: None,
+        }
+    }
+
+    /// Start the action after the given offset from the start of the
+    /// experiment.
+    pub fn start_after(mut self, offset: Duration) -> Self {
+        self.start_after = offset;
+        self
+    }
+
+    /// Set the probability of the fault when the action starts.
+    pub fn probability(mut self, probability: f64) -> Self {
+        self.probability = Some(probability);
+        self
+    }
+}
+
+/// Outcome of an experiment.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ExperimentOutcome {
+    /// All the actions ran until completion.
+    Completed,
+    /// The experiment was stopped by the stop condition with the given name.
+    Stopped(String),
+    /// The experiment was stopped because the registry's kill switch was
+    /// engaged.
+    Killed,
+}
+
+type StopCondition = Box<dyn Fn() -> bool + Send + Sync>;
+
+/// Experiment runner.
+pub struct Experiment {
+    template: ExperimentTemplate,
+    stop_conditions: Vec<(String, StopCondition)>,
+    check_interval: Duration,
+}
+
+impl fmt::Debug for Experiment {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Experiment")
+            .field("template", &self.template)
+            .field(
+                "stop_conditions",
+                &self
+                    .stop_conditions
+                    .iter()
+                    .map(|(name, _)| name)
+                    .collect::<Vec<_>>(),
+            )
+            .field("check_interval", &self.check_interval)
+            .finish()
+    }
+}
+
+impl Experiment {
+    /// Create a new `Experiment` from a template.
+    pub fn new(template: ExperimentTemplate) -> Self {
+        Self {
+            template,
+            stop_conditions: Vec::new(),
+            check_interval: Duration::from_secs(1),
+        }
+    }
+
+    /// Add a stop condition.
+    ///
+    /// The experiment stops as soon as the condition returns `true`.
+    pub fn stop_condition<F>(mut self, name: impl Into<String>, condition: F) -> Self
+    where
+        F: Fn() -> bool + Send + Sync + 'static,
+    {
+        self.stop_conditions
+            .push((name.into(), Box::new(condition)));
+        self
+    }
+
+    /// Set the interval at which the stop conditions are checked.
+    ///
+    /// Defaults to 1 second.
+    pub fn check_interval(mut self, interval: Duration) -> Self {
+        self.check_interval = interval;
+        self
+    }
+
+    /// Run the experiment against the registry.
+    ///
+    /// All the targeted faults are disabled when the experiment starts, and
+    /// when it ends. Returns an error if an action targets a fault that isn't
+    /// registered.
+    pub async fn run(&self, registry: &FaultRegistry) -> Result<ExperimentOutcome, Error> {
+        let handles = self
+            .template
+            .actions
+            .iter()
+            .map(|action| {
+                registry.get(&action.target).ok_or_else(|| {
+                    Error::InvalidConfig(format!(
+                        "action '{}' targets unknown fault '{}'",
+                        action.name, action.target
+                    ))
+                })
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        handles.iter().for_each(FaultHandle::disable);
+
+        // Events are (offset, action index, start), sorted so that actions
+        // stopping at a given offset are processed before those starting.
+        let mut events = self
+            .template
+            .actions
+            .iter()
+            .enumerate()
+            .flat_map(|(i, action)| {
+                [
+                    (action.start_after, i, true),
+                    (action.start_after + action.duration, i, false),
+                ]
+            })
+            .collect::<Vec<_>>();
+        events.sort_by_key(|(offset, i, start)| (*offset, *start, *i));
+
+        let kill_switch = registry.kill_switch();
+        let start = Instant::now();
+        let mut events = events.into_iter().peekable();
+        let outcome = loop {
+            if kill_switch.is_engaged() {
+                break ExperimentOutcome::Killed;
+            }
+            if let Some((name, _)) = self.stop_conditions.iter().find(|(_, cond)| cond()) {
+                kill_switch.engage();
+                break ExperimentOutcome::Stopped(name.clone());
+            }
+
+            let Some(&(offset, i, is_start)) = events.peek() else {
+                break ExperimentOutcome::Completed;
+            };
+            let deadline = start + offset;
+            if Instant::now() < deadline {
+                time::sleep_until(deadline.min(Instant::now() + self.check_interval)).await;
+                continue;
+            }
+
+            events.next();
+            let (action, handle) = (&self.template.actions[i], &handles[i]);
+            if is_start {
+                if let Some(probability) = action.probability {
+                    handle.set_probability(probability);
+                }
+                handle.enable();
+            } else {
+                handle.disable();
+            }
+        };
+
+        handles.iter().for_each(FaultHandle::disable);
+        Ok(outcome)
+    }
+}
+
+#[cfg(feature = "serde")]
+mod de {
+    use serde::{de::Error, Deserialize, Deserializer};
+    use std::time::Duration;
+
+    /// Deserialize an ISO-8601 duration, such as `"PT1H30M"` or `"PT0.5S"`.
+    pub(super) fn duration<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Duration, D::Error> {
+        let value = String::deserialize(deserializer)?;
+        parse(&value).ok_or_else(|| D::Error::custom(format!("invalid duration: {}", value)))
+    }
+
+    fn parse(value: &str) -> Option<Duration> {
+        let mut rest = value.strip_prefix("PT")?;
+        if rest.is_empty() {
+            return None;
+        }
+
+        let mut secs = 0.0;
+        for (unit, factor) in [('H', 3600.0), ('M', 60.0), ('S', 1.0)] {
+            if let Some((number, tail)) = rest.split_once(unit) {
+                secs += number.parse::<f64>().ok()? * factor;
+                rest = tail;
+            }
+        }
+
+        if rest.is_empty() {
+            Duration::try_from_secs_f64(secs).ok()
+        } else {
+            None
+        }
+    }
+
+    #[cfg(test)]
+    #[test]
+    fn experiment_parse_duration() {
+        assert_eq!(parse("PT1H30M"), Some(Duration::from_secs(5400)));
+        assert_eq!(parse("PT0.5S"), Some(Duration::from_millis(500)));
+        assert_eq!(parse("PT"), None);
+        assert_eq!(parse("5M"), None);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    };
+
+    #[tokio::test(start_paused = true)]
+    async fn experiment_schedule() {
+        let registry = FaultRegistry::new();
+        let fault = registry.register("fault", 0.0);
+        let template = ExperimentTemplate::new("test").action(
+            Action::new("action", "fault", Duration::from_secs(10))
+                .start_after(Duration::from_secs(5))
+                .probability(0.5),
+        );
+
+        let task = tokio::spawn({
+            let registry = registry.clone();
+            async move { Experiment::new(template).run(&registry).await }
+        });
+
+        time::sleep(Duration::from_secs(1)).await;
+        assert!(!fault.is_enabled());
+        time::sleep(Duration::from_secs(5)).await;
+        assert!(fault.is_enabled());
+        assert_eq!(fault.probability(), 0.5);
+
+        assert_eq!(task.await.unwrap(), Ok(ExperimentOutcome::Completed));
+        assert!(!fault.is_enabled());
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn experiment_stop_condition() {
+        let registry = FaultRegistry::new();
+        let fault = registry.register("fault", 1.0);
+        let alarm = Arc::new(AtomicBool::new(false));
+        let template = ExperimentTemplate::new("test").action(Action::new(
+            "action",
+            "fault",
+            Duration::from_secs(60),
+        ));
+
+        let experiment = Experiment::new(template).stop_condition("alarm", {
+            let alarm = alarm.clone();
+            move || alarm.load(Ordering::Relaxed)
+        });
+        let task = tokio::spawn({
+            let registry = registry.clone();
+            async move { experiment.run(&registry).await }
+        });
+
+        time::sleep(Duration::from_secs(10)).await;
+        assert!(fault.is_enabled());
+        alarm.store(true, Ordering::Relaxed);
+
+        assert_eq!(
+            task.await.unwrap(),
+            Ok(ExperimentOutcome::Stopped("alarm".to_string()))
+        );
+        assert!(!fault.is_enabled());
+        assert!(registry.kill_switch().is_engaged());
+    }
+
+    #[tokio::test]
+    async fn experiment_unknown_fault() {
+        let template = ExperimentTemplate::new("test").action(Action::new(
+            "action",
+            "missing",
+            Duration::from_secs(1),
+        ));
+        let err = Experiment::new(template)
+            .run(&FaultRegistry::new())
+            .await
+            .unwrap_err();
+        assert_eq!(
+            err,
+            Error::InvalidConfig("action 'action' targets unknown fault 'missing'".to_string())
+        );
+    }
+}
@@ -0,0 +1,62 @@
+use super::{chain::ContextChain, Generator};
+use std::borrow::Cow;
+
+/// Generator of [`anyhow::Error`]s with a chain of context messages.
+///
+/// The root cause of the generated errors is an
+/// [`InjectedError`](crate::error::InjectedError), so that injected errors can
+/// be told apart with [`anyhow::Error::downcast_ref`].
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct AnyhowErrors {
+    chain: ContextChain,
+}
+
+impl AnyhowErrors {
+    /// Create a new `AnyhowErrors` generator with the given message.
+    pub fn new(message: impl Into<Cow<'static, str>>) -> Self {
+        Self {
+            chain: ContextChain::new(message.into()),
+        }
+    }
+
+    /// Wrap the generated errors with the given context message.
+    ///
+    /// Context messages are added in order, the last one being the
+    /// outermost.
+    pub fn context(mut self, context: impl Into<Cow<'static, str>>) -> Self {
+        self.chain.push(context.into());
+        self
+    }
+}
+
+impl<R> Generator<R, anyhow::Error> for AnyhowErrors {
+    fn generate(&self, _req: &R) -> anyhow::Error {
+        self.chain.build(anyhow::Error::new, anyhow::Error::context)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::InjectedError;
+
+    #[test]
+    fn anyhow_context() {
+        let generator = AnyhowErrors::new("connection reset")
+            .context("failed to query the database")
+            .context("failed to load the user");
+        let err: anyhow::Error = generator.generate(&());
+
+        assert_eq!(err.to_string(), "failed to load the user");
+        let chain: Vec<_> = err.chain().map(ToString::to_string).collect();
+        assert_eq!(
+            chain,
+            [
+                "failed to load the user",
+                "failed to query the database",
+                "connection reset"
+            ]
+        );
+        assert!(err.root_cause().is::<InjectedError>());
+    }
+}
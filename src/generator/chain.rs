@@ -0,0 +1,39 @@
+use crate::error::InjectedError;
+use std::borrow::Cow;
+
+/// Message of an injected error, and the context messages wrapping it.
+///
+/// This is shared by the generators of error reporting libraries, which only
+/// differ in how they wrap an error with context.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub(super) struct ContextChain {
+    message: Cow<'static, str>,
+    context: Vec<Cow<'static, str>>,
+}
+
+impl ContextChain {
+    pub(super) fn new(message: Cow<'static, str>) -> Self {
+        Self {
+            message,
+            context: Vec::new(),
+        }
+    }
+
+    /// Add a context message, outside of the previous ones.
+    pub(super) fn push(&mut self, context: Cow<'static, str>) {
+        self.context.push(context);
+    }
+
+    /// Build an error from an [`InjectedError`] with the message, then wrap
+    /// it with the context messages, from the innermost to the outermost.
+    pub(super) fn build<E>(
+        &self,
+        root: impl FnOnce(InjectedError) -> E,
+        wrap: impl Fn(E, Cow<'static, str>) -> E,
+    ) -> E {
+        self.context.iter().fold(
+            root(InjectedError::new(self.message.clone())),
+            |err, context| wrap(err, context.clone()),
+        )
+    }
+}
@@ -0,0 +1,62 @@
+use super::{chain::ContextChain, Generator};
+use std::borrow::Cow;
+
+/// Generator of [`eyre::Report`]s with a chain of context messages.
+///
+/// The root cause of the generated errors is an
+/// [`InjectedError`](crate::error::InjectedError), so that injected errors can
+/// be told apart with [`eyre::Report::downcast_ref`].
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct EyreErrors {
+    chain: ContextChain,
+}
+
+impl EyreErrors {
+    /// Create a new `EyreErrors` generator with the given message.
+    pub fn new(message: impl Into<Cow<'static, str>>) -> Self {
+        Self {
+            chain: ContextChain::new(message.into()),
+        }
+    }
+
+    /// Wrap the generated errors with the given context message.
+    ///
+    /// Context messages are added in order, the last one being the
+    /// outermost.
+    pub fn wrap_err(mut self, context: impl Into<Cow<'static, str>>) -> Self {
+        self.chain.push(context.into());
+        self
+    }
+}
+
+impl<R> Generator<R, eyre::Report> for EyreErrors {
+    fn generate(&self, _req: &R) -> eyre::Report {
+        self.chain.build(eyre::Report::new, eyre::Report::wrap_err)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::InjectedError;
+
+    #[test]
+    fn eyre_context() {
+        let generator = EyreErrors::new("connection reset")
+            .wrap_err("failed to query the database")
+            .wrap_err("failed to load the user");
+        let err: eyre::Report = generator.generate(&());
+
+        assert_eq!(err.to_string(), "failed to load the user");
+        let chain: Vec<_> = err.chain().map(ToString::to_string).collect();
+        assert_eq!(
+            chain,
+            [
+                "failed to load the user",
+                "failed to query the database",
+                "connection reset"
+            ]
+        );
+        assert!(err.root_cause().is::<InjectedError>());
+    }
+}
@@ -0,0 +1,315 @@
+//! # Generators
+//!
+//! A __generator__ creates the error or response injected by the
+//! [`ErrorLayer`](crate::error::ErrorLayer) and the `ResponseLayer`, based on
+//! the request. This is implemented for closures, and for the
+//! [`DefaultGenerator`], which returns the default value of the type.
+//!
+//! ```rust
+//! use tower_fault::generator::{DefaultGenerator, Generator};
+//!
+//! let generator = |req: &u64| format!("value: {}", req);
+//! assert_eq!(generator.generate(&3), "value: 3");
+//!
+//! let generator = DefaultGenerator;
+//! assert_eq!(Generator::<u64, String>::generate(&generator, &3), "");
+//! ```
+//!
+//! ## Magnitude
+//!
+//! [`WithMagnitude`] samples a magnitude for each injected fault, such as a
+//! severity between 0.0 and 1.0, and passes it to a [`MagnitudeGenerator`].
+//! This way, a single configuration can produce errors of varying severity,
+//! such as transient and fatal errors.
+//!
+//! ```rust
+//! use tower_fault::generator::{Generator, WithMagnitude};
+//!
+//! let generator = WithMagnitude::new(0.0..1.0, |_: &u64, severity: f64| {
+//!     if severity > 0.9 {
+//!         "fatal"
+//!     } else {
+//!         "transient"
+//!     }
+//! });
+//! let error = generator.generate(&3);
+//! ```
+//!
+//! ## Context
+//!
+//! Some values need metadata that can't be derived from the request, such
+//! as a correlation ID. [`Contextual`] passes a [`GenerateContext`] to a
+//! [`ContextGenerator`], with the kind of fault, the number of values
+//! generated so far, the current time, and a random number generator.
+//!
+//! ```rust
+//! use tower_fault::generator::{Contextual, GenerateContext, Generator};
+//! use rand::Rng;
+//!
+//! let generator = Contextual::new("error", |req: &u64, ctx: &GenerateContext| {
+//!     let correlation_id: u32 = ctx.rng().gen();
+//!     format!("{} error #{} ({:08x})", ctx.fault, ctx.attempt, correlation_id)
+//! });
+//! assert!(generator.generate(&3).starts_with("error error #1 "));
+//! ```
+//!
+//! ## `anyhow` and `eyre`
+//!
+//! With the `anyhow` and `eyre` features, [`AnyhowErrors`] and
+//! [`EyreErrors`] generate `anyhow::Error`s and `eyre::Report`s with a chain
+//! of context messages, wrapping an
+//! [`InjectedError`](crate::error::InjectedError).
+//!
+//! ```rust
+//! # #[cfg(feature = "anyhow")]
+//! # {
+//! use tower_fault::{error::ErrorLayer, generator::AnyhowErrors};
+//! # struct MyRequest;
+//!
+//! let error_layer = ErrorLayer::new(
+//!     0.1,
+//!     AnyhowErrors::new("connection reset").context("failed to query the database"),
+//! );
+//! # }
+//! ```
+
+#[cfg(feature = "anyhow")]
+#[cfg_attr(docsrs, doc(cfg(feature = "anyhow")))]
+mod anyhow;
+#[cfg(any(feature = "anyhow", feature = "eyre"))]
+mod chain;
+#[cfg(feature = "anyhow")]
+#[cfg_attr(docsrs, doc(cfg(feature = "anyhow")))]
+pub use self::anyhow::AnyhowErrors;
+#[cfg(feature = "eyre")]
+#[cfg_attr(docsrs, doc(cfg(feature = "eyre")))]
+mod eyre;
+#[cfg(feature = "eyre")]
+#[cfg_attr(docsrs, doc(cfg(feature = "eyre")))]
+pub use self::eyre::EyreErrors;
+
+use crate::sync::{Arc, AtomicU64, Ordering};
+use rand::Rng;
+use std::{ops, time::SystemTime};
+
+/// Trait to generate an injected value based on the request.
+pub trait Generator<R, T> {
+    /// Generate a value for the given request.
+    fn generate(&self, req: &R) -> T;
+}
+
+impl<F, R, T> Generator<R, T> for F
+where
+    F: Fn(&R) -> T,
+{
+    fn generate(&self, req: &R) -> T {
+        self(req)
+    }
+}
+
+/// Generator returning the default value of the type.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct DefaultGenerator;
+
+impl<R, T> Generator<R, T> for DefaultGenerator
+where
+    T: Default,
+{
+    fn generate(&self, _req: &R) -> T {
+        T::default()
+    }
+}
+
+/// Trait to generate an injected value based on the request and the
+/// magnitude of the fault.
+///
+/// See [`WithMagnitude`] for more information.
+pub trait MagnitudeGenerator<R, T> {
+    /// Generate a value for the given request and magnitude.
+    fn generate(&self, req: &R, magnitude: f64) -> T;
+}
+
+impl<F, R, T> MagnitudeGenerator<R, T> for F
+where
+    F: Fn(&R, f64) -> T,
+{
+    fn generate(&self, req: &R, magnitude: f64) -> T {
+        self(req, magnitude)
+    }
+}
+
+/// Trait that returns the magnitude of a fault for a request.
+///
+/// This is implemented for fixed values, for ranges, sampled uniformly, and
+/// for closures.
+pub trait Magnitude<R> {
+    /// Returns the magnitude of the fault for the request.
+    fn sample(&self, req: &R) -> f64;
+}
+
+impl<R> Magnitude<R> for f64 {
+    fn sample(&self, _req: &R) -> f64 {
+        *self
+    }
+}
+
+// Like latency distributions, empty ranges sample their start value.
+impl<R> Magnitude<R> for ops::Range<f64> {
+    fn sample(&self, _req: &R) -> f64 {
+        if self.is_empty() {
+            self.start
+        } else {
+            crate::seed::rng().gen_range(self.clone())
+        }
+    }
+}
+
+impl<R> Magnitude<R> for ops::RangeInclusive<f64> {
+    fn sample(&self, _req: &R) -> f64 {
+        if self.is_empty() {
+            *self.start()
+        } else {
+            crate::seed::rng().gen_range(self.clone())
+        }
+    }
+}
+
+impl<F, R> Magnitude<R> for F
+where
+    F: Fn(&R) -> f64,
+{
+    fn sample(&self, req: &R) -> f64 {
+        self(req)
+    }
+}
+
+/// Generator that samples a magnitude for each value, and passes it to a
+/// [`MagnitudeGenerator`].
+#[derive(Clone, Copy, Debug)]
+pub struct WithMagnitude<M, G> {
+    magnitude: M,
+    generator: G,
+}
+
+impl<M, G> WithMagnitude<M, G> {
+    /// Create a new `WithMagnitude` generator.
+    pub fn new(magnitude: M, generator: G) -> Self {
+        Self {
+            magnitude,
+            generator,
+        }
+    }
+}
+
+impl<M, G, R, T> Generator<R, T> for WithMagnitude<M, G>
+where
+    M: Magnitude<R>,
+    G: MagnitudeGenerator<R, T>,
+{
+    fn generate(&self, req: &R) -> T {
+        let magnitude = self.magnitude.sample(req);
+        self.generator.generate(req, magnitude)
+    }
+}
+
+/// Trait to generate an injected value based on the request and a
+/// [`GenerateContext`].
+///
+/// See [`Contextual`] for more information.
+pub trait ContextGenerator<R, T> {
+    /// Generate a value for the given request and context.
+    fn generate(&self, req: &R, ctx: &GenerateContext) -> T;
+}
+
+impl<F, R, T> ContextGenerator<R, T> for F
+where
+    F: Fn(&R, &GenerateContext) -> T,
+{
+    fn generate(&self, req: &R, ctx: &GenerateContext) -> T {
+        self(req, ctx)
+    }
+}
+
+/// Context of a generated value, passed to [`ContextGenerator`]s.
+#[derive(Clone, Debug)]
+#[non_exhaustive]
+pub struct GenerateContext {
+    /// Kind of fault, such as `error`.
+    pub fault: &'static str,
+    /// Number of values generated so far, including this one.
+    pub attempt: u64,
+    /// Time at which the value is generated.
+    pub timestamp: SystemTime,
+}
+
+impl GenerateContext {
+    /// Returns a random number generator.
+    ///
+    /// This uses the [experiment seed](crate::seed), so that generated
+    /// values can be reproduced.
+    pub fn rng(&self) -> impl Rng {
+        crate::seed::rng()
+    }
+}
+
+/// Generator that passes a [`GenerateContext`] to a [`ContextGenerator`].
+///
+/// Clones share the number of generated values.
+#[derive(Clone, Debug)]
+pub struct Contextual<G> {
+    generator: G,
+    fault: &'static str,
+    attempts: Arc<AtomicU64>,
+}
+
+impl<G> Contextual<G> {
+    /// Create a new `Contextual` generator for the given kind of fault.
+    pub fn new(fault: &'static str, generator: G) -> Self {
+        Self {
+            generator,
+            fault,
+            attempts: Arc::default(),
+        }
+    }
+}
+
+impl<G, R, T> Generator<R, T> for Contextual<G>
+where
+    G: ContextGenerator<R, T>,
+{
+    fn generate(&self, req: &R) -> T {
+        let ctx = GenerateContext {
+            fault: self.fault,
+            attempt: self.attempts.fetch_add(1, Ordering::Relaxed) + 1,
+            timestamp: SystemTime::now(),
+        };
+        self.generator.generate(req, &ctx)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn magnitude() {
+        let generator = WithMagnitude::new(0.2..0.4, |req: &f64, magnitude: f64| req * magnitude);
+        for _ in 0..100 {
+            let value = generator.generate(&10.0);
+            assert!((2.0..4.0).contains(&value), "{}", value);
+        }
+
+        assert_eq!(Magnitude::<()>::sample(&(0.5..0.5), &()), 0.5);
+        assert_eq!(Magnitude::<()>::sample(&(0.7..=0.7), &()), 0.7);
+        assert_eq!(Magnitude::sample(&|req: &f64| req / 2.0, &1.0), 0.5);
+    }
+
+    #[test]
+    fn contextual() {
+        let generator = Contextual::new("error", |req: &u64, ctx: &GenerateContext| {
+            (ctx.fault, req + ctx.attempt)
+        });
+        assert_eq!(generator.generate(&10), ("error", 11));
+        assert_eq!(generator.clone().generate(&10), ("error", 12));
+    }
+}
@@ -0,0 +1,327 @@
+//! # Health-check faults
+//!
+//! Layer for health-check services that flaps between healthy and unhealthy
+//! on a scripted or random schedule. This can be used to test how
+//! orchestrators restart instances, or how readiness gates stop sending
+//! traffic to them.
+//!
+//! While unhealthy, the layer returns a generated error instead of calling
+//! the health-check service.
+//!
+//! ## Usage
+//!
+//! ```rust
+//! use std::time::Duration;
+//! use tower_fault::health::{Health, HealthFaultLayer};
+//! # struct HealthRequest;
+//!
+//! // Healthy for 30 seconds, then unhealthy for 10 seconds, repeatedly.
+//! let health_layer = HealthFaultLayer::scripted(
+//!     [
+//!         (Health::Healthy, Duration::from_secs(30)),
+//!         (Health::Unhealthy, Duration::from_secs(10)),
+//!     ],
+//!     |_: &HealthRequest| String::from("unhealthy"),
+//! );
+//!
+//! // Healthy for 60 seconds on average, unhealthy for 5 seconds on average.
+//! let health_layer = HealthFaultLayer::random(
+//!     Duration::from_secs(60),
+//!     Duration::from_secs(5),
+//!     |_: &HealthRequest| String::from("unhealthy"),
+//! );
+//! ```
+
+use crate::veto::Veto;
+use rand::Rng;
+use std::{
+    future::Future,
+    pin::Pin,
+    sync::{Arc, Mutex},
+    task::{Context, Poll},
+    time::Duration,
+};
+use tokio::time::Instant;
+use tower::{Layer, Service};
+
+/// Health status reported by the layer.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Health {
+    /// The health-check service is called.
+    Healthy,
+    /// A generated error is returned.
+    Unhealthy,
+}
+
+impl Health {
+    fn flip(self) -> Self {
+        match self {
+            Health::Healthy => Health::Unhealthy,
+            Health::Unhealthy => Health::Healthy,
+        }
+    }
+}
+
+#[derive(Debug)]
+enum Schedule {
+    /// Phases repeated in a loop.
+    Scripted(Vec<(Health, Duration)>),
+    /// Phases with exponentially distributed durations.
+    Random {
+        mean_healthy: Duration,
+        mean_unhealthy: Duration,
+    },
+}
+
+#[derive(Debug)]
+struct State {
+    schedule: Schedule,
+    health: Health,
+    phase: usize,
+    until: Instant,
+}
+
+impl State {
+    fn new(schedule: Schedule) -> Self {
+        let mut state = Self {
+            schedule,
+            health: Health::Healthy,
+            phase: 0,
+            until: Instant::now(),
+        };
+        match &state.schedule {
+            Schedule::Scripted(phases) => {
+                if let Some((health, duration)) = phases.first() {
+                    state.health = *health;
+                    state.until += *duration;
+                }
+            }
+            Schedule::Random { .. } => state.until += state.sample(Health::Healthy),
+        }
+        state
+    }
+
+    fn sample(&self, health: Health) -> Duration {
+        let mean = match (&self.schedule, health) {
+            (Schedule::Random { mean_healthy, .. }, Health::Healthy) => *mean_healthy,
+            (Schedule::Random { mean_unhealthy, .. }, Health::Unhealthy) => *mean_unhealthy,
+            (Schedule::Scripted(_), _) => Duration::ZERO,
+        };
+        // Exponential distribution, `u` is in (0, 1] to avoid `ln(0)`.
+        let u: f64 = 1.0 - crate::seed::rng().gen::<f64>();
+        mean.mul_f64(-u.ln())
+    }
+
+    fn health(&mut self, now: Instant) -> Health {
+        while now >= self.until {
+            match &self.schedule {
+                Schedule::Scripted(phases) => {
+                    // Phases without a duration would loop forever.
+                    if phases.iter().all(|(_, d)| d.is_zero()) {
+                        break;
+                    }
+                    self.phase = (self.phase + 1) % phases.len();
+                    let (health, duration) = phases[self.phase];
+                    self.health = health;
+                    self.until += duration;
+                }
+                Schedule::Random { .. } => {
+                    self.health = self.health.flip();
+                    self.until += self.sample(self.health).max(Duration::from_millis(1));
+                }
+            }
+        }
+        self.health
+    }
+}
+
+/// Layer that makes a health-check service flap between healthy and
+/// unhealthy.
+///
+/// The schedule starts when the layer is created, and is shared by all the
+/// services created by the layer.
+#[derive(Clone, Debug)]
+pub struct HealthFaultLayer<G, V = bool> {
+    state: Arc<Mutex<State>>,
+    generator: G,
+    veto: V,
+}
+
+impl<G> HealthFaultLayer<G> {
+    /// Create a new `HealthFaultLayer` going through the given phases in a
+    /// loop.
+    pub fn scripted(phases: impl IntoIterator<Item = (Health, Duration)>, generator: G) -> Self {
+        Self::with_schedule(Schedule::Scripted(phases.into_iter().collect()), generator)
+    }
+
+    /// Create a new `HealthFaultLayer` flapping randomly, staying healthy and
+    /// unhealthy for the given average durations.
+    pub fn random(mean_healthy: Duration, mean_unhealthy: Duration, generator: G) -> Self {
+        Self::with_schedule(
+            Schedule::Random {
+                mean_healthy,
+                mean_unhealthy,
+            },
+            generator,
+        )
+    }
+
+    fn with_schedule(schedule: Schedule, generator: G) -> Self {
+        Self {
+            state: Arc::new(Mutex::new(State::new(schedule))),
+            generator,
+            veto: false,
+        }
+    }
+}
+
+impl<G, V> HealthFaultLayer<G, V> {
+    /// Always call the health-check service for the requests vetoed by the
+    /// given veto, even while unhealthy.
+    ///
+    /// See the [`veto`](crate::veto) module for more information.
+    pub fn with_veto<NV>(self, veto: NV) -> HealthFaultLayer<G, NV> {
+        HealthFaultLayer {
+            state: self.state,
+            generator: self.generator,
+            veto,
+        }
+    }
+
+    /// Returns the current health status.
+    pub fn health(&self) -> Health {
+        current(&self.state)
+    }
+}
+
+fn current(state: &Mutex<State>) -> Health {
+    state
+        .lock()
+        .expect("health state lock poisoned")
+        .health(Instant::now())
+}
+
+impl<G, V, S> Layer<S> for HealthFaultLayer<G, V>
+where
+    G: Clone,
+    V: Clone,
+{
+    type Service = HealthFaultService<G, S, V>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        HealthFaultService {
+            inner,
+            state: self.state.clone(),
+            generator: self.generator.clone(),
+            veto: self.veto.clone(),
+        }
+    }
+}
+
+/// Service that makes a health-check service flap between healthy and
+/// unhealthy.
+#[derive(Clone, Debug)]
+pub struct HealthFaultService<G, S, V = bool> {
+    inner: S,
+    state: Arc<Mutex<State>>,
+    generator: G,
+    veto: V,
+}
+
+impl<G, S, V> HealthFaultService<G, S, V> {
+    /// Returns the current health status.
+    pub fn health(&self) -> Health {
+        current(&self.state)
+    }
+}
+
+impl<G, S, V, R> Service<R> for HealthFaultService<G, S, V>
+where
+    G: Fn(&R) -> S::Error,
+    V: Veto<R>,
+    S: Service<R>,
+    S::Future: Send + 'static,
+    S::Error: Send + 'static,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = HealthFaultFuture<R, S>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, request: R) -> Self::Future {
+        match self.health() {
+            Health::Unhealthy if !self.veto.veto(&request) => {
+                let error = (self.generator)(&request);
+                Box::pin(async move { Err(error) })
+            }
+            _ => Box::pin(self.inner.call(request)),
+        }
+    }
+}
+
+type HealthFaultFuture<R, S> = Pin<
+    Box<
+        dyn Future<Output = Result<<S as Service<R>>::Response, <S as Service<R>>::Error>>
+            + Send
+            + 'static,
+    >,
+>;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::*;
+    use tokio::time;
+
+    #[tokio::test(start_paused = true)]
+    async fn health_scripted() {
+        let layer = HealthFaultLayer::scripted(
+            [
+                (Health::Healthy, Duration::from_secs(30)),
+                (Health::Unhealthy, Duration::from_secs(10)),
+            ],
+            |_: &()| String::from("unhealthy"),
+        );
+        let mut service = layer.layer(DummyService);
+
+        assert_eq!(service.call(()).await.unwrap(), "ok");
+        time::advance(Duration::from_secs(35)).await;
+        assert_eq!(service.call(()).await.unwrap_err(), "unhealthy");
+        time::advance(Duration::from_secs(10)).await;
+        assert_eq!(layer.health(), Health::Healthy);
+        time::advance(Duration::from_secs(30)).await;
+        assert_eq!(layer.health(), Health::Unhealthy);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn health_veto() {
+        let layer = HealthFaultLayer::scripted(
+            [(Health::Unhealthy, Duration::from_secs(10))],
+            |_: &bool| String::from("unhealthy"),
+        )
+        .with_veto(|probe: &bool| *probe);
+        let mut service = layer.layer(tower::service_fn(|_: bool| async { Ok::<_, String>("ok") }));
+
+        assert_eq!(service.call(false).await.unwrap_err(), "unhealthy");
+        assert_eq!(service.call(true).await.unwrap(), "ok");
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn health_random_flaps() {
+        let layer = HealthFaultLayer::random(
+            Duration::from_secs(10),
+            Duration::from_secs(10),
+            |_: &()| String::from("unhealthy"),
+        );
+
+        let mut seen_unhealthy = false;
+        for _ in 0..1000 {
+            time::advance(Duration::from_secs(1)).await;
+            seen_unhealthy |= layer.health() == Health::Unhealthy;
+        }
+        assert!(seen_unhealthy);
+    }
+}
@@ -0,0 +1,334 @@
+use crate::{
+    decider::{Decider, Probability},
+    describe::{DeciderDescription, DescribeDecider},
+    validate::ValidateDecider,
+    Error,
+};
+use http::Request;
+use rand::Rng;
+use std::{
+    fmt,
+    sync::Arc,
+    task::{Context, Poll},
+};
+use tower::{Layer, Service};
+
+type SharedDecider<B> = Arc<dyn Decider<Request<B>> + Send + Sync>;
+
+/// Faults selected by a [`FaultArbiter`] for a request, stored in the
+/// request extensions.
+#[derive(Clone, Debug)]
+struct Selected(Arc<Vec<&'static str>>);
+
+/// Coordinates the deciders of stacked fault layers, so that at most one
+/// fault fires per request.
+///
+/// Stacked layers decide independently, so a request can get both latency
+/// and an error, which is rarely realistic. The arbiter is a layer placed
+/// before the fault layers: it runs the deciders of all the registered
+/// faults in priority order, and stores the first fault that fires in the
+/// request extensions. The fault layers then use the deciders returned by
+/// [`FaultArbiter::decider`], which only fire for the selected fault.
+///
+/// Faults are registered from the highest to the lowest priority.
+///
+/// ## Correlated faults
+///
+/// [`FaultArbiter::correlate`] models faults that happen together, such as
+/// "when the database is slow, the cache also errors": when the first fault
+/// is selected, the second one is also selected with a conditional
+/// probability, even if compound faults are not allowed.
+pub struct FaultArbiter<B> {
+    faults: Vec<(&'static str, SharedDecider<B>)>,
+    correlations: Vec<(&'static str, &'static str, f64)>,
+    compound: bool,
+}
+
+impl<B> FaultArbiter<B> {
+    /// Create a new `FaultArbiter` without faults.
+    pub fn new() -> Self {
+        Self {
+            faults: Vec::new(),
+            correlations: Vec::new(),
+            compound: false,
+        }
+    }
+
+    /// Register a fault with the given decider, with a lower priority than
+    /// the faults already registered.
+    pub fn fault<D>(mut self, name: &'static str, decider: D) -> Self
+    where
+        D: Decider<Request<B>> + Send + Sync + 'static,
+    {
+        self.faults.retain(|(n, _)| *n != name);
+        self.faults.push((name, Arc::new(decider)));
+        self
+    }
+
+    /// Allow several faults to fire for the same request.
+    ///
+    /// All the faults whose decider fires are then selected. This is
+    /// disabled by default.
+    pub fn allow_compound(mut self, compound: bool) -> Self {
+        self.compound = compound;
+        self
+    }
+
+    /// When the `cause` fault is selected, also select the `effect` fault
+    /// with the given conditional probability.
+    ///
+    /// Correlations are applied in the order they are declared, so chains
+    /// such as `a` to `b` to `c` must be declared in that order.
+    pub fn correlate(
+        mut self,
+        cause: &'static str,
+        effect: &'static str,
+        probability: f64,
+    ) -> Self {
+        self.correlations.push((cause, effect, probability));
+        self
+    }
+
+    /// Returns the decider to use in the layer injecting the given fault.
+    ///
+    /// For requests that didn't go through the arbiter, the decider falls
+    /// back to the decider registered for the fault. Faults that are not
+    /// registered never fire.
+    pub fn decider(&self, name: &'static str) -> Arbitrated<B> {
+        Arbitrated {
+            name,
+            arbiter: self.clone(),
+        }
+    }
+
+    fn select(&self, req: &Request<B>) -> Vec<&'static str> {
+        let mut selected = Vec::new();
+        for (name, decider) in &self.faults {
+            if decider.decide(req) {
+                selected.push(*name);
+                if !self.compound {
+                    break;
+                }
+            }
+        }
+        for (cause, effect, probability) in &self.correlations {
+            // Invalid probabilities are rejected by `build()`, and treated
+            // as `0.0` for `NaN` here.
+            let probability = if probability.is_nan() {
+                0.0
+            } else {
+                probability.clamp(0.0, 1.0)
+            };
+            if selected.contains(cause)
+                && !selected.contains(effect)
+                && crate::seed::rng().gen_bool(probability)
+            {
+                selected.push(*effect);
+            }
+        }
+        selected
+    }
+}
+
+impl<B> Default for FaultArbiter<B> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<B> Clone for FaultArbiter<B> {
+    fn clone(&self) -> Self {
+        Self {
+            faults: self.faults.clone(),
+            correlations: self.correlations.clone(),
+            compound: self.compound,
+        }
+    }
+}
+
+impl<B> fmt::Debug for FaultArbiter<B> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let names: Vec<_> = self.faults.iter().map(|(name, _)| name).collect();
+        f.debug_struct("FaultArbiter")
+            .field("faults", &names)
+            .field("correlations", &self.correlations)
+            .field("compound", &self.compound)
+            .finish()
+    }
+}
+
+impl<B, S> Layer<S> for FaultArbiter<B> {
+    type Service = ArbiterService<B, S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        ArbiterService {
+            inner,
+            arbiter: self.clone(),
+        }
+    }
+}
+
+/// Service that selects the faults to inject into each request.
+///
+/// See [`FaultArbiter`] for more information.
+#[derive(Clone, Debug)]
+pub struct ArbiterService<B, S> {
+    inner: S,
+    arbiter: FaultArbiter<B>,
+}
+
+impl<B, S> Service<Request<B>> for ArbiterService<B, S>
+where
+    S: Service<Request<B>>,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = S::Future;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, mut req: Request<B>) -> Self::Future {
+        let selected = self.arbiter.select(&req);
+        req.extensions_mut().insert(Selected(Arc::new(selected)));
+        self.inner.call(req)
+    }
+}
+
+/// Decider that only fires for the fault selected by a [`FaultArbiter`].
+pub struct Arbitrated<B> {
+    name: &'static str,
+    arbiter: FaultArbiter<B>,
+}
+
+impl<B> Clone for Arbitrated<B> {
+    fn clone(&self) -> Self {
+        Self {
+            name: self.name,
+            arbiter: self.arbiter.clone(),
+        }
+    }
+}
+
+impl<B> fmt::Debug for Arbitrated<B> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Arbitrated")
+            .field("name", &self.name)
+            .finish()
+    }
+}
+
+impl<B> Decider<Request<B>> for Arbitrated<B> {
+    fn decide(&self, req: &Request<B>) -> bool {
+        match req.extensions().get::<Selected>() {
+            Some(Selected(selected)) => selected.contains(&self.name),
+            None => self
+                .arbiter
+                .faults
+                .iter()
+                .find(|(name, _)| *name == self.name)
+                .is_some_and(|(_, decider)| decider.decide(req)),
+        }
+    }
+}
+
+impl<B> ValidateDecider for Arbitrated<B> {
+    fn validate_decider(&self) -> Result<(), Error> {
+        let registered = |name| self.arbiter.faults.iter().any(|(n, _)| *n == name);
+        if !registered(self.name) {
+            return Err(Error::InvalidConfig(format!(
+                "fault '{}' is not registered in the arbiter",
+                self.name
+            )));
+        }
+        for (cause, effect, probability) in &self.arbiter.correlations {
+            if !registered(cause) || !registered(effect) {
+                return Err(Error::InvalidConfig(format!(
+                    "correlation from '{}' to '{}' uses an unregistered fault",
+                    cause, effect
+                )));
+            }
+            Probability::new(*probability)?;
+        }
+        Ok(())
+    }
+}
+
+impl<B> DescribeDecider for Arbitrated<B> {
+    fn describe_decider(&self) -> DeciderDescription {
+        DeciderDescription::new(format!("arbitrated ({})", self.name), None)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tower::{service_fn, ServiceExt};
+
+    #[tokio::test]
+    async fn arbiter() {
+        let arbiter = FaultArbiter::new()
+            .fault("error", true)
+            .fault("latency", true);
+        let error = arbiter.decider("error");
+        let latency = arbiter.decider("latency");
+
+        let service = arbiter.layer(service_fn(move |req: Request<()>| {
+            let decisions = (error.decide(&req), latency.decide(&req));
+            async move { Ok::<_, ()>(decisions) }
+        }));
+        assert_eq!(service.oneshot(Request::new(())).await, Ok((true, false)));
+
+        let arbiter = arbiter.allow_compound(true);
+        let (error, latency) = (arbiter.decider("error"), arbiter.decider("latency"));
+        let service = arbiter.layer(service_fn(move |req: Request<()>| {
+            let decisions = (error.decide(&req), latency.decide(&req));
+            async move { Ok::<_, ()>(decisions) }
+        }));
+        assert_eq!(service.oneshot(Request::new(())).await, Ok((true, true)));
+    }
+
+    #[tokio::test]
+    async fn arbiter_correlated() {
+        let arbiter = FaultArbiter::new()
+            .fault("db-latency", true)
+            .fault("cache-error", false)
+            .fault("queue-error", false)
+            .correlate("db-latency", "cache-error", 1.0)
+            .correlate("db-latency", "queue-error", 0.0);
+        let deciders =
+            ["db-latency", "cache-error", "queue-error"].map(|name| arbiter.decider(name));
+        assert!(deciders[0].validate_decider().is_ok());
+
+        let service = arbiter.layer(service_fn(move |req: Request<()>| {
+            let decisions = deciders.clone().map(|decider| decider.decide(&req));
+            async move { Ok::<_, ()>(decisions) }
+        }));
+        assert_eq!(
+            service.oneshot(Request::new(())).await,
+            Ok([true, true, false])
+        );
+
+        let arbiter = FaultArbiter::<()>::new()
+            .fault("a", true)
+            .fault("b", true)
+            .correlate("a", "b", 1.5);
+        assert!(arbiter.decider("a").validate_decider().is_err());
+
+        // `NaN` probabilities are rejected, and never select the effect.
+        let arbiter = FaultArbiter::<()>::new()
+            .fault("a", true)
+            .fault("b", false)
+            .correlate("a", "b", f64::NAN);
+        assert!(arbiter.decider("a").validate_decider().is_err());
+        assert_eq!(arbiter.select(&Request::new(())), vec!["a"]);
+    }
+
+    #[test]
+    fn arbitrated_unregistered() {
+        let arbiter = FaultArbiter::<()>::new();
+        assert!(arbiter.decider("error").validate_decider().is_err());
+        assert!(!arbiter.decider("error").decide(&Request::new(())));
+    }
+}
@@ -0,0 +1,227 @@
+use crate::{
+    decider::Decider,
+    describe::{DeciderDescription, DescribeDecider},
+    generator::Generator,
+    validate::ValidateDecider,
+    Error,
+};
+use http::{
+    header::{AUTHORIZATION, CONTENT_TYPE, WWW_AUTHENTICATE},
+    HeaderValue, Request, Response, StatusCode,
+};
+
+/// Response generator producing authentication and authorization failures.
+///
+/// The responses mimic OAuth 2.0 bearer token errors (RFC 6750), with a
+/// `WWW-Authenticate` challenge and a JSON body, so that clients exercise
+/// their token-refresh and re-authentication logic without touching the
+/// real authentication service.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct AuthFailure {
+    status: StatusCode,
+    error: &'static str,
+    description: &'static str,
+    scope: Option<String>,
+}
+
+impl AuthFailure {
+    /// `401 Unauthorized` response for an expired access token.
+    pub fn expired_token() -> Self {
+        Self {
+            status: StatusCode::UNAUTHORIZED,
+            error: "invalid_token",
+            description: "The access token expired",
+            scope: None,
+        }
+    }
+
+    /// `401 Unauthorized` response for an invalid or revoked access token.
+    pub fn invalid_token() -> Self {
+        Self {
+            status: StatusCode::UNAUTHORIZED,
+            error: "invalid_token",
+            description: "The access token is invalid",
+            scope: None,
+        }
+    }
+
+    /// `403 Forbidden` response for an access token missing the given
+    /// scope.
+    pub fn insufficient_scope(scope: impl Into<String>) -> Self {
+        Self {
+            status: StatusCode::FORBIDDEN,
+            error: "insufficient_scope",
+            description: "The request requires higher privileges than provided by the access token",
+            scope: Some(scope.into()),
+        }
+    }
+
+    /// Returns the status code of the generated responses.
+    pub fn status(&self) -> StatusCode {
+        self.status
+    }
+}
+
+impl<R, B> Generator<R, Response<B>> for AuthFailure
+where
+    B: From<String>,
+{
+    fn generate(&self, _req: &R) -> Response<B> {
+        let mut challenge = format!(
+            "Bearer error=\"{}\", error_description=\"{}\"",
+            self.error, self.description
+        );
+        let mut body = format!(
+            "{{\"error\":\"{}\",\"error_description\":\"{}\"",
+            self.error, self.description
+        );
+        if let Some(scope) = &self.scope {
+            let scope = scope.replace('\\', "\\\\").replace('"', "\\\"");
+            challenge.push_str(&format!(", scope=\"{}\"", scope));
+            body.push_str(&format!(",\"scope\":\"{}\"", scope));
+        }
+        body.push('}');
+
+        let mut res = Response::new(B::from(body));
+        *res.status_mut() = self.status;
+        res.headers_mut()
+            .insert(CONTENT_TYPE, HeaderValue::from_static("application/json"));
+        if let Ok(challenge) = HeaderValue::try_from(challenge) {
+            res.headers_mut().insert(WWW_AUTHENTICATE, challenge);
+        }
+        res
+    }
+}
+
+/// Decider that injects faults based on the `Authorization` header.
+///
+/// The predicate receives the value of the header, or the token for
+/// [`AuthDecider::bearer`]. Requests without the header are never faulted.
+/// This can target test accounts or specific tokens, for example to
+/// simulate the expiration of a single session.
+#[derive(Clone, Debug)]
+pub struct AuthDecider<F, D = bool> {
+    predicate: F,
+    bearer: bool,
+    inner: D,
+}
+
+impl<F> AuthDecider<F> {
+    /// Create a new `AuthDecider` matching the value of the `Authorization`
+    /// header with the given predicate.
+    pub fn new(predicate: F) -> Self {
+        Self {
+            predicate,
+            bearer: false,
+            inner: true,
+        }
+    }
+
+    /// Create a new `AuthDecider` matching bearer tokens with the given
+    /// predicate.
+    pub fn bearer(predicate: F) -> Self {
+        Self {
+            predicate,
+            bearer: true,
+            inner: true,
+        }
+    }
+}
+
+impl<F, D> AuthDecider<F, D> {
+    /// Set the decider used for the matching requests.
+    pub fn with_decider<ND>(self, decider: ND) -> AuthDecider<F, ND> {
+        AuthDecider {
+            predicate: self.predicate,
+            bearer: self.bearer,
+            inner: decider,
+        }
+    }
+
+    fn credentials<'r, B>(&self, req: &'r Request<B>) -> Option<&'r str> {
+        let value = req.headers().get(AUTHORIZATION)?.to_str().ok()?;
+        if !self.bearer {
+            return Some(value);
+        }
+        let (scheme, token) = value.split_once(' ')?;
+        scheme.eq_ignore_ascii_case("bearer").then(|| token.trim())
+    }
+}
+
+impl<F, D, B> Decider<Request<B>> for AuthDecider<F, D>
+where
+    F: Fn(&str) -> bool,
+    D: Decider<Request<B>>,
+{
+    fn decide(&self, req: &Request<B>) -> bool {
+        self.credentials(req)
+            .is_some_and(|credentials| (self.predicate)(credentials))
+            && self.inner.decide(req)
+    }
+}
+
+impl<F, D> ValidateDecider for AuthDecider<F, D>
+where
+    D: ValidateDecider,
+{
+    fn validate_decider(&self) -> Result<(), Error> {
+        self.inner.validate_decider()
+    }
+}
+
+impl<F, D> DescribeDecider for AuthDecider<F, D>
+where
+    D: DescribeDecider,
+{
+    fn describe_decider(&self) -> DeciderDescription {
+        let inner = self.inner.describe_decider();
+        let kind = if self.bearer {
+            "bearer token"
+        } else {
+            "authorization"
+        };
+        DeciderDescription::new(format!("{} on {}", inner.kind, kind), None)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn request(authorization: Option<&str>) -> Request<()> {
+        let mut req = Request::builder();
+        if let Some(authorization) = authorization {
+            req = req.header(AUTHORIZATION, authorization);
+        }
+        req.body(()).unwrap()
+    }
+
+    #[test]
+    fn auth_failure_responses() {
+        let res: Response<String> = AuthFailure::expired_token().generate(&());
+        assert_eq!(res.status(), StatusCode::UNAUTHORIZED);
+        assert_eq!(
+            res.headers()[WWW_AUTHENTICATE],
+            "Bearer error=\"invalid_token\", error_description=\"The access token expired\""
+        );
+        assert!(res.body().contains("\"error\":\"invalid_token\""));
+
+        let res: Response<String> = AuthFailure::insufficient_scope("admin").generate(&());
+        assert_eq!(res.status(), StatusCode::FORBIDDEN);
+        assert!(res.body().ends_with(",\"scope\":\"admin\"}"));
+    }
+
+    #[test]
+    fn auth_decider() {
+        let decider = AuthDecider::bearer(|token: &str| token.starts_with("test-"));
+        assert!(decider.decide(&request(Some("Bearer test-123"))));
+        assert!(decider.decide(&request(Some("bearer test-123"))));
+        assert!(!decider.decide(&request(Some("Bearer prod-123"))));
+        assert!(!decider.decide(&request(Some("Basic dGVzdA=="))));
+        assert!(!decider.decide(&request(None)));
+
+        let decider =
+            AuthDecider::new(|value: &str| value.starts_with("Basic ")).with_decider(false);
+        assert!(!decider.decide(&request(Some("Basic dGVzdA=="))));
+    }
+}
@@ -0,0 +1,179 @@
+use crate::{
+    decider::Decider,
+    describe::{DeciderDescription, DescribeDecider},
+    validate::ValidateDecider,
+    Error,
+};
+use http::{header::HeaderName, Request};
+
+const BAGGAGE: HeaderName = HeaderName::from_static("baggage");
+const TRACEPARENT: HeaderName = HeaderName::from_static("traceparent");
+
+/// Decider that injects faults based on the W3C `baggage` header.
+///
+/// A fault is injected when one of the baggage entries matches the
+/// configured key (and value, if set). Optionally, the decider can also
+/// require the `traceparent` header to have the `sampled` flag set.
+#[derive(Clone, Debug)]
+pub struct BaggageDecider {
+    key: String,
+    value: Option<String>,
+    sampled_only: bool,
+}
+
+impl BaggageDecider {
+    /// Create a new `BaggageDecider` that matches baggage entries with the
+    /// given key and value, such as `chaos=error`.
+    pub fn new(key: impl Into<String>, value: impl Into<String>) -> Self {
+        Self {
+            key: key.into(),
+            value: Some(value.into()),
+            sampled_only: false,
+        }
+    }
+
+    /// Create a new `BaggageDecider` that matches baggage entries with the
+    /// given key, regardless of their value.
+    pub fn key(key: impl Into<String>) -> Self {
+        Self {
+            key: key.into(),
+            value: None,
+            sampled_only: false,
+        }
+    }
+
+    /// Only inject faults for requests whose `traceparent` header has the
+    /// `sampled` flag set.
+    pub fn sampled_only(mut self) -> Self {
+        self.sampled_only = true;
+        self
+    }
+
+    fn matches(&self, entry: &str) -> bool {
+        // Properties (`;prop=value`) are ignored.
+        let member = entry.split(';').next().unwrap_or_default();
+        let (key, value) = match member.split_once('=') {
+            Some((key, value)) => (key.trim(), value.trim()),
+            None => return false,
+        };
+
+        if key != self.key {
+            return false;
+        }
+
+        match &self.value {
+            Some(expected) => percent_decode(value).as_deref() == Some(expected.as_str()),
+            None => true,
+        }
+    }
+}
+
+impl<B> Decider<Request<B>> for BaggageDecider {
+    fn decide(&self, req: &Request<B>) -> bool {
+        if self.sampled_only && !is_sampled(req) {
+            return false;
+        }
+
+        req.headers()
+            .get_all(BAGGAGE)
+            .iter()
+            .filter_map(|value| value.to_str().ok())
+            .flat_map(|value| value.split(','))
+            .any(|entry| self.matches(entry))
+    }
+}
+
+impl ValidateDecider for BaggageDecider {
+    fn validate_decider(&self) -> Result<(), Error> {
+        Ok(())
+    }
+}
+
+impl DescribeDecider for BaggageDecider {
+    fn describe_decider(&self) -> DeciderDescription {
+        let kind = match &self.value {
+            Some(value) => format!("baggage {}={}", self.key, value),
+            None => format!("baggage {}", self.key),
+        };
+        DeciderDescription::new(kind, None)
+    }
+}
+
+/// Returns `true` if the `traceparent` header has the `sampled` flag set.
+fn is_sampled<B>(req: &Request<B>) -> bool {
+    let header = match req.headers().get(TRACEPARENT).map(|v| v.to_str()) {
+        Some(Ok(header)) => header,
+        _ => return false,
+    };
+
+    // Format: `{version}-{trace-id}-{parent-id}-{trace-flags}`
+    header
+        .trim()
+        .split('-')
+        .nth(3)
+        .and_then(|flags| u8::from_str_radix(flags, 16).ok())
+        .map(|flags| flags & 0x01 == 0x01)
+        .unwrap_or(false)
+}
+
+/// Decode a percent-encoded baggage value.
+fn percent_decode(value: &str) -> Option<String> {
+    let mut bytes = Vec::with_capacity(value.len());
+    let mut iter = value.bytes();
+    while let Some(byte) = iter.next() {
+        if byte == b'%' {
+            let hi = (iter.next()? as char).to_digit(16)?;
+            let lo = (iter.next()? as char).to_digit(16)?;
+            bytes.push((hi * 16 + lo) as u8);
+        } else {
+            bytes.push(byte);
+        }
+    }
+    String::from_utf8(bytes).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn request(baggage: &str, traceparent: Option<&str>) -> Request<()> {
+        let mut builder = Request::builder().header(BAGGAGE, baggage);
+        if let Some(traceparent) = traceparent {
+            builder = builder.header(TRACEPARENT, traceparent);
+        }
+        builder.body(()).unwrap()
+    }
+
+    #[test]
+    fn baggage_matches_entry() {
+        let decider = BaggageDecider::new("chaos", "error");
+
+        assert!(decider.decide(&request("chaos=error", None)));
+        assert!(decider.decide(&request("userId=alice, chaos = error;ttl=3", None)));
+        assert!(!decider.decide(&request("chaos=latency", None)));
+        assert!(!decider.decide(&request("other=error", None)));
+    }
+
+    #[test]
+    fn baggage_percent_decoding() {
+        let decider = BaggageDecider::new("chaos", "slow db");
+
+        assert!(decider.decide(&request("chaos=slow%20db", None)));
+    }
+
+    #[test]
+    fn baggage_sampled_only() {
+        let decider = BaggageDecider::key("chaos").sampled_only();
+        let trace_id = "4bf92f3577b34da6a3ce929d0e0e4736";
+
+        assert!(!decider.decide(&request("chaos=1", None)));
+        assert!(!decider.decide(&request(
+            "chaos=1",
+            Some(&format!("00-{}-00f067aa0ba902b7-00", trace_id))
+        )));
+        assert!(decider.decide(&request(
+            "chaos=1",
+            Some(&format!("00-{}-00f067aa0ba902b7-01", trace_id))
+        )));
+    }
+}
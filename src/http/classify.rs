@@ -0,0 +1,103 @@
+//! # Classification-aware responses
+//!
+//! Response generators for the [`ResponseLayer`](super::ResponseLayer) that
+//! produce responses classified as failures by [`tower_http::classify`].
+//!
+//! Placing the `ResponseLayer` inside a middleware that uses a classifier,
+//! such as `tower_http::trace::TraceLayer`, ensures that injected faults are
+//! counted as failures instead of bypassing classification entirely.
+//!
+//! ```rust
+//! use http::{Request, Response, StatusCode};
+//! use tower_fault::http::{classify::server_error, ResponseLayer};
+//!
+//! let response_layer = ResponseLayer::new(
+//!     0.1,
+//!     server_error::<Request<String>, String>(StatusCode::SERVICE_UNAVAILABLE),
+//! );
+//! ```
+
+use http::{header::HeaderValue, Response, StatusCode};
+use tower_http::classify::{ClassifiedResponse, ClassifyResponse, GrpcCode};
+
+/// Response generator producing responses that are classified as server
+/// errors.
+///
+/// Status codes that are not server errors (`5xx`) are replaced by
+/// `500 Internal Server Error`, so that the generated responses are always
+/// classified as failures by
+/// [`ServerErrorsAsFailures`](tower_http::classify::ServerErrorsAsFailures).
+pub fn server_error<R, B>(status: StatusCode) -> impl Fn(&R) -> Response<B> + Clone
+where
+    B: Default,
+{
+    let status = if status.is_server_error() {
+        status
+    } else {
+        StatusCode::INTERNAL_SERVER_ERROR
+    };
+
+    move |_: &R| {
+        let mut res = Response::new(B::default());
+        *res.status_mut() = status;
+        res
+    }
+}
+
+/// Response generator producing gRPC responses that are classified as
+/// failures by [`GrpcErrorsAsFailures`](tower_http::classify::GrpcErrorsAsFailures).
+///
+/// The response has a `200 OK` status with the `grpc-status` header set to
+/// the given code, as gRPC servers do for trailers-only responses.
+pub fn grpc_error<R, B>(code: GrpcCode) -> impl Fn(&R) -> Response<B> + Clone
+where
+    B: Default,
+{
+    move |_: &R| {
+        let mut res = Response::new(B::default());
+        res.headers_mut()
+            .insert("content-type", HeaderValue::from_static("application/grpc"));
+        res.headers_mut()
+            .insert("grpc-status", HeaderValue::from(code as i32));
+        res
+    }
+}
+
+/// Returns `true` if the given classifier classifies the response as a
+/// failure without having to wait for the end of the response stream.
+///
+/// This can be used to check that injected responses are counted as failures
+/// by classification middlewares.
+pub fn is_failure<C, B>(classifier: C, res: &Response<B>) -> bool
+where
+    C: ClassifyResponse,
+{
+    matches!(
+        classifier.classify_response(res),
+        ClassifiedResponse::Ready(Err(_))
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tower_http::classify::{GrpcErrorsAsFailures, ServerErrorsAsFailures};
+
+    #[test]
+    fn server_error_is_failure() {
+        let res = server_error::<(), ()>(StatusCode::SERVICE_UNAVAILABLE)(&());
+        assert_eq!(res.status(), StatusCode::SERVICE_UNAVAILABLE);
+        assert!(is_failure(ServerErrorsAsFailures::new(), &res));
+
+        let res = server_error::<(), ()>(StatusCode::OK)(&());
+        assert_eq!(res.status(), StatusCode::INTERNAL_SERVER_ERROR);
+        assert!(is_failure(ServerErrorsAsFailures::new(), &res));
+    }
+
+    #[test]
+    fn grpc_error_is_failure() {
+        let res = grpc_error::<(), ()>(GrpcCode::Unavailable)(&());
+        assert_eq!(res.headers()["grpc-status"], "14");
+        assert!(is_failure(GrpcErrorsAsFailures::new(), &res));
+    }
+}
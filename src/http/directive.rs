@@ -0,0 +1,90 @@
+use crate::{
+    decider::Decider,
+    describe::{DeciderDescription, DescribeDecider},
+    validate::ValidateDecider,
+    Error,
+};
+use http::Request;
+
+/// Directive inserted into the request extensions to control fault injection
+/// for that specific request.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FaultDirective {
+    /// Always inject a fault for this request.
+    Inject,
+    /// Never inject a fault for this request.
+    Skip,
+}
+
+/// Decider that reads a [`FaultDirective`] from the request extensions.
+///
+/// If the request doesn't contain a directive, the fallback decider is used
+/// instead.
+#[derive(Clone, Debug)]
+pub struct DirectiveDecider<D> {
+    fallback: D,
+}
+
+impl<D> DirectiveDecider<D> {
+    /// Create a new `DirectiveDecider` with the given fallback decider.
+    pub fn new(fallback: D) -> Self {
+        Self { fallback }
+    }
+}
+
+impl Default for DirectiveDecider<bool> {
+    fn default() -> Self {
+        Self::new(false)
+    }
+}
+
+impl<B, D> Decider<Request<B>> for DirectiveDecider<D>
+where
+    D: Decider<Request<B>>,
+{
+    fn decide(&self, req: &Request<B>) -> bool {
+        match req.extensions().get::<FaultDirective>() {
+            Some(FaultDirective::Inject) => true,
+            Some(FaultDirective::Skip) => false,
+            None => self.fallback.decide(req),
+        }
+    }
+}
+
+impl<D> ValidateDecider for DirectiveDecider<D>
+where
+    D: ValidateDecider,
+{
+    fn validate_decider(&self) -> Result<(), Error> {
+        self.fallback.validate_decider()
+    }
+}
+
+impl<D> DescribeDecider for DirectiveDecider<D>
+where
+    D: DescribeDecider,
+{
+    fn describe_decider(&self) -> DeciderDescription {
+        let fallback = self.fallback.describe_decider();
+        DeciderDescription::new(format!("directive or {}", fallback.kind), None)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn directive_overrides_fallback() {
+        let decider = DirectiveDecider::new(true);
+
+        let mut req = Request::new(());
+        assert!(decider.decide(&req));
+
+        req.extensions_mut().insert(FaultDirective::Skip);
+        assert!(!decider.decide(&req));
+
+        req.extensions_mut().insert(FaultDirective::Inject);
+        assert!(decider.decide(&req));
+    }
+}
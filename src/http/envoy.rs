@@ -0,0 +1,541 @@
+//! Envoy HTTP fault filter compatibility.
+
+use crate::{
+    decider::Decider,
+    describe::{DeciderDescription, DescribeDecider},
+    validate::ValidateDecider,
+    veto::Veto,
+    Error,
+};
+use http::{header::HeaderValue, HeaderMap, Request, Response, StatusCode};
+use rand::Rng;
+use std::{
+    future::Future,
+    pin::Pin,
+    task::{Context, Poll},
+    time::Duration,
+};
+use tokio::time;
+use tower::{Layer, Service};
+
+/// Header containing the delay to inject, in milliseconds.
+pub const DELAY_REQUEST: &str = "x-envoy-fault-delay-request";
+/// Header containing the percentage of requests to delay.
+pub const DELAY_REQUEST_PERCENTAGE: &str = "x-envoy-fault-delay-request-percentage";
+/// Header containing the HTTP status code to abort requests with.
+pub const ABORT_REQUEST: &str = "x-envoy-fault-abort-request";
+/// Header containing the gRPC status code to abort requests with.
+pub const ABORT_GRPC_REQUEST: &str = "x-envoy-fault-abort-grpc-request";
+/// Header containing the percentage of requests to abort.
+pub const ABORT_REQUEST_PERCENTAGE: &str = "x-envoy-fault-abort-request-percentage";
+
+/// Denominator of a [`FractionalPercent`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "SCREAMING_SNAKE_CASE"))]
+pub enum Denominator {
+    /// 100
+    #[default]
+    Hundred,
+    /// 10,000
+    TenThousand,
+    /// 1,000,000
+    Million,
+}
+
+impl Denominator {
+    fn value(self) -> u32 {
+        match self {
+            Denominator::Hundred => 100,
+            Denominator::TenThousand => 10_000,
+            Denominator::Million => 1_000_000,
+        }
+    }
+}
+
+/// Percentage expressed as a fraction, like Envoy's `FractionalPercent`.
+///
+/// The fraction is capped at 100%.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Deserialize))]
+pub struct FractionalPercent {
+    /// Numerator of the fraction.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub numerator: u32,
+    /// Denominator of the fraction.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub denominator: Denominator,
+}
+
+impl FractionalPercent {
+    /// Create a new `FractionalPercent`.
+    pub fn new(numerator: u32, denominator: Denominator) -> Self {
+        Self {
+            numerator,
+            denominator,
+        }
+    }
+
+    /// Create a new `FractionalPercent` with a denominator of 100.
+    pub fn percent(numerator: u32) -> Self {
+        Self::new(numerator, Denominator::Hundred)
+    }
+
+    /// Returns the fraction as a ratio between 0.0 and 1.0.
+    pub fn ratio(&self) -> f64 {
+        (self.numerator as f64 / self.denominator.value() as f64).min(1.0)
+    }
+
+    // Envoy replaces the numerator with the value of the percentage header,
+    // capped by the configured numerator.
+    fn with_header(self, headers: &HeaderMap, name: &str) -> Self {
+        match header::<u32>(headers, name) {
+            Some(numerator) => Self {
+                numerator: numerator.min(self.numerator),
+                ..self
+            },
+            None => self,
+        }
+    }
+}
+
+impl<R> Decider<R> for FractionalPercent {
+    fn decide(&self, _req: &R) -> bool {
+        crate::seed::rng().gen_range(0..self.denominator.value()) < self.numerator
+    }
+}
+
+impl ValidateDecider for FractionalPercent {
+    fn validate_decider(&self) -> Result<(), Error> {
+        Ok(())
+    }
+}
+
+impl DescribeDecider for FractionalPercent {
+    fn describe_decider(&self) -> DeciderDescription {
+        DeciderDescription::new("fractional percent", Some(self.ratio()))
+    }
+}
+
+/// Source of the delay of an Envoy fault.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+pub enum DelaySource {
+    /// Fixed delay.
+    #[cfg_attr(feature = "serde", serde(deserialize_with = "de::duration"))]
+    FixedDelay(Duration),
+    /// Delay read from the `x-envoy-fault-delay-request` header.
+    HeaderDelay {},
+}
+
+/// Source of the status of an Envoy abort fault.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+pub enum AbortSource {
+    /// HTTP status code.
+    HttpStatus(u16),
+    /// gRPC status code.
+    GrpcStatus(u32),
+    /// Status read from the `x-envoy-fault-abort-request` or
+    /// `x-envoy-fault-abort-grpc-request` headers.
+    HeaderAbort {},
+}
+
+/// Delay configuration of an Envoy fault filter.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Deserialize))]
+pub struct FaultDelay {
+    /// Source of the delay.
+    #[cfg_attr(feature = "serde", serde(flatten))]
+    pub source: DelaySource,
+    /// Percentage of requests to delay.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub percentage: FractionalPercent,
+}
+
+/// Abort configuration of an Envoy fault filter.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Deserialize))]
+pub struct FaultAbort {
+    /// Source of the status.
+    #[cfg_attr(feature = "serde", serde(flatten))]
+    pub source: AbortSource,
+    /// Percentage of requests to abort.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub percentage: FractionalPercent,
+}
+
+/// Layer implementing the semantics of Envoy's HTTP fault filter.
+///
+/// Like Envoy, the delay is applied first, then the request is either
+/// aborted or forwarded to the service. Delay and abort are decided
+/// independently.
+///
+/// With the `serde` feature, the layer can be deserialized from the JSON
+/// representation of the `envoy.extensions.filters.http.fault.v3.HTTPFault`
+/// configuration. Only the `delay` and `abort` fields are supported.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Deserialize))]
+pub struct EnvoyFaultLayer<V = bool> {
+    #[cfg_attr(feature = "serde", serde(default))]
+    delay: Option<FaultDelay>,
+    #[cfg_attr(feature = "serde", serde(default))]
+    abort: Option<FaultAbort>,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    veto: V,
+}
+
+impl EnvoyFaultLayer {
+    /// Create a new `EnvoyFaultLayer` without any fault.
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl<V> EnvoyFaultLayer<V> {
+    /// Never delay or abort the requests vetoed by the given veto.
+    ///
+    /// See the [`veto`](crate::veto) module for more information.
+    pub fn with_veto<NV>(self, veto: NV) -> EnvoyFaultLayer<NV> {
+        EnvoyFaultLayer {
+            delay: self.delay,
+            abort: self.abort,
+            veto,
+        }
+    }
+
+    /// Delay a percentage of requests by a fixed duration.
+    pub fn fixed_delay(mut self, delay: Duration, percentage: FractionalPercent) -> Self {
+        self.delay = Some(FaultDelay {
+            source: DelaySource::FixedDelay(delay),
+            percentage,
+        });
+        self
+    }
+
+    /// Delay requests based on the `x-envoy-fault-delay-request` header.
+    ///
+    /// The `x-envoy-fault-delay-request-percentage` header can lower the
+    /// percentage of delayed requests, but not raise it above the configured
+    /// percentage.
+    pub fn header_delay(mut self, percentage: FractionalPercent) -> Self {
+        self.delay = Some(FaultDelay {
+            source: DelaySource::HeaderDelay {},
+            percentage,
+        });
+        self
+    }
+
+    /// Abort a percentage of requests with the given HTTP status.
+    pub fn abort(mut self, status: StatusCode, percentage: FractionalPercent) -> Self {
+        self.abort = Some(FaultAbort {
+            source: AbortSource::HttpStatus(status.as_u16()),
+            percentage,
+        });
+        self
+    }
+
+    /// Abort a percentage of requests with the given gRPC status code.
+    pub fn grpc_abort(mut self, code: u32, percentage: FractionalPercent) -> Self {
+        self.abort = Some(FaultAbort {
+            source: AbortSource::GrpcStatus(code),
+            percentage,
+        });
+        self
+    }
+
+    /// Abort requests based on the `x-envoy-fault-abort-request` and
+    /// `x-envoy-fault-abort-grpc-request` headers.
+    ///
+    /// The `x-envoy-fault-abort-request-percentage` header can lower the
+    /// percentage of aborted requests, but not raise it above the configured
+    /// percentage.
+    pub fn header_abort(mut self, percentage: FractionalPercent) -> Self {
+        self.abort = Some(FaultAbort {
+            source: AbortSource::HeaderAbort {},
+            percentage,
+        });
+        self
+    }
+
+    fn decide_delay<B>(&self, req: &Request<B>) -> Option<Duration> {
+        let delay = self.delay.as_ref()?;
+        let (duration, percentage) = match delay.source {
+            DelaySource::FixedDelay(duration) => (duration, delay.percentage),
+            DelaySource::HeaderDelay {} => (
+                Duration::from_millis(header(req.headers(), DELAY_REQUEST)?),
+                delay
+                    .percentage
+                    .with_header(req.headers(), DELAY_REQUEST_PERCENTAGE),
+            ),
+        };
+        percentage.decide(req).then_some(duration)
+    }
+
+    fn decide_abort<B>(&self, req: &Request<B>) -> Option<Abort> {
+        let abort = self.abort.as_ref()?;
+        let (status, percentage) = match abort.source {
+            AbortSource::HttpStatus(status) => (Abort::http(status)?, abort.percentage),
+            AbortSource::GrpcStatus(code) => (Abort::Grpc(code), abort.percentage),
+            AbortSource::HeaderAbort {} => {
+                let status = match header(req.headers(), ABORT_REQUEST) {
+                    Some(status) => Abort::http(status)?,
+                    None => Abort::Grpc(header(req.headers(), ABORT_GRPC_REQUEST)?),
+                };
+                let percentage = abort
+                    .percentage
+                    .with_header(req.headers(), ABORT_REQUEST_PERCENTAGE);
+                (status, percentage)
+            }
+        };
+        percentage.decide(req).then_some(status)
+    }
+}
+
+impl<V, S> Layer<S> for EnvoyFaultLayer<V>
+where
+    V: Clone,
+{
+    type Service = EnvoyFaultService<S, V>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        EnvoyFaultService {
+            inner,
+            layer: self.clone(),
+        }
+    }
+}
+
+/// Service implementing the semantics of Envoy's HTTP fault filter.
+#[derive(Clone, Debug)]
+pub struct EnvoyFaultService<S, V = bool> {
+    inner: S,
+    layer: EnvoyFaultLayer<V>,
+}
+
+impl<S, V, ReqB, ResB> Service<Request<ReqB>> for EnvoyFaultService<S, V>
+where
+    V: Veto<Request<ReqB>>,
+    S: Service<Request<ReqB>, Response = Response<ResB>>,
+    S::Future: Send + 'static,
+    S::Error: Send + 'static,
+    ResB: Default + Send + 'static,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = EnvoyFaultFuture<Request<ReqB>, S>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, request: Request<ReqB>) -> Self::Future {
+        if self.layer.veto.veto(&request) {
+            return Box::pin(self.inner.call(request));
+        }
+
+        let delay = self.layer.decide_delay(&request).unwrap_or_default();
+
+        if let Some(abort) = self.layer.decide_abort(&request) {
+            return Box::pin(async move {
+                time::sleep(delay).await;
+                Ok(abort.response())
+            });
+        }
+
+        let fut = self.inner.call(request);
+        Box::pin(async move {
+            time::sleep(delay).await;
+            fut.await
+        })
+    }
+}
+
+type EnvoyFaultFuture<R, S> = Pin<
+    Box<
+        dyn Future<Output = Result<<S as Service<R>>::Response, <S as Service<R>>::Error>>
+            + Send
+            + 'static,
+    >,
+>;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Abort {
+    Http(StatusCode),
+    Grpc(u32),
+}
+
+impl Abort {
+    // Envoy only accepts status codes in the [200, 600) range.
+    fn http(status: u16) -> Option<Self> {
+        if (200..600).contains(&status) {
+            StatusCode::from_u16(status).ok().map(Abort::Http)
+        } else {
+            None
+        }
+    }
+
+    fn response<B: Default>(self) -> Response<B> {
+        let mut res = Response::new(B::default());
+        match self {
+            Abort::Http(status) => *res.status_mut() = status,
+            Abort::Grpc(code) => {
+                let headers = res.headers_mut();
+                headers.insert("content-type", HeaderValue::from_static("application/grpc"));
+                headers.insert("grpc-status", HeaderValue::from(code));
+            }
+        }
+        res
+    }
+}
+
+fn header<T: std::str::FromStr>(headers: &HeaderMap, name: &str) -> Option<T> {
+    headers.get(name)?.to_str().ok()?.trim().parse().ok()
+}
+
+#[cfg(feature = "serde")]
+mod de {
+    use serde::{de::Error, Deserialize, Deserializer};
+    use std::time::Duration;
+
+    /// Deserialize a protobuf JSON duration, such as `"1.5s"`.
+    pub(super) fn duration<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Duration, D::Error> {
+        let value = String::deserialize(deserializer)?;
+        value
+            .strip_suffix('s')
+            .and_then(|secs| secs.parse::<f64>().ok())
+            .and_then(|secs| Duration::try_from_secs_f64(secs).ok())
+            .ok_or_else(|| D::Error::custom(format!("invalid duration: {}", value)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use http::Request;
+
+    #[derive(Clone)]
+    struct OkService;
+
+    impl Service<Request<()>> for OkService {
+        type Response = Response<String>;
+        type Error = String;
+        type Future = std::future::Ready<Result<Self::Response, Self::Error>>;
+
+        fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+            Poll::Ready(Ok(()))
+        }
+
+        fn call(&mut self, _req: Request<()>) -> Self::Future {
+            std::future::ready(Ok(Response::new("ok".to_string())))
+        }
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn envoy_fixed_delay_and_abort() {
+        let mut service = EnvoyFaultLayer::new()
+            .fixed_delay(Duration::from_secs(1), FractionalPercent::percent(100))
+            .abort(
+                StatusCode::SERVICE_UNAVAILABLE,
+                FractionalPercent::percent(100),
+            )
+            .layer(OkService);
+
+        let start = time::Instant::now();
+        let res = service.call(Request::new(())).await.unwrap();
+        assert_eq!(res.status(), StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(start.elapsed(), Duration::from_secs(1));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn envoy_veto() {
+        let mut service = EnvoyFaultLayer::new()
+            .fixed_delay(Duration::from_secs(1), FractionalPercent::percent(100))
+            .abort(
+                StatusCode::SERVICE_UNAVAILABLE,
+                FractionalPercent::percent(100),
+            )
+            .with_veto(|req: &Request<()>| req.uri().path() == "/health")
+            .layer(OkService);
+
+        let start = time::Instant::now();
+        let req = Request::builder().uri("/health").body(()).unwrap();
+        assert_eq!(service.call(req).await.unwrap().body(), "ok");
+        assert_eq!(start.elapsed(), Duration::ZERO);
+
+        let res = service.call(Request::new(())).await.unwrap();
+        assert_eq!(res.status(), StatusCode::SERVICE_UNAVAILABLE);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn envoy_header_faults() {
+        let mut service = EnvoyFaultLayer::new()
+            .header_delay(FractionalPercent::percent(100))
+            .header_abort(FractionalPercent::percent(100))
+            .layer(OkService);
+
+        // No headers, no faults.
+        let res = service.call(Request::new(())).await.unwrap();
+        assert_eq!(res.body(), "ok");
+
+        let req = Request::builder()
+            .header(DELAY_REQUEST, "200")
+            .header(ABORT_GRPC_REQUEST, "14")
+            .body(())
+            .unwrap();
+        let start = time::Instant::now();
+        let res = service.call(req).await.unwrap();
+        assert_eq!(res.status(), StatusCode::OK);
+        assert_eq!(res.headers()["grpc-status"], "14");
+        assert_eq!(start.elapsed(), Duration::from_millis(200));
+
+        // The percentage header lowers the configured percentage.
+        let req = Request::builder()
+            .header(ABORT_REQUEST, "503")
+            .header(ABORT_REQUEST_PERCENTAGE, "0")
+            .body(())
+            .unwrap();
+        let res = service.call(req).await.unwrap();
+        assert_eq!(res.body(), "ok");
+    }
+
+    #[test]
+    fn envoy_percentage_capped() {
+        let layer = EnvoyFaultLayer::new().header_abort(FractionalPercent::percent(0));
+        let req = Request::builder()
+            .header(ABORT_REQUEST, "503")
+            .header(ABORT_REQUEST_PERCENTAGE, "100")
+            .body(())
+            .unwrap();
+        assert_eq!(layer.decide_abort(&req), None);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn envoy_deserialize() {
+        let layer: EnvoyFaultLayer = serde_json::from_str(
+            r#"{
+                "delay": {
+                    "fixed_delay": "1.5s",
+                    "percentage": { "numerator": 10, "denominator": "TEN_THOUSAND" }
+                },
+                "abort": { "header_abort": {}, "percentage": { "numerator": 50 } }
+            }"#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            layer,
+            EnvoyFaultLayer::new()
+                .fixed_delay(
+                    Duration::from_millis(1500),
+                    FractionalPercent::new(10, Denominator::TenThousand)
+                )
+                .header_abort(FractionalPercent::percent(50))
+        );
+    }
+}
@@ -0,0 +1,111 @@
+use crate::decider::KeyExtractor;
+use http::{header::CONTENT_TYPE, HeaderName, Request};
+
+/// Uses the path of the request as the key of a
+/// [`KeyExtractor`] decider.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct PathKey;
+
+impl<B> KeyExtractor<Request<B>> for PathKey {
+    type Key = String;
+
+    fn extract(&self, req: &Request<B>) -> Option<String> {
+        Some(req.uri().path().to_string())
+    }
+}
+
+/// Uses the method of the request as the key of a
+/// [`KeyExtractor`] decider.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct MethodKey;
+
+impl<B> KeyExtractor<Request<B>> for MethodKey {
+    type Key = String;
+
+    fn extract(&self, req: &Request<B>) -> Option<String> {
+        Some(req.method().as_str().to_string())
+    }
+}
+
+/// Uses the value of a header as the key of a [`KeyExtractor`] decider,
+/// such as a request identifier or a tenant header.
+///
+/// Requests without the header have no key. If the header is repeated, the
+/// first value is used.
+#[derive(Clone, Debug)]
+pub struct HeaderKey {
+    name: HeaderName,
+}
+
+impl HeaderKey {
+    /// Create a new `HeaderKey` for the given header name.
+    pub fn new(name: HeaderName) -> Self {
+        Self { name }
+    }
+
+    /// Uses the `x-request-id` header.
+    pub fn request_id() -> Self {
+        Self::new(HeaderName::from_static("x-request-id"))
+    }
+}
+
+impl<B> KeyExtractor<Request<B>> for HeaderKey {
+    type Key = Vec<u8>;
+
+    fn extract(&self, req: &Request<B>) -> Option<Vec<u8>> {
+        req.headers()
+            .get(&self.name)
+            .map(|value| value.as_bytes().to_vec())
+    }
+}
+
+/// Uses the gRPC method name of the request, such as `SayHello`, as the key
+/// of a [`KeyExtractor`] decider.
+///
+/// The method name is read from the `/package.Service/Method` path of gRPC
+/// requests, such as the requests of `tonic` services. Requests without an
+/// `application/grpc` content type have no key.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct GrpcMethodKey;
+
+impl<B> KeyExtractor<Request<B>> for GrpcMethodKey {
+    type Key = String;
+
+    fn extract(&self, req: &Request<B>) -> Option<String> {
+        let content_type = req.headers().get(CONTENT_TYPE)?.as_bytes();
+        if !content_type.starts_with(b"application/grpc") {
+            return None;
+        }
+        let (service, method) = req.uri().path().strip_prefix('/')?.split_once('/')?;
+        if service.is_empty() || method.is_empty() || method.contains('/') {
+            return None;
+        }
+        Some(method.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn key_extractors() {
+        let req = Request::post("/helloworld.Greeter/SayHello")
+            .header("content-type", "application/grpc")
+            .header("x-request-id", "abc")
+            .body(())
+            .unwrap();
+
+        assert_eq!(
+            PathKey.extract(&req).as_deref(),
+            Some("/helloworld.Greeter/SayHello")
+        );
+        assert_eq!(MethodKey.extract(&req).as_deref(), Some("POST"));
+        assert_eq!(HeaderKey::request_id().extract(&req), Some(b"abc".to_vec()));
+        assert_eq!(GrpcMethodKey.extract(&req).as_deref(), Some("SayHello"));
+
+        let req = Request::get("/users/42").body(()).unwrap();
+        assert_eq!(HeaderKey::request_id().extract(&req), None);
+        assert_eq!(GrpcMethodKey.extract(&req), None);
+    }
+}
@@ -0,0 +1,118 @@
+use crate::generator::Generator;
+use http::{
+    header::{HeaderName, CONTENT_LENGTH},
+    HeaderValue, Response,
+};
+
+/// Body of the malformed responses.
+const BODY: &str = "malformed response injected by tower-fault";
+
+/// Header carrying invalid bytes.
+const INVALID_HEADER: &str = "x-fault-injection";
+
+/// Response generator producing protocol-level anomalies.
+///
+/// Unlike body mutations, these responses break the HTTP framing or headers
+/// themselves, to test the parser and edge-case handling of clients. They
+/// rely on the server writing the headers as-is, which is the case for
+/// `hyper`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct MalformedResponse {
+    anomaly: Anomaly,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Anomaly {
+    ContentLength,
+    InvalidHeader,
+}
+
+impl MalformedResponse {
+    /// Response declaring a `content-length` larger than its body.
+    ///
+    /// The connection is closed after the body, so clients see a truncated
+    /// response.
+    pub fn content_length_mismatch() -> Self {
+        Self {
+            anomaly: Anomaly::ContentLength,
+        }
+    }
+
+    /// Response with an `x-fault-injection` header containing bytes that are
+    /// neither visible ASCII nor valid UTF-8.
+    pub fn invalid_header() -> Self {
+        Self {
+            anomaly: Anomaly::InvalidHeader,
+        }
+    }
+}
+
+impl<R, B> Generator<R, Response<B>> for MalformedResponse
+where
+    B: From<String>,
+{
+    fn generate(&self, _req: &R) -> Response<B> {
+        let mut res = Response::new(B::from(BODY.to_string()));
+        match self.anomaly {
+            Anomaly::ContentLength => {
+                res.headers_mut()
+                    .insert(CONTENT_LENGTH, HeaderValue::from(BODY.len() * 2));
+            }
+            Anomaly::InvalidHeader => {
+                if let Ok(value) = HeaderValue::from_bytes(b"\xff\xfe\x80fault") {
+                    res.headers_mut()
+                        .insert(HeaderName::from_static(INVALID_HEADER), value);
+                }
+            }
+        }
+        res
+    }
+}
+
+/// Response generator producing `hyper` responses whose chunked body is
+/// truncated.
+///
+/// The body sends a first chunk, then aborts: the server closes the
+/// connection without sending the terminating chunk.
+#[cfg(feature = "hyper")]
+#[cfg_attr(docsrs, doc(cfg(feature = "hyper")))]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct TruncatedChunked;
+
+#[cfg(feature = "hyper")]
+impl<R> Generator<R, Response<hyper::Body>> for TruncatedChunked {
+    fn generate(&self, _req: &R) -> Response<hyper::Body> {
+        let (mut sender, body) = hyper::Body::channel();
+        // The channel always has room for one chunk, and the abort uses a
+        // separate slot, so both are delivered in order.
+        let _ = sender.try_send_data(hyper::body::Bytes::from_static(BODY.as_bytes()));
+        sender.abort();
+        Response::new(body)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn malformed_response() {
+        let res: Response<String> = MalformedResponse::content_length_mismatch().generate(&());
+        assert_eq!(res.headers()[CONTENT_LENGTH], (BODY.len() * 2).to_string());
+        assert_eq!(res.body(), BODY);
+
+        let res: Response<String> = MalformedResponse::invalid_header().generate(&());
+        assert!(res.headers()[INVALID_HEADER].to_str().is_err());
+    }
+
+    #[cfg(feature = "hyper")]
+    #[tokio::test]
+    async fn truncated_chunked() {
+        use hyper::body::HttpBody;
+
+        let res: Response<hyper::Body> = TruncatedChunked.generate(&());
+        let mut body = res.into_body();
+        assert_eq!(body.data().await.unwrap().unwrap(), BODY.as_bytes());
+        assert!(body.data().await.unwrap().is_err());
+    }
+}
@@ -0,0 +1,369 @@
+//! # HTTP integration
+//!
+//! This module contains helpers for services that work with
+//! [`http::Request`]s, such as `hyper`, `axum`, or `lambda_http` stacks.
+//!
+//! ## Directives
+//!
+//! Middlewares that run before the fault layers (authentication, routing,
+//! ...) can insert a [`FaultDirective`] into the request extensions. The
+//! [`DirectiveDecider`] then uses that directive to decide if a fault should
+//! be injected, without having to parse the request again.
+//!
+//! ```rust
+//! use http::Request;
+//! use tower_fault::{
+//!     decider::Decider,
+//!     http::{DirectiveDecider, FaultDirective},
+//! };
+//!
+//! let decider = DirectiveDecider::new(false);
+//!
+//! let mut req = Request::new(());
+//! assert_eq!(false, decider.decide(&req));
+//!
+//! req.extensions_mut().insert(FaultDirective::Inject);
+//! assert_eq!(true, decider.decide(&req));
+//! ```
+//!
+//! ## Baggage
+//!
+//! The [`BaggageDecider`] injects faults for requests that carry a chaos
+//! flag in their W3C `baggage` header. As the baggage is propagated across
+//! services, this allows end-to-end fault experiments on a single request.
+//!
+//! ```rust
+//! use http::Request;
+//! use tower_fault::{decider::Decider, http::BaggageDecider};
+//!
+//! let decider = BaggageDecider::new("chaos", "error");
+//!
+//! let req = Request::builder()
+//!     .header("baggage", "userId=alice,chaos=error")
+//!     .body(())
+//!     .unwrap();
+//! assert_eq!(true, decider.decide(&req));
+//! ```
+//!
+//! ## Routes
+//!
+//! [`RouteFaults`] maps path patterns to independent fault settings, so that
+//! a single layer can apply different faults to each route of a router.
+//!
+//! ```rust
+//! use tower_fault::{http::RouteFaults, latency::LatencyLayer};
+//!
+//! let decider = RouteFaults::new()
+//!     .route("/users/:id", 0.1)
+//!     .route("/admin/**", 0.0)
+//!     .fallback(0.01);
+//! let distribution = RouteFaults::new()
+//!     .route("/users/:id", 200..500)
+//!     .fallback(50..100);
+//!
+//! let latency_layer = LatencyLayer::new(decider, distribution);
+//! ```
+//!
+//! ## Fault priorities
+//!
+//! When several fault layers are stacked, a [`FaultArbiter`] placed before
+//! them ensures that at most one fault fires per request, in a configurable
+//! priority order. It can also correlate faults, so that a fault fires
+//! with a conditional probability when another one does.
+//!
+//! ```rust
+//! # #[cfg(all(feature = "error", feature = "latency"))]
+//! # {
+//! use http::Request;
+//! use tower::ServiceBuilder;
+//! use tower_fault::{error::ErrorLayer, http::FaultArbiter, latency::LatencyLayer};
+//! # type Body = ();
+//!
+//! // Errors take precedence over latency.
+//! let arbiter = FaultArbiter::<Body>::new()
+//!     .fault("error", 0.01)
+//!     .fault("latency", 0.1);
+//!
+//! let latency_layer = LatencyLayer::new(arbiter.decider("latency"), 200..500);
+//! let error_layer =
+//!     ErrorLayer::new(arbiter.decider("error"), |_: &Request<Body>| String::from("error"));
+//! let builder = ServiceBuilder::new()
+//!     .layer(arbiter)
+//!     .layer(latency_layer)
+//!     .layer(error_layer);
+//! # }
+//! ```
+//!
+//! ## Request keys
+//!
+//! [`PathKey`], [`MethodKey`], [`HeaderKey`] and [`GrpcMethodKey`] extract
+//! the key of a request for the deciders that make stable decisions per key,
+//! such as [`Consistent`](crate::decider::Consistent) and
+//! [`Arms`](crate::decider::Arms). `lambda_http` requests are HTTP requests,
+//! so their headers can be used with [`HeaderKey`] as well.
+//!
+//! ```rust
+//! use tower_fault::{decider::Consistent, http::HeaderKey};
+//!
+//! // Fault 5% of the requests by request identifier, on all the replicas.
+//! let decider = Consistent::new(0.05, HeaderKey::request_id()).with_seed(2024);
+//! ```
+//!
+//! ## Protected requests
+//!
+//! [`NeverFault`] lists the paths, methods and headers of requests that must
+//! never be faulted, such as payment captures or health checks. It can be
+//! attached to any layer with `with_veto()`.
+//!
+//! ```rust
+//! use http::{Method, Request};
+//! use tower_fault::{http::NeverFault, latency::LatencyLayer};
+//!
+//! let never_fault = NeverFault::new()
+//!     .path("/payments/**")
+//!     .path("/health")
+//!     .method(Method::DELETE)
+//!     .header_value("x-admin", "true");
+//!
+//! let latency_layer = LatencyLayer::new(0.1, 200..500).with_veto(never_fault);
+//! ```
+//!
+//! ## Responses
+//!
+//! The [`ResponseLayer`] returns a generated response instead of calling the
+//! service, such as a `503 Service Unavailable` response. With the
+//! `tower-http` feature, the [`classify`] module contains generators that
+//! produce responses classified as failures by `tower_http::classify`, so
+//! that classification middlewares count injected faults correctly.
+//!
+//! ```rust
+//! use http::{Response, StatusCode};
+//! use tower_fault::http::ResponseLayer;
+//! # struct MyRequest;
+//!
+//! // Return a 503 response 10% of the time.
+//! let response_layer = ResponseLayer::new(0.1, |_: &MyRequest| {
+//!     let mut res = Response::new(String::new());
+//!     *res.status_mut() = StatusCode::SERVICE_UNAVAILABLE;
+//!     res
+//! });
+//! ```
+//!
+//! ## Authentication failures
+//!
+//! [`AuthFailure`] generates realistic `401 Unauthorized` and `403
+//! Forbidden` responses, and the [`AuthDecider`] targets requests based on
+//! their `Authorization` header, to test token-refresh loops and
+//! re-authentication storms.
+//!
+//! ```rust
+//! use http::Request;
+//! use tower_fault::http::{AuthDecider, AuthFailure, ResponseLayer};
+//!
+//! // Expire 10% of the test tokens.
+//! let response_layer = ResponseLayer::new(
+//!     AuthDecider::bearer(|token: &str| token.starts_with("test-")).with_decider(0.1),
+//!     AuthFailure::expired_token(),
+//! );
+//! ```
+//!
+//! ## Response mutations
+//!
+//! The [`MutateLayer`] calls the service and corrupts some of its responses
+//! with a [`Mutation`]. [`TextMutation`] contains strategies for text bodies,
+//! such as inserting invalid UTF-8 or a byte order mark, to catch lossy
+//! decoding assumptions.
+//!
+//! ```rust
+//! use tower_fault::http::{MutateLayer, TextMutation};
+//!
+//! let mutate_layer = MutateLayer::new(0.01, TextMutation::InvalidUtf8);
+//! ```
+//!
+//! With the `serde_json` feature, [`JsonMutation`] parses JSON bodies and
+//! corrupts a single field, such as dropping it or changing the sign of a
+//! number, which finds more bugs than random byte flips.
+//!
+//! ```rust
+//! # #[cfg(feature = "serde_json")]
+//! # {
+//! use tower_fault::http::{JsonMutation, MutateLayer};
+//!
+//! let mutate_layer = MutateLayer::new(0.01, JsonMutation::NullField);
+//! # }
+//! ```
+//!
+//! With the `prost` feature, [`ProtobufMutation`] decodes protobuf messages,
+//! clears or alters their fields by tag number, and re-encodes them,
+//! including in gRPC frames, without the generated code.
+//!
+//! ```rust
+//! # #[cfg(feature = "prost")]
+//! # {
+//! use tower_fault::http::{MutateLayer, ProtobufMutation};
+//!
+//! // Clear the field 2 and set the field 5 to 0 in gRPC responses.
+//! let mutation = ProtobufMutation::new().clear(2).set_varint(5, 0).grpc(true);
+//! let mutate_layer = MutateLayer::new(0.01, mutation);
+//! # }
+//! ```
+//!
+//! ## Redirects
+//!
+//! [`Redirect`] responds with `3xx` redirects, to a fixed location or
+//! forming loops of a configurable length, to test how clients handle
+//! redirect limits. The [`RedirectLoop`] decider keeps faulting the requests
+//! already in a loop.
+//!
+//! ```rust
+//! use tower_fault::http::{Redirect, RedirectLoop, ResponseLayer};
+//!
+//! // Send 1% of the requests into a loop of 5 redirects.
+//! let response_layer = ResponseLayer::new(RedirectLoop::new(0.01), Redirect::looping(5));
+//! ```
+//!
+//! ## Rate limiting
+//!
+//! With the `latency` feature, [`TooManyRequests`] generates `429 Too Many
+//! Requests` responses with a fixed or sampled `Retry-After` header, and the
+//! [`ThrottlingEpisode`] decider throttles all the requests for a while once
+//! an episode starts, to test that clients back off.
+//!
+//! ```rust
+//! # #[cfg(feature = "latency")]
+//! # {
+//! use http::Request;
+//! use std::time::Duration;
+//! use tower_fault::http::{ResponseLayer, ThrottlingEpisode, TooManyRequests};
+//!
+//! // Start a 30-second throttling episode for 0.1% of the requests, and ask
+//! // clients to retry after 1 to 5 seconds.
+//! let response_layer = ResponseLayer::new(
+//!     ThrottlingEpisode::new(0.001, Duration::from_secs(30)),
+//!     TooManyRequests::new(1_000..5_000),
+//! );
+//! # }
+//! ```
+//!
+//! ## Malformed responses
+//!
+//! [`MalformedResponse`] generates protocol-level anomalies, such as a
+//! `content-length` that doesn't match the body or headers with invalid
+//! bytes. With the `hyper` feature, [`TruncatedChunked`] generates chunked
+//! responses that end before the terminating chunk.
+//!
+//! ```rust
+//! use tower_fault::http::{MalformedResponse, ResponseLayer};
+//!
+//! let response_layer = ResponseLayer::new(0.01, MalformedResponse::content_length_mismatch());
+//! ```
+//!
+//! ## Oversized responses
+//!
+//! With the `hyper` feature, [`OversizedResponse`] replaces the body with a
+//! streamed payload of a configurable size, to test client-side body limits
+//! and memory safeguards.
+//!
+//! ```rust
+//! # #[cfg(feature = "hyper")]
+//! # {
+//! use tower_fault::http::{OversizedResponse, ResponseLayer};
+//!
+//! // Respond with 4 GiB of data.
+//! let response_layer = ResponseLayer::new(0.01, OversizedResponse::new(4 << 30));
+//! # }
+//! ```
+//!
+//! ## Envoy
+//!
+//! With the `latency` feature, the [`EnvoyFaultLayer`] implements the
+//! semantics of Envoy's HTTP fault filter, including the
+//! `x-envoy-fault-delay-request` and `x-envoy-fault-abort-request` headers.
+//! Services behind Envoy and services using this layer then react the same
+//! way to the same chaos tests.
+//!
+//! ```rust
+//! # #[cfg(feature = "latency")]
+//! # {
+//! use http::StatusCode;
+//! use std::time::Duration;
+//! use tower_fault::http::{EnvoyFaultLayer, FractionalPercent};
+//!
+//! let envoy_layer = EnvoyFaultLayer::new()
+//!     .fixed_delay(Duration::from_secs(1), FractionalPercent::percent(10))
+//!     .abort(StatusCode::SERVICE_UNAVAILABLE, FractionalPercent::percent(5));
+//! # }
+//! ```
+
+use http::Request;
+use std::net::SocketAddr;
+
+mod arbiter;
+mod auth;
+mod baggage;
+mod directive;
+#[cfg(feature = "latency")]
+mod envoy;
+mod key;
+mod malformed;
+mod mutate;
+mod never;
+#[cfg(feature = "hyper")]
+mod oversized;
+mod redirect;
+mod response;
+mod routes;
+#[cfg(feature = "latency")]
+mod throttle;
+pub use arbiter::{ArbiterService, Arbitrated, FaultArbiter};
+pub use auth::{AuthDecider, AuthFailure};
+pub use baggage::BaggageDecider;
+pub use directive::{DirectiveDecider, FaultDirective};
+#[cfg(feature = "latency")]
+#[cfg_attr(docsrs, doc(cfg(feature = "latency")))]
+pub use envoy::{
+    AbortSource, DelaySource, Denominator, EnvoyFaultLayer, EnvoyFaultService, FaultAbort,
+    FaultDelay, FractionalPercent, ABORT_GRPC_REQUEST, ABORT_REQUEST, ABORT_REQUEST_PERCENTAGE,
+    DELAY_REQUEST, DELAY_REQUEST_PERCENTAGE,
+};
+pub use key::{GrpcMethodKey, HeaderKey, MethodKey, PathKey};
+pub use malformed::MalformedResponse;
+#[cfg(feature = "hyper")]
+#[cfg_attr(docsrs, doc(cfg(feature = "hyper")))]
+pub use malformed::TruncatedChunked;
+#[cfg(feature = "serde_json")]
+#[cfg_attr(docsrs, doc(cfg(feature = "serde_json")))]
+pub use mutate::JsonMutation;
+#[cfg(feature = "prost")]
+#[cfg_attr(docsrs, doc(cfg(feature = "prost")))]
+pub use mutate::ProtobufMutation;
+pub use mutate::{MutateFuture, MutateLayer, MutateService, Mutation, TextMutation};
+pub use never::NeverFault;
+#[cfg(feature = "hyper")]
+#[cfg_attr(docsrs, doc(cfg(feature = "hyper")))]
+pub use oversized::{OversizedBody, OversizedResponse};
+pub use redirect::{Redirect, RedirectLoop};
+pub use response::{ResponseFuture, ResponseLayer, ResponseService};
+pub use routes::RouteFaults;
+#[cfg(feature = "latency")]
+#[cfg_attr(docsrs, doc(cfg(feature = "latency")))]
+pub use throttle::{ThrottlingEpisode, TooManyRequests};
+
+#[cfg(feature = "tower-http")]
+#[cfg_attr(docsrs, doc(cfg(feature = "tower-http")))]
+pub mod classify;
+
+/// Returns the peer address stored in the request extensions, if any.
+///
+/// This can be used as the extractor for a
+/// [`PeerDecider`](crate::decider::PeerDecider).
+///
+/// ```rust
+/// use tower_fault::{decider::PeerDecider, http::peer_addr};
+/// # type Body = ();
+///
+/// let decider = PeerDecider::new(0.1, peer_addr::<Body>);
+/// ```
+pub fn peer_addr<B>(req: &Request<B>) -> Option<SocketAddr> {
+    req.extensions().get::<SocketAddr>().copied()
+}
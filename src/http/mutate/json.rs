@@ -0,0 +1,173 @@
+use super::Mutation;
+use http::Response;
+use rand::{seq::SliceRandom, Rng};
+use serde_json::{Number, Value};
+
+/// Mutation strategies corrupting a single field of JSON bodies.
+///
+/// Random byte flips mostly produce parse errors. These strategies parse
+/// the body and apply a targeted corruption to a random field anywhere in
+/// the document, so the result is still valid JSON, but breaks the
+/// assumptions of the client about its content.
+///
+/// Bodies that are not valid JSON, or that have no field matching the
+/// strategy, are returned unchanged.
+///
+/// This is implemented for [`Value`]s, byte vectors, and for responses whose
+/// body converts to and from a byte vector, such as `Bytes`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum JsonMutation {
+    /// Remove a field from an object.
+    DropField,
+    /// Replace the value of a field with `null`.
+    NullField,
+    /// Change the sign of a number.
+    NegateNumber,
+    /// Swap two elements of an array.
+    SwapElements,
+}
+
+impl JsonMutation {
+    fn matches(&self, value: &Value, in_object: bool) -> bool {
+        match self {
+            Self::DropField => in_object,
+            Self::NullField => in_object && !value.is_null(),
+            Self::NegateNumber => value.as_f64().is_some_and(|n| n != 0.0),
+            Self::SwapElements => value.as_array().is_some_and(|a| a.len() >= 2),
+        }
+    }
+
+    /// Collect the JSON pointers of the values matching the strategy.
+    fn collect(&self, value: &Value, pointer: String, in_object: bool, out: &mut Vec<String>) {
+        if self.matches(value, in_object) {
+            out.push(pointer.clone());
+        }
+        match value {
+            Value::Object(map) => {
+                for (key, value) in map {
+                    let key = key.replace('~', "~0").replace('/', "~1");
+                    self.collect(value, format!("{}/{}", pointer, key), true, out);
+                }
+            }
+            Value::Array(values) => {
+                for (index, value) in values.iter().enumerate() {
+                    self.collect(value, format!("{}/{}", pointer, index), false, out);
+                }
+            }
+            _ => (),
+        }
+    }
+}
+
+impl Mutation<Value> for JsonMutation {
+    fn mutate(&self, mut value: Value) -> Value {
+        let mut pointers = Vec::new();
+        self.collect(&value, String::new(), false, &mut pointers);
+        let mut rng = crate::seed::rng();
+        let pointer = match pointers.choose(&mut rng) {
+            Some(pointer) => pointer,
+            None => return value,
+        };
+
+        if let Self::DropField = self {
+            // Members of objects always have a parent.
+            if let Some((parent, key)) = pointer.rsplit_once('/') {
+                let key = key.replace("~1", "/").replace("~0", "~");
+                if let Some(Value::Object(map)) = value.pointer_mut(parent) {
+                    map.remove(&key);
+                }
+            }
+            return value;
+        }
+
+        if let Some(target) = value.pointer_mut(pointer) {
+            match self {
+                Self::NullField => *target = Value::Null,
+                Self::NegateNumber => *target = negate(target),
+                Self::SwapElements => {
+                    if let Value::Array(values) = target {
+                        let a = rng.gen_range(0..values.len());
+                        let b = (a + rng.gen_range(1..values.len())) % values.len();
+                        values.swap(a, b);
+                    }
+                }
+                Self::DropField => (),
+            }
+        }
+        value
+    }
+}
+
+impl Mutation<Vec<u8>> for JsonMutation {
+    fn mutate(&self, value: Vec<u8>) -> Vec<u8> {
+        match serde_json::from_slice::<Value>(&value) {
+            Ok(json) => serde_json::to_vec(&self.mutate(json)).unwrap_or(value),
+            Err(_) => value,
+        }
+    }
+}
+
+impl<B> Mutation<Response<B>> for JsonMutation
+where
+    B: From<Vec<u8>>,
+    Vec<u8>: From<B>,
+{
+    fn mutate(&self, value: Response<B>) -> Response<B> {
+        super::mutate_body(self, value)
+    }
+}
+
+/// Returns the number with the opposite sign.
+fn negate(value: &Value) -> Value {
+    let number = if let Some(n) = value.as_i64() {
+        n.checked_neg().map(Number::from)
+    } else {
+        value.as_f64().and_then(|n| Number::from_f64(-n))
+    };
+    number.map(Value::Number).unwrap_or_else(|| value.clone())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use http::header::CONTENT_LENGTH;
+    use serde_json::json;
+
+    #[test]
+    fn json_mutations() {
+        let value = json!({ "id": 42, "name": "a/b", "tags": [1, 2] });
+
+        let mutated = JsonMutation::DropField.mutate(json!({ "user": { "a/b": 1 } }));
+        assert!(mutated == json!({}) || mutated == json!({ "user": {} }));
+
+        let mutated = JsonMutation::NullField.mutate(json!({ "id": 42, "name": null }));
+        assert_eq!(mutated, json!({ "id": null, "name": null }));
+
+        let mutated = JsonMutation::NegateNumber.mutate(json!({ "id": 42, "price": 1.5 }));
+        assert!(
+            mutated == json!({ "id": -42, "price": 1.5 })
+                || mutated == json!({ "id": 42, "price": -1.5 })
+        );
+
+        let mutated = JsonMutation::SwapElements.mutate(value.clone());
+        assert_eq!(mutated["tags"], json!([2, 1]));
+
+        // Invalid JSON and values without a matching field are unchanged.
+        let body = b"{ not json".to_vec();
+        assert_eq!(JsonMutation::DropField.mutate(body.clone()), body);
+        assert_eq!(JsonMutation::SwapElements.mutate(json!([1])), json!([1]));
+    }
+
+    #[test]
+    fn json_mutation_content_length() {
+        let body = br#"{"id":42}"#.to_vec();
+        let res = Response::builder()
+            .header(CONTENT_LENGTH, body.len())
+            .body(body)
+            .unwrap();
+        let res = JsonMutation::NullField.mutate(res);
+        assert_eq!(res.body(), br#"{"id":null}"#);
+        assert_eq!(res.headers()[CONTENT_LENGTH], "11");
+    }
+}
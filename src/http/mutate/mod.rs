@@ -0,0 +1,292 @@
+use crate::{
+    decider::{Decider, WithContext},
+    describe::{DescribeDecider, FaultDescription},
+    observe::FaultEvent,
+    options::FaultOptions,
+    validate::ValidateDecider,
+    veto::Vetoed,
+    Error,
+};
+use http::{header::CONTENT_LENGTH, HeaderValue, Response};
+use std::{
+    fmt,
+    future::Future,
+    pin::Pin,
+    task::{Context, Poll},
+};
+use tower::{Layer, Service};
+
+#[cfg(feature = "serde_json")]
+mod json;
+#[cfg(feature = "prost")]
+mod protobuf;
+mod text;
+#[cfg(feature = "serde_json")]
+#[cfg_attr(docsrs, doc(cfg(feature = "serde_json")))]
+pub use json::JsonMutation;
+#[cfg(feature = "prost")]
+#[cfg_attr(docsrs, doc(cfg(feature = "prost")))]
+pub use protobuf::ProtobufMutation;
+pub use text::TextMutation;
+
+/// Trait to corrupt a response returned by the service.
+///
+/// This is implemented for closures, and for the mutation strategies of this
+/// module, such as [`TextMutation`] and, with the `serde_json` and `prost`
+/// features, `JsonMutation` and `ProtobufMutation`.
+pub trait Mutation<T> {
+    /// Mutate the given value.
+    fn mutate(&self, value: T) -> T;
+}
+
+impl<F, T> Mutation<T> for F
+where
+    F: Fn(T) -> T,
+{
+    fn mutate(&self, value: T) -> T {
+        self(value)
+    }
+}
+
+/// Mutate the body of a response as bytes, with the given mutation.
+///
+/// The `Content-Length` header, if any, is updated to the length of the
+/// mutated body, so that clients don't read a truncated body or wait for
+/// missing bytes.
+fn mutate_body<M, B>(mutation: &M, response: Response<B>) -> Response<B>
+where
+    M: Mutation<Vec<u8>>,
+    B: From<Vec<u8>>,
+    Vec<u8>: From<B>,
+{
+    let (mut parts, body) = response.into_parts();
+    let body = mutation.mutate(Vec::from(body));
+    if parts.headers.contains_key(CONTENT_LENGTH) {
+        parts
+            .headers
+            .insert(CONTENT_LENGTH, HeaderValue::from(body.len()));
+    }
+    Response::from_parts(parts, B::from(body))
+}
+
+/// Layer that randomly mutates the responses returned by the service.
+///
+/// Unlike the [`ResponseLayer`](super::ResponseLayer), the service is always
+/// called, and the injected fault is a corruption of its actual response.
+/// The decider receives the request.
+#[derive(Clone, Debug)]
+pub struct MutateLayer<D, M> {
+    decider: D,
+    mutation: M,
+    options: FaultOptions,
+}
+
+impl<D, M> MutateLayer<D, M> {
+    /// Create a new `MutateLayer` with the given decider and mutation.
+    pub fn new(decider: D, mutation: M) -> Self {
+        Self {
+            decider,
+            mutation,
+            options: FaultOptions::default(),
+        }
+    }
+
+    /// Set the given decider to be used to determine if a response should
+    /// be mutated.
+    pub fn with_decider<ND>(self, decider: ND) -> MutateLayer<ND, M> {
+        MutateLayer {
+            decider,
+            mutation: self.mutation,
+            options: self.options,
+        }
+    }
+
+    /// Set the given mutation to corrupt responses.
+    pub fn with_mutation<NM>(self, mutation: NM) -> MutateLayer<D, NM> {
+        MutateLayer {
+            decider: self.decider,
+            mutation,
+            options: self.options,
+        }
+    }
+
+    /// Call the decider with a `(request, context)` tuple, where the context
+    /// is returned by the given extractor.
+    ///
+    /// See [`WithContext`] for more information.
+    pub fn with_context<X>(self, extractor: X) -> MutateLayer<WithContext<D, X>, M> {
+        MutateLayer {
+            decider: WithContext::new(self.decider, extractor),
+            mutation: self.mutation,
+            options: self.options,
+        }
+    }
+
+    /// Never mutate the responses of the requests vetoed by the given veto,
+    /// regardless of the decider.
+    ///
+    /// See the [`veto`](crate::veto) module for more information.
+    pub fn with_veto<V>(self, veto: V) -> MutateLayer<Vetoed<D, V>, M> {
+        MutateLayer {
+            decider: Vetoed::new(self.decider, veto),
+            mutation: self.mutation,
+            options: self.options,
+        }
+    }
+}
+
+impl<D, M> MutateLayer<D, M> {
+    crate::options::impl_fault_options! { "layer", shadow }
+}
+
+impl<D, M> MutateLayer<D, M>
+where
+    D: ValidateDecider,
+{
+    /// Create a new `MutateLayer`, returning an error if the configuration is
+    /// invalid.
+    pub fn try_new(decider: D, mutation: M) -> Result<Self, Error> {
+        Self::new(decider, mutation).build()
+    }
+
+    /// Validate the configuration of the layer.
+    ///
+    /// Returns an [`Error`] if the decider is misconfigured, instead of
+    /// panicking at request time.
+    pub fn build(self) -> Result<Self, Error> {
+        self.decider.validate_decider()?;
+        Ok(self)
+    }
+
+    /// Validate the configuration of the layer, and wrap the given service.
+    pub fn build_service<S>(self, inner: S) -> Result<MutateService<D, M, S>, Error>
+    where
+        D: Clone,
+        M: Clone,
+    {
+        Ok(self.build()?.layer(inner))
+    }
+}
+
+impl<D, M> MutateLayer<D, M>
+where
+    D: DescribeDecider,
+{
+    /// Returns a description of the configured fault.
+    pub fn describe(&self) -> FaultDescription {
+        FaultDescription::new("mutate", self.decider.describe_decider())
+            .with_enabled(self.options.enabled)
+    }
+}
+
+impl<D, M> fmt::Display for MutateLayer<D, M>
+where
+    D: DescribeDecider,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.describe().fmt(f)
+    }
+}
+
+impl<D, M, S> Layer<S> for MutateLayer<D, M>
+where
+    D: Clone,
+    M: Clone,
+{
+    type Service = MutateService<D, M, S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        MutateService {
+            inner,
+            decider: self.decider.clone(),
+            mutation: self.mutation.clone(),
+            options: self.options.clone(),
+        }
+    }
+}
+
+/// Service that randomly mutates the responses of the underlying service.
+#[derive(Clone, Debug)]
+pub struct MutateService<D, M, S> {
+    inner: S,
+    decider: D,
+    mutation: M,
+    options: FaultOptions,
+}
+
+impl<D, M, S> MutateService<D, M, S>
+where
+    D: DescribeDecider,
+{
+    /// Returns a description of the configured fault.
+    pub fn describe(&self) -> FaultDescription {
+        FaultDescription::new("mutate", self.decider.describe_decider())
+            .with_enabled(self.options.enabled)
+    }
+}
+
+impl<D, M, S> fmt::Display for MutateService<D, M, S>
+where
+    D: DescribeDecider,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.describe().fmt(f)
+    }
+}
+
+impl<D, M, S, R> Service<R> for MutateService<D, M, S>
+where
+    D: Decider<R> + Clone,
+    M: Mutation<S::Response> + Clone + Send + 'static,
+    S: Service<R> + Send,
+    S::Future: Send + 'static,
+    S::Response: Send + 'static,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = MutateFuture<S::Response, S::Error>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, request: R) -> Self::Future {
+        if self.options.is_active("mutate")
+            && self.options.decide("mutate", &self.decider, &request)
+        {
+            if self.options.shadow {
+                let fut = self.inner.call(request);
+                return Box::pin(self.options.shadow_call(
+                    FaultEvent::new("mutate"),
+                    fut,
+                    |actual| actual,
+                ));
+            }
+            if self.options.inject(FaultEvent::new("mutate")) {
+                let mutation = self.mutation.clone();
+                let fut = self.inner.call(request);
+                return Box::pin(async move { fut.await.map(|res| mutation.mutate(res)) });
+            }
+        }
+
+        Box::pin(self.inner.call(request))
+    }
+}
+
+/// Future returned by [`MutateService`].
+pub type MutateFuture<T, E> = Pin<Box<dyn Future<Output = Result<T, E>> + Send>>;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::*;
+
+    #[tokio::test]
+    async fn response_mutated() {
+        let layer = MutateLayer::new(true, |res: String| res + " (mutated)");
+        let mut service = layer.layer(DummyService);
+
+        let res = service.call(()).await;
+        assert_eq!(res.unwrap(), String::from("ok (mutated)"));
+    }
+}
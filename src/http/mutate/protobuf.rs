@@ -0,0 +1,250 @@
+use super::Mutation;
+use http::Response;
+use prost::{
+    encoding::{self, DecodeContext, WireType},
+    DecodeError,
+};
+
+/// Mutation clearing or altering the fields of protobuf messages by tag
+/// number.
+///
+/// The message is decoded at the wire-format level with `prost`, so this
+/// works with any message type without generated code. Only the top-level
+/// fields are mutated, and groups can only be cleared. Messages that can't
+/// be decoded, such as truncated messages, are returned unchanged.
+///
+/// With [`ProtobufMutation::grpc`], the body is read as a sequence of
+/// length-prefixed gRPC frames, and each uncompressed message is mutated.
+///
+/// This is implemented for byte vectors, and for responses whose body
+/// converts to and from a byte vector, such as `Bytes`.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ProtobufMutation {
+    fields: Vec<(u32, Action)>,
+    grpc: bool,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum Action {
+    Clear,
+    Varint(u64),
+    Bytes(Vec<u8>),
+}
+
+impl ProtobufMutation {
+    /// Create a new `ProtobufMutation` that doesn't alter any field.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Remove the field with the given tag, so that the client reads its
+    /// default value.
+    pub fn clear(self, tag: u32) -> Self {
+        self.field(tag, Action::Clear)
+    }
+
+    /// Replace the value of the varint field with the given tag, such as an
+    /// integer, a boolean or an enum.
+    pub fn set_varint(self, tag: u32, value: u64) -> Self {
+        self.field(tag, Action::Varint(value))
+    }
+
+    /// Replace the value of the length-delimited field with the given tag,
+    /// such as a string, bytes or a nested message.
+    pub fn set_bytes(self, tag: u32, value: impl Into<Vec<u8>>) -> Self {
+        self.field(tag, Action::Bytes(value.into()))
+    }
+
+    /// Read the body as length-prefixed gRPC frames.
+    pub fn grpc(mut self, grpc: bool) -> Self {
+        self.grpc = grpc;
+        self
+    }
+
+    fn field(mut self, tag: u32, action: Action) -> Self {
+        self.fields.retain(|(t, _)| *t != tag);
+        self.fields.push((tag, action));
+        self
+    }
+
+    fn mutate_message(&self, msg: &[u8]) -> Result<Vec<u8>, DecodeError> {
+        let mut out = Vec::with_capacity(msg.len());
+        let mut buf = msg;
+        while !buf.is_empty() {
+            let field = buf;
+            let (tag, wire_type) = encoding::decode_key(&mut buf)?;
+            encoding::skip_field(wire_type, tag, &mut buf, DecodeContext::default())?;
+            let field = &field[..field.len() - buf.len()];
+
+            let action = self
+                .fields
+                .iter()
+                .find(|(t, _)| *t == tag)
+                .map(|(_, action)| action);
+            match (action, wire_type) {
+                (Some(Action::Clear), _) => (),
+                (Some(Action::Varint(value)), WireType::Varint) => {
+                    encoding::encode_key(tag, wire_type, &mut out);
+                    encoding::encode_varint(*value, &mut out);
+                }
+                (Some(Action::Bytes(value)), WireType::LengthDelimited) => {
+                    encoding::encode_key(tag, wire_type, &mut out);
+                    encoding::encode_varint(value.len() as u64, &mut out);
+                    out.extend_from_slice(value);
+                }
+                _ => out.extend_from_slice(field),
+            }
+        }
+        Ok(out)
+    }
+
+    fn mutate_frames(&self, body: &[u8]) -> Option<Vec<u8>> {
+        let mut out = Vec::with_capacity(body.len());
+        let mut pos = 0;
+        while pos < body.len() {
+            let header = body.get(pos..pos + 5)?;
+            let len = u32::from_be_bytes([header[1], header[2], header[3], header[4]]) as usize;
+            let msg = body.get(pos + 5..pos + 5 + len)?;
+            pos += 5 + len;
+
+            // Compressed messages are forwarded as-is.
+            if header[0] != 0 {
+                out.extend_from_slice(&body[pos - 5 - len..pos]);
+                continue;
+            }
+            let msg = self.mutate_message(msg).ok()?;
+            out.push(0);
+            out.extend_from_slice(&u32::try_from(msg.len()).ok()?.to_be_bytes());
+            out.extend_from_slice(&msg);
+        }
+        Some(out)
+    }
+}
+
+impl Mutation<Vec<u8>> for ProtobufMutation {
+    fn mutate(&self, value: Vec<u8>) -> Vec<u8> {
+        let mutated = if self.grpc {
+            self.mutate_frames(&value)
+        } else {
+            self.mutate_message(&value).ok()
+        };
+        mutated.unwrap_or(value)
+    }
+}
+
+impl<B> Mutation<Response<B>> for ProtobufMutation
+where
+    B: From<Vec<u8>>,
+    Vec<u8>: From<B>,
+{
+    fn mutate(&self, value: Response<B>) -> Response<B> {
+        super::mutate_body(self, value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use http::header::CONTENT_LENGTH;
+
+    // Field 1 = 150 (varint), field 2 = "hi" (string), field 3 = fixed32.
+    const MESSAGE: &[u8] = &[0x08, 0x96, 0x01, 0x12, 0x02, b'h', b'i', 0x1d, 1, 2, 3, 4];
+
+    #[test]
+    fn protobuf_mutation() {
+        let mutation = ProtobufMutation::new().clear(3).set_varint(1, 1);
+        assert_eq!(
+            mutation.mutate(MESSAGE.to_vec()),
+            vec![0x08, 0x01, 0x12, 0x02, b'h', b'i']
+        );
+
+        let mutation = ProtobufMutation::new().set_bytes(2, "hello");
+        let mutated = mutation.mutate(MESSAGE.to_vec());
+        assert_eq!(&mutated[3..10], b"\x12\x05hello");
+
+        // Invalid messages are unchanged.
+        assert_eq!(mutation.mutate(vec![0x12, 0x05]), vec![0x12, 0x05]);
+    }
+
+    #[test]
+    fn protobuf_mutation_groups() {
+        // Field 4 is a group containing field 1 = 1, followed by field 1.
+        let message = vec![0x23, 0x08, 0x01, 0x24, 0x08, 0x02];
+
+        let mutation = ProtobufMutation::new().clear(4);
+        assert_eq!(mutation.mutate(message.clone()), vec![0x08, 0x02]);
+
+        // Fields inside groups are left alone.
+        let mutation = ProtobufMutation::new().set_varint(1, 5);
+        assert_eq!(
+            mutation.mutate(message.clone()),
+            vec![0x23, 0x08, 0x01, 0x24, 0x08, 0x05]
+        );
+        // Groups can't be replaced.
+        let mutation = ProtobufMutation::new().set_varint(4, 5);
+        assert_eq!(mutation.mutate(message.clone()), message);
+    }
+
+    #[test]
+    fn protobuf_mutation_malformed() {
+        let mutation = ProtobufMutation::new().clear(1).set_bytes(2, "hello");
+        for message in [
+            // Truncated varint.
+            vec![0x08, 0x96],
+            // Truncated key.
+            vec![0x08, 0x01, 0x96],
+            // Length beyond the end of the message.
+            vec![0x12, 0x05, b'h', b'i'],
+            // Truncated fixed32 and fixed64.
+            vec![0x1d, 1, 2],
+            vec![0x19, 1, 2, 3, 4],
+            // Invalid wire types.
+            vec![0x0e, 0x01],
+            vec![0x0f, 0x01],
+            // Tag 0.
+            vec![0x00, 0x01],
+            // Group without its end, and unexpected group end.
+            vec![0x23, 0x08, 0x01],
+            vec![0x24, 0x08, 0x01],
+            // Group ended with another tag.
+            vec![0x23, 0x08, 0x01, 0x2c],
+        ] {
+            assert_eq!(mutation.mutate(message.clone()), message);
+        }
+        assert_eq!(mutation.mutate(Vec::new()), Vec::<u8>::new());
+    }
+
+    #[test]
+    fn protobuf_mutation_grpc() {
+        let mut body = vec![0, 0, 0, 0, MESSAGE.len() as u8];
+        body.extend_from_slice(MESSAGE);
+
+        let mutation = ProtobufMutation::new().clear(2).grpc(true);
+        let res = Response::builder()
+            .header(CONTENT_LENGTH, body.len())
+            .body(body.clone())
+            .unwrap();
+        let res = mutation.mutate(res);
+        assert_eq!(res.headers()[CONTENT_LENGTH], "13");
+        assert_eq!(
+            res.into_body(),
+            vec![0, 0, 0, 0, 8, 0x08, 0x96, 0x01, 0x1d, 1, 2, 3, 4]
+        );
+
+        // Compressed frames are forwarded as-is.
+        let mut compressed = vec![1, 0, 0, 0, MESSAGE.len() as u8];
+        compressed.extend_from_slice(MESSAGE);
+        assert_eq!(mutation.mutate(compressed.clone()), compressed);
+
+        // Truncated frames, and frames with malformed messages, leave the
+        // body unchanged.
+        for body in [
+            vec![0, 0, 0],
+            vec![0, 0, 0, 0, 20, 0x08, 0x01],
+            vec![0, 0, 0, 0, 2, 0x08, 0x96],
+            [body.clone(), vec![0, 0, 0, 0, 2, 0x08, 0x96]].concat(),
+        ] {
+            assert_eq!(mutation.mutate(body.clone()), body);
+        }
+    }
+}
@@ -0,0 +1,164 @@
+use super::Mutation;
+use http::Response;
+use rand::Rng;
+
+/// UTF-8 byte order mark.
+const BOM: &[u8] = b"\xef\xbb\xbf";
+
+/// Mutation strategies corrupting the encoding of text bodies.
+///
+/// These keep the content mostly intact, but break assumptions made by
+/// lossy decoders, such as bodies always being valid UTF-8, never starting
+/// with a byte order mark, or being normalized.
+///
+/// This is implemented for byte vectors, and for responses whose body
+/// converts to and from a byte vector, such as `Bytes`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum TextMutation {
+    /// Insert an invalid UTF-8 byte at a random position.
+    InvalidUtf8,
+    /// Prepend a UTF-8 byte order mark.
+    Bom,
+    /// Decompose the accented Latin-1 letters into a base letter followed
+    /// by a combining mark, as in Unicode normalization form D.
+    Decompose,
+}
+
+impl Mutation<Vec<u8>> for TextMutation {
+    fn mutate(&self, mut value: Vec<u8>) -> Vec<u8> {
+        match self {
+            Self::InvalidUtf8 => {
+                let index = crate::seed::rng().gen_range(0..=value.len());
+                value.insert(index, 0xff);
+                value
+            }
+            Self::Bom => {
+                if !value.starts_with(BOM) {
+                    value.splice(0..0, BOM.iter().copied());
+                }
+                value
+            }
+            Self::Decompose => match String::from_utf8(value) {
+                Ok(text) => {
+                    let mut decomposed = String::with_capacity(text.len());
+                    for c in text.chars() {
+                        match decompose(c) {
+                            Some((base, mark)) => {
+                                decomposed.push(base);
+                                decomposed.push(mark);
+                            }
+                            None => decomposed.push(c),
+                        }
+                    }
+                    decomposed.into_bytes()
+                }
+                Err(err) => err.into_bytes(),
+            },
+        }
+    }
+}
+
+impl<B> Mutation<Response<B>> for TextMutation
+where
+    B: From<Vec<u8>>,
+    Vec<u8>: From<B>,
+{
+    fn mutate(&self, value: Response<B>) -> Response<B> {
+        super::mutate_body(self, value)
+    }
+}
+
+/// Returns the canonical decomposition of an accented Latin-1 letter.
+fn decompose(c: char) -> Option<(char, char)> {
+    const GRAVE: char = '\u{300}';
+    const ACUTE: char = '\u{301}';
+    const CIRCUMFLEX: char = '\u{302}';
+    const TILDE: char = '\u{303}';
+    const DIAERESIS: char = '\u{308}';
+    const RING: char = '\u{30a}';
+    const CEDILLA: char = '\u{327}';
+
+    let (base, mark) = match c.to_lowercase().next()? {
+        'à' => ('a', GRAVE),
+        'á' => ('a', ACUTE),
+        'â' => ('a', CIRCUMFLEX),
+        'ã' => ('a', TILDE),
+        'ä' => ('a', DIAERESIS),
+        'å' => ('a', RING),
+        'ç' => ('c', CEDILLA),
+        'è' => ('e', GRAVE),
+        'é' => ('e', ACUTE),
+        'ê' => ('e', CIRCUMFLEX),
+        'ë' => ('e', DIAERESIS),
+        'ì' => ('i', GRAVE),
+        'í' => ('i', ACUTE),
+        'î' => ('i', CIRCUMFLEX),
+        'ï' => ('i', DIAERESIS),
+        'ñ' => ('n', TILDE),
+        'ò' => ('o', GRAVE),
+        'ó' => ('o', ACUTE),
+        'ô' => ('o', CIRCUMFLEX),
+        'õ' => ('o', TILDE),
+        'ö' => ('o', DIAERESIS),
+        'ù' => ('u', GRAVE),
+        'ú' => ('u', ACUTE),
+        'û' => ('u', CIRCUMFLEX),
+        'ü' => ('u', DIAERESIS),
+        'ý' => ('y', ACUTE),
+        'ÿ' => ('y', DIAERESIS),
+        _ => return None,
+    };
+    let base = if c.is_lowercase() {
+        base
+    } else {
+        base.to_ascii_uppercase()
+    };
+    Some((base, mark))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use http::header::CONTENT_LENGTH;
+
+    #[test]
+    fn text_mutations() {
+        let body = "Crème brûlée".as_bytes().to_vec();
+
+        let mutated = TextMutation::InvalidUtf8.mutate(body.clone());
+        assert_eq!(mutated.len(), body.len() + 1);
+        assert!(String::from_utf8(mutated).is_err());
+
+        let mutated = TextMutation::Bom.mutate(body.clone());
+        assert_eq!(&mutated[..3], BOM);
+        assert_eq!(TextMutation::Bom.mutate(mutated.clone()), mutated);
+
+        let mutated = TextMutation::Decompose.mutate(body);
+        assert_eq!(
+            String::from_utf8(mutated).unwrap(),
+            "Cre\u{300}me bru\u{302}le\u{301}e"
+        );
+
+        let res = Response::new(b"\xc9t\xe9".to_vec());
+        let res = TextMutation::Decompose.mutate(res);
+        assert_eq!(res.body(), b"\xc9t\xe9");
+        let res = TextMutation::Decompose.mutate(Response::new("Été".as_bytes().to_vec()));
+        assert_eq!(res.body(), "E\u{301}te\u{301}".as_bytes());
+    }
+
+    #[test]
+    fn text_mutation_content_length() {
+        let res = Response::builder()
+            .header(CONTENT_LENGTH, 5)
+            .body("Été".as_bytes().to_vec())
+            .unwrap();
+        let res = TextMutation::Decompose.mutate(res);
+        assert_eq!(res.headers()[CONTENT_LENGTH], "7");
+        assert_eq!(res.body().len(), 7);
+
+        // Responses without a length, such as chunked ones, are left as-is.
+        let res = TextMutation::Bom.mutate(Response::new(b"ok".to_vec()));
+        assert!(!res.headers().contains_key(CONTENT_LENGTH));
+    }
+}
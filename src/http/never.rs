@@ -0,0 +1,212 @@
+use super::routes::Pattern;
+use crate::veto::Veto;
+use http::{HeaderMap, Method, Request};
+use std::{fmt, sync::Arc};
+
+type HeaderPredicate = Arc<dyn Fn(&HeaderMap) -> bool + Send + Sync>;
+
+/// Deny list of HTTP requests that must never be faulted.
+///
+/// A request is protected if it matches any of the paths, methods or header
+/// predicates of the list. Paths use the same patterns as
+/// [`RouteFaults`](super::RouteFaults).
+///
+/// `NeverFault` implements [`Veto`], so it can be attached to any layer with
+/// `with_veto()`, to a registered fault with
+/// [`FaultHandle::with_veto`](crate::registry::FaultHandle::with_veto), or to
+/// all the faults of a registry with
+/// [`FaultRegistry::add_veto`](crate::registry::FaultRegistry::add_veto).
+/// The registry needs the body type of the requests:
+///
+/// ```rust
+/// use http::Request;
+/// use tower_fault::{http::NeverFault, registry::FaultRegistry};
+/// # type Body = ();
+///
+/// let registry = FaultRegistry::new();
+/// registry.add_veto::<Request<Body>, _>(NeverFault::new().path("/health"));
+/// ```
+#[derive(Clone, Default)]
+pub struct NeverFault {
+    paths: Vec<Pattern>,
+    methods: Vec<Method>,
+    headers: Vec<(String, HeaderPredicate)>,
+}
+
+impl NeverFault {
+    /// Create a new empty `NeverFault` deny list.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Protect the requests whose path matches the given pattern.
+    pub fn path(mut self, pattern: &str) -> Self {
+        self.paths.push(Pattern::new(pattern));
+        self
+    }
+
+    /// Protect the requests with the given method.
+    pub fn method(mut self, method: Method) -> Self {
+        self.methods.push(method);
+        self
+    }
+
+    /// Protect the requests that carry the given header.
+    pub fn header(self, name: &'static str) -> Self {
+        self.header_if(name, move |headers| headers.contains_key(name))
+    }
+
+    /// Protect the requests that carry the given header with the given
+    /// value.
+    pub fn header_value(self, name: &'static str, value: &'static str) -> Self {
+        self.header_if(name, move |headers| {
+            headers.get_all(name).iter().any(|v| v == value)
+        })
+    }
+
+    /// Protect the requests whose headers match the given predicate.
+    ///
+    /// The name is only used to describe the predicate.
+    pub fn header_if<F>(mut self, name: impl Into<String>, predicate: F) -> Self
+    where
+        F: Fn(&HeaderMap) -> bool + Send + Sync + 'static,
+    {
+        self.headers.push((name.into(), Arc::new(predicate)));
+        self
+    }
+
+    /// Returns `true` if the request must never be faulted.
+    pub fn protects<B>(&self, req: &Request<B>) -> bool {
+        let path = req.uri().path();
+        self.paths.iter().any(|pattern| pattern.matches(path))
+            || self.methods.contains(req.method())
+            || self
+                .headers
+                .iter()
+                .any(|(_, predicate)| predicate(req.headers()))
+    }
+}
+
+impl<B> Veto<Request<B>> for NeverFault {
+    fn veto(&self, req: &Request<B>) -> bool {
+        self.protects(req)
+    }
+}
+
+impl fmt::Debug for NeverFault {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("NeverFault")
+            .field(
+                "paths",
+                &self
+                    .paths
+                    .iter()
+                    .map(ToString::to_string)
+                    .collect::<Vec<_>>(),
+            )
+            .field("methods", &self.methods)
+            .field(
+                "headers",
+                &self
+                    .headers
+                    .iter()
+                    .map(|(name, _)| name)
+                    .collect::<Vec<_>>(),
+            )
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        decider::{Decider, Suppression, Verdict},
+        registry::FaultRegistry,
+        veto::Vetoed,
+    };
+
+    fn never_fault() -> NeverFault {
+        NeverFault::new()
+            .path("/payments/**")
+            .path("/health")
+            .method(Method::DELETE)
+            .header_value("x-admin", "true")
+    }
+
+    fn request(method: Method, path: &str) -> Request<()> {
+        Request::builder()
+            .method(method)
+            .uri(path)
+            .body(())
+            .unwrap()
+    }
+
+    #[test]
+    fn never_fault_protected_requests() {
+        let decider = Vetoed::new(true, never_fault());
+
+        for req in [
+            request(Method::POST, "/payments/123/capture"),
+            request(Method::GET, "/health"),
+            request(Method::DELETE, "/users/1"),
+            Request::builder()
+                .uri("/users/1")
+                .header("x-admin", "true")
+                .body(())
+                .unwrap(),
+        ] {
+            for _ in 0..1000 {
+                assert!(!decider.decide(&req), "{} {}", req.method(), req.uri());
+            }
+        }
+
+        assert!(decider.decide(&request(Method::GET, "/users/1")));
+        assert!(decider.decide(&request(Method::GET, "/healthz")));
+    }
+
+    #[test]
+    fn never_fault_registry() {
+        let registry = FaultRegistry::new();
+        let decider = registry.register("errors", 1.0).with_veto(never_fault());
+
+        assert!(!decider.decide(&request(Method::GET, "/payments")));
+        assert!(decider.decide(&request(Method::GET, "/users")));
+    }
+
+    #[test]
+    fn never_fault_registry_veto() {
+        let registry = FaultRegistry::new();
+        let before = registry.register("errors", 1.0);
+        registry.add_veto::<Request<()>, _>(never_fault());
+        let after = registry.register("latency", 1.0);
+
+        for handle in [before, after] {
+            assert!(!handle.decide(&request(Method::GET, "/payments")));
+            assert_eq!(
+                handle.verdict(&request(Method::GET, "/payments")),
+                Verdict::Suppressed(Suppression::Veto)
+            );
+            assert!(handle.decide(&request(Method::GET, "/users")));
+            // Requests of other types aren't vetoed.
+            assert!(handle.decide(&()));
+        }
+    }
+
+    #[cfg(feature = "error")]
+    #[tokio::test]
+    async fn never_fault_layer() {
+        use crate::error::ErrorLayer;
+        use tower::{service_fn, Layer, Service};
+
+        let layer = ErrorLayer::new(1.0, |_: &Request<()>| "error").with_veto(never_fault());
+        let mut service = layer.layer(service_fn(|_: Request<()>| async { Ok("ok") }));
+
+        for _ in 0..1000 {
+            let res = service.call(request(Method::POST, "/payments/1")).await;
+            assert_eq!(res, Ok("ok"));
+        }
+        let res = service.call(request(Method::POST, "/orders/1")).await;
+        assert_eq!(res, Err("error"));
+    }
+}
@@ -0,0 +1,119 @@
+use crate::generator::Generator;
+use http::{HeaderMap, Response};
+use hyper::body::{Bytes, HttpBody, SizeHint};
+use std::{
+    convert::Infallible,
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+/// Chunk of the streamed payload.
+static CHUNK: [u8; 16 * 1024] = [0; 16 * 1024];
+
+/// Response generator replacing the body with a very large payload.
+///
+/// The payload is streamed in chunks and never buffered, so sizes of several
+/// gigabytes are fine for the service, while clients without body limits
+/// run out of memory.
+///
+/// The generated responses use an [`OversizedBody`]. Use
+/// [`OversizedResponse::map_body`] to convert it to the body type of the
+/// service.
+#[derive(Clone, Debug)]
+pub struct OversizedResponse<F = fn(OversizedBody) -> OversizedBody> {
+    size: u64,
+    map: F,
+}
+
+impl OversizedResponse {
+    /// Create a new `OversizedResponse` with a payload of the given size, in
+    /// bytes.
+    pub fn new(size: u64) -> Self {
+        Self {
+            size,
+            map: |body| body,
+        }
+    }
+}
+
+impl<F> OversizedResponse<F> {
+    /// Convert the body into the body type of the service, such as
+    /// `axum::body::boxed`.
+    pub fn map_body<NF>(self, map: NF) -> OversizedResponse<NF> {
+        OversizedResponse {
+            size: self.size,
+            map,
+        }
+    }
+}
+
+impl<R, F, B> Generator<R, Response<B>> for OversizedResponse<F>
+where
+    F: Fn(OversizedBody) -> B,
+{
+    fn generate(&self, _req: &R) -> Response<B> {
+        Response::new((self.map)(OversizedBody::new(self.size)))
+    }
+}
+
+/// Body streaming a payload of zeros of the given size.
+#[derive(Clone, Debug)]
+pub struct OversizedBody {
+    remaining: u64,
+}
+
+impl OversizedBody {
+    /// Create a new `OversizedBody` of the given size, in bytes.
+    pub fn new(size: u64) -> Self {
+        Self { remaining: size }
+    }
+}
+
+impl HttpBody for OversizedBody {
+    type Data = Bytes;
+    type Error = Infallible;
+
+    fn poll_data(
+        self: Pin<&mut Self>,
+        _cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<Bytes, Infallible>>> {
+        let this = self.get_mut();
+        if this.remaining == 0 {
+            return Poll::Ready(None);
+        }
+        let len = this.remaining.min(CHUNK.len() as u64);
+        this.remaining -= len;
+        Poll::Ready(Some(Ok(Bytes::from_static(&CHUNK[..len as usize]))))
+    }
+
+    fn poll_trailers(
+        self: Pin<&mut Self>,
+        _cx: &mut Context<'_>,
+    ) -> Poll<Result<Option<HeaderMap>, Infallible>> {
+        Poll::Ready(Ok(None))
+    }
+
+    fn is_end_stream(&self) -> bool {
+        self.remaining == 0
+    }
+
+    fn size_hint(&self) -> SizeHint {
+        SizeHint::with_exact(self.remaining)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn oversized_response() {
+        let generator = OversizedResponse::new(40_000).map_body(Some);
+        let res: Response<Option<OversizedBody>> = generator.generate(&());
+        let res = res.map(Option::unwrap);
+        assert_eq!(res.body().size_hint().exact(), Some(40_000));
+
+        let body = hyper::body::to_bytes(res.into_body()).await.unwrap();
+        assert_eq!(body.len(), 40_000);
+    }
+}
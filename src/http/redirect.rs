@@ -0,0 +1,199 @@
+use crate::{
+    decider::Decider,
+    describe::{DeciderDescription, DescribeDecider},
+    generator::Generator,
+    validate::ValidateDecider,
+    Error,
+};
+use http::{header::LOCATION, HeaderValue, Request, Response, StatusCode};
+
+/// Query parameter tracking the position of a request in a redirect loop.
+const HOP: &str = "fault-redirect";
+
+/// Response generator producing `3xx` redirects.
+///
+/// The redirect either points to a fixed location, or forms a loop: each
+/// response redirects to the requested path with a `fault-redirect` query
+/// parameter tracking the position in the loop, which wraps around after the
+/// configured number of hops. Clients following redirects never reach the
+/// service, which exercises their redirect-limit handling.
+///
+/// Use a [`RedirectLoop`] decider to keep faulting the requests that are
+/// already in a loop.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Redirect {
+    status: StatusCode,
+    target: Target,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum Target {
+    Fixed(HeaderValue),
+    Loop(u32),
+}
+
+impl Redirect {
+    /// Create a new `Redirect` to the given location.
+    pub fn to(location: HeaderValue) -> Self {
+        Self {
+            status: StatusCode::FOUND,
+            target: Target::Fixed(location),
+        }
+    }
+
+    /// Create a new `Redirect` forming loops of the given length.
+    ///
+    /// A length of 1 redirects the requests to themselves.
+    pub fn looping(length: u32) -> Self {
+        Self {
+            status: StatusCode::FOUND,
+            target: Target::Loop(length.max(1)),
+        }
+    }
+
+    /// Set the status code of the redirects.
+    ///
+    /// Status codes that are not redirections (`3xx`) are replaced by
+    /// `302 Found`. Defaults to `302 Found`.
+    pub fn status(mut self, status: StatusCode) -> Self {
+        self.status = if status.is_redirection() {
+            status
+        } else {
+            StatusCode::FOUND
+        };
+        self
+    }
+
+    fn location<B>(&self, req: &Request<B>) -> Option<HeaderValue> {
+        let length = match &self.target {
+            Target::Fixed(location) => return Some(location.clone()),
+            Target::Loop(length) => *length,
+        };
+
+        let next = (hop(req).unwrap_or(0) + 1) % length;
+        let mut location = req.uri().path().to_string();
+        let params = req
+            .uri()
+            .query()
+            .into_iter()
+            .flat_map(|query| query.split('&'))
+            .filter(|param| !param.is_empty() && param.split('=').next() != Some(HOP));
+        location.push('?');
+        for param in params {
+            location.push_str(param);
+            location.push('&');
+        }
+        location.push_str(&format!("{}={}", HOP, next));
+        HeaderValue::try_from(location).ok()
+    }
+}
+
+impl<B, RB> Generator<Request<B>, Response<RB>> for Redirect
+where
+    RB: Default,
+{
+    fn generate(&self, req: &Request<B>) -> Response<RB> {
+        let mut res = Response::new(RB::default());
+        *res.status_mut() = self.status;
+        if let Some(location) = self.location(req) {
+            res.headers_mut().insert(LOCATION, location);
+        }
+        res
+    }
+}
+
+/// Decider that faults all the requests already in a redirect loop, and
+/// delegates to the inner decider for the other requests.
+///
+/// Without it, a probabilistic decider would let the client escape the loop
+/// after a few hops.
+#[derive(Clone, Debug)]
+pub struct RedirectLoop<D> {
+    inner: D,
+}
+
+impl<D> RedirectLoop<D> {
+    /// Create a new `RedirectLoop` with the decider starting the loops.
+    pub fn new(inner: D) -> Self {
+        Self { inner }
+    }
+}
+
+impl<D, B> Decider<Request<B>> for RedirectLoop<D>
+where
+    D: Decider<Request<B>>,
+{
+    fn decide(&self, req: &Request<B>) -> bool {
+        hop(req).is_some() || self.inner.decide(req)
+    }
+}
+
+impl<D> ValidateDecider for RedirectLoop<D>
+where
+    D: ValidateDecider,
+{
+    fn validate_decider(&self) -> Result<(), Error> {
+        self.inner.validate_decider()
+    }
+}
+
+impl<D> DescribeDecider for RedirectLoop<D>
+where
+    D: DescribeDecider,
+{
+    fn describe_decider(&self) -> DeciderDescription {
+        let inner = self.inner.describe_decider();
+        DeciderDescription::new(format!("{} (redirect loop)", inner.kind), None)
+    }
+}
+
+/// Returns the position of the request in a redirect loop, if any.
+fn hop<B>(req: &Request<B>) -> Option<u32> {
+    req.uri()
+        .query()?
+        .split('&')
+        .filter_map(|param| param.split_once('='))
+        .find(|(key, _)| *key == HOP)
+        .and_then(|(_, value)| value.parse().ok())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn request(uri: &str) -> Request<()> {
+        Request::builder().uri(uri).body(()).unwrap()
+    }
+
+    fn location(redirect: &Redirect, uri: &str) -> String {
+        let res: Response<()> = redirect.generate(&request(uri));
+        res.headers()[LOCATION].to_str().unwrap().to_string()
+    }
+
+    #[test]
+    fn redirect_loop() {
+        let redirect = Redirect::looping(3).status(StatusCode::TEMPORARY_REDIRECT);
+        let res: Response<()> = redirect.generate(&request("/a?x=1"));
+        assert_eq!(res.status(), StatusCode::TEMPORARY_REDIRECT);
+
+        assert_eq!(location(&redirect, "/a?x=1"), "/a?x=1&fault-redirect=1");
+        assert_eq!(
+            location(&redirect, "/a?x=1&fault-redirect=1"),
+            "/a?x=1&fault-redirect=2"
+        );
+        assert_eq!(
+            location(&redirect, "/a?fault-redirect=2&x=1"),
+            "/a?x=1&fault-redirect=0"
+        );
+
+        let redirect = Redirect::to(HeaderValue::from_static("/login"));
+        assert_eq!(location(&redirect, "/a"), "/login");
+    }
+
+    #[test]
+    fn redirect_loop_decider() {
+        let decider = RedirectLoop::new(false);
+        assert!(!decider.decide(&request("/a")));
+        assert!(decider.decide(&request("/a?fault-redirect=0")));
+    }
+}
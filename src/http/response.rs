@@ -0,0 +1,252 @@
+use crate::{
+    decider::{Decider, Probability, WithContext},
+    describe::{DescribeDecider, FaultDescription},
+    generator::{DefaultGenerator, Generator},
+    observe::{FaultEvent, Outcome},
+    options::FaultOptions,
+    validate::ValidateDecider,
+    veto::Vetoed,
+    Error,
+};
+use std::{
+    fmt,
+    future::Future,
+    pin::Pin,
+    task::{Context, Poll},
+    time::Duration,
+};
+use tower::{Layer, Service};
+
+/// Layer that randomly returns a generated response instead of calling the
+/// service.
+///
+/// Unlike the [`ErrorLayer`](crate::error::ErrorLayer), the injected fault is
+/// returned as a successful response, such as a `503 Service Unavailable`.
+/// This means that middlewares looking at responses, such as metrics or
+/// classification middlewares, see the injected fault like any other
+/// response.
+#[derive(Clone, Debug)]
+pub struct ResponseLayer<D, G> {
+    decider: D,
+    generator: G,
+    options: FaultOptions,
+}
+
+impl Default for ResponseLayer<Probability, DefaultGenerator> {
+    /// Create a new `ResponseLayer` that never injects responses, and returns
+    /// the default value of the response type once armed with a decider.
+    fn default() -> Self {
+        Self::new(Probability::NEVER, DefaultGenerator)
+    }
+}
+
+impl<D, G> ResponseLayer<D, G> {
+    /// Create a new `ResponseLayer` with the given decider and response
+    /// generator.
+    pub fn new(decider: D, generator: G) -> Self {
+        Self {
+            decider,
+            generator,
+            options: FaultOptions::default(),
+        }
+    }
+
+    /// Set the given decider to be used to determine if a response should
+    /// be injected.
+    pub fn with_decider<ND>(self, decider: ND) -> ResponseLayer<ND, G> {
+        ResponseLayer {
+            decider,
+            generator: self.generator,
+            options: self.options,
+        }
+    }
+
+    /// Set the given response generator to generate responses.
+    pub fn with_generator<NG>(self, generator: NG) -> ResponseLayer<D, NG> {
+        ResponseLayer {
+            decider: self.decider,
+            generator,
+            options: self.options,
+        }
+    }
+
+    /// Call the decider with a `(request, context)` tuple, where the context
+    /// is returned by the given extractor.
+    ///
+    /// See [`WithContext`] for more information.
+    pub fn with_context<X>(self, extractor: X) -> ResponseLayer<WithContext<D, X>, G> {
+        ResponseLayer {
+            decider: WithContext::new(self.decider, extractor),
+            generator: self.generator,
+            options: self.options,
+        }
+    }
+
+    /// Never inject responses into the requests vetoed by the given veto,
+    /// regardless of the decider.
+    ///
+    /// See the [`veto`](crate::veto) module for more information.
+    pub fn with_veto<V>(self, veto: V) -> ResponseLayer<Vetoed<D, V>, G> {
+        ResponseLayer {
+            decider: Vetoed::new(self.decider, veto),
+            generator: self.generator,
+            options: self.options,
+        }
+    }
+}
+
+impl<D, G> ResponseLayer<D, G> {
+    crate::options::impl_fault_options! { "layer", shadow }
+}
+
+impl<D, G> ResponseLayer<D, G>
+where
+    D: ValidateDecider,
+{
+    /// Create a new `ResponseLayer`, returning an error if the configuration is
+    /// invalid.
+    pub fn try_new(decider: D, generator: G) -> Result<Self, Error> {
+        Self::new(decider, generator).build()
+    }
+
+    /// Validate the configuration of the layer.
+    ///
+    /// Returns an [`Error`] if the decider is misconfigured, instead of
+    /// panicking at request time.
+    pub fn build(self) -> Result<Self, Error> {
+        self.decider.validate_decider()?;
+        Ok(self)
+    }
+
+    /// Validate the configuration of the layer, and wrap the given service.
+    pub fn build_service<S>(self, inner: S) -> Result<ResponseService<D, G, S>, Error>
+    where
+        D: Clone,
+        G: Clone,
+    {
+        Ok(self.build()?.layer(inner))
+    }
+}
+
+impl<D, G> ResponseLayer<D, G>
+where
+    D: DescribeDecider,
+{
+    /// Returns a description of the configured fault.
+    pub fn describe(&self) -> FaultDescription {
+        FaultDescription::new("response", self.decider.describe_decider())
+            .with_enabled(self.options.enabled)
+    }
+}
+
+impl<D, G> fmt::Display for ResponseLayer<D, G>
+where
+    D: DescribeDecider,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.describe().fmt(f)
+    }
+}
+
+impl<D, G, S> Layer<S> for ResponseLayer<D, G>
+where
+    D: Clone,
+    G: Clone,
+{
+    type Service = ResponseService<D, G, S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        ResponseService {
+            inner,
+            decider: self.decider.clone(),
+            generator: self.generator.clone(),
+            options: self.options.clone(),
+        }
+    }
+}
+
+/// Service that randomly returns a generated response instead of calling the
+/// underlying service.
+#[derive(Clone, Debug)]
+pub struct ResponseService<D, G, S> {
+    inner: S,
+    decider: D,
+    generator: G,
+    options: FaultOptions,
+}
+
+impl<D, G, S> ResponseService<D, G, S>
+where
+    D: DescribeDecider,
+{
+    /// Returns a description of the configured fault.
+    pub fn describe(&self) -> FaultDescription {
+        FaultDescription::new("response", self.decider.describe_decider())
+            .with_enabled(self.options.enabled)
+    }
+}
+
+impl<D, G, S> fmt::Display for ResponseService<D, G, S>
+where
+    D: DescribeDecider,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.describe().fmt(f)
+    }
+}
+
+impl<D, G, S, R> Service<R> for ResponseService<D, G, S>
+where
+    D: Decider<R> + Clone,
+    G: Generator<R, S::Response> + Clone,
+    S: Service<R> + Send,
+    S::Future: Send + 'static,
+    S::Response: Send + 'static,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = ResponseFuture<S::Response, S::Error>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, request: R) -> Self::Future {
+        if self.options.is_active("response")
+            && self.options.decide("response", &self.decider, &request)
+        {
+            if self.options.shadow {
+                let fut = self.inner.call(request);
+                return Box::pin(self.options.shadow_call(
+                    FaultEvent::new("response"),
+                    fut,
+                    |_| Outcome::new(true, Duration::ZERO),
+                ));
+            }
+            if self.options.inject(FaultEvent::new("response")) {
+                let response = self.generator.generate(&request);
+                return Box::pin(async move { Ok(response) });
+            }
+        }
+
+        Box::pin(self.inner.call(request))
+    }
+}
+
+/// Future returned by [`ResponseService`].
+pub type ResponseFuture<T, E> = Pin<Box<dyn Future<Output = Result<T, E>> + Send>>;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::*;
+
+    #[tokio::test]
+    async fn response_injected() {
+        let layer = ResponseLayer::new(true, |_: &()| String::from("injected"));
+        let mut service = layer.layer(DummyService);
+
+        let res = service.call(()).await;
+        assert_eq!(res.unwrap(), String::from("injected"));
+    }
+}
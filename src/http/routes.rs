@@ -0,0 +1,276 @@
+use crate::{
+    decider::{Decider, ExplainStep, Explanation, Verdict},
+    describe::{DeciderDescription, DescribeDecider, DescribeDistribution},
+    validate::{ValidateDecider, ValidateDistribution},
+    Error,
+};
+use http::Request;
+
+/// Map of path patterns to independent fault settings.
+///
+/// `RouteFaults` implements [`Decider`] (and
+/// [`Distribution`](crate::latency::Distribution) when the `latency` feature
+/// is enabled) by delegating to the settings of the first route matching the
+/// request path. This allows a single layer to cover a whole router, instead
+/// of wrapping each route's service individually.
+///
+/// Patterns are made of `/`-separated segments:
+///
+/// * `users` matches that exact segment.
+/// * `*` or `:name` matches any single segment.
+/// * `**` as the last segment matches any remaining segments, including none.
+///
+/// Routes are evaluated in the order they were added. If no route matches,
+/// the fallback is used, if any. Otherwise, no fault is injected.
+#[derive(Clone, Debug)]
+pub struct RouteFaults<T> {
+    routes: Vec<(Pattern, T)>,
+    fallback: Option<T>,
+}
+
+impl<T> RouteFaults<T> {
+    /// Create a new empty `RouteFaults`.
+    pub fn new() -> Self {
+        Self {
+            routes: Vec::new(),
+            fallback: None,
+        }
+    }
+
+    /// Add fault settings for the given path pattern.
+    pub fn route(mut self, pattern: &str, settings: T) -> Self {
+        self.routes.push((Pattern::new(pattern), settings));
+        self
+    }
+
+    /// Set the fault settings used when no route matches.
+    pub fn fallback(mut self, settings: T) -> Self {
+        self.fallback = Some(settings);
+        self
+    }
+
+    /// Returns the settings matching the given path, if any.
+    pub fn find(&self, path: &str) -> Option<&T> {
+        self.routes
+            .iter()
+            .find(|(pattern, _)| pattern.matches(path))
+            .map(|(_, settings)| settings)
+            .or(self.fallback.as_ref())
+    }
+}
+
+impl<T> Default for RouteFaults<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<B, T> Decider<Request<B>> for RouteFaults<T>
+where
+    T: Decider<Request<B>>,
+{
+    fn decide(&self, req: &Request<B>) -> bool {
+        match self.find(req.uri().path()) {
+            Some(settings) => settings.decide(req),
+            None => false,
+        }
+    }
+
+    fn explain(&self, req: &Request<B>, explanation: &mut Explanation) -> Verdict {
+        let path = req.uri().path();
+        let route = self
+            .routes
+            .iter()
+            .find(|(pattern, _)| pattern.matches(path))
+            .map(|(pattern, settings)| (format!("matched {}", pattern), settings));
+        let (detail, verdict) = match route.or_else(|| {
+            self.fallback
+                .as_ref()
+                .map(|settings| (String::from("fallback"), settings))
+        }) {
+            Some((detail, settings)) => (detail, settings.explain(req, explanation)),
+            None => (String::from("no matching route"), Verdict::Pass),
+        };
+        explanation.record(ExplainStep::new("routes", verdict).with_detail(detail));
+        verdict
+    }
+}
+
+#[cfg(feature = "latency")]
+impl<B, T> crate::latency::Distribution<Request<B>> for RouteFaults<T>
+where
+    T: crate::latency::Distribution<Request<B>>,
+{
+    fn sample(&self, req: &Request<B>) -> std::time::Duration {
+        match self.find(req.uri().path()) {
+            Some(settings) => settings.sample(req),
+            None => std::time::Duration::ZERO,
+        }
+    }
+}
+
+impl<T> RouteFaults<T> {
+    fn validate(&self, f: impl Fn(&T) -> Result<(), Error>) -> Result<(), Error> {
+        self.routes
+            .iter()
+            .map(|(_, settings)| settings)
+            .chain(self.fallback.as_ref())
+            .try_for_each(f)
+    }
+}
+
+impl<T> ValidateDecider for RouteFaults<T>
+where
+    T: ValidateDecider,
+{
+    fn validate_decider(&self) -> Result<(), Error> {
+        self.validate(T::validate_decider)
+    }
+}
+
+impl<T> ValidateDistribution for RouteFaults<T>
+where
+    T: ValidateDistribution,
+{
+    fn validate_distribution(&self) -> Result<(), Error> {
+        self.validate(T::validate_distribution)
+    }
+}
+
+impl<T> RouteFaults<T> {
+    fn describe(&self, f: impl Fn(&T) -> String) -> String {
+        let routes = self
+            .routes
+            .iter()
+            .map(|(pattern, settings)| format!("{} => {}", pattern, f(settings)))
+            .chain(
+                self.fallback
+                    .iter()
+                    .map(|settings| format!("* => {}", f(settings))),
+            )
+            .collect::<Vec<_>>();
+        format!("routes [{}]", routes.join(", "))
+    }
+}
+
+impl<T> DescribeDecider for RouteFaults<T>
+where
+    T: DescribeDecider,
+{
+    fn describe_decider(&self) -> DeciderDescription {
+        DeciderDescription::new(self.describe(|s| s.describe_decider().to_string()), None)
+    }
+}
+
+impl<T> DescribeDistribution for RouteFaults<T>
+where
+    T: DescribeDistribution,
+{
+    fn describe_distribution(&self) -> String {
+        self.describe(T::describe_distribution)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum Segment {
+    Exact(String),
+    Any,
+    Rest,
+}
+
+#[derive(Clone, Debug)]
+pub(super) struct Pattern {
+    segments: Vec<Segment>,
+}
+
+impl Pattern {
+    pub(super) fn new(pattern: &str) -> Self {
+        let segments = split(pattern)
+            .map(|segment| match segment {
+                "**" => Segment::Rest,
+                "*" => Segment::Any,
+                s if s.starts_with(':') => Segment::Any,
+                s => Segment::Exact(s.to_string()),
+            })
+            .collect();
+        Self { segments }
+    }
+
+    pub(super) fn matches(&self, path: &str) -> bool {
+        let mut parts = split(path);
+        for segment in &self.segments {
+            match segment {
+                Segment::Rest => return true,
+                Segment::Any => {
+                    if parts.next().is_none() {
+                        return false;
+                    }
+                }
+                Segment::Exact(expected) => {
+                    if parts.next() != Some(expected.as_str()) {
+                        return false;
+                    }
+                }
+            }
+        }
+        parts.next().is_none()
+    }
+}
+
+impl std::fmt::Display for Pattern {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.segments.is_empty() {
+            return f.write_str("/");
+        }
+        for segment in &self.segments {
+            match segment {
+                Segment::Exact(s) => write!(f, "/{}", s)?,
+                Segment::Any => f.write_str("/*")?,
+                Segment::Rest => f.write_str("/**")?,
+            }
+        }
+        Ok(())
+    }
+}
+
+fn split(path: &str) -> impl Iterator<Item = &str> {
+    path.split('/').filter(|segment| !segment.is_empty())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn request(path: &str) -> Request<()> {
+        Request::builder().uri(path).body(()).unwrap()
+    }
+
+    #[test]
+    fn routes_match_patterns() {
+        let routes = RouteFaults::new()
+            .route("/users/:id", 1)
+            .route("/users/*/orders", 2)
+            .route("/admin/**", 3)
+            .route("/", 4);
+
+        assert_eq!(routes.find("/users/42"), Some(&1));
+        assert_eq!(routes.find("/users/42/orders"), Some(&2));
+        assert_eq!(routes.find("/users/42/orders/1"), None);
+        assert_eq!(routes.find("/admin"), Some(&3));
+        assert_eq!(routes.find("/admin/users/1"), Some(&3));
+        assert_eq!(routes.find("/"), Some(&4));
+        assert_eq!(routes.find("/unknown"), None);
+    }
+
+    #[test]
+    fn routes_decide() {
+        let routes = RouteFaults::new()
+            .route("/faulty", true)
+            .route("/healthy", false)
+            .fallback(true);
+
+        assert!(routes.decide(&request("/faulty")));
+        assert!(!routes.decide(&request("/healthy")));
+        assert!(routes.decide(&request("/other")));
+    }
+}
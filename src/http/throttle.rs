@@ -0,0 +1,160 @@
+use crate::{
+    decider::{Decider, Probability},
+    describe::{DeciderDescription, DescribeDecider},
+    generator::Generator,
+    latency::Distribution,
+    validate::ValidateDecider,
+    Error,
+};
+use http::{header::RETRY_AFTER, HeaderValue, Response, StatusCode};
+use rand::Rng;
+use std::{
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+use tokio::time::Instant;
+
+/// Response generator producing `429 Too Many Requests` responses with a
+/// `Retry-After` header.
+///
+/// The delay is sampled from the given distribution, in the same way as
+/// latencies, and rounded up to the second. This can be used to test that
+/// clients honor the delay before retrying.
+#[derive(Clone, Debug)]
+pub struct TooManyRequests<D> {
+    retry_after: D,
+}
+
+impl<D> TooManyRequests<D> {
+    /// Create a new `TooManyRequests` generator with the given
+    /// `Retry-After` delay distribution.
+    pub fn new(retry_after: D) -> Self {
+        Self { retry_after }
+    }
+}
+
+impl<D, R, B> Generator<R, Response<B>> for TooManyRequests<D>
+where
+    D: Distribution<R>,
+    B: Default,
+{
+    fn generate(&self, req: &R) -> Response<B> {
+        let delay = self.retry_after.sample(req);
+        let seconds = delay.as_secs() + u64::from(delay.subsec_nanos() > 0);
+
+        let mut res = Response::new(B::default());
+        *res.status_mut() = StatusCode::TOO_MANY_REQUESTS;
+        res.headers_mut()
+            .insert(RETRY_AFTER, HeaderValue::from(seconds));
+        res
+    }
+}
+
+/// Decider that enters throttling episodes, during which all the requests
+/// are faulted.
+///
+/// Outside of an episode, each request starts a new episode with the given
+/// probability. Real rate limiters throttle clients for a while rather than
+/// for isolated requests, so this tests whether clients back off, instead
+/// of retrying into the episode.
+///
+/// Clones share the same episode.
+#[derive(Clone, Debug)]
+pub struct ThrottlingEpisode {
+    probability: f64,
+    duration: Duration,
+    until: Arc<Mutex<Option<Instant>>>,
+}
+
+impl ThrottlingEpisode {
+    /// Create a new `ThrottlingEpisode` starting episodes of the given
+    /// duration with the given probability per request.
+    pub fn new(probability: f64, duration: Duration) -> Self {
+        Self {
+            probability,
+            duration,
+            until: Arc::default(),
+        }
+    }
+
+    /// Start a throttling episode now, regardless of the probability.
+    pub fn start(&self) {
+        *self.lock() = Some(Instant::now() + self.duration);
+    }
+
+    /// End the current throttling episode, if any.
+    pub fn stop(&self) {
+        *self.lock() = None;
+    }
+
+    /// Returns `true` if a throttling episode is in progress.
+    pub fn is_throttling(&self) -> bool {
+        self.lock().is_some_and(|until| Instant::now() < until)
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, Option<Instant>> {
+        self.until.lock().unwrap_or_else(|err| err.into_inner())
+    }
+}
+
+impl<R> Decider<R> for ThrottlingEpisode {
+    fn decide(&self, _req: &R) -> bool {
+        let now = Instant::now();
+        let mut until = self.lock();
+        if until.is_some_and(|until| now < until) {
+            return true;
+        }
+        if crate::seed::rng().gen_bool(self.probability) {
+            *until = Some(now + self.duration);
+            return true;
+        }
+        false
+    }
+}
+
+impl ValidateDecider for ThrottlingEpisode {
+    fn validate_decider(&self) -> Result<(), Error> {
+        Probability::new(self.probability).map(|_| ())
+    }
+}
+
+impl DescribeDecider for ThrottlingEpisode {
+    fn describe_decider(&self) -> DeciderDescription {
+        DeciderDescription::new(
+            format!("throttling episodes of {:?}", self.duration),
+            Some(self.probability),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn too_many_requests() {
+        let generator = TooManyRequests::new(Duration::from_millis(1_500));
+        let res: Response<()> = generator.generate(&());
+
+        assert_eq!(res.status(), StatusCode::TOO_MANY_REQUESTS);
+        assert_eq!(res.headers()[RETRY_AFTER], "2");
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn throttling_episode() {
+        let episode = ThrottlingEpisode::new(0.0, Duration::from_secs(30));
+        assert!(!episode.decide(&()));
+
+        episode.start();
+        assert!(episode.decide(&()));
+
+        tokio::time::sleep(Duration::from_secs(30)).await;
+        assert!(!episode.is_throttling());
+        assert!(!episode.decide(&()));
+
+        // Episodes start with the given probability.
+        let episode = ThrottlingEpisode::new(1.0, Duration::from_secs(30));
+        assert!(episode.decide(&()));
+        assert!(episode.is_throttling());
+    }
+}
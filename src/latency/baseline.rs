@@ -0,0 +1,191 @@
+use super::Distribution;
+use crate::{describe::DescribeDistribution, validate::ValidateDistribution, Error};
+use rand::Rng;
+use std::{
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+/// Distribution that expresses the injected latency as a percentage of the
+/// baseline latency of the inner service.
+///
+/// Absolute durations that are significant for a slow dependency are noise
+/// for a fast one. With a baseline, a single configuration, such as "+30%
+/// latency", works across dependencies: the
+/// [`LatencyLayer`](super::LatencyLayer) records the latency of the inner
+/// service for each request when configured with
+/// [`LatencyLayer::relative`](super::LatencyLayer::relative), and the
+/// baseline is the exponentially weighted moving average (EWMA) of these
+/// latencies.
+///
+/// No latency is injected until the first latency is recorded.
+///
+/// Clones share the same state.
+#[derive(Clone, Debug)]
+pub struct Baseline {
+    percent: f64,
+    jitter: f64,
+    alpha: f64,
+    ewma: Arc<Mutex<Option<f64>>>,
+}
+
+impl Baseline {
+    /// Create a new `Baseline` injecting the given percentage of the
+    /// baseline latency.
+    ///
+    /// By default, each recorded latency accounts for 10% of the moving
+    /// average.
+    pub fn percent(percent: f64) -> Self {
+        Self {
+            percent,
+            jitter: 0.0,
+            alpha: 0.1,
+            ewma: Arc::default(),
+        }
+    }
+
+    /// Add a random jitter of up to the given percentage of the baseline
+    /// latency, above or below the configured percentage.
+    pub fn jitter(mut self, jitter: f64) -> Self {
+        self.jitter = jitter;
+        self
+    }
+
+    /// Set the weight of each recorded latency in the moving average,
+    /// between 0.0 and 1.0.
+    ///
+    /// Higher values follow changes of the baseline faster, but are more
+    /// sensitive to noise.
+    pub fn alpha(mut self, alpha: f64) -> Self {
+        self.alpha = alpha;
+        self
+    }
+
+    /// Returns the current baseline latency, if any latency was recorded.
+    pub fn baseline(&self) -> Option<Duration> {
+        self.lock()
+            .and_then(|ewma| Duration::try_from_secs_f64(ewma).ok())
+    }
+
+    /// Record the latency of the inner service for a request.
+    ///
+    /// This is called by the [`LatencyLayer`](super::LatencyLayer) when
+    /// configured with
+    /// [`LatencyLayer::relative`](super::LatencyLayer::relative).
+    pub fn record(&self, latency: Duration) {
+        let latency = latency.as_secs_f64();
+        let mut ewma = self.ewma.lock().unwrap_or_else(|err| err.into_inner());
+        *ewma = Some(match *ewma {
+            Some(ewma) => ewma + (latency - ewma) * self.alpha,
+            None => latency,
+        });
+    }
+
+    fn lock(&self) -> Option<f64> {
+        *self.ewma.lock().unwrap_or_else(|err| err.into_inner())
+    }
+}
+
+impl<R> Distribution<R> for Baseline {
+    fn sample(&self, _req: &R) -> Duration {
+        let baseline = match self.lock() {
+            Some(baseline) => baseline,
+            None => return Duration::ZERO,
+        };
+        let percent = if self.jitter > 0.0 {
+            crate::seed::rng().gen_range(self.percent - self.jitter..=self.percent + self.jitter)
+        } else {
+            self.percent
+        };
+        Duration::try_from_secs_f64(baseline * percent.max(0.0) / 100.0).unwrap_or(Duration::ZERO)
+    }
+}
+
+impl ValidateDistribution for Baseline {
+    fn validate_distribution(&self) -> Result<(), Error> {
+        if !(self.percent.is_finite() && self.percent >= 0.0) {
+            return Err(Error::InvalidConfig(format!(
+                "baseline percentage must be positive, got {}",
+                self.percent
+            )));
+        }
+        if !(self.jitter.is_finite() && self.jitter >= 0.0) {
+            return Err(Error::InvalidConfig(format!(
+                "baseline jitter must be positive, got {}",
+                self.jitter
+            )));
+        }
+        if !(self.alpha > 0.0 && self.alpha <= 1.0) {
+            return Err(Error::InvalidConfig(format!(
+                "baseline alpha must be between 0.0 and 1.0, got {}",
+                self.alpha
+            )));
+        }
+        Ok(())
+    }
+}
+
+impl DescribeDistribution for Baseline {
+    fn describe_distribution(&self) -> String {
+        let mut description = format!("+{}%", self.percent);
+        if self.jitter > 0.0 {
+            description.push_str(&format!(" ± {}%", self.jitter));
+        }
+        match self.baseline() {
+            Some(baseline) => format!("{} of baseline (currently {:?})", description, baseline),
+            None => format!("{} of baseline", description),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn baseline_percent() {
+        let baseline = Baseline::percent(30.0).alpha(0.5);
+        assert_eq!(baseline.sample(&()), Duration::ZERO);
+
+        baseline.record(Duration::from_millis(100));
+        assert_eq!(baseline.sample(&()), Duration::from_millis(30));
+        baseline.record(Duration::from_millis(300));
+        assert_eq!(baseline.baseline(), Some(Duration::from_millis(200)));
+
+        let baseline = baseline.jitter(10.0);
+        for _ in 0..100 {
+            let latency = baseline.sample(&()).as_millis();
+            assert!((40..=80).contains(&latency), "latency: {}", latency);
+        }
+
+        assert!(baseline.validate_distribution().is_ok());
+        assert!(Baseline::percent(-1.0).validate_distribution().is_err());
+        assert!(Baseline::percent(30.0)
+            .alpha(0.0)
+            .validate_distribution()
+            .is_err());
+    }
+
+    #[tokio::test(start_paused = true)]
+    #[cfg(feature = "tokio")]
+    async fn baseline_records_layer() {
+        use crate::latency::LatencyLayer;
+        use tokio::time;
+        use tower::{service_fn, Layer, Service};
+
+        let baseline = Baseline::percent(50.0);
+        let layer = LatencyLayer::new(true, 0).relative(baseline.clone());
+        let mut service = layer.layer(service_fn(|()| async {
+            time::sleep(Duration::from_millis(100)).await;
+            Ok::<_, ()>(())
+        }));
+
+        service.call(()).await.unwrap();
+        assert_eq!(baseline.baseline(), Some(Duration::from_millis(100)));
+
+        let start = time::Instant::now();
+        service.call(()).await.unwrap();
+        assert_eq!(start.elapsed(), Duration::from_millis(150));
+        assert_eq!(baseline.baseline(), Some(Duration::from_millis(100)));
+    }
+}
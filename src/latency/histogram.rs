@@ -0,0 +1,231 @@
+use std::{
+    fmt,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+
+/// Values below this number of microseconds have their own bucket.
+const LINEAR: u64 = 16;
+/// Number of buckets per power of two above `LINEAR`, giving a relative
+/// precision of 12.5%.
+const SUB_BUCKETS: u64 = 8;
+/// Number of buckets, covering values up to 2^64 microseconds.
+const BUCKETS: usize = (LINEAR + (64 - 4) * SUB_BUCKETS) as usize;
+
+/// Histogram of the latencies actually injected by a
+/// [`LatencyLayer`](super::LatencyLayer).
+///
+/// Comparing the histogram with the configured distribution shows whether
+/// the experiment achieved the expected latencies, as sampling bugs or timer
+/// coarseness would otherwise go unnoticed.
+///
+/// Latencies are recorded in microseconds with a relative precision of
+/// 12.5%. Clones share the same histogram.
+#[derive(Clone)]
+pub struct LatencyHistogram {
+    state: Arc<HistogramState>,
+}
+
+struct HistogramState {
+    buckets: Vec<AtomicU64>,
+    count: AtomicU64,
+    sum: AtomicU64,
+    min: AtomicU64,
+    max: AtomicU64,
+}
+
+impl Default for LatencyHistogram {
+    fn default() -> Self {
+        Self {
+            state: Arc::new(HistogramState {
+                buckets: (0..BUCKETS).map(|_| AtomicU64::new(0)).collect(),
+                count: AtomicU64::new(0),
+                sum: AtomicU64::new(0),
+                min: AtomicU64::new(u64::MAX),
+                max: AtomicU64::new(0),
+            }),
+        }
+    }
+}
+
+impl LatencyHistogram {
+    /// Create a new empty `LatencyHistogram`.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Record a latency.
+    pub fn record(&self, latency: Duration) {
+        let micros = u64::try_from(latency.as_micros()).unwrap_or(u64::MAX);
+        let state = &self.state;
+        state.buckets[index(micros)].fetch_add(1, Ordering::Relaxed);
+        state.count.fetch_add(1, Ordering::Relaxed);
+        state.sum.fetch_add(micros, Ordering::Relaxed);
+        state.min.fetch_min(micros, Ordering::Relaxed);
+        state.max.fetch_max(micros, Ordering::Relaxed);
+    }
+
+    /// Returns the number of recorded latencies.
+    pub fn count(&self) -> u64 {
+        self.state.count.load(Ordering::Relaxed)
+    }
+
+    /// Returns the smallest recorded latency.
+    pub fn min(&self) -> Option<Duration> {
+        self.non_empty()
+            .then(|| Duration::from_micros(self.state.min.load(Ordering::Relaxed)))
+    }
+
+    /// Returns the largest recorded latency.
+    pub fn max(&self) -> Option<Duration> {
+        self.non_empty()
+            .then(|| Duration::from_micros(self.state.max.load(Ordering::Relaxed)))
+    }
+
+    /// Returns the mean of the recorded latencies.
+    pub fn mean(&self) -> Option<Duration> {
+        self.non_empty()
+            .then(|| Duration::from_micros(self.state.sum.load(Ordering::Relaxed) / self.count()))
+    }
+
+    /// Returns the latency below which the given fraction of the recorded
+    /// latencies fall, such as `0.99` for the 99th percentile.
+    ///
+    /// The quantile is clamped between 0.0 and 1.0.
+    pub fn quantile(&self, quantile: f64) -> Option<Duration> {
+        let count = self.count();
+        if count == 0 {
+            return None;
+        }
+        let rank = ((quantile.clamp(0.0, 1.0) * count as f64).ceil() as u64).max(1);
+        let mut seen = 0;
+        for (i, bucket) in self.state.buckets.iter().enumerate() {
+            seen += bucket.load(Ordering::Relaxed);
+            if seen >= rank {
+                let value = upper_bound(i).clamp(
+                    self.state.min.load(Ordering::Relaxed),
+                    self.state.max.load(Ordering::Relaxed),
+                );
+                return Some(Duration::from_micros(value));
+            }
+        }
+        self.max()
+    }
+
+    /// Remove all the recorded latencies.
+    pub fn reset(&self) {
+        let state = &self.state;
+        state
+            .buckets
+            .iter()
+            .for_each(|bucket| bucket.store(0, Ordering::Relaxed));
+        state.count.store(0, Ordering::Relaxed);
+        state.sum.store(0, Ordering::Relaxed);
+        state.min.store(u64::MAX, Ordering::Relaxed);
+        state.max.store(0, Ordering::Relaxed);
+    }
+
+    fn non_empty(&self) -> bool {
+        self.count() > 0
+    }
+}
+
+impl fmt::Debug for LatencyHistogram {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("LatencyHistogram")
+            .field("count", &self.count())
+            .field("min", &self.min())
+            .field("mean", &self.mean())
+            .field("max", &self.max())
+            .finish()
+    }
+}
+
+/// Returns the bucket of a value in microseconds.
+fn index(micros: u64) -> usize {
+    if micros < LINEAR {
+        return micros as usize;
+    }
+    let exponent = 63 - u64::from(micros.leading_zeros());
+    let sub = (micros >> (exponent - 3)) & (SUB_BUCKETS - 1);
+    (LINEAR + (exponent - 4) * SUB_BUCKETS + sub) as usize
+}
+
+/// Returns the largest value in microseconds of a bucket.
+fn upper_bound(index: usize) -> u64 {
+    let index = index as u64;
+    if index < LINEAR {
+        return index;
+    }
+    let exponent = (index - LINEAR) / SUB_BUCKETS + 4;
+    let sub = (index - LINEAR) % SUB_BUCKETS;
+    let lower = (SUB_BUCKETS + sub) << (exponent - 3);
+    lower.saturating_add((1 << (exponent - 3)) - 1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{latency::LatencyLayer, test_utils::*};
+    use tower::{Layer, Service};
+
+    #[test]
+    fn histogram_buckets() {
+        for micros in [0, 15, 16, 17, 1_000, 123_456, u64::MAX] {
+            let i = index(micros);
+            assert!(i < BUCKETS);
+            assert!(upper_bound(i) >= micros);
+            if i > 0 {
+                assert!(upper_bound(i - 1) < micros);
+            }
+        }
+    }
+
+    #[test]
+    fn histogram_quantiles() {
+        let histogram = LatencyHistogram::new();
+        assert_eq!(histogram.quantile(0.5), None);
+
+        for ms in 1..=100 {
+            histogram.record(Duration::from_millis(ms));
+        }
+        assert_eq!(histogram.count(), 100);
+        assert_eq!(histogram.min(), Some(Duration::from_millis(1)));
+        assert_eq!(histogram.max(), Some(Duration::from_millis(100)));
+        assert_eq!(histogram.mean(), Some(Duration::from_micros(50_500)));
+
+        let p50 = histogram.quantile(0.5).unwrap().as_secs_f64();
+        assert!((0.050..=0.050 * 1.125).contains(&p50), "p50 = {}", p50);
+        assert_eq!(histogram.quantile(1.0), Some(Duration::from_millis(100)));
+
+        histogram.reset();
+        assert_eq!(histogram.count(), 0);
+    }
+
+    #[tokio::test(start_paused = true)]
+    #[cfg(feature = "tokio")]
+    async fn histogram_records_layer() {
+        let histogram = LatencyHistogram::new();
+        let layer = LatencyLayer::new(true, 200).with_histogram(histogram.clone());
+        let mut service = layer.layer(DummyService);
+
+        for _ in 0..10 {
+            service.call(()).await.unwrap();
+        }
+        assert_eq!(histogram.count(), 10);
+        assert!(histogram.min().unwrap() >= Duration::from_millis(200));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn histogram_ignores_shadow() {
+        let histogram = LatencyHistogram::new();
+        let layer = LatencyLayer::new(true, 200)
+            .shadow(true)
+            .with_histogram(histogram.clone());
+        layer.layer(DummyService).call(()).await.unwrap();
+        assert_eq!(histogram.count(), 0);
+    }
+}
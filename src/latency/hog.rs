@@ -0,0 +1,150 @@
+use super::{Distribution, LatencyLayer};
+use crate::{decider::Decider, observe::FaultEvent, options::FaultOptions, veto::Vetoed};
+use std::{
+    future::Future,
+    mem,
+    pin::Pin,
+    task::{Context, Poll},
+};
+use tokio::time;
+use tower::{Layer, Service};
+
+impl<De, Di> LatencyLayer<De, Di> {
+    /// Hold the readiness of the inner service for the latency before
+    /// calling it.
+    ///
+    /// The returned layer waits for the inner service to be ready, and then
+    /// sits on that readiness for the sampled latency before issuing the
+    /// call. Middlewares that acquire capacity on readiness, such as
+    /// `ConcurrencyLimit`, keep that capacity for the whole duration, which
+    /// starves concurrency-limited stacks and exercises their queue timeout
+    /// handling.
+    ///
+    /// The layer must be placed above the middlewares whose capacity it
+    /// should hold.
+    pub fn hold_slot(self) -> SlotHogLayer<De, Di> {
+        SlotHogLayer {
+            decider: self.decider,
+            distribution: self.distribution,
+            options: self.options,
+        }
+    }
+}
+
+/// Layer that randomly holds the readiness of the service before calling it.
+///
+/// This is created with [`LatencyLayer::hold_slot`].
+#[derive(Clone, Debug)]
+pub struct SlotHogLayer<De, Di> {
+    decider: De,
+    distribution: Di,
+    options: FaultOptions,
+}
+
+impl<De, Di> SlotHogLayer<De, Di> {
+    /// Never hold the readiness of the service for the requests vetoed by
+    /// the given veto, regardless of the decider.
+    ///
+    /// See the [`veto`](crate::veto) module for more information.
+    pub fn with_veto<V>(self, veto: V) -> SlotHogLayer<Vetoed<De, V>, Di> {
+        SlotHogLayer {
+            decider: Vetoed::new(self.decider, veto),
+            distribution: self.distribution,
+            options: self.options,
+        }
+    }
+}
+
+impl<De, Di, S> Layer<S> for SlotHogLayer<De, Di>
+where
+    De: Clone,
+    Di: Clone,
+{
+    type Service = SlotHogService<De, Di, S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        SlotHogService {
+            inner,
+            decider: self.decider.clone(),
+            distribution: self.distribution.clone(),
+            options: self.options.clone(),
+        }
+    }
+}
+
+/// Service that randomly holds the readiness of a service before calling
+/// it.
+#[derive(Clone, Debug)]
+pub struct SlotHogService<De, Di, S> {
+    inner: S,
+    decider: De,
+    distribution: Di,
+    options: FaultOptions,
+}
+
+impl<De, Di, S, R> Service<R> for SlotHogService<De, Di, S>
+where
+    De: Decider<R>,
+    Di: Distribution<R>,
+    S: Service<R> + Clone + Send + 'static,
+    S::Future: Send + 'static,
+    R: Send + 'static,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = SlotHogFuture<S::Response, S::Error>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, request: R) -> Self::Future {
+        let latency = (self.options.is_active("slot_hog")
+            && self.options.decide("slot_hog", &self.decider, &request))
+        .then(|| self.distribution.sample(&request))
+        .filter(|latency| {
+            self.options
+                .inject(FaultEvent::new("slot_hog").with_latency(*latency))
+        });
+
+        match latency {
+            Some(latency) => {
+                // Keep the service that was polled ready, and the capacity it
+                // acquired, for the delayed call.
+                let clone = self.inner.clone();
+                let mut inner = mem::replace(&mut self.inner, clone);
+                Box::pin(async move {
+                    time::sleep(latency).await;
+                    inner.call(request).await
+                })
+            }
+            None => Box::pin(self.inner.call(request)),
+        }
+    }
+}
+
+/// Future returned by [`SlotHogService`].
+pub type SlotHogFuture<T, E> = Pin<Box<dyn Future<Output = Result<T, E>> + Send>>;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+    use tower::{limit::ConcurrencyLimitLayer, service_fn, ServiceBuilder, ServiceExt};
+
+    #[tokio::test(start_paused = true)]
+    async fn slot_hog_holds_capacity() {
+        let service = ServiceBuilder::new()
+            .layer(LatencyLayer::new(|req: &u64| *req == 0, 500).hold_slot())
+            .layer(ConcurrencyLimitLayer::new(1))
+            .service(service_fn(|_: u64| async { Ok::<_, String>("ok") }));
+
+        let start = time::Instant::now();
+        let hogged = tokio::spawn(service.clone().oneshot(0));
+        tokio::task::yield_now().await;
+
+        assert_eq!(service.oneshot(1).await.unwrap(), "ok");
+        assert_eq!(start.elapsed(), Duration::from_millis(500));
+        assert_eq!(hogged.await.unwrap().unwrap(), "ok");
+    }
+}
@@ -70,19 +70,273 @@
 //! LatencyLayer::new(0.3, |req: &MyRequest| req.value);
 //! ```
 //!
+//! ### Classes
+//!
+//! [`ByClass`] uses a different distribution for each class of requests,
+//! such as reads and writes, so a single layer can model heterogeneous
+//! traffic.
+//!
+//! ```rust
+//! use tower_fault::latency::{ByClass, LatencyLayer};
+//! # struct MyRequest { write: bool };
+//!
+//! let distribution = ByClass::new(|req: &MyRequest| req.write)
+//!     .class(false, 10..50)
+//!     .class(true, 200..500);
+//! let latency_layer = LatencyLayer::new(0.1, distribution);
+//! ```
+//!
+//! ### Payload size
+//!
+//! [`LatencyBySize`] scales the latency with the size of the request, using
+//! a [`SizeCurve`](crate::decider::SizeCurve) in milliseconds.
+//!
+//! ```rust
+//! use tower_fault::{decider::SizeCurve, latency::{LatencyBySize, LatencyLayer}};
+//! # struct MyRequest { body: Vec<u8> };
+//!
+//! // 10 milliseconds, plus 2 milliseconds per KiB of body.
+//! let distribution = LatencyBySize::new(|req: &MyRequest| req.body.len(), SizeCurve::linear(10.0, 2.0));
+//! let latency_layer = LatencyLayer::new(0.1, distribution);
+//! ```
+//!
+//! ### Default
+//!
+//! `LatencyLayer::default()` never injects latency, so it can be added to a
+//! service stack and armed later with a decider.
+//!
+//! ```rust
+//! use tower_fault::latency::LatencyLayer;
+//!
+//! // 1% of the requests, with 200 to 500 milliseconds of latency.
+//! let latency_layer = LatencyLayer::default().with_decider(0.01);
+//! ```
+//!
+//! ### Presets
+//!
+//! Uniform ranges rarely look like real failures. The preset constructors
+//! use heavy-tailed distributions, such as [`LogNormal`] and [`Pareto`], and
+//! bursty deciders based on documented failure profiles.
+//!
+//! ```rust
+//! use tower_fault::latency::LatencyLayer;
+//!
+//! let latency_layer = LatencyLayer::slow_dependency();
+//! let latency_layer = LatencyLayer::packet_lossy_link();
+//! let latency_layer = LatencyLayer::overloaded_db();
+//! ```
+//!
+//! ### Timelines
+//!
+//! A [`LatencyTimeline`] replays a recorded latency curve, such as the one
+//! of a past incident, minute by minute over the course of the experiment.
+//!
+//! ```rust,no_run
+//! use tower_fault::latency::{LatencyLayer, LatencyTimeline};
+//!
+//! let timeline = LatencyTimeline::from_file("incident.timeline").unwrap();
+//! let latency_layer = LatencyLayer::new(true, timeline);
+//! ```
+//!
+//! ### Readiness
+//!
+//! By default, the latency is added to the response future. With
+//! [`LatencyLayer::on_ready`], the latency is injected in `poll_ready`
+//! instead, which holds back middlewares that acquire capacity on readiness.
+//!
+//! ```rust
+//! use tower_fault::latency::LatencyLayer;
+//!
+//! let latency_layer = LatencyLayer::new(0.1, 200..500).on_ready();
+//! ```
+//!
+//! With [`LatencyLayer::hold_slot`], the layer waits for the inner service
+//! to be ready, and holds that readiness for the latency before calling it.
+//! This starves the capacity of concurrency-limited stacks, to test their
+//! queue timeout handling.
+//!
+//! ```rust
+//! use tower::{limit::ConcurrencyLimitLayer, ServiceBuilder};
+//! use tower_fault::latency::LatencyLayer;
+//! # async fn my_service(_req: ()) -> Result<(), String> { Ok(()) }
+//!
+//! let service = ServiceBuilder::new()
+//!     .layer(LatencyLayer::new(0.1, 1000..2000).hold_slot())
+//!     .layer(ConcurrencyLimitLayer::new(16))
+//!     .service_fn(my_service);
+//! ```
+//!
+//! ### Verification
+//!
+//! A [`LatencyHistogram`] records the latencies actually injected by the
+//! layer, to check that they match the configured distribution.
+//!
+//! ```rust
+//! use tower_fault::latency::{LatencyHistogram, LatencyLayer};
+//!
+//! let histogram = LatencyHistogram::new();
+//! let latency_layer = LatencyLayer::new(0.1, 200..500).with_histogram(histogram.clone());
+//!
+//! // After running the experiment.
+//! let p99 = histogram.quantile(0.99);
+//! ```
+//!
+//! ### Pacing
+//!
+//! A [`Pacer`] adjusts the injected latency with a feedback loop, so that the
+//! combined latency, including the service's own latency, hits a target p99
+//! even when the service's latency drifts.
+//!
+//! ```rust
+//! use std::time::Duration;
+//! use tower_fault::latency::{LatencyLayer, Pacer};
+//!
+//! let latency_layer = LatencyLayer::new(true, 0).paced(Pacer::new(Duration::from_millis(500)));
+//! ```
+//!
+//! ### Relative latency
+//!
+//! A [`Baseline`] expresses the injected latency as a percentage of the
+//! moving average of the service's own latency, so that the same
+//! configuration works for fast and slow dependencies.
+//!
+//! ```rust
+//! use tower_fault::latency::{Baseline, LatencyLayer};
+//!
+//! // Add 20 to 40% of the baseline latency to 10% of the requests.
+//! let latency_layer = LatencyLayer::new(0.1, 0).relative(Baseline::percent(30.0).jitter(10.0));
+//! ```
+//!
+//! ### Precise timer
+//!
+//! `tokio::time::sleep` has a millisecond granularity. With the
+//! `precise-timer` feature, [`LatencyLayer::precise_timer`] sleeps for most
+//! of the latency, then spins until the end, for accurate sub-millisecond
+//! latencies on fast in-process services.
+//!
+//! ```rust
+//! # #[cfg(feature = "precise-timer")]
+//! # {
+//! use std::time::Duration;
+//! use tower_fault::latency::LatencyLayer;
+//!
+//! // Inject 100 to 500 microseconds of latency.
+//! let latency_layer = LatencyLayer::new(0.1, 0.1..0.5).precise_timer(Duration::from_millis(1));
+//! # }
+//! ```
+//!
+//! ### WebAssembly
+//!
+//! With the `wasm` feature, the layer waits with JavaScript timers on
+//! `wasm32` targets, such as browser-side `tonic-web` clients, where
+//! `tokio::time` is unavailable. There is no clock on
+//! `wasm32-unknown-unknown`, so arming delays, maximum durations,
+//! histograms, relative latencies, pacing and the
+//! [`ReadyLatencyLayer`] are not supported there.
+//!
+//! ### WASI and other runtimes
+//!
+//! With the `wasi` feature instead of the `latency` feature, the error and
+//! latency layers are available without depending on Tokio, for example for
+//! services deployed on WASI or edge platforms. The latency layer then needs
+//! a timer of the runtime, set with [`LatencyLayer::with_sleep`]: it doesn't
+//! inject latency without one, and [`LatencyLayer::build`] returns an
+//! [`Error::Missing`] error.
+//!
+//! ```rust
+//! use tower_fault::latency::LatencyLayer;
+//!
+//! # async fn runtime_sleep(_: std::time::Duration) {}
+//! let latency_layer = LatencyLayer::new(0.1, 200..500).with_sleep(runtime_sleep);
+//! ```
+//!
+//! ### Enabling
+//!
+//! Layers can stay in the service stack permanently, and only be armed in
+//! specific environments.
+//!
+//! ```rust
+//! use tower_fault::latency::LatencyLayer;
+//!
+//! // Only inject latency when `CHAOS_ENABLED=true`.
+//! let latency_layer = LatencyLayer::new(0.1, 200..500).enabled_if_env("CHAOS_ENABLED");
+//! ```
+//!
+//! ### Validation
+//!
+//! The `build()` method validates the decider and the distribution,
+//! returning an error instead of panicking at request time. See the
+//! [`validate`](crate::validate) module for more information.
+//!
+//! ```rust
+//! use tower_fault::latency::LatencyLayer;
+//!
+//! let latency_layer = LatencyLayer::new(0.3, 200..500);
+//! # #[cfg(not(feature = "tokio"))]
+//! # let latency_layer = latency_layer.with_sleep(|_| async {});
+//! assert!(latency_layer.build().is_ok());
+//! assert!(LatencyLayer::new(1.3, 200..500).build().is_err());
+//! ```
+//!
 
-use crate::decider::Decider;
+use crate::{
+    decider::{Decider, Probability, WithContext},
+    describe::{DescribeDecider, DescribeDistribution, FaultDescription},
+    observe::{FaultEvent, Outcome},
+    options::FaultOptions,
+    validate::{self, ValidateDecider, ValidateDistribution},
+    veto::Vetoed,
+    Error,
+};
+#[cfg(not(feature = "tokio"))]
+use std::time::Instant;
 use std::{
+    fmt,
     future::Future,
-    marker::PhantomData,
+    ops,
     pin::Pin,
     task::{Context, Poll},
 };
-use tokio::time;
+#[cfg(feature = "tokio")]
+use tokio::time::Instant;
 use tower::{Layer, Service};
 
-mod distribution;
-pub use distribution::Distribution;
+mod baseline;
+mod histogram;
+#[cfg(feature = "tokio")]
+mod hog;
+mod pacing;
+mod presets;
+#[cfg(feature = "tokio")]
+mod ready;
+mod size;
+mod tail;
+mod timeline;
+mod timer;
+pub use crate::{class::ByClass, distribution::Distribution};
+pub use baseline::Baseline;
+pub use histogram::LatencyHistogram;
+#[cfg(feature = "tokio")]
+#[cfg_attr(docsrs, doc(cfg(feature = "tokio")))]
+pub use hog::{SlotHogFuture, SlotHogLayer, SlotHogService};
+pub use pacing::Pacer;
+#[cfg(feature = "tokio")]
+#[cfg_attr(docsrs, doc(cfg(feature = "tokio")))]
+pub use ready::{ReadyLatencyLayer, ReadyLatencyService};
+pub use size::LatencyBySize;
+pub use tail::{LogNormal, Pareto};
+pub use timeline::LatencyTimeline;
+pub use timer::Sleep;
+use timer::Timer;
+
+/// Distribution fed with the latencies observed by the layer.
+#[derive(Clone, Debug)]
+enum Feedback {
+    /// Fed with the combined latency.
+    Pacer(Pacer),
+    /// Fed with the latency of the inner service.
+    Baseline(Baseline),
+}
 
 /// Layer that randomly adds latency to the service.
 ///
@@ -90,116 +344,347 @@ pub use distribution::Distribution;
 /// that the service will have a minimal latency (set by the distribution) before
 /// returning a response.
 #[derive(Clone, Debug)]
-pub struct LatencyLayer<'a, De, Di> {
+pub struct LatencyLayer<De, Di> {
     decider: De,
     distribution: Di,
-    _phantom: PhantomData<&'a ()>,
+    options: FaultOptions,
+    histogram: Option<LatencyHistogram>,
+    timer: Timer,
+    feedback: Option<Feedback>,
 }
 
-impl<'a> LatencyLayer<'a, (), ()> {
+impl LatencyLayer<(), ()> {
     /// Create a new `LatencyLayer` builder.
     pub fn builder() -> Self {
         Self {
             decider: (),
             distribution: (),
-            _phantom: PhantomData,
+            options: FaultOptions::default(),
+            histogram: None,
+            timer: Timer::default(),
+            feedback: None,
         }
     }
 }
 
-impl<'a, De, Di> LatencyLayer<'a, De, Di> {
+impl Default for LatencyLayer<Probability, ops::Range<u64>> {
+    /// Create a new `LatencyLayer` that never injects latency, and injects
+    /// 200 to 500 milliseconds of latency once armed with
+    /// [`LatencyLayer::with_decider`].
+    fn default() -> Self {
+        Self::new(Probability::NEVER, 200..500)
+    }
+}
+
+impl<De, Di> LatencyLayer<De, Di> {
     /// Create a new `LatencyLayer` builder with the given probability
     /// and latency distribution.
+    ///
+    /// Without the `tokio` feature, set a timer with
+    /// [`with_sleep`](LatencyLayer::with_sleep), as the layer doesn't inject
+    /// latency without one.
     pub fn new(decider: De, distribution: Di) -> Self {
         Self {
             decider,
             distribution,
-            _phantom: PhantomData,
+            options: FaultOptions::default(),
+            histogram: None,
+            timer: Timer::default(),
+            feedback: None,
         }
     }
 
     /// Set the given decider to be used to determine if a latency
     /// should be injected.
-    pub fn with_decider<NDe>(self, decider: NDe) -> LatencyLayer<'a, NDe, Di> {
+    pub fn with_decider<NDe>(self, decider: NDe) -> LatencyLayer<NDe, Di> {
+        LatencyLayer {
+            decider,
+            distribution: self.distribution,
+            options: self.options,
+            histogram: self.histogram,
+            timer: self.timer,
+            feedback: self.feedback,
+        }
+    }
+
+    /// Call the decider with a `(request, context)` tuple, where the context
+    /// is returned by the given extractor.
+    ///
+    /// See [`WithContext`] for more information.
+    pub fn with_context<X>(self, extractor: X) -> LatencyLayer<WithContext<De, X>, Di> {
+        let decider = WithContext::new(self.decider, extractor);
+        LatencyLayer {
+            decider,
+            distribution: self.distribution,
+            options: self.options,
+            histogram: self.histogram,
+            timer: self.timer,
+            feedback: self.feedback,
+        }
+    }
+
+    /// Never inject latency into the requests vetoed by the given veto,
+    /// regardless of the decider.
+    ///
+    /// See the [`veto`](crate::veto) module for more information.
+    pub fn with_veto<V>(self, veto: V) -> LatencyLayer<Vetoed<De, V>, Di> {
+        let decider = Vetoed::new(self.decider, veto);
         LatencyLayer {
             decider,
             distribution: self.distribution,
-            _phantom: PhantomData,
+            options: self.options,
+            histogram: self.histogram,
+            timer: self.timer,
+            feedback: self.feedback,
         }
     }
 
     /// Set the given latency distribution to set the latency.
-    pub fn with_distribution<NDi>(self, distribution: NDi) -> LatencyLayer<'a, De, NDi> {
+    pub fn with_distribution<NDi>(self, distribution: NDi) -> LatencyLayer<De, NDi> {
         LatencyLayer {
             decider: self.decider,
             distribution,
-            _phantom: PhantomData,
+            options: self.options,
+            histogram: self.histogram,
+            timer: self.timer,
+            feedback: None,
+        }
+    }
+}
+
+impl<De, Di> LatencyLayer<De, Di> {
+    crate::options::impl_fault_options! { "layer", shadow }
+
+    /// Record the latencies actually injected into the given histogram.
+    ///
+    /// The recorded latency is the time spent waiting, which can be longer
+    /// than the sampled latency because of the timer granularity.
+    pub fn with_histogram(mut self, histogram: LatencyHistogram) -> Self {
+        self.histogram = Some(histogram);
+        self
+    }
+
+    /// Returns the histogram of injected latencies, if any.
+    pub fn histogram(&self) -> Option<&LatencyHistogram> {
+        self.histogram.as_ref()
+    }
+
+    /// Use the given pacer as the distribution, and feed it with the
+    /// combined latency of each request, including the inner service's own
+    /// latency.
+    ///
+    /// See [`Pacer`] for more information.
+    pub fn paced(self, pacer: Pacer) -> LatencyLayer<De, Pacer> {
+        LatencyLayer {
+            decider: self.decider,
+            distribution: pacer.clone(),
+            options: self.options,
+            histogram: self.histogram,
+            timer: self.timer,
+            feedback: Some(Feedback::Pacer(pacer)),
+        }
+    }
+
+    /// Use the given baseline as the distribution, and feed it with the
+    /// latency of the inner service for each request, so that the injected
+    /// latency is a percentage of the service's own latency.
+    ///
+    /// See [`Baseline`] for more information.
+    pub fn relative(self, baseline: Baseline) -> LatencyLayer<De, Baseline> {
+        LatencyLayer {
+            decider: self.decider,
+            distribution: baseline.clone(),
+            options: self.options,
+            histogram: self.histogram,
+            timer: self.timer,
+            feedback: Some(Feedback::Baseline(baseline)),
         }
     }
 }
 
-impl<'a, De, Di, S> Layer<S> for LatencyLayer<'a, De, Di>
+impl<De, Di> LatencyLayer<De, Di>
+where
+    De: ValidateDecider,
+    Di: ValidateDistribution,
+{
+    /// Create a new `LatencyLayer`, returning an error if the configuration is
+    /// invalid.
+    pub fn try_new(decider: De, distribution: Di) -> Result<Self, Error> {
+        Self::new(decider, distribution).build()
+    }
+
+    /// Validate the configuration of the layer.
+    ///
+    /// Returns an [`Error`] if the decider or the distribution is
+    /// misconfigured, instead of panicking at request time, or
+    /// [`Error::Missing`] if they were not set on a [`LatencyLayer::builder`].
+    /// Without the `tokio` feature, this also returns [`Error::Missing`] if
+    /// no timer was set with [`with_sleep`](LatencyLayer::with_sleep).
+    pub fn build(self) -> Result<Self, Error> {
+        validate::all([
+            self.decider.validate_decider(),
+            self.distribution.validate_distribution(),
+            self.timer.validate(),
+        ])?;
+        Ok(self)
+    }
+
+    /// Validate the configuration of the layer, and wrap the given service.
+    pub fn build_service<S>(self, inner: S) -> Result<LatencyService<De, Di, S>, Error>
+    where
+        De: Clone,
+        Di: Clone,
+    {
+        Ok(self.build()?.layer(inner))
+    }
+}
+
+impl<De, Di> LatencyLayer<De, Di>
+where
+    De: DescribeDecider,
+    Di: DescribeDistribution,
+{
+    /// Returns a description of the configured fault.
+    pub fn describe(&self) -> FaultDescription {
+        FaultDescription::new("latency", self.decider.describe_decider())
+            .with_enabled(self.options.enabled)
+            .with_distribution(self.distribution.describe_distribution())
+    }
+}
+
+impl<De, Di> fmt::Display for LatencyLayer<De, Di>
+where
+    De: DescribeDecider,
+    Di: DescribeDistribution,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.describe().fmt(f)
+    }
+}
+
+impl<De, Di, S> Layer<S> for LatencyLayer<De, Di>
 where
     De: Clone,
     Di: Clone,
 {
-    type Service = LatencyService<'a, De, Di, S>;
+    type Service = LatencyService<De, Di, S>;
 
     fn layer(&self, inner: S) -> Self::Service {
         LatencyService {
             inner,
             decider: self.decider.clone(),
             distribution: self.distribution.clone(),
-            _phantom: PhantomData,
+            options: self.options.clone(),
+            histogram: self.histogram.clone(),
+            timer: self.timer.clone(),
+            feedback: self.feedback.clone(),
         }
     }
 }
 
 /// Service that randomly injects latency into a service.
 #[derive(Clone, Debug)]
-pub struct LatencyService<'a, De, Di, S> {
+pub struct LatencyService<De, Di, S> {
     inner: S,
     decider: De,
     distribution: Di,
-    _phantom: PhantomData<&'a ()>,
+    options: FaultOptions,
+    histogram: Option<LatencyHistogram>,
+    timer: Timer,
+    feedback: Option<Feedback>,
+}
+
+impl<De, Di, S> LatencyService<De, Di, S>
+where
+    De: DescribeDecider,
+    Di: DescribeDistribution,
+{
+    /// Returns a description of the configured fault.
+    pub fn describe(&self) -> FaultDescription {
+        FaultDescription::new("latency", self.decider.describe_decider())
+            .with_enabled(self.options.enabled)
+            .with_distribution(self.distribution.describe_distribution())
+    }
+}
+
+impl<De, Di, S> fmt::Display for LatencyService<De, Di, S>
+where
+    De: DescribeDecider,
+    Di: DescribeDistribution,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.describe().fmt(f)
+    }
 }
 
-impl<'a, De, Di, S, R> Service<R> for LatencyService<'a, De, Di, S>
+impl<De, Di, S, R> Service<R> for LatencyService<De, Di, S>
 where
     De: Decider<R> + Clone,
     Di: Distribution<R> + Clone,
     S: Service<R> + Send,
-    S::Future: Send + 'a,
+    S::Future: Send + 'static,
 {
     type Response = S::Response;
     type Error = S::Error;
-    type Future = LatencyFuture<'a, R, S>;
+    type Future = LatencyFuture<S::Response, S::Error>;
 
     fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
         self.inner.poll_ready(cx)
     }
 
     fn call(&mut self, request: R) -> Self::Future {
-        let latency = if self.decider.decide(&request) {
-            Some(self.distribution.sample(&request))
+        let latency = if self.timer.is_set()
+            && self.options.is_active("latency")
+            && self.options.decide("latency", &self.decider, &request)
+        {
+            let latency = self.distribution.sample(&request);
+            if self.options.shadow {
+                let fut = self.inner.call(request);
+                return Box::pin(self.options.shadow_call(
+                    FaultEvent::new("latency").with_latency(latency),
+                    fut,
+                    move |actual| {
+                        Outcome::new(actual.is_ok, actual.latency.saturating_add(latency))
+                    },
+                ));
+            }
+            self.options
+                .inject(FaultEvent::new("latency").with_latency(latency))
+                .then_some(latency)
         } else {
             None
         };
 
+        let (histogram, timer, feedback) = (
+            self.histogram.clone(),
+            self.timer.clone(),
+            self.feedback.clone(),
+        );
         let fut = self.inner.call(request);
         Box::pin(async move {
+            // Only read the clock when needed, as there is no clock on some
+            // targets, such as `wasm32-unknown-unknown`.
+            let clock = histogram.is_some() || feedback.is_some();
+            let start = clock.then(Instant::now);
             if let Some(latency) = latency {
-                time::sleep(latency).await;
+                timer.sleep(latency).await;
+                if let (Some(histogram), Some(start)) = (histogram, start) {
+                    histogram.record(start.elapsed());
+                }
+            }
+            let called = clock.then(Instant::now);
+            let res = fut.await;
+            match (feedback, start, called) {
+                (Some(Feedback::Pacer(pacer)), Some(start), _) => pacer.record(start.elapsed()),
+                (Some(Feedback::Baseline(baseline)), _, Some(called)) => {
+                    baseline.record(called.elapsed())
+                }
+                _ => (),
             }
-            fut.await
+            res
         })
     }
 }
 
-type LatencyFuture<'a, R, S> = Pin<
-    Box<
-        dyn Future<Output = Result<<S as Service<R>>::Response, <S as Service<R>>::Error>>
-            + Send
-            + 'a,
-    >,
->;
+/// Future returned by [`LatencyService`].
+pub type LatencyFuture<T, E> = Pin<Box<dyn Future<Output = Result<T, E>> + Send>>;
@@ -0,0 +1,175 @@
+use super::Distribution;
+use crate::{describe::DescribeDistribution, validate::ValidateDistribution, Error};
+use std::{
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+/// Distribution that adjusts the injected latency so that the combined
+/// latency, including the inner service's own latency, hits a target
+/// quantile.
+///
+/// Fixed distributions over or under-shoot when the latency of the upstream
+/// service drifts. The pacer uses a feedback loop instead: the
+/// [`LatencyLayer`](super::LatencyLayer) records the combined latency of
+/// each request when configured with
+/// [`LatencyLayer::paced`](super::LatencyLayer::paced), and after each
+/// window of requests, the pacer moves the injected latency towards the
+/// difference between the target and the observed quantile.
+///
+/// Clones share the same state.
+#[derive(Clone, Debug)]
+pub struct Pacer {
+    target: Duration,
+    quantile: f64,
+    window: usize,
+    gain: f64,
+    state: Arc<Mutex<PacerState>>,
+}
+
+#[derive(Debug, Default)]
+struct PacerState {
+    samples: Vec<Duration>,
+    injected: Duration,
+}
+
+impl Pacer {
+    /// Create a new `Pacer` targeting the given p99 combined latency.
+    ///
+    /// By default, the injected latency is adjusted every 1,000 requests,
+    /// by half of the difference between the target and the observed p99.
+    pub fn new(target: Duration) -> Self {
+        Self {
+            target,
+            quantile: 0.99,
+            window: 1_000,
+            gain: 0.5,
+            state: Arc::default(),
+        }
+    }
+
+    /// Set the quantile of the combined latency to target, between 0.0 and
+    /// 1.0.
+    pub fn quantile(mut self, quantile: f64) -> Self {
+        self.quantile = quantile;
+        self
+    }
+
+    /// Set the number of requests observed before adjusting the injected
+    /// latency.
+    pub fn window(mut self, window: usize) -> Self {
+        self.window = window;
+        self
+    }
+
+    /// Set the ratio of the error corrected at each adjustment, between 0.0
+    /// and 1.0.
+    ///
+    /// Lower values converge slower, but are less sensitive to noise.
+    pub fn gain(mut self, gain: f64) -> Self {
+        self.gain = gain;
+        self
+    }
+
+    /// Returns the latency currently injected.
+    pub fn injected(&self) -> Duration {
+        self.lock().injected
+    }
+
+    /// Record the combined latency of a request.
+    ///
+    /// This is called by the [`LatencyLayer`](super::LatencyLayer) when
+    /// configured with [`LatencyLayer::paced`](super::LatencyLayer::paced).
+    pub fn record(&self, latency: Duration) {
+        let mut state = self.lock();
+        state.samples.push(latency);
+        if state.samples.len() < self.window.max(1) {
+            return;
+        }
+
+        state.samples.sort_unstable();
+        let index = ((state.samples.len() as f64 * self.quantile).ceil() as usize)
+            .clamp(1, state.samples.len());
+        let observed = state.samples[index - 1].as_secs_f64();
+        state.samples.clear();
+
+        let target = self.target.as_secs_f64();
+        let injected = state.injected.as_secs_f64() + (target - observed) * self.gain;
+        state.injected = Duration::try_from_secs_f64(injected.max(0.0)).unwrap_or(Duration::ZERO);
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, PacerState> {
+        self.state.lock().unwrap_or_else(|err| err.into_inner())
+    }
+}
+
+impl<R> Distribution<R> for Pacer {
+    fn sample(&self, _req: &R) -> Duration {
+        self.injected()
+    }
+}
+
+impl ValidateDistribution for Pacer {
+    fn validate_distribution(&self) -> Result<(), Error> {
+        if !(self.quantile > 0.0 && self.quantile <= 1.0) {
+            return Err(Error::InvalidConfig(format!(
+                "pacer quantile must be between 0.0 and 1.0, got {}",
+                self.quantile
+            )));
+        }
+        if !(self.gain > 0.0 && self.gain <= 1.0) {
+            return Err(Error::InvalidConfig(format!(
+                "pacer gain must be between 0.0 and 1.0, got {}",
+                self.gain
+            )));
+        }
+        if self.window == 0 {
+            return Err(Error::InvalidConfig(
+                "pacer window must contain at least one request".to_string(),
+            ));
+        }
+        Ok(())
+    }
+}
+
+impl DescribeDistribution for Pacer {
+    fn describe_distribution(&self) -> String {
+        format!(
+            "paced (p{} {:?}, currently {:?})",
+            self.quantile * 100.0,
+            self.target,
+            self.injected()
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pacer_converges() {
+        let pacer = Pacer::new(Duration::from_millis(500)).window(100);
+
+        // The inner service takes 100 to 199 milliseconds, so the injected
+        // latency should converge to around 302 milliseconds.
+        for _ in 0..20 {
+            for i in 0..100 {
+                let inner = Duration::from_millis(100 + i);
+                pacer.record(inner + pacer.sample(&()));
+            }
+        }
+
+        let injected = pacer.injected().as_millis();
+        assert!((300..=303).contains(&injected), "injected: {}", injected);
+    }
+
+    #[test]
+    fn pacer_validation() {
+        let pacer = Pacer::new(Duration::from_millis(500));
+        assert!(pacer.validate_distribution().is_ok());
+        assert!(pacer.clone().quantile(1.5).validate_distribution().is_err());
+        assert!(pacer.clone().gain(0.0).validate_distribution().is_err());
+        assert!(pacer.window(0).validate_distribution().is_err());
+    }
+}
@@ -0,0 +1,65 @@
+use super::{LatencyLayer, LogNormal, Pareto};
+use crate::decider::{Bursty, Probability};
+use std::time::Duration;
+
+impl LatencyLayer<(), ()> {
+    /// Latency profile of a slow downstream dependency.
+    ///
+    /// 5% of the requests are delayed following a log-normal distribution
+    /// with a median of 200ms and a sigma of 0.8, which puts the 99th
+    /// percentile around 1.3s. Delays are capped at 5 seconds.
+    pub fn slow_dependency() -> LatencyLayer<Probability, LogNormal> {
+        LatencyLayer::new(
+            Probability::new_const(0.05),
+            LogNormal::new(Duration::from_millis(200), 0.8).max(Duration::from_secs(5)),
+        )
+    }
+
+    /// Latency profile of a link dropping packets.
+    ///
+    /// Lost packets cause TCP retransmissions, which happen in bursts and
+    /// add at least one retransmission timeout (200ms on Linux) to the
+    /// affected requests. Bursts start for 1% of the requests and last 5
+    /// requests on average. Delays follow a Pareto distribution with a
+    /// minimum of 200ms and a shape of 1.5, capped at 3 seconds.
+    pub fn packet_lossy_link() -> LatencyLayer<Bursty, Pareto> {
+        LatencyLayer::new(
+            Bursty::new(0.01, 0.2),
+            Pareto::new(Duration::from_millis(200), 1.5).max(Duration::from_secs(3)),
+        )
+    }
+
+    /// Latency profile of an overloaded database.
+    ///
+    /// Overload episodes start for 2% of the requests and last 20 requests
+    /// on average, during which queries queue up. Delays follow a
+    /// log-normal distribution with a median of 500ms and a sigma of 1.0,
+    /// capped at 10 seconds.
+    pub fn overloaded_db() -> LatencyLayer<Bursty, LogNormal> {
+        LatencyLayer::new(
+            Bursty::new(0.02, 0.05),
+            LogNormal::new(Duration::from_millis(500), 1.0).max(Duration::from_secs(10)),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Without Tokio, layers also need a timer to be valid.
+    fn with_timer<De, Di>(layer: LatencyLayer<De, Di>) -> LatencyLayer<De, Di> {
+        #[cfg(not(feature = "tokio"))]
+        let layer = layer.with_sleep(|_| async {});
+        layer
+    }
+
+    #[test]
+    fn presets_are_valid() {
+        assert!(with_timer(LatencyLayer::slow_dependency()).build().is_ok());
+        assert!(with_timer(LatencyLayer::packet_lossy_link())
+            .build()
+            .is_ok());
+        assert!(with_timer(LatencyLayer::overloaded_db()).build().is_ok());
+    }
+}
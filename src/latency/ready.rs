@@ -0,0 +1,189 @@
+use super::{Distribution, LatencyLayer};
+use crate::{decider::Decider, observe::FaultEvent, options::FaultOptions, veto::Vetoed};
+use std::{
+    fmt,
+    future::Future,
+    pin::Pin,
+    task::{ready, Context, Poll},
+};
+use tokio::time::{self, Sleep};
+use tower::{Layer, Service};
+
+impl<De, Di> LatencyLayer<De, Di> {
+    /// Inject the latency in the readiness path instead of the response
+    /// future.
+    ///
+    /// The returned layer delays `poll_ready` before polling the inner
+    /// service, so middlewares that acquire capacity on readiness, such as
+    /// `Buffer` or `ConcurrencyLimit`, observe the delay where it happens in
+    /// real systems.
+    ///
+    /// As there is no request yet when the service is polled for readiness,
+    /// the decider and distribution are called with `&()`.
+    pub fn on_ready(self) -> ReadyLatencyLayer<De, Di> {
+        ReadyLatencyLayer {
+            decider: self.decider,
+            distribution: self.distribution,
+            options: self.options,
+        }
+    }
+}
+
+/// Layer that randomly adds latency to the readiness of the service.
+///
+/// This is created with [`LatencyLayer::on_ready`].
+#[derive(Clone, Debug)]
+pub struct ReadyLatencyLayer<De, Di> {
+    decider: De,
+    distribution: Di,
+    options: FaultOptions,
+}
+
+impl<De, Di> ReadyLatencyLayer<De, Di> {
+    /// Never delay the readiness of the service when the given veto vetoes
+    /// it, regardless of the decider.
+    ///
+    /// Like the decider, the veto is called with `&()`, so it can only
+    /// depend on external state, such as a kill switch.
+    ///
+    /// See the [`veto`](crate::veto) module for more information.
+    pub fn with_veto<V>(self, veto: V) -> ReadyLatencyLayer<Vetoed<De, V>, Di> {
+        ReadyLatencyLayer {
+            decider: Vetoed::new(self.decider, veto),
+            distribution: self.distribution,
+            options: self.options,
+        }
+    }
+}
+
+impl<De, Di, S> Layer<S> for ReadyLatencyLayer<De, Di>
+where
+    De: Clone,
+    Di: Clone,
+{
+    type Service = ReadyLatencyService<De, Di, S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        ReadyLatencyService {
+            inner,
+            decider: self.decider.clone(),
+            distribution: self.distribution.clone(),
+            options: self.options.clone(),
+            state: State::Idle,
+        }
+    }
+}
+
+enum State {
+    /// Nothing decided yet for the next request.
+    Idle,
+    /// Waiting for the injected latency.
+    Sleeping(Pin<Box<Sleep>>),
+    /// Latency decided (and waited for), polling the inner service.
+    Done,
+}
+
+/// Service that randomly adds latency to the readiness of a service.
+pub struct ReadyLatencyService<De, Di, S> {
+    inner: S,
+    decider: De,
+    distribution: Di,
+    options: FaultOptions,
+    state: State,
+}
+
+impl<De, Di, S> Clone for ReadyLatencyService<De, Di, S>
+where
+    De: Clone,
+    Di: Clone,
+    S: Clone,
+{
+    fn clone(&self) -> Self {
+        // The clone is polled for readiness independently.
+        Self {
+            inner: self.inner.clone(),
+            decider: self.decider.clone(),
+            distribution: self.distribution.clone(),
+            options: self.options.clone(),
+            state: State::Idle,
+        }
+    }
+}
+
+impl<De, Di, S> fmt::Debug for ReadyLatencyService<De, Di, S>
+where
+    De: fmt::Debug,
+    Di: fmt::Debug,
+    S: fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ReadyLatencyService")
+            .field("inner", &self.inner)
+            .field("decider", &self.decider)
+            .field("distribution", &self.distribution)
+            .field("sleeping", &matches!(self.state, State::Sleeping(_)))
+            .finish()
+    }
+}
+
+impl<De, Di, S, R> Service<R> for ReadyLatencyService<De, Di, S>
+where
+    De: Decider<()>,
+    Di: Distribution<()>,
+    S: Service<R>,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = S::Future;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        loop {
+            match &mut self.state {
+                State::Idle => {
+                    let latency = (self.options.is_active("latency")
+                        && self.options.decide("latency", &self.decider, &()))
+                    .then(|| self.distribution.sample(&()))
+                    .filter(|latency| {
+                        self.options
+                            .inject(FaultEvent::new("latency").with_latency(*latency))
+                    });
+                    self.state = match latency {
+                        Some(latency) => State::Sleeping(Box::pin(time::sleep(latency))),
+                        None => State::Done,
+                    };
+                }
+                State::Sleeping(sleep) => {
+                    ready!(sleep.as_mut().poll(cx));
+                    self.state = State::Done;
+                }
+                State::Done => return self.inner.poll_ready(cx),
+            }
+        }
+    }
+
+    fn call(&mut self, request: R) -> Self::Future {
+        self.state = State::Idle;
+        self.inner.call(request)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::*;
+    use std::time::Duration;
+    use tower::ServiceExt;
+
+    #[tokio::test(start_paused = true)]
+    async fn ready_latency_delays_readiness() {
+        let mut service = LatencyLayer::new(1.0, 500).on_ready().layer(DummyService);
+
+        let start = time::Instant::now();
+        service.ready().await.unwrap();
+        assert_eq!(start.elapsed(), Duration::from_millis(500));
+
+        let start = time::Instant::now();
+        assert_eq!(service.call(()).await.unwrap(), "ok");
+        assert_eq!(start.elapsed(), Duration::ZERO);
+    }
+}
@@ -0,0 +1,73 @@
+use super::Distribution;
+use crate::{
+    decider::SizeCurve, describe::DescribeDistribution, distribution::from_millis_f64,
+    validate::ValidateDistribution, Error,
+};
+use std::{fmt, time::Duration};
+
+/// Latency distribution that scales with the size of the request.
+///
+/// The size is extracted from the request with a closure, such as the
+/// length of the body or the value of a `Content-Length` header, and mapped
+/// to a latency in milliseconds with a [`SizeCurve`].
+#[derive(Clone)]
+pub struct LatencyBySize<F> {
+    size: F,
+    curve: SizeCurve,
+}
+
+impl<F> LatencyBySize<F> {
+    /// Create a new `LatencyBySize` distribution.
+    pub fn new(size: F, curve: SizeCurve) -> Self {
+        Self { size, curve }
+    }
+}
+
+impl<F> fmt::Debug for LatencyBySize<F> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("LatencyBySize")
+            .field("curve", &self.curve)
+            .finish()
+    }
+}
+
+impl<F, R> Distribution<R> for LatencyBySize<F>
+where
+    F: Fn(&R) -> usize,
+{
+    fn sample(&self, req: &R) -> Duration {
+        from_millis_f64(self.curve.value((self.size)(req)))
+    }
+}
+
+impl<F> ValidateDistribution for LatencyBySize<F> {
+    fn validate_distribution(&self) -> Result<(), Error> {
+        self.curve.validate()
+    }
+}
+
+impl<F> DescribeDistribution for LatencyBySize<F> {
+    fn describe_distribution(&self) -> String {
+        format!("latency by size (ms: {})", self.curve)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn latency_by_size() {
+        let distribution =
+            LatencyBySize::new(|req: &Vec<u8>| req.len(), SizeCurve::linear(10.0, 5.0));
+        assert_eq!(distribution.sample(&Vec::new()), Duration::from_millis(10));
+        assert_eq!(
+            distribution.sample(&vec![0; 4096]),
+            Duration::from_millis(30)
+        );
+        assert_eq!(
+            distribution.describe_distribution(),
+            "latency by size (ms: 10 + 5/KiB)"
+        );
+    }
+}
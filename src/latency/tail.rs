@@ -0,0 +1,159 @@
+use super::Distribution;
+use crate::{describe::DescribeDistribution, validate::ValidateDistribution, Error};
+use rand::Rng;
+use std::{f64::consts::PI, time::Duration};
+
+/// Log-normal latency distribution.
+///
+/// Most samples are close to the median, with a long tail of slow ones,
+/// which matches the latency profile of most network services. The `sigma`
+/// parameter controls the length of the tail: with a `sigma` of 1.0, the
+/// 99th percentile is about 10 times the median.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct LogNormal {
+    median: Duration,
+    sigma: f64,
+    max: Duration,
+}
+
+impl LogNormal {
+    /// Create a new `LogNormal` distribution with the given median and
+    /// shape.
+    pub fn new(median: Duration, sigma: f64) -> Self {
+        Self {
+            median,
+            sigma,
+            max: Duration::MAX,
+        }
+    }
+
+    /// Cap the sampled latencies to the given value.
+    pub fn max(mut self, max: Duration) -> Self {
+        self.max = max;
+        self
+    }
+}
+
+impl<R> Distribution<R> for LogNormal {
+    fn sample(&self, _req: &R) -> Duration {
+        let mut rng = crate::seed::rng();
+        // Box-Muller transform, `u1` is in (0, 1] to avoid `ln(0)`.
+        let u1: f64 = 1.0 - rng.gen::<f64>();
+        let u2: f64 = rng.gen();
+        let z = (-2.0 * u1.ln()).sqrt() * (2.0 * PI * u2).cos();
+        scale(self.median, (self.sigma * z).exp(), self.max)
+    }
+}
+
+impl ValidateDistribution for LogNormal {
+    fn validate_distribution(&self) -> Result<(), Error> {
+        if !self.sigma.is_finite() || self.sigma < 0.0 {
+            return Err(Error::InvalidConfig(format!(
+                "invalid log-normal sigma: {}",
+                self.sigma
+            )));
+        }
+        Ok(())
+    }
+}
+
+impl DescribeDistribution for LogNormal {
+    fn describe_distribution(&self) -> String {
+        describe("log-normal", self.median, "sigma", self.sigma, self.max)
+    }
+}
+
+/// Pareto latency distribution.
+///
+/// Samples are at least `scale`, with a heavy tail controlled by `shape`:
+/// the lower the shape, the heavier the tail. This models rare but very
+/// slow requests, such as retransmission timeouts or lock contention.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Pareto {
+    scale: Duration,
+    shape: f64,
+    max: Duration,
+}
+
+impl Pareto {
+    /// Create a new `Pareto` distribution with the given minimum latency
+    /// and shape.
+    pub fn new(scale: Duration, shape: f64) -> Self {
+        Self {
+            scale,
+            shape,
+            max: Duration::MAX,
+        }
+    }
+
+    /// Cap the sampled latencies to the given value.
+    pub fn max(mut self, max: Duration) -> Self {
+        self.max = max;
+        self
+    }
+}
+
+impl<R> Distribution<R> for Pareto {
+    fn sample(&self, _req: &R) -> Duration {
+        // Inverse transform sampling, `u` is in (0, 1] to avoid dividing by
+        // zero.
+        let u: f64 = 1.0 - crate::seed::rng().gen::<f64>();
+        scale(self.scale, u.powf(-1.0 / self.shape), self.max)
+    }
+}
+
+impl ValidateDistribution for Pareto {
+    fn validate_distribution(&self) -> Result<(), Error> {
+        if !self.shape.is_finite() || self.shape <= 0.0 {
+            return Err(Error::InvalidConfig(format!(
+                "invalid Pareto shape: {}",
+                self.shape
+            )));
+        }
+        Ok(())
+    }
+}
+
+impl DescribeDistribution for Pareto {
+    fn describe_distribution(&self) -> String {
+        describe("pareto", self.scale, "shape", self.shape, self.max)
+    }
+}
+
+/// Multiply a duration by a factor, saturating to `max`.
+fn scale(base: Duration, factor: f64, max: Duration) -> Duration {
+    Duration::try_from_secs_f64(base.as_secs_f64() * factor)
+        .unwrap_or(Duration::MAX)
+        .min(max)
+}
+
+fn describe(name: &str, base: Duration, param: &str, value: f64, max: Duration) -> String {
+    if max == Duration::MAX {
+        format!("{}({:?}, {} {})", name, base, param, value)
+    } else {
+        format!("{}({:?}, {} {}, max {:?})", name, base, param, value, max)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn log_normal_median() {
+        let dist = LogNormal::new(Duration::from_millis(100), 0.5);
+        let mut samples = (0..10_000).map(|_| dist.sample(&())).collect::<Vec<_>>();
+        samples.sort();
+        let median = samples[samples.len() / 2].as_millis();
+        assert!((90..110).contains(&median), "median: {}", median);
+    }
+
+    #[test]
+    fn pareto_bounds() {
+        let dist = Pareto::new(Duration::from_millis(200), 1.5).max(Duration::from_secs(1));
+        for _ in 0..10_000 {
+            let sample = dist.sample(&());
+            assert!(sample >= Duration::from_millis(200) && sample <= Duration::from_secs(1));
+        }
+    }
+}
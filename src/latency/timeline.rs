@@ -0,0 +1,121 @@
+use super::Distribution;
+use crate::{
+    describe::DescribeDistribution, distribution::from_millis_f64, timeline::Timeline,
+    validate::ValidateDistribution, Error,
+};
+use std::{
+    path::Path,
+    time::{Duration, Instant},
+};
+
+/// Distribution replaying a recorded latency curve, such as the latency of
+/// a past incident, over the elapsed time of the experiment.
+///
+/// The timeline has one `offset,latency` point per line, where the offset
+/// is a number of seconds, or a `MM:SS` or `HH:MM:SS` time since the start
+/// of the experiment, and the latency is in milliseconds. Points can also be
+/// separated by whitespace, and lines starting with `#` are ignored.
+///
+/// Each latency applies until the offset of the next point, and the last
+/// latency applies until the end of the experiment, so timelines usually
+/// end with a latency of `0`. No latency is injected before the first point.
+///
+/// ```text
+/// # Replay of the 2024-03-12 incident.
+/// 00:00, 20
+/// 01:00, 250
+/// 02:00, 1200
+/// 05:00, 0
+/// ```
+///
+/// The experiment starts when the timeline is created, unless set with
+/// [`LatencyTimeline::starting_at`].
+#[derive(Clone, Debug)]
+pub struct LatencyTimeline {
+    timeline: Timeline<Duration>,
+}
+
+impl LatencyTimeline {
+    /// Parse a latency timeline.
+    pub fn parse(input: &str) -> Result<Self, Error> {
+        Timeline::parse(input, parse_latency).map(|timeline| Self { timeline })
+    }
+
+    /// Read and parse a latency timeline file.
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Self, Error> {
+        Timeline::read(path.as_ref(), parse_latency).map(|timeline| Self { timeline })
+    }
+
+    /// Set the start of the experiment.
+    pub fn starting_at(self, start: Instant) -> Self {
+        Self {
+            timeline: self.timeline.starting_at(start),
+        }
+    }
+
+    /// Returns the latency injected at the given elapsed time since the
+    /// start of the experiment.
+    pub fn latency_at(&self, elapsed: Duration) -> Duration {
+        self.timeline.at(elapsed).unwrap_or(Duration::ZERO)
+    }
+}
+
+fn parse_latency(value: &str) -> Option<Duration> {
+    value
+        .parse::<f64>()
+        .ok()
+        .filter(|value| value.is_finite() && *value >= 0.0)
+        .map(from_millis_f64)
+}
+
+impl<R> Distribution<R> for LatencyTimeline {
+    fn sample(&self, _req: &R) -> Duration {
+        self.timeline.current().unwrap_or(Duration::ZERO)
+    }
+}
+
+impl ValidateDistribution for LatencyTimeline {
+    fn validate_distribution(&self) -> Result<(), Error> {
+        Ok(())
+    }
+}
+
+impl DescribeDistribution for LatencyTimeline {
+    fn describe_distribution(&self) -> String {
+        format!(
+            "timeline ({} points over {:?})",
+            self.timeline.len(),
+            self.timeline.duration()
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn latency_timeline() {
+        let timeline = LatencyTimeline::parse("0,20\n1:00,250.5\n2:00,0").unwrap();
+        assert_eq!(
+            timeline.latency_at(Duration::from_secs(30)),
+            Duration::from_millis(20)
+        );
+        assert_eq!(
+            timeline.latency_at(Duration::from_secs(90)),
+            Duration::from_micros(250_500)
+        );
+        assert_eq!(
+            timeline.latency_at(Duration::from_secs(600)),
+            Duration::ZERO
+        );
+        assert_eq!(timeline.sample(&()), Duration::from_millis(20));
+        assert_eq!(
+            timeline.describe_distribution(),
+            "timeline (3 points over 120s)"
+        );
+
+        assert!(LatencyTimeline::parse("0,-1").is_err());
+        assert!(LatencyTimeline::from_file("/does/not/exist").is_err());
+    }
+}
@@ -0,0 +1,211 @@
+use super::LatencyLayer;
+use crate::Error;
+use std::{fmt, future::Future, pin::Pin, sync::Arc, time::Duration};
+#[cfg(feature = "tokio")]
+use tokio::time;
+
+/// Trait to wait for the injected latency with a custom timer.
+///
+/// This is implemented for closures returning a future, such as the sleep
+/// function of an async runtime.
+pub trait Sleep: Send + Sync {
+    /// Returns a future that completes after the given duration.
+    fn sleep(&self, duration: Duration) -> Pin<Box<dyn Future<Output = ()> + Send>>;
+}
+
+impl<F, Fut> Sleep for F
+where
+    F: Fn(Duration) -> Fut + Send + Sync,
+    Fut: Future<Output = ()> + Send + 'static,
+{
+    fn sleep(&self, duration: Duration) -> Pin<Box<dyn Future<Output = ()> + Send>> {
+        Box::pin(self(duration))
+    }
+}
+
+/// Strategy used to wait for the injected latency.
+#[derive(Clone, Default)]
+pub(crate) enum Timer {
+    /// `tokio::time::sleep`, with a millisecond granularity.
+    #[cfg(feature = "tokio")]
+    #[cfg_attr(not(all(feature = "wasm", target_arch = "wasm32")), default)]
+    Tokio,
+    /// No timer was set. Without Tokio, no latency is injected until a
+    /// timer is set with [`LatencyLayer::with_sleep`].
+    #[cfg(not(feature = "tokio"))]
+    #[default]
+    Unset,
+    /// Sleep for most of the latency, then spin for the remaining time.
+    #[cfg(feature = "precise-timer")]
+    Hybrid { spin: Duration },
+    /// JavaScript `setTimeout`, for `wasm32-unknown-unknown` targets where
+    /// `tokio::time` is unavailable.
+    #[cfg(feature = "wasm")]
+    #[cfg_attr(all(feature = "wasm", target_arch = "wasm32"), default)]
+    Wasm,
+    /// Timer provided by the user.
+    Custom(Arc<dyn Sleep>),
+}
+
+impl fmt::Debug for Timer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            #[cfg(feature = "tokio")]
+            Timer::Tokio => f.write_str("Tokio"),
+            #[cfg(not(feature = "tokio"))]
+            Timer::Unset => f.write_str("Unset"),
+            #[cfg(feature = "precise-timer")]
+            Timer::Hybrid { spin } => f.debug_struct("Hybrid").field("spin", spin).finish(),
+            #[cfg(feature = "wasm")]
+            Timer::Wasm => f.write_str("Wasm"),
+            Timer::Custom(_) => f.write_str("Custom"),
+        }
+    }
+}
+
+impl Timer {
+    /// Returns an error if no timer was set.
+    pub(crate) fn validate(&self) -> Result<(), Error> {
+        #[cfg(not(feature = "tokio"))]
+        if let Timer::Unset = self {
+            return Err(Error::Missing(vec!["sleep"]));
+        }
+        Ok(())
+    }
+
+    pub(crate) fn is_set(&self) -> bool {
+        self.validate().is_ok()
+    }
+
+    pub(crate) async fn sleep(self, latency: Duration) {
+        match self {
+            #[cfg(feature = "tokio")]
+            Timer::Tokio => time::sleep(latency).await,
+            // Layers without a timer don't inject latency.
+            #[cfg(not(feature = "tokio"))]
+            Timer::Unset => (),
+            #[cfg(feature = "precise-timer")]
+            Timer::Hybrid { spin } => {
+                let deadline = std::time::Instant::now() + latency;
+                if latency > spin {
+                    time::sleep(latency - spin).await;
+                }
+                // Yield between checks so other tasks can run on the same
+                // worker thread.
+                while std::time::Instant::now() < deadline {
+                    std::hint::spin_loop();
+                    tokio::task::yield_now().await;
+                }
+            }
+            #[cfg(feature = "wasm")]
+            Timer::Wasm => {
+                // The timeout future isn't `Send`, so it runs on the local
+                // task queue of the browser, and signals the end of the sleep.
+                let (tx, rx) = tokio::sync::oneshot::channel();
+                wasm_bindgen_futures::spawn_local(async move {
+                    gloo_timers::future::sleep(latency).await;
+                    let _ = tx.send(());
+                });
+                let _ = rx.await;
+            }
+            Timer::Custom(sleep) => sleep.sleep(latency).await,
+        }
+    }
+}
+
+impl<De, Di> LatencyLayer<De, Di> {
+    /// Use a custom timer to wait for the injected latency.
+    ///
+    /// This is useful for runtimes other than Tokio, such as on WASI or
+    /// edge platforms. Without Tokio, a timer is required: the layer doesn't
+    /// inject latency until one is set, and
+    /// [`build`](LatencyLayer::build) returns an [`Error::Missing`] error.
+    pub fn with_sleep(mut self, sleep: impl Sleep + 'static) -> Self {
+        self.timer = Timer::Custom(Arc::new(sleep));
+        self
+    }
+}
+
+#[cfg(feature = "precise-timer")]
+impl<De, Di> LatencyLayer<De, Di> {
+    /// Use a high-resolution timer for accurate sub-millisecond latencies.
+    ///
+    /// `tokio::time::sleep` has a millisecond granularity. With this timer,
+    /// the service sleeps until `spin` before the end of the latency, then
+    /// spins until the end. Spinning keeps a CPU busy, so `spin` should be
+    /// slightly above the timer granularity, such as 1 or 2 milliseconds.
+    ///
+    /// The spinning part uses the system clock, so it is not affected by
+    /// pausing tokio's time.
+    #[cfg_attr(docsrs, doc(cfg(feature = "precise-timer")))]
+    pub fn precise_timer(mut self, spin: Duration) -> Self {
+        self.timer = Timer::Hybrid { spin };
+        self
+    }
+}
+
+#[cfg(feature = "wasm")]
+impl<De, Di> LatencyLayer<De, Di> {
+    /// Use JavaScript timers to wait for the injected latency.
+    ///
+    /// This is the default on `wasm32` targets with the `wasm` feature, such
+    /// as browser-side clients, where `tokio::time` is unavailable. This
+    /// requires a JavaScript environment with `setTimeout`.
+    #[cfg_attr(docsrs, doc(cfg(feature = "wasm")))]
+    pub fn wasm_timer(mut self) -> Self {
+        self.timer = Timer::Wasm;
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Mutex;
+    use tower::{service_fn, Layer, ServiceExt};
+
+    #[tokio::test]
+    async fn timer_custom() {
+        let slept = Arc::new(Mutex::new(Vec::new()));
+        let recorder = slept.clone();
+        let layer = LatencyLayer::new(1.0, 10).with_sleep(move |latency| {
+            recorder.lock().unwrap().push(latency);
+            async {}
+        });
+
+        let service = layer.layer(service_fn(|()| async { Ok::<_, ()>(()) }));
+        service.oneshot(()).await.unwrap();
+        assert_eq!(*slept.lock().unwrap(), [Duration::from_millis(10)]);
+    }
+
+    #[tokio::test]
+    #[cfg(feature = "precise-timer")]
+    async fn timer_hybrid_precision() {
+        let timer = Timer::Hybrid {
+            spin: Duration::from_millis(2),
+        };
+        for micros in [50, 300, 1_500] {
+            let latency = Duration::from_micros(micros);
+            let start = std::time::Instant::now();
+            timer.clone().sleep(latency).await;
+            let elapsed = start.elapsed();
+            assert!(elapsed >= latency);
+            assert!(
+                elapsed < latency + Duration::from_millis(1),
+                "{:?}",
+                elapsed
+            );
+        }
+    }
+
+    #[test]
+    #[cfg(not(feature = "tokio"))]
+    fn timer_unset() {
+        let layer = LatencyLayer::new(1.0, 10);
+        assert_eq!(
+            layer.clone().build().err(),
+            Some(Error::Missing(vec!["sleep"]))
+        );
+        assert!(layer.with_sleep(|_| async {}).build().is_ok());
+    }
+}
@@ -1,5 +1,6 @@
 #![warn(missing_debug_implementations, missing_docs, unreachable_pub)]
 #![cfg_attr(docsrs, feature(doc_cfg))]
+#![cfg_attr(not(any(feature = "std", test)), no_std)]
 //! # Fault injection utilities for `tower`
 //!
 //! This crate provides [`tower::Layer`]s that can be used to inject various
@@ -44,16 +45,198 @@
 //!     .layer(error_layer)
 //!     .service(service_fn(my_service));
 //! ```
+//!
+//! ## Migrating from `tower-fault-injector`
+//!
+//! The [`compat`] module provides the probabilistic constructors of
+//! `tower-fault-injector`, such as `LatencyLayer::new_with_bernoulli`, on
+//! top of the layers of this crate.
+//!
+//! ## `no_std`
+//!
+//! Without the default `std` feature, this crate is `no_std` and only
+//! requires `alloc`. The [`decider`], [`distribution`], [`veto`],
+//! [`describe`] and [`validate`] modules remain available, so that other
+//! stacks can reuse the deciders and the latency distributions. Deciders
+//! and distributions that make random decisions, such as probabilities and
+//! ranges of latencies, as well as all the layers, require `std`.
+
+extern crate alloc;
+
+#[cfg(feature = "cascade")]
+#[cfg_attr(docsrs, doc(cfg(feature = "cascade")))]
+pub mod cascade;
+
+#[cfg(feature = "chaos-mesh")]
+#[cfg_attr(docsrs, doc(cfg(feature = "chaos-mesh")))]
+pub mod chaos_mesh;
+
+#[cfg(feature = "connect")]
+#[cfg_attr(docsrs, doc(cfg(feature = "connect")))]
+pub mod connect;
+
+#[cfg(feature = "degrade")]
+#[cfg_attr(docsrs, doc(cfg(feature = "degrade")))]
+pub mod degrade;
+
+#[cfg(feature = "discover")]
+#[cfg_attr(docsrs, doc(cfg(feature = "discover")))]
+pub mod discover;
+
+#[cfg(feature = "duplicate")]
+#[cfg_attr(docsrs, doc(cfg(feature = "duplicate")))]
+pub mod duplicate;
 
 #[cfg(feature = "error")]
 #[cfg_attr(docsrs, doc(cfg(feature = "error")))]
 pub mod error;
 
-#[cfg(feature = "latency")]
-#[cfg_attr(docsrs, doc(cfg(feature = "latency")))]
+#[cfg(feature = "experiment")]
+#[cfg_attr(docsrs, doc(cfg(feature = "experiment")))]
+pub mod experiment;
+
+#[cfg(feature = "health")]
+#[cfg_attr(docsrs, doc(cfg(feature = "health")))]
+pub mod health;
+
+#[cfg(any(feature = "latency", feature = "wasi"))]
+#[cfg_attr(docsrs, doc(cfg(any(feature = "latency", feature = "wasi"))))]
 pub mod latency;
 
+#[cfg(feature = "outage")]
+#[cfg_attr(docsrs, doc(cfg(feature = "outage")))]
+pub mod outage;
+
+#[cfg(feature = "saturation")]
+#[cfg_attr(docsrs, doc(cfg(feature = "saturation")))]
+pub mod saturation;
+
+#[cfg(feature = "slo")]
+#[cfg_attr(docsrs, doc(cfg(feature = "slo")))]
+pub mod slo;
+
+#[cfg(feature = "stale")]
+#[cfg_attr(docsrs, doc(cfg(feature = "stale")))]
+pub mod stale;
+
+#[cfg(feature = "stream")]
+#[cfg_attr(docsrs, doc(cfg(feature = "stream")))]
+pub mod stream;
+
+#[cfg(feature = "testing")]
+#[cfg_attr(docsrs, doc(cfg(feature = "testing")))]
+pub mod testing;
+
+#[cfg(feature = "std")]
+#[cfg_attr(docsrs, doc(cfg(feature = "std")))]
+pub mod boxed;
+#[cfg(any(feature = "error", feature = "latency"))]
+mod class;
+#[cfg(any(feature = "error", feature = "latency"))]
+#[cfg_attr(docsrs, doc(cfg(any(feature = "error", feature = "latency"))))]
+pub mod compat;
+#[cfg(feature = "std")]
+#[cfg_attr(docsrs, doc(cfg(feature = "std")))]
+pub mod control;
+#[cfg(feature = "std")]
+#[cfg_attr(docsrs, doc(cfg(feature = "std")))]
+pub mod coverage;
 pub mod decider;
+#[cfg(any(feature = "error", feature = "http", feature = "latency"))]
+#[cfg_attr(
+    docsrs,
+    doc(cfg(any(feature = "error", feature = "http", feature = "latency")))
+)]
+pub mod decision;
+pub mod describe;
+pub mod distribution;
+#[cfg(any(feature = "error", feature = "http"))]
+#[cfg_attr(docsrs, doc(cfg(any(feature = "error", feature = "http"))))]
+pub mod generator;
+#[cfg(any(
+    feature = "degrade",
+    feature = "duplicate",
+    feature = "error",
+    feature = "http",
+    feature = "latency",
+    feature = "stale"
+))]
+#[cfg_attr(
+    docsrs,
+    doc(cfg(any(
+        feature = "degrade",
+        feature = "duplicate",
+        feature = "error",
+        feature = "http",
+        feature = "latency",
+        feature = "stale"
+    )))
+)]
+pub mod observe;
+#[cfg(any(
+    feature = "degrade",
+    feature = "duplicate",
+    feature = "error",
+    feature = "http",
+    feature = "latency",
+    feature = "stale"
+))]
+mod options;
+#[cfg(feature = "std")]
+#[cfg_attr(docsrs, doc(cfg(feature = "std")))]
+pub mod registry;
+#[cfg(feature = "std")]
+#[cfg_attr(docsrs, doc(cfg(feature = "std")))]
+pub mod seed;
+#[cfg(feature = "std")]
+mod sync;
+#[cfg(feature = "std")]
+mod timeline;
+pub mod validate;
+pub mod veto;
+pub use validate::Error;
+
+#[cfg(feature = "balance")]
+#[cfg_attr(docsrs, doc(cfg(feature = "balance")))]
+pub mod balance;
+#[cfg(feature = "bench")]
+#[cfg_attr(docsrs, doc(cfg(feature = "bench")))]
+pub mod bench;
+
+#[cfg(feature = "axum")]
+#[cfg_attr(docsrs, doc(cfg(feature = "axum")))]
+pub mod axum;
+
+#[cfg(feature = "http")]
+#[cfg_attr(docsrs, doc(cfg(feature = "http")))]
+pub mod http;
+
+#[cfg(feature = "reqwest")]
+#[cfg_attr(docsrs, doc(cfg(feature = "reqwest")))]
+pub mod reqwest;
+
+#[cfg(feature = "tonic")]
+#[cfg_attr(docsrs, doc(cfg(feature = "tonic")))]
+pub mod tonic;
+
+#[cfg(feature = "toxiproxy")]
+#[cfg_attr(docsrs, doc(cfg(feature = "toxiproxy")))]
+pub mod toxiproxy;
+
+#[cfg(feature = "warp")]
+#[cfg_attr(docsrs, doc(cfg(feature = "warp")))]
+pub mod warp;
 
-#[cfg(test)]
+#[cfg(all(
+    test,
+    any(
+        feature = "degrade",
+        feature = "duplicate",
+        feature = "error",
+        feature = "health",
+        feature = "http",
+        feature = "latency",
+        feature = "stale",
+    )
+))]
 mod test_utils;
@@ -0,0 +1,241 @@
+//! # Observing faults
+//!
+//! Layers report every fault they inject to an optional [`FaultObserver`],
+//! for example to export metrics. With the `tracing` feature, they also emit
+//! a `tracing` event under the `tower_fault` target.
+//!
+//! ## Dry run
+//!
+//! In dry-run mode, layers evaluate their deciders and distributions and
+//! report what _would_ have been injected, without altering the behavior of
+//! the service. This is useful to validate targeting rules before arming an
+//! experiment.
+//!
+//! ```rust
+//! use std::sync::{
+//!     atomic::{AtomicUsize, Ordering},
+//!     Arc,
+//! };
+//! use tower_fault::{latency::LatencyLayer, observe::FaultEvent};
+//!
+//! let would_inject = Arc::new(AtomicUsize::new(0));
+//! let latency_layer = LatencyLayer::new(0.1, 200..500)
+//!     .dry_run(true)
+//!     .with_observer({
+//!         let would_inject = would_inject.clone();
+//!         move |event: &FaultEvent| {
+//!             would_inject.fetch_add(1, Ordering::Relaxed);
+//!         }
+//!     });
+//! ```
+//!
+//! ## Suppressed faults
+//!
+//! Vetoes, kill switches and [`Budget`](crate::decider::Budget)s suppress
+//! faults that the decider approved. Observers are notified of these in
+//! [`FaultObserver::on_suppressed`], to show how much chaos was throttled by
+//! safety mechanisms.
+//!
+//! ## Disarmed faults
+//!
+//! Layers configured with `disarm_after` stop injecting faults once the
+//! maximum duration of the experiment has elapsed. Observers are notified
+//! once in [`FaultObserver::on_disarmed`] when this happens.
+//!
+//! ## Explain mode
+//!
+//! In explain mode, layers record the chain of decider evaluations that led
+//! to the decision for a sample of the requests, such as the matched route,
+//! the probability draw or the state of a budget, and report it to
+//! [`FaultObserver::on_explain`]. This helps debugging why a request was or
+//! wasn't faulted with complex composed deciders. With the `tracing`
+//! feature, layers also emit a `tracing` event for each explained request.
+//!
+//! ```rust
+//! use tower_fault::{
+//!     decider::Budget,
+//!     latency::LatencyLayer,
+//!     observe::{ExplainEvent, FaultEvent, FaultObserver},
+//! };
+//!
+//! struct Explainer;
+//!
+//! impl FaultObserver for Explainer {
+//!     fn on_fault(&self, _event: &FaultEvent) {}
+//!
+//!     fn on_explain(&self, event: &ExplainEvent) {
+//!         for step in &event.steps {
+//!             println!("{}: {:?} ({:?})", step.decider, step.verdict, step.detail);
+//!         }
+//!     }
+//! }
+//!
+//! // Explain the decisions for 1% of the requests.
+//! let latency_layer = LatencyLayer::new(Budget::new(0.1, 100), 200..500)
+//!     .explain(0.01)
+//!     .with_observer(Explainer);
+//! ```
+//!
+//! ## Shadow mode
+//!
+//! In shadow mode, the inner service is always called normally, but the
+//! layer also computes the outcome the request would have had with the
+//! fault, and reports both in a [`ShadowEvent`]. This estimates the impact
+//! of a planned experiment from live traffic before injecting anything.
+//!
+//! ```rust
+//! use tower_fault::{
+//!     error::ErrorLayer,
+//!     observe::{FaultEvent, FaultObserver, ShadowEvent},
+//! };
+//! # struct MyRequest;
+//!
+//! struct ImpactEstimator;
+//!
+//! impl FaultObserver for ImpactEstimator {
+//!     fn on_fault(&self, _event: &FaultEvent) {}
+//!
+//!     fn on_shadow(&self, event: &ShadowEvent) {
+//!         if event.actual.is_ok && !event.faulted.is_ok {
+//!             // This request would have failed.
+//!         }
+//!     }
+//! }
+//!
+//! let error_layer = ErrorLayer::new(0.1, |_: &MyRequest| String::from("error"))
+//!     .shadow(true)
+//!     .with_observer(ImpactEstimator);
+//! ```
+
+use crate::decider::{ExplainStep, Suppression, Verdict};
+use std::time::Duration;
+
+/// Fault injected by a layer, or that would have been injected in dry-run or
+/// shadow mode.
+#[derive(Clone, Debug, PartialEq)]
+#[non_exhaustive]
+pub struct FaultEvent {
+    /// Kind of fault, such as `latency` or `error`.
+    pub fault: &'static str,
+    /// Whether the fault was only evaluated, and not applied, as in dry-run
+    /// or shadow mode.
+    pub dry_run: bool,
+    /// Injected latency, for faults that have one.
+    pub latency: Option<Duration>,
+}
+
+impl FaultEvent {
+    pub(crate) fn new(fault: &'static str) -> Self {
+        Self {
+            fault,
+            dry_run: false,
+            latency: None,
+        }
+    }
+
+    #[cfg_attr(not(feature = "latency"), allow(dead_code))]
+    pub(crate) fn with_latency(mut self, latency: Duration) -> Self {
+        self.latency = Some(latency);
+        self
+    }
+}
+
+/// Outcome of a request.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct Outcome {
+    /// Whether the service returned a response rather than an error.
+    pub is_ok: bool,
+    /// Time taken by the service to respond.
+    pub latency: Duration,
+}
+
+impl Outcome {
+    pub(crate) fn new(is_ok: bool, latency: Duration) -> Self {
+        Self { is_ok, latency }
+    }
+}
+
+/// Actual and faulted outcomes of a request in shadow mode.
+#[derive(Clone, Debug, PartialEq)]
+#[non_exhaustive]
+pub struct ShadowEvent {
+    /// Kind of fault, such as `latency` or `error`.
+    pub fault: &'static str,
+    /// Outcome of the request without the fault.
+    pub actual: Outcome,
+    /// Outcome the request would have had with the fault.
+    pub faulted: Outcome,
+}
+
+/// Fault approved by the decider of a layer, but suppressed by a safety
+/// mechanism, such as a veto, a kill switch or a budget.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct SuppressedEvent {
+    /// Kind of fault, such as `latency` or `error`.
+    pub fault: &'static str,
+    /// Safety mechanism that suppressed the fault.
+    pub reason: Suppression,
+}
+
+/// Decision of a layer explained in explain mode.
+#[derive(Clone, Debug, PartialEq)]
+#[non_exhaustive]
+pub struct ExplainEvent {
+    /// Kind of fault, such as `latency` or `error`.
+    pub fault: &'static str,
+    /// Verdict of the decider of the layer.
+    pub verdict: Verdict,
+    /// Evaluations that led to the verdict, in the order they finished.
+    pub steps: Vec<ExplainStep>,
+}
+
+/// Fault automatically disarmed at the end of its maximum duration.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct DisarmedEvent {
+    /// Kind of fault, such as `latency` or `error`.
+    pub fault: &'static str,
+}
+
+/// Observer notified of the faults injected by a layer.
+///
+/// This is implemented for closures taking a [`FaultEvent`].
+pub trait FaultObserver: Send + Sync {
+    /// Called every time a fault is injected, or would have been injected in
+    /// dry-run or shadow mode.
+    fn on_fault(&self, event: &FaultEvent);
+
+    /// Called when a request that would have been faulted completes in
+    /// shadow mode.
+    fn on_shadow(&self, event: &ShadowEvent) {
+        let _ = event;
+    }
+
+    /// Called when a safety mechanism suppresses a fault that the decider
+    /// approved.
+    fn on_suppressed(&self, event: &SuppressedEvent) {
+        let _ = event;
+    }
+
+    /// Called with the evaluations of the decider for the requests sampled
+    /// in explain mode.
+    fn on_explain(&self, event: &ExplainEvent) {
+        let _ = event;
+    }
+
+    /// Called once when a layer is automatically disarmed.
+    fn on_disarmed(&self, event: &DisarmedEvent) {
+        let _ = event;
+    }
+}
+
+impl<F> FaultObserver for F
+where
+    F: Fn(&FaultEvent) + Send + Sync,
+{
+    fn on_fault(&self, event: &FaultEvent) {
+        self(event)
+    }
+}
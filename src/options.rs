@@ -0,0 +1,422 @@
+//! Options shared by the fault layers.
+
+use crate::{
+    decider::{Decider, Explanation, Suppression, Verdict},
+    observe::{
+        DisarmedEvent, ExplainEvent, FaultEvent, FaultObserver, Outcome, ShadowEvent,
+        SuppressedEvent,
+    },
+};
+use rand::Rng;
+use std::{
+    fmt,
+    future::Future,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::{Duration, Instant},
+};
+
+/// Options shared by the fault layers and their services.
+#[derive(Clone)]
+pub(crate) struct FaultOptions {
+    /// Whether the layer injects faults at all.
+    pub(crate) enabled: bool,
+    /// Time before which the layer doesn't inject faults.
+    pub(crate) armed_at: Option<Instant>,
+    /// Time after which the layer doesn't inject faults anymore.
+    pub(crate) disarm_at: Option<Instant>,
+    /// Whether the disarming has been reported, shared between clones.
+    pub(crate) disarmed: Arc<AtomicBool>,
+    /// Whether faults are only reported, and not applied.
+    pub(crate) dry_run: bool,
+    /// Whether faults are compared with the actual outcome, and not applied.
+    pub(crate) shadow: bool,
+    /// Fraction of the requests for which the decisions are explained.
+    pub(crate) explain: f64,
+    /// Observer notified of the injected faults.
+    pub(crate) observer: Option<Arc<dyn FaultObserver>>,
+}
+
+impl FaultOptions {
+    /// Returns `true` if the layer is enabled, its arming delay has elapsed,
+    /// and it hasn't been disarmed yet.
+    ///
+    /// The first time the layer is found disarmed, this reports it.
+    pub(crate) fn is_active(&self, fault: &'static str) -> bool {
+        if !self.enabled {
+            return false;
+        }
+        if self.armed_at.is_none() && self.disarm_at.is_none() {
+            // Avoid reading the clock, which isn't available on all targets.
+            return true;
+        }
+        let now = Instant::now();
+        if self.armed_at.is_some_and(|at| now < at) {
+            return false;
+        }
+        if self.disarm_at.is_some_and(|at| now >= at) {
+            if !self.disarmed.swap(true, Ordering::Relaxed) {
+                self.disarm(fault);
+            }
+            return false;
+        }
+        true
+    }
+
+    /// Keep the layer inert until the delay has elapsed, from now.
+    pub(crate) fn arm_after(&mut self, delay: Duration) {
+        self.armed_at = Some(Instant::now() + delay);
+    }
+
+    /// Stop the layer from injecting once the delay has elapsed, from now.
+    pub(crate) fn disarm_after(&mut self, delay: Duration) {
+        self.disarm_at = Some(Instant::now() + delay);
+        self.disarmed = Arc::default();
+    }
+
+    /// Report a layer disarmed at the end of its maximum duration.
+    fn disarm(&self, fault: &'static str) {
+        #[cfg(feature = "tracing")]
+        tracing::info!(target: "tower_fault", fault, "fault disarmed");
+
+        if let Some(observer) = &self.observer {
+            observer.on_disarmed(&DisarmedEvent { fault });
+        }
+    }
+
+    /// Returns `true` if the decider approves a fault for the request.
+    ///
+    /// With an observer, this reports the faults suppressed by safety
+    /// mechanisms. In explain mode, this reports the evaluations of the
+    /// decider for a sample of the requests.
+    pub(crate) fn decide<D, R>(&self, fault: &'static str, decider: &D, req: &R) -> bool
+    where
+        D: Decider<R>,
+    {
+        let verdict = if self.explain > 0.0 && crate::seed::rng().gen_bool(self.explain.min(1.0)) {
+            self.explain(fault, decider, req)
+        } else if self.observer.is_some() {
+            decider.verdict(req)
+        } else {
+            return decider.decide(req);
+        };
+        match verdict {
+            Verdict::Inject => true,
+            Verdict::Pass => false,
+            Verdict::Suppressed(reason) => {
+                self.suppress(fault, reason);
+                false
+            }
+        }
+    }
+
+    /// Explain the verdict of the decider for the request, and report it.
+    fn explain<D, R>(&self, fault: &'static str, decider: &D, req: &R) -> Verdict
+    where
+        D: Decider<R>,
+    {
+        let mut explanation = Explanation::new();
+        let verdict = decider.explain(req, &mut explanation);
+        let event = ExplainEvent {
+            fault,
+            verdict,
+            steps: explanation.into_steps(),
+        };
+
+        #[cfg(feature = "tracing")]
+        tracing::debug!(
+            target: "tower_fault",
+            fault,
+            verdict = ?event.verdict,
+            steps = ?event.steps,
+            "fault decision explained"
+        );
+
+        if let Some(observer) = &self.observer {
+            observer.on_explain(&event);
+        }
+        verdict
+    }
+
+    /// Report a fault suppressed by a safety mechanism.
+    pub(crate) fn suppress(&self, fault: &'static str, reason: Suppression) {
+        #[cfg(feature = "tracing")]
+        tracing::debug!(
+            target: "tower_fault",
+            fault,
+            reason = ?reason,
+            "fault suppressed"
+        );
+
+        if let Some(observer) = &self.observer {
+            observer.on_suppressed(&SuppressedEvent { fault, reason });
+        }
+    }
+
+    /// Report an injected fault, returning `true` if the fault should be
+    /// applied.
+    pub(crate) fn inject(&self, mut event: FaultEvent) -> bool {
+        event.dry_run = self.dry_run || self.shadow;
+
+        #[cfg(feature = "tracing")]
+        if event.dry_run {
+            tracing::info!(
+                target: "tower_fault",
+                fault = event.fault,
+                latency = ?event.latency,
+                "fault would have been injected (dry run)"
+            );
+        } else {
+            tracing::debug!(
+                target: "tower_fault",
+                fault = event.fault,
+                latency = ?event.latency,
+                "fault injected"
+            );
+        }
+
+        if let Some(observer) = &self.observer {
+            observer.on_fault(&event);
+        }
+        !event.dry_run
+    }
+
+    /// Report the fault as a dry run, then call the inner service normally,
+    /// and report its outcome along with the outcome computed by `faulted`.
+    pub(crate) fn shadow_call<'a, F, T, E>(
+        &self,
+        event: FaultEvent,
+        fut: F,
+        faulted: impl FnOnce(Outcome) -> Outcome + Send + 'a,
+    ) -> impl Future<Output = Result<T, E>> + Send + 'a
+    where
+        F: Future<Output = Result<T, E>> + Send + 'a,
+    {
+        let fault = event.fault;
+        self.inject(event);
+        let observer = self.observer.clone();
+        async move {
+            let start = Instant::now();
+            let result = fut.await;
+            let actual = Outcome::new(result.is_ok(), start.elapsed());
+            let event = ShadowEvent {
+                fault,
+                actual,
+                faulted: faulted(actual),
+            };
+
+            #[cfg(feature = "tracing")]
+            tracing::info!(
+                target: "tower_fault",
+                fault = event.fault,
+                actual = ?event.actual,
+                faulted = ?event.faulted,
+                "shadow fault"
+            );
+
+            if let Some(observer) = observer {
+                observer.on_shadow(&event);
+            }
+            result
+        }
+    }
+}
+
+impl Default for FaultOptions {
+    fn default() -> Self {
+        // Generate the experiment seed now, so that it is logged when the
+        // layer is created.
+        crate::seed::seed();
+        Self {
+            enabled: true,
+            armed_at: None,
+            disarm_at: None,
+            disarmed: Arc::default(),
+            dry_run: false,
+            shadow: false,
+            explain: 0.0,
+            observer: None,
+        }
+    }
+}
+
+impl fmt::Debug for FaultOptions {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("FaultOptions")
+            .field("enabled", &self.enabled)
+            .field("armed_at", &self.armed_at)
+            .field("disarm_at", &self.disarm_at)
+            .field("dry_run", &self.dry_run)
+            .field("shadow", &self.shadow)
+            .field("explain", &self.explain)
+            .field("observer", &self.observer.is_some())
+            .finish()
+    }
+}
+
+/// Returns `true` if the environment variable is set to a truthy value:
+/// `1`, `true`, `yes` or `on`, case-insensitive.
+pub(crate) fn env_flag(name: &str) -> bool {
+    std::env::var(name)
+        .map(|value| {
+            matches!(
+                value.trim().to_ascii_lowercase().as_str(),
+                "1" | "true" | "yes" | "on"
+            )
+        })
+        .unwrap_or(false)
+}
+
+/// Implement the builder methods setting the [`FaultOptions`] of a layer or
+/// policy, in its `options` field.
+///
+/// The first argument names the kind of type in the docs, such as "layer".
+/// Pass `shadow` to also implement the shadow mode, for the layers that
+/// call the inner service.
+macro_rules! impl_fault_options {
+    ($kind:literal) => {
+        #[doc = concat!("Enable or disable the ", $kind, ".")]
+        ///
+        #[doc = concat!("A disabled ", $kind, " stays in place, but never injects faults.")]
+        #[doc = concat!("The ", $kind, " is enabled by default.")]
+        pub fn enabled(mut self, enabled: bool) -> Self {
+            self.options.enabled = enabled;
+            self
+        }
+
+        #[doc = concat!("Enable the ", $kind, " only if the given environment variable is")]
+        /// set to `1`, `true`, `yes` or `on`.
+        pub fn enabled_if_env(self, name: &str) -> Self {
+            self.enabled($crate::options::env_flag(name))
+        }
+
+        #[doc = concat!("Keep the ", $kind, " from injecting faults until the given delay")]
+        /// has elapsed.
+        ///
+        /// The delay starts when this method is called, usually when the
+        /// service is built at startup. This lets health checks pass and traffic
+        /// stabilize before faults are injected.
+        pub fn arm_after(mut self, delay: ::std::time::Duration) -> Self {
+            self.options.arm_after(delay);
+            self
+        }
+
+        #[doc = concat!("Stop the ", $kind, " from injecting faults once the given delay")]
+        /// has elapsed.
+        ///
+        #[doc = concat!("This caps the duration of an experiment, even if the ", $kind, " is")]
+        /// never removed. The delay starts when this method is called. Once
+        /// disarmed, the observer is notified once, and a `tracing` event is
+        /// emitted with the `tracing` feature.
+        pub fn disarm_after(mut self, delay: ::std::time::Duration) -> Self {
+            self.options.disarm_after(delay);
+            self
+        }
+
+        /// Only report the faults that would have been injected, without
+        /// applying them.
+        ///
+        /// See the [`observe`](crate::observe) module for more information.
+        pub fn dry_run(mut self, dry_run: bool) -> Self {
+            self.options.dry_run = dry_run;
+            self
+        }
+
+        /// Report why the decider did or didn't approve a fault for the given
+        /// fraction of the requests, between 0.0 and 1.0.
+        ///
+        /// See the [`observe`](crate::observe) module for more information.
+        pub fn explain(mut self, rate: f64) -> Self {
+            self.options.explain = rate;
+            self
+        }
+
+        /// Notify the given observer of the injected faults.
+        pub fn with_observer<O>(mut self, observer: O) -> Self
+        where
+            O: $crate::observe::FaultObserver + 'static,
+        {
+            self.options.observer = Some(::std::sync::Arc::new(observer));
+            self
+        }
+    };
+    ($kind:literal, shadow) => {
+        $crate::options::impl_fault_options! { $kind }
+
+        /// Call the inner service normally, and report the outcome the request
+        /// would have had with the fault along with the actual one.
+        ///
+        /// See the [`observe`](crate::observe) module for more information.
+        pub fn shadow(mut self, shadow: bool) -> Self {
+            self.options.shadow = shadow;
+            self
+        }
+    };
+}
+pub(crate) use impl_fault_options;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicBool, Ordering};
+
+    #[test]
+    fn options_dry_run() {
+        let seen = Arc::new(AtomicBool::new(false));
+        let options = FaultOptions {
+            dry_run: true,
+            observer: Some(Arc::new({
+                let seen = seen.clone();
+                move |event: &FaultEvent| seen.store(event.dry_run, Ordering::Relaxed)
+            })),
+            ..Default::default()
+        };
+
+        assert!(!options.inject(FaultEvent::new("error")));
+        assert!(seen.load(Ordering::Relaxed));
+    }
+
+    #[test]
+    fn options_explain() {
+        struct Recorder(std::sync::Mutex<Vec<ExplainEvent>>);
+
+        impl FaultObserver for Recorder {
+            fn on_fault(&self, _event: &FaultEvent) {}
+
+            fn on_explain(&self, event: &ExplainEvent) {
+                self.0.lock().unwrap().push(event.clone());
+            }
+        }
+
+        let recorder = Arc::new(Recorder(Default::default()));
+        let options = FaultOptions {
+            explain: 1.0,
+            observer: Some(recorder.clone()),
+            ..Default::default()
+        };
+
+        let decider = crate::decider::Budget::new(1.0, 0);
+        assert!(!options.decide("error", &decider, &()));
+
+        let events = recorder.0.lock().unwrap();
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].verdict, Verdict::Suppressed(Suppression::Budget));
+        let kinds: Vec<_> = events[0]
+            .steps
+            .iter()
+            .map(|step| step.decider.as_str())
+            .collect();
+        assert_eq!(kinds, ["probability", "budget"]);
+    }
+
+    #[test]
+    fn env_flag_values() {
+        std::env::set_var("TOWER_FAULT_TEST_FLAG_ON", "True");
+        std::env::set_var("TOWER_FAULT_TEST_FLAG_OFF", "0");
+        assert!(env_flag("TOWER_FAULT_TEST_FLAG_ON"));
+        assert!(!env_flag("TOWER_FAULT_TEST_FLAG_OFF"));
+        assert!(!env_flag("TOWER_FAULT_TEST_FLAG_UNSET"));
+    }
+}
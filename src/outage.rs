@@ -0,0 +1,217 @@
+//! # Zonal outages
+//!
+//! Helper to emulate zonal or regional outages in integration environments.
+//! Each label, such as an availability zone, has its own
+//! [`FaultGroup`](crate::decider::FaultGroup) used as decider by the layers
+//! of the endpoints in that zone. Failing a label activates all of them at
+//! once.
+//!
+//! ## Usage
+//!
+//! ```rust
+//! use std::time::Duration;
+//! use tower_fault::{
+//!     error::ErrorLayer,
+//!     outage::{Outage, Outages},
+//! };
+//! # struct MyRequest;
+//! # async fn run() {
+//!
+//! let outages = Outages::new();
+//! let zone_a = ErrorLayer::new(outages.zone("us-east-1a"), |_: &MyRequest| "unavailable");
+//! let zone_b = ErrorLayer::new(outages.zone("us-east-1b"), |_: &MyRequest| "unavailable");
+//!
+//! // Fail all the endpoints of a zone at once.
+//! outages.fail("us-east-1a");
+//! outages.recover("us-east-1a");
+//!
+//! // Or ramp up the outage over 30 seconds, hold it for 5 minutes, and
+//! // recover over 1 minute.
+//! let outage = Outage::new(Duration::from_secs(300))
+//!     .ramp_up(Duration::from_secs(30))
+//!     .recovery(Duration::from_secs(60));
+//! outages.simulate("us-east-1b", outage).await;
+//! # }
+//! ```
+
+use crate::decider::FaultGroup;
+use std::{
+    collections::BTreeMap,
+    sync::{Arc, RwLock},
+    time::Duration,
+};
+use tokio::time;
+
+/// Set of labeled failure domains, such as availability zones or regions.
+///
+/// Cloning is cheap, and all clones share the same labels.
+#[derive(Clone, Debug, Default)]
+pub struct Outages {
+    zones: Arc<RwLock<BTreeMap<String, FaultGroup>>>,
+}
+
+impl Outages {
+    /// Create a new empty `Outages`.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the group of the given label, creating it if needed.
+    ///
+    /// The group can be used as decider for the layers of the endpoints in
+    /// that zone.
+    pub fn zone(&self, label: &str) -> FaultGroup {
+        if let Some(group) = self.get(label) {
+            return group;
+        }
+        let mut zones = self.zones.write().expect("outages lock poisoned");
+        zones
+            .entry(label.to_string())
+            .or_insert_with(|| FaultGroup::new(label))
+            .clone()
+    }
+
+    /// Returns the group of the given label, if any.
+    pub fn get(&self, label: &str) -> Option<FaultGroup> {
+        let zones = self.zones.read().expect("outages lock poisoned");
+        zones.get(label).cloned()
+    }
+
+    /// Returns the labels, ordered by name.
+    pub fn labels(&self) -> Vec<String> {
+        let zones = self.zones.read().expect("outages lock poisoned");
+        zones.keys().cloned().collect()
+    }
+
+    /// Fail all the endpoints of the given label immediately.
+    pub fn fail(&self, label: &str) {
+        self.zone(label).activate();
+    }
+
+    /// Recover all the endpoints of the given label immediately.
+    pub fn recover(&self, label: &str) {
+        self.zone(label).deactivate();
+    }
+
+    /// Recover all the labels immediately.
+    pub fn recover_all(&self) {
+        let zones = self.zones.read().expect("outages lock poisoned");
+        zones.values().for_each(FaultGroup::deactivate);
+    }
+
+    /// Simulate an outage of the given label, following the given profile.
+    ///
+    /// The returned future completes once the label has fully recovered.
+    pub async fn simulate(&self, label: &str, outage: Outage) {
+        let group = self.zone(label);
+        ramp(&group, outage.ramp_up, outage.steps, |progress| progress).await;
+        group.activate();
+        time::sleep(outage.duration).await;
+        ramp(&group, outage.recovery, outage.steps, |progress| {
+            1.0 - progress
+        })
+        .await;
+        group.deactivate();
+    }
+}
+
+async fn ramp(group: &FaultGroup, duration: Duration, steps: u32, intensity: impl Fn(f64) -> f64) {
+    if duration.is_zero() || steps == 0 {
+        return;
+    }
+    let step = duration / steps;
+    for i in 0..steps {
+        group.set_intensity(intensity(i as f64 / steps as f64));
+        time::sleep(step).await;
+    }
+}
+
+/// Profile of a simulated outage.
+///
+/// During the ramp up, the probability of failing requests grows linearly
+/// from 0% to 100%. During the recovery, it decreases linearly back to 0%.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Outage {
+    duration: Duration,
+    ramp_up: Duration,
+    recovery: Duration,
+    steps: u32,
+}
+
+impl Outage {
+    /// Create a new `Outage` failing all the requests for the given
+    /// duration, without ramp up or recovery.
+    pub fn new(duration: Duration) -> Self {
+        Self {
+            duration,
+            ramp_up: Duration::ZERO,
+            recovery: Duration::ZERO,
+            steps: 10,
+        }
+    }
+
+    /// Set the duration of the ramp up before the full outage.
+    pub fn ramp_up(mut self, ramp_up: Duration) -> Self {
+        self.ramp_up = ramp_up;
+        self
+    }
+
+    /// Set the duration of the recovery after the full outage.
+    pub fn recovery(mut self, recovery: Duration) -> Self {
+        self.recovery = recovery;
+        self
+    }
+
+    /// Set the number of steps used for the ramp up and the recovery.
+    ///
+    /// Defaults to 10.
+    pub fn steps(mut self, steps: u32) -> Self {
+        self.steps = steps;
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::decider::Decider;
+
+    #[test]
+    fn outages_fail_label() {
+        let outages = Outages::new();
+        let zone_a = outages.zone("us-east-1a");
+        let zone_b = outages.zone("us-east-1b");
+
+        outages.fail("us-east-1a");
+        assert!(zone_a.decide(&()));
+        assert!(!zone_b.decide(&()));
+
+        outages.recover_all();
+        assert!(!zone_a.decide(&()));
+        assert_eq!(outages.labels(), ["us-east-1a", "us-east-1b"]);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn outages_simulate_ramp() {
+        let outages = Outages::new();
+        let zone = outages.zone("us-east-1a");
+        let outage = Outage::new(Duration::from_secs(10))
+            .ramp_up(Duration::from_secs(10))
+            .recovery(Duration::from_secs(10));
+
+        let task = tokio::spawn({
+            let outages = outages.clone();
+            async move { outages.simulate("us-east-1a", outage).await }
+        });
+
+        time::sleep(Duration::from_millis(5_500)).await;
+        assert_eq!(zone.intensity(), 0.5);
+        time::sleep(Duration::from_secs(10)).await;
+        assert_eq!(zone.intensity(), 1.0);
+        time::sleep(Duration::from_secs(10)).await;
+        assert_eq!(zone.intensity(), 0.5);
+
+        task.await.unwrap();
+        assert!(!zone.is_active());
+    }
+}
@@ -0,0 +1,239 @@
+use super::FaultRegistry;
+use crate::sync::{Arc, Mutex, MutexGuard};
+use std::{
+    collections::BTreeMap,
+    future::Future,
+    pin::Pin,
+    task::{Context, Poll},
+};
+use tower::{Layer, Service};
+
+tokio::task_local! {
+    static SCOPE: Arc<Scope>;
+}
+
+/// Record that the fault was injected into the current request, if it is
+/// tracked by a [`JointLayer`].
+pub(super) fn record(fault: &str) {
+    let _ = SCOPE.try_with(|scope| scope.record(fault));
+}
+
+/// Statistics about the registry faults injected into the same requests,
+/// returned by [`FaultRegistry::joint_stats`].
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct JointStats {
+    requests: u64,
+    faults: BTreeMap<String, u64>,
+    pairs: BTreeMap<(String, String), u64>,
+}
+
+impl JointStats {
+    /// Returns the number of requests tracked.
+    pub fn requests(&self) -> u64 {
+        self.requests
+    }
+
+    /// Returns the number of tracked requests the fault was injected into.
+    pub fn injected(&self, fault: &str) -> u64 {
+        self.faults.get(fault).copied().unwrap_or(0)
+    }
+
+    /// Returns the number of tracked requests both faults were injected
+    /// into.
+    pub fn both(&self, a: &str, b: &str) -> u64 {
+        let key = if a <= b { (a, b) } else { (b, a) };
+        self.pairs
+            .get(&(key.0.to_string(), key.1.to_string()))
+            .copied()
+            .unwrap_or(0)
+    }
+
+    /// Returns the correlation between the injections of both faults, as the
+    /// phi coefficient between -1.0 and 1.0.
+    ///
+    /// Independent faults have a correlation close to 0.0. This returns
+    /// `None` if one of the faults was injected into all or none of the
+    /// tracked requests.
+    pub fn correlation(&self, a: &str, b: &str) -> Option<f64> {
+        let n = self.requests as f64;
+        let (na, nb) = (self.injected(a) as f64, self.injected(b) as f64);
+        let denominator = (na * (n - na) * nb * (n - nb)).sqrt();
+        if denominator == 0.0 {
+            return None;
+        }
+        Some((n * self.both(a, b) as f64 - na * nb) / denominator)
+    }
+
+    fn add(&mut self, mut faults: Vec<String>) {
+        faults.sort();
+        faults.dedup();
+        self.requests += 1;
+        for (i, a) in faults.iter().enumerate() {
+            *self.faults.entry(a.clone()).or_default() += 1;
+            for b in &faults[i + 1..] {
+                *self.pairs.entry((a.clone(), b.clone())).or_default() += 1;
+            }
+        }
+    }
+}
+
+/// Joint statistics shared by a registry and its [`JointLayer`]s.
+#[derive(Clone, Debug, Default)]
+pub(super) struct SharedStats(Arc<Mutex<JointStats>>);
+
+impl SharedStats {
+    pub(super) fn lock(&self) -> MutexGuard<'_, JointStats> {
+        self.0.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+/// Faults injected into a single request, added to the statistics once the
+/// request is done.
+struct Scope {
+    faults: Mutex<Vec<String>>,
+    stats: SharedStats,
+}
+
+impl Scope {
+    fn record(&self, fault: &str) {
+        self.faults
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .push(fault.to_string());
+    }
+}
+
+impl Drop for Scope {
+    fn drop(&mut self) {
+        let faults = std::mem::take(&mut *self.faults.lock().unwrap_or_else(|e| e.into_inner()));
+        self.stats.lock().add(faults);
+    }
+}
+
+/// Layer that tracks the registry faults injected into the same requests by
+/// the layers it wraps, returned by [`FaultRegistry::joint_layer`].
+#[derive(Clone, Debug)]
+pub struct JointLayer {
+    stats: SharedStats,
+}
+
+impl<S> Layer<S> for JointLayer {
+    type Service = JointService<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        JointService {
+            inner,
+            stats: self.stats.clone(),
+        }
+    }
+}
+
+/// Service returned by [`JointLayer`].
+#[derive(Clone, Debug)]
+pub struct JointService<S> {
+    inner: S,
+    stats: SharedStats,
+}
+
+impl<S, R> Service<R> for JointService<S>
+where
+    S: Service<R>,
+    S::Future: Send + 'static,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = Pin<Box<dyn Future<Output = Result<S::Response, S::Error>> + Send>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, request: R) -> Self::Future {
+        let scope = Arc::new(Scope {
+            faults: Mutex::default(),
+            stats: self.stats.clone(),
+        });
+        // Layers can decide in `call` or while polling the future.
+        let fut = SCOPE.sync_scope(scope.clone(), || self.inner.call(request));
+        Box::pin(SCOPE.scope(scope, fut))
+    }
+}
+
+impl FaultRegistry {
+    /// Returns a layer tracking which faults of this registry are injected
+    /// into the same requests.
+    ///
+    /// The layer must wrap all the fault layers to track, and the statistics
+    /// are available with [`FaultRegistry::joint_stats`].
+    pub fn joint_layer(&self) -> JointLayer {
+        JointLayer {
+            stats: self.joint.clone(),
+        }
+    }
+
+    /// Returns the statistics tracked by the layers returned by
+    /// [`FaultRegistry::joint_layer`].
+    pub fn joint_stats(&self) -> JointStats {
+        self.joint.lock().clone()
+    }
+
+    /// Reset the statistics tracked by the layers returned by
+    /// [`FaultRegistry::joint_layer`].
+    pub fn reset_joint_stats(&self) {
+        *self.joint.lock() = JointStats::default();
+    }
+}
+
+#[cfg(all(test, feature = "error", feature = "latency"))]
+mod tests {
+    use super::*;
+    use crate::{error::ErrorLayer, latency::LatencyLayer};
+    use std::time::Duration;
+    use tower::{service_fn, ServiceBuilder, ServiceExt};
+
+    #[tokio::test]
+    async fn joint_stats() {
+        let registry = FaultRegistry::new();
+        let latency = registry.register("latency", 1.0);
+        let errors = registry.register("errors", 1.0);
+
+        let service = ServiceBuilder::new()
+            .layer(registry.joint_layer())
+            .layer(LatencyLayer::new(latency.clone(), Duration::ZERO))
+            .layer(ErrorLayer::new(errors.clone(), |_: &u64| {
+                String::from("error")
+            }))
+            .service(service_fn(|_: u64| async { Ok::<_, String>(()) }));
+
+        for i in 0..4 {
+            if i == 2 {
+                errors.disable();
+            }
+            let _ = service.clone().oneshot(i).await;
+        }
+
+        let stats = registry.joint_stats();
+        assert_eq!(stats.requests(), 4);
+        assert_eq!(stats.injected("latency"), 4);
+        assert_eq!(stats.injected("errors"), 2);
+        assert_eq!(stats.both("errors", "latency"), 2);
+        assert_eq!(stats.correlation("latency", "errors"), None);
+
+        registry.reset_joint_stats();
+        assert_eq!(registry.joint_stats(), JointStats::default());
+    }
+
+    #[test]
+    fn joint_correlation() {
+        let mut stats = JointStats::default();
+        stats.add(vec![String::from("a"), String::from("b")]);
+        stats.add(vec![String::from("a"), String::from("b")]);
+        stats.add(vec![]);
+        stats.add(vec![]);
+        assert_eq!(stats.correlation("a", "b"), Some(1.0));
+
+        stats.add(vec![String::from("a")]);
+        stats.add(vec![String::from("b")]);
+        assert!(stats.correlation("a", "b").unwrap() < 1.0);
+    }
+}
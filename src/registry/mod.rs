@@ -0,0 +1,1412 @@
+//! # Fault registry
+//!
+//! This module contains the [`FaultRegistry`], which keeps track of named
+//! faults whose settings can be changed at runtime, for example from an
+//! admin endpoint.
+//!
+//! Registering a fault returns a [`FaultHandle`], which implements the
+//! [`Decider`] trait and can be used with any layer of this crate.
+//!
+//! ## Example
+//!
+//! ```rust
+//! use tower_fault::{latency::LatencyLayer, registry::FaultRegistry};
+//!
+//! let registry = FaultRegistry::new();
+//!
+//! // Register a fault with a 10% probability of injecting latency.
+//! let handle = registry.register("db-latency", 0.1);
+//! let latency_layer = LatencyLayer::new(handle, 200..500);
+//!
+//! // Later on, change the probability or disable the fault.
+//! let handle = registry.get("db-latency").unwrap();
+//! handle.set_probability(0.5);
+//! handle.disable();
+//! ```
+//!
+//! ## Kill switch
+//!
+//! Each registry has a [`KillSwitch`] shared by all its faults. Engaging the
+//! kill switch stops all the faults of the registry from injecting, without
+//! changing their individual settings. This is the guardrail used by
+//! automated experiments to stop injecting faults when something goes wrong.
+//!
+//! ```rust
+//! use tower_fault::{decider::Decider, registry::FaultRegistry};
+//!
+//! let registry = FaultRegistry::new();
+//! let handle = registry.register("db-errors", 1.0);
+//!
+//! registry.kill_switch().engage();
+//! assert_eq!(false, handle.decide(&()));
+//! ```
+//!
+//! ## Vetoes
+//!
+//! [`FaultRegistry::add_veto`] protects requests from all the faults of the
+//! registry, including the ones registered later, such as payment captures
+//! or health checks. A veto applies to the faults deciding on requests of
+//! its request type. To protect requests from a single fault, use
+//! [`FaultHandle::with_veto`] instead.
+//!
+//! ```rust
+//! use tower_fault::{decider::Decider, registry::FaultRegistry};
+//! # struct MyRequest { path: String }
+//!
+//! let registry = FaultRegistry::new();
+//! registry.add_veto(|req: &MyRequest| req.path.starts_with("/payments/"));
+//!
+//! let handle = registry.register("db-errors", 1.0);
+//! let req = MyRequest { path: String::from("/payments/123") };
+//! assert_eq!(false, handle.decide(&req));
+//! ```
+//!
+//! ## Snapshots
+//!
+//! [`FaultRegistry::snapshot`] captures the settings of all the faults in a
+//! [`RegistryState`], which can be restored later with
+//! [`FaultRegistry::restore`], for example after a configuration push, or on
+//! another replica. With the `serde` feature, the state can be serialized.
+//!
+//! The state also contains the [experiment seed](crate::seed), so that a run
+//! can be reproduced with the same settings and the same random decisions.
+//!
+//! ```rust
+//! use tower_fault::registry::FaultRegistry;
+//!
+//! let registry = FaultRegistry::new();
+//! registry.register("db-latency", 0.1);
+//!
+//! let other = FaultRegistry::new();
+//! other.restore(&registry.snapshot());
+//! assert_eq!(other.get("db-latency").unwrap().probability(), 0.1);
+//! ```
+//!
+//! ## Severity
+//!
+//! Each fault has a [`Severity`], [`Severity::Minor`] by default. The
+//! registry can cap the maximum severity of the faults allowed to inject
+//! with [`FaultRegistry::set_max_severity`], for example to only allow minor
+//! faults in a shared staging environment. Faults above the cap don't
+//! inject, even when triggered, regardless of their settings.
+//!
+//! ```rust
+//! use tower_fault::{
+//!     decider::Decider,
+//!     registry::{FaultRegistry, Severity},
+//! };
+//!
+//! let registry = FaultRegistry::new();
+//! registry.set_max_severity(Severity::Minor);
+//!
+//! let handle = registry.register("db-outage", 1.0);
+//! handle.set_severity(Severity::Critical);
+//! assert_eq!(false, handle.decide(&()));
+//! ```
+//!
+//! ## Effective configuration
+//!
+//! [`FaultRegistry::effective`] returns the effective configuration of each
+//! fault, taking into account the runtime changes, the arming delays, the
+//! kill switch and the maximum severity, along with the reasons why a fault
+//! can't inject. This helps finding out why a fault didn't fire.
+//!
+//! ```rust
+//! use tower_fault::registry::{FaultRegistry, InactiveReason};
+//!
+//! let registry = FaultRegistry::new();
+//! let handle = registry.register("db-errors", 0.5);
+//! registry.kill_switch().engage();
+//!
+//! let effective = handle.effective();
+//! assert_eq!(effective.effective_probability, 0.0);
+//! assert_eq!(effective.inactive, vec![InactiveReason::KillSwitch]);
+//! ```
+//!
+//! ## Joint statistics
+//!
+//! With the `tokio` feature, [`FaultRegistry::joint_layer`] returns a layer
+//! tracking which faults of the registry are injected into the same
+//! requests, for example by a latency layer and an error layer. The
+//! statistics returned by [`FaultRegistry::joint_stats`] tell how many
+//! requests got both faults, to verify the independence or correlation
+//! assumptions of an experiment.
+//!
+//! ```rust
+//! # #[cfg(all(feature = "error", feature = "latency"))]
+//! # {
+//! use tower::{service_fn, ServiceBuilder};
+//! use tower_fault::{error::ErrorLayer, latency::LatencyLayer, registry::FaultRegistry};
+//! # async fn my_service(_req: u64) -> Result<(), String> {
+//! #     Ok(())
+//! # }
+//!
+//! let registry = FaultRegistry::new();
+//! let service = ServiceBuilder::new()
+//!     // The joint layer must wrap the fault layers.
+//!     .layer(registry.joint_layer())
+//!     .layer(LatencyLayer::new(registry.register("latency", 0.1), 200..500))
+//!     .layer(ErrorLayer::new(registry.register("errors", 0.1), |_: &u64| {
+//!         String::from("error")
+//!     }))
+//!     .service(service_fn(my_service));
+//!
+//! // Later on.
+//! let stats = registry.joint_stats();
+//! let both = stats.both("latency", "errors");
+//! let correlation = stats.correlation("latency", "errors");
+//! # }
+//! ```
+//!
+//! ## Arming delay
+//!
+//! [`FaultRegistry::arm_after`] keeps all the faults of the registry inert
+//! for some time, for example to let health checks pass and traffic
+//! stabilize after the process starts. [`FaultHandle::arm_after`] does the
+//! same for a single fault.
+//!
+//! ```rust
+//! use std::time::Duration;
+//! use tower_fault::{decider::Decider, registry::FaultRegistry};
+//!
+//! let registry = FaultRegistry::new();
+//! registry.arm_after(Duration::from_secs(60));
+//!
+//! let handle = registry.register("db-errors", 1.0);
+//! assert_eq!(false, handle.decide(&()));
+//! ```
+//!
+//! ## Maximum duration
+//!
+//! [`FaultRegistry::disarm_after`] and [`FaultHandle::disarm_after`] stop
+//! the faults from injecting once an experiment has run for the given time,
+//! even if nobody disables them. With the `tracing` feature, each fault
+//! emits a `tracing` event under the `tower_fault` target when it is
+//! disarmed.
+//!
+//! ```rust
+//! use std::time::Duration;
+//! use tower_fault::registry::FaultRegistry;
+//!
+//! let registry = FaultRegistry::new();
+//! registry.disarm_after(Duration::from_secs(15 * 60));
+//! ```
+
+#[cfg(feature = "tokio")]
+mod joint;
+#[cfg(feature = "tokio")]
+#[cfg_attr(docsrs, doc(cfg(feature = "tokio")))]
+pub use self::joint::{JointLayer, JointService, JointStats};
+
+use crate::{
+    control::{ControlMessage, ControlTransport, SharedTransport},
+    decider::{Decider, ExplainStep, Explanation, Suppression, Verdict},
+    describe::{DeciderDescription, DescribeDecider},
+    seed,
+    sync::{Arc, AtomicBool, AtomicU64, Mutex, MutexGuard, Ordering, RwLock},
+    validate::ValidateDecider,
+    veto::{Veto, Vetoed},
+    Error,
+};
+use rand::Rng;
+use std::{
+    any::Any,
+    collections::BTreeMap,
+    fmt,
+    marker::PhantomData,
+    time::{Duration, Instant},
+};
+
+/// Registry of named faults.
+///
+/// Cloning the registry is cheap, and all clones share the same faults.
+#[derive(Clone, Debug)]
+pub struct FaultRegistry {
+    faults: Arc<RwLock<BTreeMap<String, FaultHandle>>>,
+    kill_switch: KillSwitch,
+    max_severity: Arc<AtomicU64>,
+    schedule: Schedule,
+    vetoes: Vetoes,
+    transport: SharedTransport,
+    #[cfg(feature = "tokio")]
+    joint: joint::SharedStats,
+}
+
+impl Default for FaultRegistry {
+    fn default() -> Self {
+        // Generate the experiment seed now, so that it is logged when the
+        // registry is created.
+        seed::seed();
+        let transport = SharedTransport::default();
+        Self {
+            faults: Arc::default(),
+            kill_switch: KillSwitch {
+                engaged: Arc::default(),
+                transport: transport.clone(),
+            },
+            max_severity: Arc::new(AtomicU64::new(Severity::highest() as u64)),
+            schedule: Schedule::default(),
+            vetoes: Vetoes::default(),
+            transport,
+            #[cfg(feature = "tokio")]
+            joint: joint::SharedStats::default(),
+        }
+    }
+}
+
+impl FaultRegistry {
+    /// Create a new empty `FaultRegistry`.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Register a new fault with the given name and probability.
+    ///
+    /// If a fault with the same name is already registered, its handle is
+    /// returned and its settings are left untouched.
+    pub fn register(&self, name: impl Into<String>, probability: f64) -> FaultHandle {
+        let name = name.into();
+        let mut faults = self.faults.write().expect("fault registry lock poisoned");
+        faults
+            .entry(name.clone())
+            .or_insert_with(|| {
+                FaultHandle::new(
+                    name,
+                    probability,
+                    self.kill_switch.clone(),
+                    self.max_severity.clone(),
+                    self.schedule.clone(),
+                    self.vetoes.clone(),
+                    self.transport.clone(),
+                )
+            })
+            .clone()
+    }
+
+    /// Returns the handle of the fault with the given name, if any.
+    pub fn get(&self, name: &str) -> Option<FaultHandle> {
+        let faults = self.faults.read().expect("fault registry lock poisoned");
+        faults.get(name).cloned()
+    }
+
+    /// Returns the handles of all the registered faults, ordered by name.
+    pub fn handles(&self) -> Vec<FaultHandle> {
+        let faults = self.faults.read().expect("fault registry lock poisoned");
+        faults.values().cloned().collect()
+    }
+
+    /// Returns information about all the registered faults, ordered by name.
+    pub fn faults(&self) -> Vec<FaultInfo> {
+        self.handles().iter().map(FaultHandle::info).collect()
+    }
+
+    /// Returns the effective configuration of all the registered faults,
+    /// ordered by name.
+    pub fn effective(&self) -> Vec<EffectiveConfig> {
+        self.handles().iter().map(FaultHandle::effective).collect()
+    }
+
+    /// Returns the current state of the registry and of all its faults.
+    ///
+    /// Arming and disarming delays are tied to the current process, and are
+    /// not part of the snapshot.
+    pub fn snapshot(&self) -> RegistryState {
+        RegistryState {
+            kill_switch: self.kill_switch.is_engaged(),
+            max_severity: self.max_severity(),
+            seed: seed::seed(),
+            faults: self.handles().iter().map(FaultHandle::snapshot).collect(),
+        }
+    }
+
+    /// Restore a state returned by [`FaultRegistry::snapshot`].
+    ///
+    /// The faults of the state are registered if needed, and their settings
+    /// are replaced. The faults missing from the state are left untouched.
+    pub fn restore(&self, state: &RegistryState) {
+        if state.kill_switch {
+            self.kill_switch.engage();
+        } else {
+            self.kill_switch.release();
+        }
+        self.set_max_severity(state.max_severity);
+        for fault in &state.faults {
+            self.register(fault.name.as_str(), fault.probability)
+                .restore(fault);
+        }
+    }
+
+    /// Broadcast the changes made to this registry to the other replicas
+    /// with the given transport.
+    ///
+    /// See the [`control`](crate::control) module for more information.
+    pub fn set_transport(&self, transport: impl ControlTransport + 'static) {
+        self.transport.set(std::sync::Arc::new(transport));
+    }
+
+    /// Apply a change published by another replica.
+    ///
+    /// Changes to the faults that are not registered are ignored. Applying a
+    /// change doesn't publish it again.
+    pub fn apply(&self, message: &ControlMessage) {
+        let handle = |fault: &str| self.get(fault).map(|handle| handle.state);
+        match message {
+            ControlMessage::Enable { fault } => {
+                if let Some(state) = handle(fault) {
+                    state.enabled.store(true, Ordering::Relaxed);
+                }
+            }
+            ControlMessage::Disable { fault } => {
+                if let Some(state) = handle(fault) {
+                    state.enabled.store(false, Ordering::Relaxed);
+                }
+            }
+            ControlMessage::Pause { fault } => {
+                if let Some(state) = handle(fault) {
+                    state.paused.store(true, Ordering::Relaxed);
+                }
+            }
+            ControlMessage::Resume { fault } => {
+                if let Some(state) = handle(fault) {
+                    state.paused.store(false, Ordering::Relaxed);
+                }
+            }
+            ControlMessage::SetProbability { fault, probability } => {
+                if let Some(state) = handle(fault) {
+                    state.store_probability(*probability);
+                }
+            }
+            ControlMessage::KillSwitch { engaged } => {
+                self.kill_switch.engaged.store(*engaged, Ordering::Relaxed);
+            }
+            ControlMessage::SetSeverity { fault, severity } => {
+                if let Some(state) = handle(fault) {
+                    state.severity.store(*severity as u64, Ordering::Relaxed);
+                }
+            }
+            ControlMessage::MaxSeverity { severity } => {
+                self.max_severity.store(*severity as u64, Ordering::Relaxed);
+            }
+        }
+    }
+
+    /// Returns the kill switch shared by all the faults of this registry.
+    pub fn kill_switch(&self) -> KillSwitch {
+        self.kill_switch.clone()
+    }
+
+    /// Only allow the faults of this registry up to the given severity to
+    /// inject.
+    ///
+    /// The faults above this severity don't inject, regardless of their
+    /// settings. By default, faults of all severities can inject.
+    pub fn set_max_severity(&self, severity: Severity) {
+        self.max_severity.store(severity as u64, Ordering::Relaxed);
+        self.transport
+            .publish(ControlMessage::MaxSeverity { severity });
+    }
+
+    /// Returns the maximum severity of the faults allowed to inject.
+    pub fn max_severity(&self) -> Severity {
+        Severity::from_u64(self.max_severity.load(Ordering::Relaxed))
+    }
+
+    /// Never inject the faults of this registry, including the ones
+    /// registered later, into the requests vetoed by the given veto.
+    ///
+    /// The veto only applies to the faults deciding on requests of type `R`.
+    /// Vetoed requests are reported as suppressed, without evaluating the
+    /// faults. Vetoes are local to the registry, and aren't replicated by
+    /// the [control transport](crate::control).
+    ///
+    /// See the [`veto`](crate::veto) module for more information.
+    pub fn add_veto<R, V>(&self, veto: V)
+    where
+        R: 'static,
+        V: Veto<R> + Send + Sync + 'static,
+    {
+        self.vetoes
+            .0
+            .write()
+            .expect("fault registry lock poisoned")
+            .push(Arc::new(TypedVeto(veto, PhantomData)));
+    }
+
+    /// Keep all the faults of this registry, including the ones registered
+    /// later, from injecting until the given delay has elapsed.
+    ///
+    /// The delay starts when this method is called, and replaces any
+    /// previous delay set on the registry.
+    pub fn arm_after(&self, delay: Duration) {
+        self.schedule.arm_after(delay);
+    }
+
+    /// Stop all the faults of this registry, including the ones registered
+    /// later, from injecting once the given delay has elapsed.
+    ///
+    /// The delay starts when this method is called, and replaces any
+    /// previous delay set on the registry.
+    pub fn disarm_after(&self, delay: Duration) {
+        self.schedule.disarm_after(delay);
+        for handle in self.handles() {
+            handle.state.disarmed.store(false, Ordering::Relaxed);
+        }
+    }
+}
+
+/// Vetoes shared by all the faults of a [`FaultRegistry`].
+#[derive(Clone, Default)]
+struct Vetoes(Arc<RwLock<Vec<Arc<dyn AnyVeto>>>>);
+
+impl Vetoes {
+    fn veto<R: 'static>(&self, req: &R) -> bool {
+        self.0
+            .read()
+            .expect("fault registry lock poisoned")
+            .iter()
+            .any(|veto| veto.veto_any(req))
+    }
+}
+
+impl fmt::Debug for Vetoes {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let len = self.0.read().map(|vetoes| vetoes.len()).unwrap_or(0);
+        f.debug_struct("Vetoes").field("len", &len).finish()
+    }
+}
+
+/// Veto for any request type, only vetoing the requests of its own type.
+trait AnyVeto: Send + Sync {
+    fn veto_any(&self, req: &dyn Any) -> bool;
+}
+
+struct TypedVeto<R, V>(V, PhantomData<fn(&R)>);
+
+impl<R, V> AnyVeto for TypedVeto<R, V>
+where
+    R: 'static,
+    V: Veto<R> + Send + Sync,
+{
+    fn veto_any(&self, req: &dyn Any) -> bool {
+        req.downcast_ref::<R>().is_some_and(|req| self.0.veto(req))
+    }
+}
+
+/// Kill switch shared by all the faults of a [`FaultRegistry`].
+///
+/// While the kill switch is engaged, none of the faults of the registry
+/// inject, regardless of their settings.
+#[derive(Clone, Debug, Default)]
+pub struct KillSwitch {
+    engaged: Arc<AtomicBool>,
+    transport: SharedTransport,
+}
+
+impl KillSwitch {
+    /// Engage the kill switch, stopping all faults from injecting.
+    pub fn engage(&self) {
+        self.engaged.store(true, Ordering::Relaxed);
+        self.transport
+            .publish(ControlMessage::KillSwitch { engaged: true });
+    }
+
+    /// Release the kill switch, letting faults inject again.
+    pub fn release(&self) {
+        self.engaged.store(false, Ordering::Relaxed);
+        self.transport
+            .publish(ControlMessage::KillSwitch { engaged: false });
+    }
+
+    /// Returns `true` if the kill switch is engaged.
+    pub fn is_engaged(&self) -> bool {
+        self.engaged.load(Ordering::Relaxed)
+    }
+}
+
+/// Severity of a fault.
+///
+/// Severities are ordered from [`Severity::Minor`] to
+/// [`Severity::Critical`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+pub enum Severity {
+    /// Fault with a limited impact, such as a small latency or a transient
+    /// error.
+    #[default]
+    Minor,
+    /// Fault with a significant impact.
+    Major,
+    /// Fault that can cause an outage.
+    Critical,
+}
+
+impl Severity {
+    fn highest() -> Self {
+        Severity::Critical
+    }
+
+    fn from_u64(value: u64) -> Self {
+        match value {
+            0 => Severity::Minor,
+            1 => Severity::Major,
+            _ => Severity::Critical,
+        }
+    }
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Severity::Minor => "minor",
+            Severity::Major => "major",
+            Severity::Critical => "critical",
+        })
+    }
+}
+
+/// Handle to a fault registered in a [`FaultRegistry`].
+///
+/// The handle implements the [`Decider`] trait: it decides to inject a fault
+/// if the fault is enabled and the registry's [`KillSwitch`] is not engaged,
+/// using the current probability.
+#[derive(Clone, Debug)]
+pub struct FaultHandle {
+    state: Arc<FaultState>,
+}
+
+#[derive(Debug)]
+struct FaultState {
+    name: String,
+    enabled: AtomicBool,
+    paused: AtomicBool,
+    probability: AtomicU64,
+    severity: AtomicU64,
+    kill_switch: KillSwitch,
+    max_severity: Arc<AtomicU64>,
+    schedule: Schedule,
+    registry_schedule: Schedule,
+    vetoes: Vetoes,
+    disarmed: AtomicBool,
+    triggers: AtomicU64,
+    transport: SharedTransport,
+}
+
+impl FaultState {
+    fn store_probability(&self, probability: f64) {
+        self.probability
+            .store(clamp(probability).to_bits(), Ordering::Relaxed);
+    }
+}
+
+impl FaultHandle {
+    fn new(
+        name: String,
+        probability: f64,
+        kill_switch: KillSwitch,
+        max_severity: Arc<AtomicU64>,
+        registry_schedule: Schedule,
+        vetoes: Vetoes,
+        transport: SharedTransport,
+    ) -> Self {
+        Self {
+            state: Arc::new(FaultState {
+                name,
+                enabled: AtomicBool::new(true),
+                paused: AtomicBool::new(false),
+                probability: AtomicU64::new(clamp(probability).to_bits()),
+                severity: AtomicU64::new(Severity::default() as u64),
+                kill_switch,
+                max_severity,
+                schedule: Schedule::default(),
+                registry_schedule,
+                vetoes,
+                disarmed: AtomicBool::new(false),
+                triggers: AtomicU64::new(0),
+                transport,
+            }),
+        }
+    }
+
+    /// Returns the name of the fault.
+    pub fn name(&self) -> &str {
+        &self.state.name
+    }
+
+    /// Enable the fault.
+    pub fn enable(&self) {
+        self.state.enabled.store(true, Ordering::Relaxed);
+        self.state.transport.publish(ControlMessage::Enable {
+            fault: self.name().to_string(),
+        });
+    }
+
+    /// Disable the fault.
+    pub fn disable(&self) {
+        self.state.enabled.store(false, Ordering::Relaxed);
+        self.state.transport.publish(ControlMessage::Disable {
+            fault: self.name().to_string(),
+        });
+    }
+
+    /// Returns `true` if the fault is enabled.
+    pub fn is_enabled(&self) -> bool {
+        self.state.enabled.load(Ordering::Relaxed)
+    }
+
+    /// Temporarily stop injecting the fault.
+    ///
+    /// Unlike [`FaultHandle::disable`], pausing is meant to be undone with
+    /// [`FaultHandle::resume`], for example when a guardrail trips: the
+    /// settings, the pending triggers and the state of the decider wrapping
+    /// the handle are preserved, and time keeps running for the arming
+    /// window.
+    pub fn pause(&self) {
+        self.state.paused.store(true, Ordering::Relaxed);
+        self.state.transport.publish(ControlMessage::Pause {
+            fault: self.name().to_string(),
+        });
+    }
+
+    /// Resume injecting a paused fault.
+    pub fn resume(&self) {
+        self.state.paused.store(false, Ordering::Relaxed);
+        self.state.transport.publish(ControlMessage::Resume {
+            fault: self.name().to_string(),
+        });
+    }
+
+    /// Returns `true` if the fault is paused.
+    pub fn is_paused(&self) -> bool {
+        self.state.paused.load(Ordering::Relaxed)
+    }
+
+    /// Keep the fault from injecting until the given delay has elapsed.
+    ///
+    /// The delay starts when this method is called, and replaces any
+    /// previous delay set on the fault. The delay set on the registry with
+    /// [`FaultRegistry::arm_after`] still applies.
+    pub fn arm_after(&self, delay: Duration) {
+        self.state.schedule.arm_after(delay);
+    }
+
+    /// Stop the fault from injecting once the given delay has elapsed.
+    ///
+    /// The delay starts when this method is called, and replaces any
+    /// previous delay set on the fault. The delay set on the registry with
+    /// [`FaultRegistry::disarm_after`] still applies.
+    pub fn disarm_after(&self, delay: Duration) {
+        self.state.schedule.disarm_after(delay);
+        self.state.disarmed.store(false, Ordering::Relaxed);
+    }
+
+    /// Returns `true` if the arming delays of the fault and its registry
+    /// have elapsed, and neither of them has been disarmed yet.
+    pub fn is_armed(&self) -> bool {
+        let now = Instant::now();
+        let windows = [
+            self.state.schedule.window(),
+            self.state.registry_schedule.window(),
+        ];
+        if windows
+            .iter()
+            .any(|w| w.armed_at.is_some_and(|at| now < at))
+        {
+            return false;
+        }
+        if windows
+            .iter()
+            .any(|w| w.disarm_at.is_some_and(|at| now >= at))
+        {
+            if !self.state.disarmed.swap(true, Ordering::Relaxed) {
+                #[cfg(feature = "tracing")]
+                tracing::info!(target: "tower_fault", fault = self.name(), "fault disarmed");
+            }
+            return false;
+        }
+        true
+    }
+
+    /// Set the probability of injecting the fault.
+    ///
+    /// The probability is clamped between 0.0 and 1.0.
+    pub fn set_probability(&self, probability: f64) {
+        self.state.store_probability(probability);
+        self.state
+            .transport
+            .publish(ControlMessage::SetProbability {
+                fault: self.name().to_string(),
+                probability: self.probability(),
+            });
+    }
+
+    /// Returns the probability of injecting the fault.
+    pub fn probability(&self) -> f64 {
+        f64::from_bits(self.state.probability.load(Ordering::Relaxed))
+    }
+
+    /// Set the severity of the fault.
+    ///
+    /// See [`FaultRegistry::set_max_severity`] to cap the severity of the
+    /// faults allowed to inject.
+    pub fn set_severity(&self, severity: Severity) {
+        self.state
+            .severity
+            .store(severity as u64, Ordering::Relaxed);
+        self.state.transport.publish(ControlMessage::SetSeverity {
+            fault: self.name().to_string(),
+            severity,
+        });
+    }
+
+    /// Returns the severity of the fault.
+    pub fn severity(&self) -> Severity {
+        Severity::from_u64(self.state.severity.load(Ordering::Relaxed))
+    }
+
+    /// Returns `true` if the severity of the fault is allowed by its
+    /// registry.
+    fn is_allowed(&self) -> bool {
+        self.state.severity.load(Ordering::Relaxed)
+            <= self.state.max_severity.load(Ordering::Relaxed)
+    }
+
+    /// Force the fault to be injected into the next `n` requests, regardless
+    /// of its probability.
+    ///
+    /// Triggered faults are injected even if the fault is disabled or
+    /// outside of its arming window, but not while the [`KillSwitch`] is
+    /// engaged or the severity of the fault is above the maximum allowed by
+    /// its registry. Calling this again adds to the pending triggers.
+    pub fn trigger(&self, n: u64) {
+        self.state.triggers.fetch_add(n, Ordering::Relaxed);
+    }
+
+    /// Returns the number of requests that will still be faulted because of
+    /// [`FaultHandle::trigger`].
+    pub fn pending_triggers(&self) -> u64 {
+        self.state.triggers.load(Ordering::Relaxed)
+    }
+
+    fn take_trigger(&self) -> bool {
+        self.state
+            .triggers
+            .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |n| n.checked_sub(1))
+            .is_ok()
+    }
+
+    /// Record that the fault was injected into the current request.
+    fn injected(&self) {
+        #[cfg(feature = "tokio")]
+        joint::record(self.name());
+    }
+
+    /// Returns `true` if the settings of the fault approve a fault.
+    fn approve(&self) -> bool {
+        self.is_enabled() && self.is_armed() && crate::seed::rng().gen_bool(self.probability())
+    }
+
+    fn snapshot(&self) -> FaultSnapshot {
+        FaultSnapshot {
+            name: self.name().to_string(),
+            enabled: self.is_enabled(),
+            paused: self.is_paused(),
+            probability: self.probability(),
+            severity: self.severity(),
+            pending_triggers: self.pending_triggers(),
+        }
+    }
+
+    fn restore(&self, snapshot: &FaultSnapshot) {
+        self.state
+            .enabled
+            .store(snapshot.enabled, Ordering::Relaxed);
+        self.state.paused.store(snapshot.paused, Ordering::Relaxed);
+        self.state.store_probability(snapshot.probability);
+        self.state
+            .severity
+            .store(snapshot.severity as u64, Ordering::Relaxed);
+        self.state
+            .triggers
+            .store(snapshot.pending_triggers, Ordering::Relaxed);
+    }
+
+    /// Returns a decider for this fault that never injects into the requests
+    /// vetoed by the given veto.
+    ///
+    /// See the [`veto`](crate::veto) module for more information.
+    pub fn with_veto<V>(&self, veto: V) -> Vetoed<FaultHandle, V> {
+        Vetoed::new(self.clone(), veto)
+    }
+
+    /// Returns the effective configuration of the fault.
+    ///
+    /// Unlike [`FaultHandle::info`], this takes into account everything that
+    /// keeps the fault from injecting.
+    pub fn effective(&self) -> EffectiveConfig {
+        let mut inactive = Vec::new();
+        if !self.is_enabled() {
+            inactive.push(InactiveReason::Disabled);
+        }
+        if self.is_paused() {
+            inactive.push(InactiveReason::Paused);
+        }
+        let now = Instant::now();
+        let windows = [
+            self.state.schedule.window(),
+            self.state.registry_schedule.window(),
+        ];
+        if windows
+            .iter()
+            .any(|w| w.armed_at.is_some_and(|at| now < at))
+        {
+            inactive.push(InactiveReason::NotArmed);
+        } else if windows
+            .iter()
+            .any(|w| w.disarm_at.is_some_and(|at| now >= at))
+        {
+            inactive.push(InactiveReason::Disarmed);
+        }
+        if self.state.kill_switch.is_engaged() {
+            inactive.push(InactiveReason::KillSwitch);
+        }
+        if !self.is_allowed() {
+            inactive.push(InactiveReason::Severity);
+        }
+
+        // Triggers bypass the settings and the arming window, but not the
+        // other reasons.
+        let pending_triggers = self.pending_triggers();
+        let effective_probability = if inactive.iter().any(|reason| {
+            matches!(
+                reason,
+                InactiveReason::Paused | InactiveReason::KillSwitch | InactiveReason::Severity
+            )
+        }) {
+            0.0
+        } else if pending_triggers > 0 {
+            1.0
+        } else if inactive.is_empty() {
+            self.probability()
+        } else {
+            0.0
+        };
+
+        EffectiveConfig {
+            name: self.name().to_string(),
+            probability: self.probability(),
+            effective_probability,
+            severity: self.severity(),
+            pending_triggers,
+            inactive,
+        }
+    }
+
+    /// Returns information about the current settings of the fault.
+    pub fn info(&self) -> FaultInfo {
+        FaultInfo {
+            name: self.name().to_string(),
+            enabled: self.is_enabled(),
+            probability: self.probability(),
+            severity: self.severity(),
+        }
+    }
+}
+
+/// Requests vetoed by the registry are never faulted, so the request type
+/// must be `'static`.
+impl<R: 'static> Decider<R> for FaultHandle {
+    fn decide(&self, req: &R) -> bool {
+        let inject = !self.state.vetoes.veto(req)
+            && !self.is_paused()
+            && !self.state.kill_switch.is_engaged()
+            && self.is_allowed()
+            && (self.take_trigger() || self.approve());
+        if inject {
+            self.injected();
+        }
+        inject
+    }
+
+    fn verdict(&self, req: &R) -> Verdict {
+        if self.state.vetoes.veto(req) {
+            Verdict::Suppressed(Suppression::Veto)
+        } else if self.is_paused() {
+            Verdict::Pass
+        } else if self.state.kill_switch.is_engaged() {
+            if self.pending_triggers() > 0 || self.approve() {
+                Verdict::Suppressed(Suppression::KillSwitch)
+            } else {
+                Verdict::Pass
+            }
+        } else if !self.is_allowed() {
+            if self.pending_triggers() > 0 || self.approve() {
+                Verdict::Suppressed(Suppression::Severity)
+            } else {
+                Verdict::Pass
+            }
+        } else if self.take_trigger() || self.approve() {
+            self.injected();
+            Verdict::Inject
+        } else {
+            Verdict::Pass
+        }
+    }
+
+    fn explain(&self, req: &R, explanation: &mut Explanation) -> Verdict {
+        let effective = self.effective();
+        let verdict = self.verdict(req);
+        let mut detail = format!("probability {}", effective.effective_probability);
+        if effective.pending_triggers > 0 {
+            detail.push_str(&format!(
+                ", {} pending triggers",
+                effective.pending_triggers
+            ));
+        }
+        if !effective.inactive.is_empty() {
+            detail.push_str(&format!(", inactive {:?}", effective.inactive));
+        }
+        explanation.record(
+            ExplainStep::new(format!("registry '{}'", self.name()), verdict).with_detail(detail),
+        );
+        verdict
+    }
+}
+
+impl ValidateDecider for FaultHandle {
+    fn validate_decider(&self) -> Result<(), Error> {
+        Ok(())
+    }
+}
+
+impl DescribeDecider for FaultHandle {
+    fn describe_decider(&self) -> DeciderDescription {
+        let probability = if self.is_enabled()
+            && !self.is_paused()
+            && self.is_armed()
+            && !self.state.kill_switch.is_engaged()
+            && self.is_allowed()
+        {
+            self.probability()
+        } else {
+            0.0
+        };
+        DeciderDescription::new(format!("registry '{}'", self.name()), Some(probability))
+    }
+}
+
+/// Information about the current settings of a fault.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct FaultInfo {
+    /// Name of the fault.
+    pub name: String,
+    /// Whether the fault is enabled.
+    pub enabled: bool,
+    /// Probability of injecting the fault.
+    pub probability: f64,
+    /// Severity of the fault.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub severity: Severity,
+}
+
+/// Effective configuration of a fault, returned by
+/// [`FaultHandle::effective`].
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct EffectiveConfig {
+    /// Name of the fault.
+    pub name: String,
+    /// Configured probability of injecting the fault.
+    pub probability: f64,
+    /// Probability of injecting the fault into the next request.
+    pub effective_probability: f64,
+    /// Severity of the fault.
+    pub severity: Severity,
+    /// Number of requests that will be faulted because of
+    /// [`FaultHandle::trigger`].
+    pub pending_triggers: u64,
+    /// Reasons why the fault can't inject, if any.
+    pub inactive: Vec<InactiveReason>,
+}
+
+/// Reason why a fault can't inject, in an [`EffectiveConfig`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+#[non_exhaustive]
+pub enum InactiveReason {
+    /// The fault is disabled.
+    Disabled,
+    /// The fault is paused.
+    Paused,
+    /// The arming delay of the fault or its registry hasn't elapsed yet.
+    NotArmed,
+    /// The fault or its registry has been disarmed.
+    Disarmed,
+    /// The kill switch of the registry is engaged.
+    KillSwitch,
+    /// The severity of the fault is above the maximum allowed by its
+    /// registry.
+    Severity,
+}
+
+/// Times during which faults can inject, shared between clones.
+#[derive(Clone, Debug, Default)]
+struct Schedule {
+    window: Arc<Mutex<Window>>,
+}
+
+#[derive(Clone, Copy, Debug, Default)]
+struct Window {
+    armed_at: Option<Instant>,
+    disarm_at: Option<Instant>,
+}
+
+impl Schedule {
+    fn arm_after(&self, delay: Duration) {
+        self.lock().armed_at = Some(Instant::now() + delay);
+    }
+
+    fn disarm_after(&self, delay: Duration) {
+        self.lock().disarm_at = Some(Instant::now() + delay);
+    }
+
+    fn window(&self) -> Window {
+        *self.lock()
+    }
+
+    fn lock(&self) -> MutexGuard<'_, Window> {
+        self.window.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+/// State of a [`FaultRegistry`], returned by [`FaultRegistry::snapshot`].
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct RegistryState {
+    /// Whether the kill switch is engaged.
+    pub kill_switch: bool,
+    /// Maximum severity of the faults allowed to inject.
+    #[cfg_attr(feature = "serde", serde(default = "Severity::highest"))]
+    pub max_severity: Severity,
+    /// [Experiment seed](crate::seed) used for the random decisions.
+    ///
+    /// Restoring a state doesn't change the seed of the current process.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub seed: u64,
+    /// State of the faults, ordered by name.
+    pub faults: Vec<FaultSnapshot>,
+}
+
+impl Default for RegistryState {
+    fn default() -> Self {
+        Self {
+            kill_switch: false,
+            max_severity: Severity::highest(),
+            seed: 0,
+            faults: Vec::new(),
+        }
+    }
+}
+
+/// State of a fault in a [`RegistryState`].
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct FaultSnapshot {
+    /// Name of the fault.
+    pub name: String,
+    /// Whether the fault is enabled.
+    pub enabled: bool,
+    /// Whether the fault is paused.
+    pub paused: bool,
+    /// Probability of injecting the fault.
+    pub probability: f64,
+    /// Severity of the fault.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub severity: Severity,
+    /// Number of requests that will be faulted because of
+    /// [`FaultHandle::trigger`].
+    pub pending_triggers: u64,
+}
+
+fn clamp(probability: f64) -> f64 {
+    if probability.is_nan() {
+        0.0
+    } else {
+        probability.clamp(0.0, 1.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn registry_shares_handles() {
+        let registry = FaultRegistry::new();
+        let handle = registry.register("fault", 1.0);
+
+        assert!(handle.decide(&()));
+
+        registry.get("fault").unwrap().disable();
+        assert!(!handle.decide(&()));
+
+        // Registering the same name again returns the existing fault.
+        let other = registry.register("fault", 0.0);
+        assert!(!other.is_enabled());
+        assert_eq!(other.probability(), 1.0);
+    }
+
+    #[test]
+    fn registry_kill_switch() {
+        let registry = FaultRegistry::new();
+        let handle = registry.register("fault", 1.0);
+
+        registry.kill_switch().engage();
+        assert!(!handle.decide(&()));
+        assert!(handle.is_enabled());
+        assert_eq!(
+            handle.verdict(&()),
+            Verdict::Suppressed(Suppression::KillSwitch)
+        );
+
+        registry.kill_switch().release();
+        assert!(handle.decide(&()));
+    }
+
+    #[test]
+    fn registry_arm_after() {
+        let registry = FaultRegistry::new();
+        let handle = registry.register("fault", 1.0);
+
+        handle.arm_after(Duration::from_secs(60));
+        assert!(!handle.is_armed());
+        assert_eq!(handle.verdict(&()), Verdict::Pass);
+        handle.arm_after(Duration::ZERO);
+        assert!(handle.decide(&()));
+
+        // The registry delay applies to the faults registered later.
+        registry.arm_after(Duration::from_secs(60));
+        let other = registry.register("other", 1.0);
+        assert!(!handle.decide(&()));
+        assert!(!other.decide(&()));
+    }
+
+    #[test]
+    fn registry_disarm_after() {
+        let registry = FaultRegistry::new();
+        let handle = registry.register("fault", 1.0);
+
+        handle.disarm_after(Duration::from_secs(60));
+        assert!(handle.decide(&()));
+        handle.disarm_after(Duration::ZERO);
+        assert!(!handle.is_armed());
+        assert!(handle.is_enabled());
+        assert_eq!(handle.verdict(&()), Verdict::Pass);
+
+        registry.disarm_after(Duration::ZERO);
+        assert!(!registry.register("other", 1.0).decide(&()));
+    }
+
+    #[test]
+    fn registry_trigger() {
+        let registry = FaultRegistry::new();
+        let handle = registry.register("fault", 0.0);
+        handle.disable();
+
+        handle.trigger(2);
+        assert_eq!(handle.pending_triggers(), 2);
+        assert!(handle.decide(&()));
+
+        registry.kill_switch().engage();
+        assert_eq!(
+            handle.verdict(&()),
+            Verdict::Suppressed(Suppression::KillSwitch)
+        );
+        assert_eq!(handle.pending_triggers(), 1);
+
+        registry.kill_switch().release();
+        assert_eq!(handle.verdict(&()), Verdict::Inject);
+        assert!(!handle.decide(&()));
+    }
+
+    #[test]
+    fn registry_pause() {
+        let registry = FaultRegistry::new();
+        let handle = registry.register("fault", 1.0);
+        handle.trigger(1);
+
+        handle.pause();
+        assert!(handle.is_paused());
+        assert!(handle.is_enabled());
+        assert!(!handle.decide(&()));
+        assert_eq!(handle.verdict(&()), Verdict::Pass);
+        assert_eq!(handle.pending_triggers(), 1);
+
+        handle.resume();
+        assert!(handle.decide(&()));
+        assert_eq!(handle.pending_triggers(), 0);
+    }
+
+    #[test]
+    fn registry_severity() {
+        let registry = FaultRegistry::new();
+        let minor = registry.register("minor", 1.0);
+        let critical = registry.register("critical", 1.0);
+        critical.set_severity(Severity::Critical);
+        critical.trigger(1);
+
+        registry.set_max_severity(Severity::Minor);
+        assert!(minor.decide(&()));
+        assert!(!critical.decide(&()));
+        assert_eq!(
+            critical.verdict(&()),
+            Verdict::Suppressed(Suppression::Severity)
+        );
+        assert_eq!(critical.pending_triggers(), 1);
+
+        registry.set_max_severity(Severity::Critical);
+        assert!(critical.decide(&()));
+        assert_eq!(critical.pending_triggers(), 0);
+    }
+
+    #[test]
+    fn registry_effective() {
+        let registry = FaultRegistry::new();
+        let handle = registry.register("fault", 0.5);
+        let effective = handle.effective();
+        assert_eq!(effective.effective_probability, 0.5);
+        assert!(effective.inactive.is_empty());
+
+        handle.disable();
+        handle.arm_after(Duration::from_secs(60));
+        let effective = handle.effective();
+        assert_eq!(effective.probability, 0.5);
+        assert_eq!(effective.effective_probability, 0.0);
+        assert_eq!(
+            effective.inactive,
+            vec![InactiveReason::Disabled, InactiveReason::NotArmed]
+        );
+
+        // Triggers bypass the settings.
+        handle.trigger(1);
+        assert_eq!(handle.effective().effective_probability, 1.0);
+
+        handle.set_severity(Severity::Major);
+        registry.set_max_severity(Severity::Minor);
+        let effective = registry.effective().remove(0);
+        assert_eq!(effective.effective_probability, 0.0);
+        assert_eq!(effective.inactive.last(), Some(&InactiveReason::Severity));
+    }
+
+    #[test]
+    fn registry_snapshot() {
+        let registry = FaultRegistry::new();
+        registry.register("latency", 0.5).pause();
+        registry.register("error", 1.0).trigger(3);
+        registry.kill_switch().engage();
+        let state = registry.snapshot();
+        assert_eq!(state.faults[0].name, "error");
+
+        let other = FaultRegistry::new();
+        other.register("latency", 0.0);
+        other.register("other", 1.0);
+        other.restore(&state);
+        assert_eq!(other.snapshot().faults.len(), 3);
+        assert!(other.kill_switch().is_engaged());
+
+        let latency = other.get("latency").unwrap();
+        assert!(latency.is_paused());
+        assert_eq!(latency.probability(), 0.5);
+        assert_eq!(other.get("error").unwrap().pending_triggers(), 3);
+    }
+
+    #[test]
+    fn registry_transport() {
+        struct Recorder(Arc<Mutex<Vec<ControlMessage>>>);
+        impl ControlTransport for Recorder {
+            fn publish(&self, message: &ControlMessage) {
+                self.0.lock().unwrap().push(message.clone());
+            }
+        }
+
+        let messages = Arc::new(Mutex::new(Vec::new()));
+        let registry = FaultRegistry::new();
+        let handle = registry.register("fault", 1.0);
+        registry.set_transport(Recorder(messages.clone()));
+
+        handle.set_probability(1.5);
+        registry.kill_switch().engage();
+        let published = messages.lock().unwrap().clone();
+        assert_eq!(
+            published,
+            vec![
+                ControlMessage::SetProbability {
+                    fault: "fault".to_string(),
+                    probability: 1.0
+                },
+                ControlMessage::KillSwitch { engaged: true },
+            ]
+        );
+
+        // Applying a message doesn't publish it again.
+        let replica = FaultRegistry::new();
+        let other = replica.register("fault", 0.0);
+        for message in &published {
+            replica.apply(message);
+            registry.apply(message);
+        }
+        assert_eq!(other.probability(), 1.0);
+        assert!(replica.kill_switch().is_engaged());
+        assert_eq!(messages.lock().unwrap().len(), 2);
+    }
+
+    #[test]
+    fn registry_clamps_probability() {
+        let registry = FaultRegistry::new();
+        let handle = registry.register("fault", 1.5);
+        assert_eq!(handle.probability(), 1.0);
+
+        handle.set_probability(-1.0);
+        assert_eq!(handle.probability(), 0.0);
+
+        handle.set_probability(f64::NAN);
+        assert_eq!(handle.probability(), 0.0);
+    }
+}
+
+#[cfg(all(test, tower_fault_loom, feature = "test-determinism"))]
+mod loom_tests {
+    use super::*;
+    use loom::thread;
+
+    #[test]
+    fn trigger_concurrent() {
+        loom::model(|| {
+            let registry = FaultRegistry::new();
+            let handle = registry.register("error", 0.0);
+            handle.trigger(1);
+
+            let other = handle.clone();
+            let thread = thread::spawn(move || other.decide(&()));
+            let injected = handle.decide(&());
+            assert!(injected ^ thread.join().unwrap());
+            assert_eq!(handle.pending_triggers(), 0);
+        });
+    }
+
+    #[test]
+    fn kill_switch_concurrent() {
+        loom::model(|| {
+            let registry = FaultRegistry::new();
+            let handle = registry.register("error", 1.0);
+
+            let kill_switch = registry.kill_switch();
+            let thread = thread::spawn(move || kill_switch.engage());
+            handle.decide(&());
+            thread.join().unwrap();
+            assert!(!handle.decide(&()));
+        });
+    }
+}
@@ -0,0 +1,127 @@
+//! # `reqwest` integration
+//!
+//! This module contains [`reqwest_middleware::Middleware`]s that inject
+//! faults into outbound HTTP calls made with [`reqwest`], using the same
+//! deciders and distributions as the tower layers.
+//!
+//! ## Example
+//!
+//! ```rust
+//! use reqwest_middleware::{ClientBuilder, Error};
+//! use tower_fault::reqwest::{ErrorMiddleware, LatencyMiddleware};
+//!
+//! let client = ClientBuilder::new(reqwest::Client::new())
+//!     // Inject 200 to 500 milliseconds of latency 10% of the time.
+//!     .with(LatencyMiddleware::new(0.1, 200..500))
+//!     // Fail 5% of the requests without sending them.
+//!     .with(ErrorMiddleware::new(0.05, |_: &reqwest::Request| {
+//!         Error::Middleware(anyhow::anyhow!("injected error"))
+//!     }))
+//!     .build();
+//! ```
+
+use crate::{decider::Decider, latency::Distribution};
+use reqwest::{Request, Response};
+use reqwest_middleware::{Error, Middleware, Next, Result};
+use task_local_extensions::Extensions;
+use tokio::time;
+
+/// Middleware that randomly adds latency to outbound requests.
+///
+/// The latency is added before the request is sent.
+#[derive(Clone, Debug)]
+pub struct LatencyMiddleware<De, Di> {
+    decider: De,
+    distribution: Di,
+}
+
+impl<De, Di> LatencyMiddleware<De, Di> {
+    /// Create a new `LatencyMiddleware` with the given decider and latency
+    /// distribution.
+    pub fn new(decider: De, distribution: Di) -> Self {
+        Self {
+            decider,
+            distribution,
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl<De, Di> Middleware for LatencyMiddleware<De, Di>
+where
+    De: Decider<Request> + Send + Sync + 'static,
+    Di: Distribution<Request> + Send + Sync + 'static,
+{
+    async fn handle(
+        &self,
+        req: Request,
+        extensions: &mut Extensions,
+        next: Next<'_>,
+    ) -> Result<Response> {
+        if self.decider.decide(&req) {
+            time::sleep(self.distribution.sample(&req)).await;
+        }
+        next.run(req, extensions).await
+    }
+}
+
+/// Middleware that randomly fails outbound requests instead of sending them.
+#[derive(Clone, Debug)]
+pub struct ErrorMiddleware<D, G> {
+    decider: D,
+    generator: G,
+}
+
+impl<D, G> ErrorMiddleware<D, G> {
+    /// Create a new `ErrorMiddleware` with the given decider and error
+    /// generator.
+    pub fn new(decider: D, generator: G) -> Self {
+        Self { decider, generator }
+    }
+}
+
+#[async_trait::async_trait]
+impl<D, G> Middleware for ErrorMiddleware<D, G>
+where
+    D: Decider<Request> + Send + Sync + 'static,
+    G: Fn(&Request) -> Error + Send + Sync + 'static,
+{
+    async fn handle(
+        &self,
+        req: Request,
+        extensions: &mut Extensions,
+        next: Next<'_>,
+    ) -> Result<Response> {
+        if self.decider.decide(&req) {
+            return Err((self.generator)(&req));
+        }
+        next.run(req, extensions).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use reqwest_middleware::ClientBuilder;
+
+    #[tokio::test]
+    async fn reqwest_error_skips_request() {
+        let client = ClientBuilder::new(reqwest::Client::new())
+            .with(ErrorMiddleware::new(true, |req: &Request| {
+                Error::Middleware(anyhow::anyhow!("injected: {}", req.url()))
+            }))
+            .build();
+
+        let err = client
+            .get("http://127.0.0.1:1/resource")
+            .send()
+            .await
+            .unwrap_err();
+        match err {
+            Error::Middleware(err) => {
+                assert_eq!(err.to_string(), "injected: http://127.0.0.1:1/resource")
+            }
+            err => panic!("unexpected error: {}", err),
+        }
+    }
+}
@@ -0,0 +1,380 @@
+//! # Capacity saturation for `tower`
+//!
+//! Layer that artificially holds slots of the inner service's capacity for
+//! sampled durations. This targets the readiness pipeline rather than
+//! individual calls: middlewares that reserve capacity in `poll_ready`, such
+//! as `Buffer` or `ConcurrencyLimit`, experience queue buildup while the
+//! slots are held.
+//!
+//! When the decider triggers for a request, the layer spawns background
+//! tasks that each clone the inner service, wait until it is ready, and hold
+//! that readiness for a duration sampled from the distribution. At most
+//! `slots` slots are held at the same time.
+//!
+//! This requires a Tokio runtime, as the slots are held in spawned tasks.
+//!
+//! ## Usage
+//!
+//! ```rust
+//! use tower_fault::saturation::SaturationLayer;
+//! use tower::{service_fn, ServiceBuilder};
+//! # async fn my_service(_req: ()) -> Result<(), ()> {
+//! #     Ok(())
+//! # }
+//!
+//! // 1% of the requests trigger holding 8 of the 16 concurrency slots for
+//! // 1 to 2 seconds.
+//! let saturation_layer = SaturationLayer::new(0.01, 1_000..2_000, 8);
+//!
+//! let service = ServiceBuilder::new()
+//!     .layer(saturation_layer)
+//!     .concurrency_limit(16)
+//!     .service(service_fn(my_service));
+//! ```
+
+use crate::{
+    decider::{Decider, Probability, WithContext},
+    describe::{DescribeDecider, DescribeDistribution, FaultDescription},
+    latency::Distribution,
+    observe::FaultEvent,
+    options::FaultOptions,
+    validate::{self, ValidateDecider, ValidateDistribution},
+    veto::Vetoed,
+    Error,
+};
+use std::{
+    fmt, ops,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+    task::{Context, Poll},
+    time::Duration,
+};
+use tokio::time;
+use tower::{Layer, Service, ServiceExt};
+
+/// Layer that holds slots of the inner service's capacity.
+#[derive(Clone, Debug)]
+pub struct SaturationLayer<De, Di> {
+    decider: De,
+    distribution: Di,
+    slots: usize,
+    options: FaultOptions,
+}
+
+impl Default for SaturationLayer<Probability, ops::Range<u64>> {
+    /// Create a new `SaturationLayer` that never saturates the service, and
+    /// holds a single slot for 200 to 500 milliseconds once armed with a
+    /// decider.
+    fn default() -> Self {
+        Self::new(Probability::NEVER, 200..500, 1)
+    }
+}
+
+impl<De, Di> SaturationLayer<De, Di> {
+    /// Create a new `SaturationLayer` with the given decider, hold duration
+    /// distribution, and maximum number of slots held at the same time.
+    pub fn new(decider: De, distribution: Di, slots: usize) -> Self {
+        Self {
+            decider,
+            distribution,
+            slots,
+            options: FaultOptions::default(),
+        }
+    }
+
+    /// Set the given decider to be used to determine if the service should
+    /// be saturated.
+    pub fn with_decider<NDe>(self, decider: NDe) -> SaturationLayer<NDe, Di> {
+        SaturationLayer {
+            decider,
+            distribution: self.distribution,
+            slots: self.slots,
+            options: self.options,
+        }
+    }
+
+    /// Call the decider with a `(request, context)` tuple, where the context
+    /// is returned by the given extractor.
+    ///
+    /// See [`WithContext`] for more information.
+    pub fn with_context<X>(self, extractor: X) -> SaturationLayer<WithContext<De, X>, Di> {
+        SaturationLayer {
+            decider: WithContext::new(self.decider, extractor),
+            distribution: self.distribution,
+            slots: self.slots,
+            options: self.options,
+        }
+    }
+
+    /// Never saturate the service on the requests vetoed by the given veto,
+    /// regardless of the decider.
+    ///
+    /// See the [`veto`](crate::veto) module for more information.
+    pub fn with_veto<V>(self, veto: V) -> SaturationLayer<Vetoed<De, V>, Di> {
+        SaturationLayer {
+            decider: Vetoed::new(self.decider, veto),
+            distribution: self.distribution,
+            slots: self.slots,
+            options: self.options,
+        }
+    }
+}
+
+impl<De, Di> SaturationLayer<De, Di> {
+    crate::options::impl_fault_options! { "layer" }
+}
+
+impl<De, Di> SaturationLayer<De, Di>
+where
+    De: ValidateDecider,
+    Di: ValidateDistribution,
+{
+    /// Create a new `SaturationLayer`, returning an error if the
+    /// configuration is invalid.
+    pub fn try_new(decider: De, distribution: Di, slots: usize) -> Result<Self, Error> {
+        Self::new(decider, distribution, slots).build()
+    }
+
+    /// Validate the configuration of the layer.
+    ///
+    /// Returns an [`Error`] if the decider or the distribution is
+    /// misconfigured, or if the layer doesn't hold any slot.
+    pub fn build(self) -> Result<Self, Error> {
+        validate::all([
+            self.decider.validate_decider(),
+            self.distribution.validate_distribution(),
+        ])?;
+        if self.slots == 0 {
+            return Err(Error::InvalidConfig(
+                "saturation layer must hold at least one slot".to_string(),
+            ));
+        }
+        Ok(self)
+    }
+
+    /// Validate the configuration of the layer, and wrap the given service.
+    pub fn build_service<S>(self, inner: S) -> Result<SaturationService<De, Di, S>, Error>
+    where
+        De: Clone,
+        Di: Clone,
+    {
+        Ok(self.build()?.layer(inner))
+    }
+}
+
+impl<De, Di> SaturationLayer<De, Di>
+where
+    De: DescribeDecider,
+    Di: DescribeDistribution,
+{
+    /// Returns a description of the configured fault.
+    pub fn describe(&self) -> FaultDescription {
+        FaultDescription::new("saturation", self.decider.describe_decider())
+            .with_enabled(self.options.enabled)
+            .with_distribution(format!(
+                "{}, up to {} slots",
+                self.distribution.describe_distribution(),
+                self.slots
+            ))
+    }
+}
+
+impl<De, Di> fmt::Display for SaturationLayer<De, Di>
+where
+    De: DescribeDecider,
+    Di: DescribeDistribution,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.describe().fmt(f)
+    }
+}
+
+impl<De, Di, S> Layer<S> for SaturationLayer<De, Di>
+where
+    De: Clone,
+    Di: Clone,
+{
+    type Service = SaturationService<De, Di, S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        SaturationService {
+            inner,
+            decider: self.decider.clone(),
+            distribution: self.distribution.clone(),
+            slots: self.slots,
+            options: self.options.clone(),
+            held: Arc::new(AtomicUsize::new(0)),
+        }
+    }
+}
+
+/// Service that holds slots of the inner service's capacity.
+#[derive(Clone, Debug)]
+pub struct SaturationService<De, Di, S> {
+    inner: S,
+    decider: De,
+    distribution: Di,
+    slots: usize,
+    options: FaultOptions,
+    held: Arc<AtomicUsize>,
+}
+
+impl<De, Di, S> SaturationService<De, Di, S> {
+    /// Returns the number of slots currently held.
+    pub fn held(&self) -> usize {
+        self.held.load(Ordering::Relaxed)
+    }
+
+    fn try_acquire(&self) -> bool {
+        self.held
+            .fetch_update(Ordering::AcqRel, Ordering::Acquire, |held| {
+                (held < self.slots).then(|| held + 1)
+            })
+            .is_ok()
+    }
+}
+
+impl<De, Di, S> SaturationService<De, Di, S> {
+    /// Hold the readiness of clones of the inner service for the given
+    /// duration, until all the slots are taken.
+    fn saturate<R>(&self, duration: Duration)
+    where
+        S: Service<R> + Clone + Send + 'static,
+        S::Error: Send,
+    {
+        while self.try_acquire() {
+            let mut inner = self.inner.clone();
+            let held = self.held.clone();
+            tokio::spawn(async move {
+                // Holding the readiness reserves a slot in middlewares
+                // such as `Buffer` or `ConcurrencyLimit`, until the
+                // service is dropped.
+                if ServiceExt::<R>::ready(&mut inner).await.is_ok() {
+                    time::sleep(duration).await;
+                }
+                drop(inner);
+                held.fetch_sub(1, Ordering::AcqRel);
+            });
+        }
+    }
+}
+
+impl<De, Di, S> SaturationService<De, Di, S>
+where
+    De: DescribeDecider,
+    Di: DescribeDistribution,
+{
+    /// Returns a description of the configured fault.
+    pub fn describe(&self) -> FaultDescription {
+        FaultDescription::new("saturation", self.decider.describe_decider())
+            .with_enabled(self.options.enabled)
+            .with_distribution(format!(
+                "{}, up to {} slots",
+                self.distribution.describe_distribution(),
+                self.slots
+            ))
+    }
+}
+
+impl<De, Di, S> fmt::Display for SaturationService<De, Di, S>
+where
+    De: DescribeDecider,
+    Di: DescribeDistribution,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.describe().fmt(f)
+    }
+}
+
+impl<De, Di, S, R> Service<R> for SaturationService<De, Di, S>
+where
+    De: Decider<R>,
+    Di: Distribution<R>,
+    S: Service<R> + Clone + Send + 'static,
+    S::Error: Send,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = S::Future;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, request: R) -> Self::Future {
+        if self.options.is_active("saturation")
+            && self.options.decide("saturation", &self.decider, &request)
+        {
+            let duration = self.distribution.sample(&request);
+            if self
+                .options
+                .inject(FaultEvent::new("saturation").with_latency(duration))
+            {
+                self.saturate(duration);
+            }
+        }
+
+        self.inner.call(request)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{future::Ready, time::Duration};
+    use tower::limit::ConcurrencyLimitLayer;
+
+    #[derive(Clone)]
+    struct Echo;
+
+    impl Service<()> for Echo {
+        type Response = ();
+        type Error = ();
+        type Future = Ready<Result<(), ()>>;
+
+        fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), ()>> {
+            Poll::Ready(Ok(()))
+        }
+
+        fn call(&mut self, _request: ()) -> Self::Future {
+            std::future::ready(Ok(()))
+        }
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn saturation_holds_slots() {
+        let limited = ConcurrencyLimitLayer::new(2).layer(Echo);
+        let mut service =
+            SaturationLayer::new(true, Duration::from_secs(10), 2).layer(limited.clone());
+
+        service.ready().await.unwrap().call(()).await.unwrap();
+        // Let the spawned tasks acquire the slots.
+        tokio::task::yield_now().await;
+        assert_eq!(service.held(), 2);
+
+        // All the slots are held, so a new clone cannot become ready.
+        let mut other = limited.clone();
+        let ready = time::timeout(Duration::from_secs(5), other.ready()).await;
+        assert!(ready.is_err());
+
+        // Once the hold duration has passed, the slots are released.
+        time::sleep(Duration::from_secs(10)).await;
+        assert_eq!(service.held(), 0);
+        other.ready().await.unwrap();
+    }
+
+    #[test]
+    fn saturation_build() {
+        assert!(SaturationLayer::try_new(0.5, 100..200, 4).is_ok());
+        assert!(matches!(
+            SaturationLayer::try_new(0.5, 100..200, 0),
+            Err(Error::InvalidConfig(_))
+        ));
+        assert!(matches!(
+            SaturationLayer::try_new(0.5, 200..200, 4),
+            Err(Error::InvalidRange(_))
+        ));
+    }
+}
@@ -0,0 +1,164 @@
+//! # Reproducible experiments
+//!
+//! All the random decisions made by this crate, such as whether to inject a
+//! fault or how much latency to add, use a pseudo-random generator seeded
+//! from a single experiment seed.
+//!
+//! When no seed is set explicitly, one is generated when the first layer or
+//! registry is created, and logged with the `tracing` feature, so that a run
+//! can be reproduced after the fact. The seed can be set with [`set_seed`],
+//! or with the `TOWER_FAULT_SEED` environment variable.
+//!
+//! ```rust
+//! use tower_fault::{decider::Decider, seed};
+//!
+//! seed::set_seed(42);
+//! let first: Vec<bool> = (0..10).map(|_| 0.5.decide(&())).collect();
+//!
+//! seed::set_seed(42);
+//! let second: Vec<bool> = (0..10).map(|_| 0.5.decide(&())).collect();
+//! assert_eq!(first, second);
+//! ```
+//!
+//! The seed is also reported in the descriptions returned by the
+//! `describe()` method of the layers, see the [`describe`](crate::describe)
+//! module, and in the [`RegistryState`](crate::registry::RegistryState)
+//! returned by
+//! [`FaultRegistry::snapshot`](crate::registry::FaultRegistry::snapshot).
+//!
+//! The seed is shared by all the layers of a process: setting it applies to
+//! the layers created before, and there is no seed per layer.
+//!
+//! ## Threads
+//!
+//! Each thread uses its own generator, derived from the experiment seed and
+//! the order in which the threads first made a random decision. Runs are
+//! reproducible as long as the requests are handled in the same order, for
+//! example with a single-threaded runtime.
+
+use rand::{rngs::StdRng, RngCore, SeedableRng};
+use std::{
+    cell::RefCell,
+    env,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Mutex,
+    },
+};
+
+/// Name of the environment variable used to set the seed.
+pub const SEED_ENV: &str = "TOWER_FAULT_SEED";
+
+static SEED: Mutex<Option<u64>> = Mutex::new(None);
+/// Incremented every time the seed changes, so that the threads reseed their
+/// generator.
+static GENERATION: AtomicU64 = AtomicU64::new(1);
+static THREADS: AtomicU64 = AtomicU64::new(0);
+
+thread_local! {
+    static RNG: RefCell<Option<ThreadRng>> = const { RefCell::new(None) };
+}
+
+struct ThreadRng {
+    index: u64,
+    generation: u64,
+    rng: StdRng,
+}
+
+/// Returns the experiment seed, generating it if it wasn't set.
+pub fn seed() -> u64 {
+    let mut current = SEED.lock().unwrap_or_else(|e| e.into_inner());
+    *current.get_or_insert_with(|| {
+        let from_env = env::var(SEED_ENV).ok();
+        from_env
+            .and_then(|value| value.trim().parse().ok())
+            .unwrap_or_else(|| {
+                let seed = rand::random();
+                #[cfg(feature = "tracing")]
+                tracing::info!(target: "tower_fault", seed, "generated experiment seed");
+                seed
+            })
+    })
+}
+
+/// Set the experiment seed.
+///
+/// This resets the generators of all the threads.
+pub fn set_seed(seed: u64) {
+    let mut current = SEED.lock().unwrap_or_else(|e| e.into_inner());
+    *current = Some(seed);
+    GENERATION.fetch_add(1, Ordering::Relaxed);
+}
+
+/// Returns the generator of the current thread, seeded from the experiment
+/// seed.
+pub(crate) fn rng() -> SeededRng {
+    SeededRng(())
+}
+
+/// Handle to the generator of the current thread.
+#[derive(Debug)]
+pub(crate) struct SeededRng(());
+
+impl SeededRng {
+    fn with<T>(&mut self, f: impl FnOnce(&mut StdRng) -> T) -> T {
+        RNG.with(|rng| {
+            let mut rng = rng.borrow_mut();
+            let generation = GENERATION.load(Ordering::Relaxed);
+            let index = match rng.as_ref() {
+                Some(current) if current.generation == generation => None,
+                Some(current) => Some(current.index),
+                None => Some(THREADS.fetch_add(1, Ordering::Relaxed)),
+            };
+            if let Some(index) = index {
+                // Distinct stream for each thread.
+                let seed = seed() ^ index.wrapping_mul(0x9e37_79b9_7f4a_7c15);
+                *rng = Some(ThreadRng {
+                    index,
+                    generation,
+                    rng: StdRng::seed_from_u64(seed),
+                });
+            }
+            let current = rng.as_mut().map(|current| &mut current.rng);
+            f(current.expect("generator was just initialized"))
+        })
+    }
+}
+
+impl RngCore for SeededRng {
+    fn next_u32(&mut self) -> u32 {
+        self.with(|rng| rng.next_u32())
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        self.with(|rng| rng.next_u64())
+    }
+
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        self.with(|rng| rng.fill_bytes(dest))
+    }
+
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), rand::Error> {
+        self.with(|rng| rng.try_fill_bytes(dest))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::Rng;
+
+    #[test]
+    fn seeded() {
+        set_seed(7);
+        assert_eq!(seed(), 7);
+        let first: Vec<u64> = (0..5).map(|_| rng().gen()).collect();
+
+        set_seed(7);
+        let second: Vec<u64> = (0..5).map(|_| rng().gen()).collect();
+        assert_eq!(first, second);
+
+        let other = std::thread::spawn(|| rng().gen::<u64>()).join().unwrap();
+        assert_ne!(other, first[0]);
+    }
+}
@@ -0,0 +1,282 @@
+//! # SLO burn-rate experiments
+//!
+//! Helper to compute and drive an injection schedule that burns a chosen
+//! fraction of the error budget of a service level objective (SLO) over a
+//! chosen period, for example to check that burn-rate alerts fire as
+//! expected.
+//!
+//! An [`Slo`] is defined by its availability objective and its window. The
+//! error budget is the ratio of requests allowed to fail over the window,
+//! and the burn rate is how fast the budget is consumed, relative to
+//! consuming it exactly over the window. A [`BurnPlan`] computes the burn
+//! rate and the error rate needed to consume a fraction of the budget over
+//! a period.
+//!
+//! ## Usage
+//!
+//! ```rust
+//! use std::time::Duration;
+//! use tower_fault::{registry::FaultRegistry, slo::Slo};
+//! # #[tokio::main(flavor = "current_thread", start_paused = true)]
+//! # async fn main() {
+//!
+//! // 99.9% availability over 30 days.
+//! let slo = Slo::new(0.999, Duration::from_secs(30 * 24 * 3600));
+//!
+//! // Burn half of the error budget over 10 hours.
+//! let plan = slo.burn(0.5, Duration::from_secs(10 * 3600)).unwrap();
+//! assert_eq!(plan.burn_rate(), 36.0);
+//! println!("{}", plan);
+//!
+//! // Drive a fault of the registry with the plan.
+//! let registry = FaultRegistry::new();
+//! registry.register("errors", 0.0);
+//! plan.run(&registry, "errors", |progress| {
+//!     println!("{:.1}% of the budget burned", progress.budget_burned * 100.0);
+//! })
+//! .await
+//! .unwrap();
+//! # }
+//! ```
+//!
+//! Running the plan sets the probability of the fault to the planned error
+//! rate, which doesn't account for the real errors of the service. To
+//! include them, use the [`ErrorPacer`] returned by [`BurnPlan::pacer`] as
+//! the decider of an [`ErrorLayer`](crate::error::ErrorLayer) instead.
+
+use crate::{decider::ErrorPacer, registry::FaultRegistry, Error};
+use std::{fmt, time::Duration};
+use tokio::time::{self, Instant};
+
+/// Service level objective.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Slo {
+    objective: f64,
+    window: Duration,
+}
+
+impl Slo {
+    /// Create a new `Slo` with the given availability objective, between
+    /// 0.0 and 1.0, over the given window.
+    pub fn new(objective: f64, window: Duration) -> Self {
+        Self { objective, window }
+    }
+
+    /// Returns the availability objective.
+    pub fn objective(&self) -> f64 {
+        self.objective
+    }
+
+    /// Returns the window of the objective.
+    pub fn window(&self) -> Duration {
+        self.window
+    }
+
+    /// Returns the error budget, as the ratio of requests allowed to fail
+    /// over the window.
+    pub fn error_budget(&self) -> f64 {
+        1.0 - self.objective
+    }
+
+    /// Plan burning the given fraction of the error budget, between 0.0 and
+    /// 1.0, over the given period.
+    ///
+    /// Returns an error if the SLO is invalid, or if burning the budget that
+    /// fast would require failing more than all the requests.
+    pub fn burn(&self, fraction: f64, period: Duration) -> Result<BurnPlan, Error> {
+        if !(self.objective > 0.0 && self.objective < 1.0) {
+            return Err(Error::InvalidConfig(format!(
+                "slo objective must be between 0.0 and 1.0, got {}",
+                self.objective
+            )));
+        }
+        if self.window.is_zero() || period.is_zero() {
+            return Err(Error::InvalidConfig(
+                "slo window and burn period must not be zero".to_string(),
+            ));
+        }
+        if !(0.0..=1.0).contains(&fraction) {
+            return Err(Error::InvalidProbability(fraction));
+        }
+
+        let plan = BurnPlan {
+            slo: *self,
+            fraction,
+            period,
+            report_interval: Duration::from_secs(60),
+        };
+        if plan.error_rate() > 1.0 {
+            return Err(Error::InvalidConfig(format!(
+                "burning {}% of the error budget over {:?} requires an error rate of {}%",
+                fraction * 100.0,
+                period,
+                plan.error_rate() * 100.0
+            )));
+        }
+        Ok(plan)
+    }
+}
+
+/// Plan to burn a fraction of the error budget of an [`Slo`] over a period.
+#[derive(Clone, Debug, PartialEq)]
+pub struct BurnPlan {
+    slo: Slo,
+    fraction: f64,
+    period: Duration,
+    report_interval: Duration,
+}
+
+impl BurnPlan {
+    /// Returns the burn rate, relative to consuming the whole budget exactly
+    /// over the window of the SLO.
+    pub fn burn_rate(&self) -> f64 {
+        self.fraction * self.slo.window.as_secs_f64() / self.period.as_secs_f64()
+    }
+
+    /// Returns the error rate needed to follow the plan.
+    pub fn error_rate(&self) -> f64 {
+        self.burn_rate() * self.slo.error_budget()
+    }
+
+    /// Returns the period over which the budget is burned.
+    pub fn period(&self) -> Duration {
+        self.period
+    }
+
+    /// Returns an [`ErrorPacer`] targeting the error rate of the plan,
+    /// including the real errors of the service.
+    pub fn pacer(&self) -> ErrorPacer {
+        ErrorPacer::new(self.error_rate())
+    }
+
+    /// Set the interval at which the progress is reported by
+    /// [`BurnPlan::run`].
+    ///
+    /// Defaults to 1 minute.
+    pub fn report_interval(mut self, interval: Duration) -> Self {
+        self.report_interval = interval;
+        self
+    }
+
+    /// Returns the planned progress after the given elapsed time.
+    pub fn progress(&self, elapsed: Duration) -> BurnProgress {
+        let elapsed = elapsed.min(self.period);
+        BurnProgress {
+            elapsed,
+            remaining: self.period - elapsed,
+            budget_burned: self.fraction * elapsed.as_secs_f64() / self.period.as_secs_f64(),
+        }
+    }
+
+    /// Run the plan against the fault with the given name in the registry.
+    ///
+    /// The fault is enabled with the error rate of the plan as probability
+    /// for the period, then disabled. The progress is reported at each
+    /// report interval, and when the plan ends. The plan stops early if the
+    /// registry's kill switch is engaged.
+    ///
+    /// Returns the final progress, or an error if the fault isn't
+    /// registered.
+    pub async fn run<F>(
+        &self,
+        registry: &FaultRegistry,
+        fault: &str,
+        mut report: F,
+    ) -> Result<BurnProgress, Error>
+    where
+        F: FnMut(&BurnProgress),
+    {
+        let handle = registry.get(fault).ok_or_else(|| {
+            Error::InvalidConfig(format!("burn plan targets unknown fault '{}'", fault))
+        })?;
+        let kill_switch = registry.kill_switch();
+        let interval = self.report_interval.max(Duration::from_millis(1));
+
+        handle.set_probability(self.error_rate());
+        handle.enable();
+        let start = Instant::now();
+        let progress = loop {
+            let progress = self.progress(start.elapsed());
+            if kill_switch.is_engaged() || progress.remaining.is_zero() {
+                break progress;
+            }
+            report(&progress);
+            time::sleep(interval.min(progress.remaining)).await;
+        };
+        handle.disable();
+
+        report(&progress);
+        Ok(progress)
+    }
+}
+
+impl fmt::Display for BurnPlan {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "burn {}% of the {}% error budget over {:?}: burn rate {}x, error rate {}%",
+            self.fraction * 100.0,
+            self.slo.error_budget() * 100.0,
+            self.period,
+            self.burn_rate(),
+            self.error_rate() * 100.0
+        )
+    }
+}
+
+/// Progress of a [`BurnPlan`].
+#[derive(Clone, Debug, PartialEq)]
+#[non_exhaustive]
+pub struct BurnProgress {
+    /// Time elapsed since the start of the plan.
+    pub elapsed: Duration,
+    /// Time remaining until the end of the plan.
+    pub remaining: Duration,
+    /// Planned fraction of the whole error budget burned so far.
+    pub budget_burned: f64,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DAY: Duration = Duration::from_secs(24 * 3600);
+
+    #[test]
+    fn burn_plan() {
+        let slo = Slo::new(0.99, 30 * DAY);
+        let plan = slo.burn(0.5, 3 * DAY).unwrap();
+        assert_eq!(plan.burn_rate(), 5.0);
+        assert!((plan.error_rate() - 0.05).abs() < 1e-9);
+        assert_eq!(plan.progress(DAY + DAY / 2).budget_burned, 0.25);
+        assert_eq!(plan.progress(4 * DAY).remaining, Duration::ZERO);
+
+        // Burning half of the budget in a minute would fail all requests.
+        assert!(slo.burn(0.5, Duration::from_secs(60)).is_err());
+        assert!(Slo::new(1.0, 30 * DAY).burn(0.5, DAY).is_err());
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn burn_plan_run() {
+        let registry = FaultRegistry::new();
+        let handle = registry.register("errors", 0.0);
+        let plan = Slo::new(0.99, 30 * DAY)
+            .burn(0.5, 3 * DAY)
+            .unwrap()
+            .report_interval(DAY);
+
+        let mut reports = Vec::new();
+        let progress = plan
+            .run(&registry, "errors", |progress| {
+                reports.push(progress.budget_burned)
+            })
+            .await
+            .unwrap();
+
+        assert_eq!(progress.budget_burned, 0.5);
+        assert_eq!(reports.len(), 4);
+        assert!(!handle.is_enabled());
+        assert!((handle.probability() - 0.05).abs() < 1e-9);
+        assert!(plan.run(&registry, "unknown", |_| {}).await.is_err());
+    }
+}
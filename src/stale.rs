@@ -0,0 +1,451 @@
+//! # Stale data
+//!
+//! Layer that captures the responses of the service in a small cache, keyed
+//! by a [`KeyExtractor`], and randomly returns a previously captured
+//! response instead of calling the service. This simulates stale caches and
+//! replication lag.
+//!
+//! Only successful responses are captured. Requests without a key, or
+//! without a captured response for their key, always call the service.
+//!
+//! ## Usage
+//!
+//! ```rust
+//! use tower::{service_fn, ServiceBuilder};
+//! use tower_fault::stale::StaleLayer;
+//! # struct MyRequest { user_id: u64 }
+//! # async fn my_service(_req: MyRequest) -> Result<String, String> {
+//! #     Ok(String::from("user"))
+//! # }
+//!
+//! // Return a previous response for the same user 10% of the time, keeping
+//! // the responses for up to 1000 users.
+//! let stale_layer: StaleLayer<_, _, String> =
+//!     StaleLayer::new(0.1, |req: &MyRequest| Some(req.user_id)).capacity(1000);
+//!
+//! let service = ServiceBuilder::new()
+//!     .layer(stale_layer)
+//!     .service(service_fn(my_service));
+//! ```
+//!
+//! ### Cache
+//!
+//! The cache is shared by all the services created by a layer, and keeps the
+//! last response for each key. Once it holds `capacity` keys, capturing the
+//! response for a new key evicts the oldest one. Keys are compared by value,
+//! so they must implement [`Eq`] along with [`Hash`].
+
+use crate::{
+    decider::{Decider, KeyExtractor},
+    describe::{DescribeDecider, FaultDescription},
+    observe::{FaultEvent, Outcome},
+    options::FaultOptions,
+    validate::ValidateDecider,
+    veto::Vetoed,
+    Error,
+};
+use std::{
+    any::Any,
+    collections::{hash_map::DefaultHasher, HashMap, VecDeque},
+    fmt,
+    future::Future,
+    hash::{Hash, Hasher},
+    pin::Pin,
+    sync::{Arc, Mutex, MutexGuard},
+    task::{Context, Poll},
+    time::Duration,
+};
+use tower::{Layer, Service};
+
+/// Default number of keys kept in the cache.
+const DEFAULT_CAPACITY: usize = 128;
+
+/// Layer that randomly returns a previously captured response instead of
+/// calling the service.
+///
+/// `T` is the response type of the service. It is inferred once the service
+/// is called, or can be set explicitly.
+#[derive(Clone, Debug)]
+pub struct StaleLayer<D, K, T> {
+    decider: D,
+    key: K,
+    cache: SharedCache<T>,
+    options: FaultOptions,
+}
+
+impl<D, K, T> StaleLayer<D, K, T> {
+    /// Create a new `StaleLayer` with the given decider and key extractor.
+    pub fn new(decider: D, key: K) -> Self {
+        Self {
+            decider,
+            key,
+            cache: SharedCache::new(DEFAULT_CAPACITY),
+            options: FaultOptions::default(),
+        }
+    }
+
+    /// Set the maximum number of keys kept in the cache.
+    ///
+    /// This replaces the cache, dropping the captured responses.
+    pub fn capacity(mut self, capacity: usize) -> Self {
+        self.cache = SharedCache::new(capacity);
+        self
+    }
+
+    /// Never return a stale response to the requests vetoed by the given
+    /// veto, regardless of the decider.
+    ///
+    /// See the [`veto`](crate::veto) module for more information.
+    pub fn with_veto<V>(self, veto: V) -> StaleLayer<Vetoed<D, V>, K, T> {
+        StaleLayer {
+            decider: Vetoed::new(self.decider, veto),
+            key: self.key,
+            cache: self.cache,
+            options: self.options,
+        }
+    }
+
+    crate::options::impl_fault_options! { "layer", shadow }
+}
+
+impl<D, K, T> StaleLayer<D, K, T>
+where
+    D: ValidateDecider,
+{
+    /// Validate the configuration of the layer.
+    ///
+    /// Returns an [`Error`] if the decider is misconfigured, instead of
+    /// panicking at request time.
+    pub fn build(self) -> Result<Self, Error> {
+        self.decider.validate_decider()?;
+        Ok(self)
+    }
+}
+
+impl<D, K, T> StaleLayer<D, K, T>
+where
+    D: DescribeDecider,
+{
+    /// Returns a description of the configured fault.
+    pub fn describe(&self) -> FaultDescription {
+        FaultDescription::new("stale", self.decider.describe_decider())
+            .with_enabled(self.options.enabled)
+    }
+}
+
+impl<D, K, T> fmt::Display for StaleLayer<D, K, T>
+where
+    D: DescribeDecider,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.describe().fmt(f)
+    }
+}
+
+impl<D, K, T, S> Layer<S> for StaleLayer<D, K, T>
+where
+    D: Clone,
+    K: Clone,
+{
+    type Service = StaleService<D, K, T, S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        StaleService {
+            inner,
+            decider: self.decider.clone(),
+            key: self.key.clone(),
+            cache: self.cache.clone(),
+            options: self.options.clone(),
+        }
+    }
+}
+
+/// Service that randomly returns a previously captured response instead of
+/// calling the underlying service.
+#[derive(Clone, Debug)]
+pub struct StaleService<D, K, T, S> {
+    inner: S,
+    decider: D,
+    key: K,
+    cache: SharedCache<T>,
+    options: FaultOptions,
+}
+
+impl<D, K, T, S> StaleService<D, K, T, S>
+where
+    D: DescribeDecider,
+{
+    /// Returns a description of the configured fault.
+    pub fn describe(&self) -> FaultDescription {
+        FaultDescription::new("stale", self.decider.describe_decider())
+            .with_enabled(self.options.enabled)
+    }
+}
+
+impl<D, K, T, S> fmt::Display for StaleService<D, K, T, S>
+where
+    D: DescribeDecider,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.describe().fmt(f)
+    }
+}
+
+impl<D, K, T, S, R> Service<R> for StaleService<D, K, T, S>
+where
+    D: Decider<R>,
+    K: KeyExtractor<R>,
+    K::Key: Eq + Send + Sync + 'static,
+    T: Clone + Send + 'static,
+    S: Service<R, Response = T>,
+    S::Future: Send + 'static,
+    S::Error: Send + 'static,
+{
+    type Response = T;
+    type Error = S::Error;
+    type Future = StaleFuture<T, S::Error>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, request: R) -> Self::Future {
+        let key = match self.key.extract(&request) {
+            Some(key) => CacheKey::new(key),
+            None => return Box::pin(self.inner.call(request)),
+        };
+
+        // Only look up the captured response once the decider approved the
+        // fault, as most requests call the service.
+        if self.options.is_active("stale") && self.options.decide("stale", &self.decider, &request)
+        {
+            if self.options.shadow {
+                if self.cache.lock().contains(&key) {
+                    let fut = capture(self.cache.clone(), key, self.inner.call(request));
+                    return Box::pin(self.options.shadow_call(
+                        FaultEvent::new("stale"),
+                        fut,
+                        |_| Outcome::new(true, Duration::ZERO),
+                    ));
+                }
+            } else {
+                let captured = self.cache.lock().get(&key);
+                if let Some(response) = captured {
+                    if self.options.inject(FaultEvent::new("stale")) {
+                        return Box::pin(async move { Ok(response) });
+                    }
+                }
+            }
+        }
+
+        Box::pin(capture(self.cache.clone(), key, self.inner.call(request)))
+    }
+}
+
+/// Future returned by [`StaleService`].
+pub type StaleFuture<T, E> = Pin<Box<dyn Future<Output = Result<T, E>> + Send>>;
+
+/// Capture the response returned by the future in the cache.
+async fn capture<T, E, F>(cache: SharedCache<T>, key: CacheKey, fut: F) -> Result<T, E>
+where
+    T: Clone,
+    F: Future<Output = Result<T, E>>,
+{
+    let res = fut.await;
+    if let Ok(response) = &res {
+        cache.lock().insert(key, response.clone());
+    }
+    res
+}
+
+/// Key of a captured response.
+///
+/// The key type depends on the request, so it is erased to share the cache
+/// between the services created by a layer. Keys are still compared by value,
+/// and not only by hash.
+#[derive(Clone)]
+struct CacheKey {
+    hash: u64,
+    key: Arc<dyn DynKey>,
+}
+
+impl CacheKey {
+    fn new<K>(key: K) -> Self
+    where
+        K: Eq + Hash + Send + Sync + 'static,
+    {
+        let mut hasher = DefaultHasher::new();
+        key.hash(&mut hasher);
+        Self {
+            hash: hasher.finish(),
+            key: Arc::new(key),
+        }
+    }
+}
+
+impl PartialEq for CacheKey {
+    fn eq(&self, other: &Self) -> bool {
+        self.hash == other.hash && self.key.eq_key(other.key.as_any())
+    }
+}
+
+impl Eq for CacheKey {}
+
+impl Hash for CacheKey {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        state.write_u64(self.hash);
+    }
+}
+
+/// Type-erased key that can be compared with another key.
+trait DynKey: Send + Sync {
+    fn as_any(&self) -> &dyn Any;
+
+    fn eq_key(&self, other: &dyn Any) -> bool;
+}
+
+impl<K> DynKey for K
+where
+    K: Eq + Send + Sync + 'static,
+{
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn eq_key(&self, other: &dyn Any) -> bool {
+        other.downcast_ref::<K>() == Some(self)
+    }
+}
+
+/// Cache shared by the services created by a layer.
+struct SharedCache<T>(Arc<Mutex<Cache<T>>>);
+
+impl<T> SharedCache<T> {
+    fn new(capacity: usize) -> Self {
+        Self(Arc::new(Mutex::new(Cache {
+            capacity,
+            entries: HashMap::new(),
+            order: VecDeque::new(),
+        })))
+    }
+
+    fn lock(&self) -> MutexGuard<'_, Cache<T>> {
+        self.0.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+impl<T> Clone for SharedCache<T> {
+    fn clone(&self) -> Self {
+        Self(self.0.clone())
+    }
+}
+
+impl<T> fmt::Debug for SharedCache<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let cache = self.lock();
+        f.debug_struct("SharedCache")
+            .field("capacity", &cache.capacity)
+            .field("len", &cache.entries.len())
+            .finish()
+    }
+}
+
+struct Cache<T> {
+    capacity: usize,
+    entries: HashMap<CacheKey, T>,
+    order: VecDeque<CacheKey>,
+}
+
+impl<T: Clone> Cache<T> {
+    fn contains(&self, key: &CacheKey) -> bool {
+        self.entries.contains_key(key)
+    }
+
+    fn get(&self, key: &CacheKey) -> Option<T> {
+        self.entries.get(key).cloned()
+    }
+
+    fn insert(&mut self, key: CacheKey, response: T) {
+        if self.capacity == 0 {
+            return;
+        }
+        if self.entries.insert(key.clone(), response).is_none() {
+            self.order.push_back(key);
+            if self.order.len() > self.capacity {
+                if let Some(oldest) = self.order.pop_front() {
+                    self.entries.remove(&oldest);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::*;
+    use std::sync::atomic::{AtomicU64, Ordering};
+    use tower::{service_fn, ServiceExt};
+
+    #[tokio::test]
+    async fn stale_replays_response() {
+        let version = Arc::new(AtomicU64::new(0));
+        let service = service_fn({
+            let version = version.clone();
+            move |key: u64| {
+                let version = version.fetch_add(1, Ordering::Relaxed);
+                async move { Ok::<_, String>((key, version)) }
+            }
+        });
+        let decider = |key: &u64| *key != 3;
+        let service = StaleLayer::new(decider, |key: &u64| Some(*key))
+            .capacity(1)
+            .layer(service);
+
+        // Nothing captured yet.
+        assert_eq!(service.clone().oneshot(1).await, Ok((1, 0)));
+        assert_eq!(service.clone().oneshot(1).await, Ok((1, 0)));
+
+        // Capturing another key evicts the first one.
+        assert_eq!(service.clone().oneshot(2).await, Ok((2, 1)));
+        assert_eq!(service.clone().oneshot(1).await, Ok((1, 2)));
+
+        // The decider rejects the fault.
+        assert_eq!(service.clone().oneshot(3).await, Ok((3, 3)));
+        assert_eq!(service.clone().oneshot(3).await, Ok((3, 4)));
+    }
+
+    #[tokio::test]
+    async fn stale_colliding_keys() {
+        /// Key whose instances all have the same hash.
+        #[derive(PartialEq, Eq)]
+        struct Colliding(u64);
+
+        impl Hash for Colliding {
+            fn hash<H: Hasher>(&self, state: &mut H) {
+                state.write_u64(0);
+            }
+        }
+
+        let service = service_fn(|user: u64| async move { Ok::<_, String>(user) });
+        let service = StaleLayer::new(true, |user: &u64| Some(Colliding(*user))).layer(service);
+
+        assert_eq!(service.clone().oneshot(1).await, Ok(1));
+        // Another user never receives the response captured for the first
+        // one, even though their keys have the same hash.
+        assert_eq!(service.clone().oneshot(2).await, Ok(2));
+        assert_eq!(service.clone().oneshot(1).await, Ok(1));
+        assert_eq!(service.cache.lock().entries.len(), 2);
+    }
+
+    #[tokio::test]
+    async fn stale_shadow() {
+        let mut service = StaleLayer::new(true, |_: &()| Some(()))
+            .shadow(true)
+            .layer(DummyService);
+
+        assert_eq!(service.call(()).await.unwrap(), "ok");
+        assert_eq!(service.call(()).await.unwrap(), "ok");
+        assert_eq!(service.cache.lock().entries.len(), 1);
+    }
+}
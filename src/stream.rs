@@ -0,0 +1,321 @@
+//! # Stream fault injection
+//!
+//! This module contains [`FaultStream`], which wraps a [`Stream`] and
+//! randomly delays, drops, duplicates, or replaces its individual items. This
+//! simulates faults in streaming responses, such as server-sent events,
+//! WebSocket messages, or gRPC streams, which request-level faults don't
+//! cover.
+//!
+//! Deciders and distributions receive the item.
+//!
+//! ## Example
+//!
+//! ```rust
+//! use tower::{service_fn, ServiceExt};
+//! use tower_fault::stream::FaultStream;
+//! # use futures_core::Stream;
+//! # fn events() -> impl Stream<Item = Result<String, String>> {
+//! #     struct Empty;
+//! #     impl Stream for Empty {
+//! #         type Item = Result<String, String>;
+//! #         fn poll_next(
+//! #             self: std::pin::Pin<&mut Self>,
+//! #             _: &mut std::task::Context<'_>,
+//! #         ) -> std::task::Poll<Option<Self::Item>> {
+//! #             std::task::Poll::Ready(None)
+//! #         }
+//! #     }
+//! #     Empty
+//! # }
+//!
+//! // Inject faults into the stream returned by the service.
+//! let service = service_fn(|_: ()| async { Ok::<_, ()>(events()) }).map_response(|events| {
+//!     FaultStream::new(events)
+//!         // Delay 10% of the items by 100 to 500 milliseconds.
+//!         .with_delay(0.1, 100..500)
+//!         // Drop 1% of the items.
+//!         .with_drop(0.01)
+//!         // Duplicate 1% of the items.
+//!         .with_duplicate(0.01)
+//!         // Replace 1% of the items with an error.
+//!         .with_error(0.01, |_: &Result<String, String>| Err::<String, _>(String::from("error")))
+//! });
+//! ```
+
+use crate::{decider::Decider, latency::Distribution};
+use futures_core::Stream;
+use pin_project_lite::pin_project;
+use std::{
+    future::Future,
+    pin::Pin,
+    task::{Context, Poll},
+};
+use tokio::time::{self, Sleep};
+
+pin_project! {
+    /// Stream with injected faults.
+    ///
+    /// Duplicating items requires the item to be [`Clone`].
+    #[derive(Debug)]
+    pub struct FaultStream<St, T, De = bool, Di = u64, Dr = bool, Du = bool, Er = bool, G = fn(&T) -> T> {
+        #[pin]
+        inner: St,
+        delay: De,
+        distribution: Di,
+        drop: Dr,
+        duplicate: Du,
+        cloner: Option<fn(&T) -> T>,
+        error: Er,
+        generator: Option<G>,
+        sleep: Option<Pin<Box<Sleep>>>,
+        delayed: Option<T>,
+        duplicated: Option<T>,
+    }
+}
+
+impl<St, T> FaultStream<St, T> {
+    /// Wrap the given stream. No fault is injected until deciders are set
+    /// with the `with_*` methods.
+    pub fn new(inner: St) -> Self
+    where
+        St: Stream<Item = T>,
+    {
+        Self {
+            inner,
+            delay: false,
+            distribution: 0,
+            drop: false,
+            duplicate: false,
+            cloner: None,
+            error: false,
+            generator: None,
+            sleep: None,
+            delayed: None,
+            duplicated: None,
+        }
+    }
+}
+
+impl<St, T, De, Di, Dr, Du, Er, G> FaultStream<St, T, De, Di, Dr, Du, Er, G> {
+    /// Delay items using the given decider and latency distribution.
+    pub fn with_delay<NDe, NDi>(
+        self,
+        decider: NDe,
+        distribution: NDi,
+    ) -> FaultStream<St, T, NDe, NDi, Dr, Du, Er, G> {
+        FaultStream {
+            inner: self.inner,
+            delay: decider,
+            distribution,
+            drop: self.drop,
+            duplicate: self.duplicate,
+            cloner: self.cloner,
+            error: self.error,
+            generator: self.generator,
+            sleep: self.sleep,
+            delayed: self.delayed,
+            duplicated: self.duplicated,
+        }
+    }
+
+    /// Drop items using the given decider.
+    pub fn with_drop<NDr>(self, decider: NDr) -> FaultStream<St, T, De, Di, NDr, Du, Er, G> {
+        FaultStream {
+            inner: self.inner,
+            delay: self.delay,
+            distribution: self.distribution,
+            drop: decider,
+            duplicate: self.duplicate,
+            cloner: self.cloner,
+            error: self.error,
+            generator: self.generator,
+            sleep: self.sleep,
+            delayed: self.delayed,
+            duplicated: self.duplicated,
+        }
+    }
+
+    /// Duplicate items using the given decider.
+    pub fn with_duplicate<NDu>(self, decider: NDu) -> FaultStream<St, T, De, Di, Dr, NDu, Er, G>
+    where
+        T: Clone,
+    {
+        FaultStream {
+            inner: self.inner,
+            delay: self.delay,
+            distribution: self.distribution,
+            drop: self.drop,
+            duplicate: decider,
+            cloner: Some(T::clone),
+            error: self.error,
+            generator: self.generator,
+            sleep: self.sleep,
+            delayed: self.delayed,
+            duplicated: self.duplicated,
+        }
+    }
+
+    /// Replace items with the ones returned by the generator, such as
+    /// errors, using the given decider.
+    pub fn with_error<NEr, NG>(
+        self,
+        decider: NEr,
+        generator: NG,
+    ) -> FaultStream<St, T, De, Di, Dr, Du, NEr, NG> {
+        FaultStream {
+            inner: self.inner,
+            delay: self.delay,
+            distribution: self.distribution,
+            drop: self.drop,
+            duplicate: self.duplicate,
+            cloner: self.cloner,
+            error: decider,
+            generator: Some(generator),
+            sleep: self.sleep,
+            delayed: self.delayed,
+            duplicated: self.duplicated,
+        }
+    }
+}
+
+impl<St, T, De, Di, Dr, Du, Er, G> Stream for FaultStream<St, T, De, Di, Dr, Du, Er, G>
+where
+    St: Stream<Item = T>,
+    De: Decider<T>,
+    Di: Distribution<T>,
+    Dr: Decider<T>,
+    Du: Decider<T>,
+    Er: Decider<T>,
+    G: Fn(&T) -> T,
+{
+    type Item = T;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<T>> {
+        let mut this = self.project();
+
+        // A delayed item is emitted before its duplicate.
+        if let Some(sleep) = this.sleep.as_mut() {
+            if sleep.as_mut().poll(cx).is_pending() {
+                return Poll::Pending;
+            }
+            *this.sleep = None;
+            if let Some(item) = this.delayed.take() {
+                return Poll::Ready(Some(item));
+            }
+        }
+
+        if let Some(item) = this.duplicated.take() {
+            return Poll::Ready(Some(item));
+        }
+
+        loop {
+            let item = match this.inner.as_mut().poll_next(cx) {
+                Poll::Ready(Some(item)) => item,
+                other => return other,
+            };
+
+            if this.drop.decide(&item) {
+                continue;
+            }
+
+            let item = match this.generator {
+                Some(generator) if this.error.decide(&item) => generator(&item),
+                _ => item,
+            };
+
+            if let Some(cloner) = this.cloner {
+                if this.duplicate.decide(&item) {
+                    *this.duplicated = Some(cloner(&item));
+                }
+            }
+
+            if this.delay.decide(&item) {
+                let mut sleep = Box::pin(time::sleep(this.distribution.sample(&item)));
+                if sleep.as_mut().poll(cx).is_pending() {
+                    *this.sleep = Some(sleep);
+                    *this.delayed = Some(item);
+                    return Poll::Pending;
+                }
+            }
+
+            return Poll::Ready(Some(item));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{future::poll_fn, time::Duration};
+
+    struct Items(Vec<Result<u32, ()>>);
+
+    impl Stream for Items {
+        type Item = Result<u32, ()>;
+
+        fn poll_next(mut self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+            if self.0.is_empty() {
+                Poll::Ready(None)
+            } else {
+                Poll::Ready(Some(self.0.remove(0)))
+            }
+        }
+    }
+
+    async fn collect<St: Stream>(stream: St) -> Vec<St::Item> {
+        let mut stream = Box::pin(stream);
+        let mut items = Vec::new();
+        while let Some(item) = poll_fn(|cx| stream.as_mut().poll_next(cx)).await {
+            items.push(item);
+        }
+        items
+    }
+
+    fn items() -> Items {
+        Items(vec![Ok(1), Ok(2), Ok(3)])
+    }
+
+    #[tokio::test]
+    async fn stream_drop_duplicate_and_error() {
+        let stream = FaultStream::new(items())
+            .with_drop(|item: &Result<u32, ()>| *item == Ok(1))
+            .with_duplicate(|item: &Result<u32, ()>| *item == Ok(2))
+            .with_error(
+                |item: &Result<u32, ()>| *item == Ok(3),
+                |_: &Result<u32, ()>| Err(()),
+            );
+
+        assert_eq!(collect(stream).await, vec![Ok(2), Ok(2), Err(())]);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn stream_delay() {
+        let stream = FaultStream::new(items()).with_delay(true, Duration::from_secs(1));
+
+        let start = time::Instant::now();
+        assert_eq!(collect(stream).await, vec![Ok(1), Ok(2), Ok(3)]);
+        assert!(start.elapsed() >= Duration::from_secs(3));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn stream_delay_and_duplicate() {
+        let stream = FaultStream::new(items())
+            .with_delay(
+                |item: &Result<u32, ()>| *item == Ok(2),
+                Duration::from_secs(1),
+            )
+            .with_duplicate(|item: &Result<u32, ()>| *item == Ok(2));
+
+        let start = time::Instant::now();
+        let mut stream = Box::pin(stream);
+        let mut items = Vec::new();
+        while let Some(item) = poll_fn(|cx| stream.as_mut().poll_next(cx)).await {
+            items.push((item, start.elapsed() >= Duration::from_secs(1)));
+        }
+        // The duplicate isn't emitted before the delayed original.
+        assert_eq!(
+            items,
+            vec![(Ok(1), false), (Ok(2), true), (Ok(2), true), (Ok(3), true)]
+        );
+    }
+}
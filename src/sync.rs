@@ -0,0 +1,26 @@
+//! Synchronization primitives for the state shared between requests.
+//!
+//! With the `test-determinism` feature and the `tower_fault_loom` cfg, these
+//! are the primitives of [`loom`](https://docs.rs/loom), which explores all
+//! the possible interleavings of the threads in the tests running in
+//! `loom::model`:
+//!
+//! ```sh
+//! RUSTFLAGS="--cfg tower_fault_loom" cargo test --release --features test-determinism --lib loom
+//! ```
+//!
+//! This doesn't use the usual `loom` cfg, as it changes how Tokio is built.
+//!
+//! Otherwise, these are the primitives of the standard library.
+
+#[cfg(all(tower_fault_loom, feature = "test-determinism"))]
+pub(crate) use loom::sync::{
+    atomic::{AtomicBool, AtomicU64, Ordering},
+    Arc, Mutex, MutexGuard, RwLock,
+};
+
+#[cfg(not(all(tower_fault_loom, feature = "test-determinism")))]
+pub(crate) use std::sync::{
+    atomic::{AtomicBool, AtomicU64, Ordering},
+    Arc, Mutex, MutexGuard, RwLock,
+};
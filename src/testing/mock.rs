@@ -0,0 +1,98 @@
+//! # Mock services
+//!
+//! Adapters to combine fault layers with the [`Mock`] services of the
+//! `tower-test` crate, and assert how the fault layer interacted with the
+//! inner service.
+//!
+//! [`mock_layer`] wraps a mock service with a layer, and returns the
+//! [`Handle`] to control the mock and the number of times the mock was
+//! called. [`MockErrors`] generates errors compatible with the error type of
+//! mock services, which [`is_injected`] tells apart from the errors sent
+//! through the handle.
+//!
+//! ```rust
+//! use tower::ServiceExt;
+//! use tower_fault::{error::ErrorLayer, testing::mock};
+//! # #[tokio::main(flavor = "current_thread")]
+//! # async fn main() {
+//!
+//! let layer = ErrorLayer::new(1.0, mock::MockErrors);
+//! let (service, _handle, calls) = mock::mock_layer::<_, u64, u64>(layer);
+//!
+//! let err = service.oneshot(42).await.unwrap_err();
+//! assert!(mock::is_injected(&err));
+//! // The inner service was not called.
+//! assert_eq!(calls.get(), 0);
+//! # }
+//! ```
+
+use super::{counted, CallCount, Counted};
+use crate::generator::Generator;
+use std::{error, fmt};
+use tower::Layer;
+pub use tower_test::mock::{Handle, Mock};
+
+/// Error type of [`Mock`] services.
+pub type Error = Box<dyn error::Error + Send + Sync>;
+
+/// Wrap a new mock service with the layer.
+///
+/// This returns the layered service, the handle to control the mock
+/// service, and the number of times the mock service was called.
+pub fn mock_layer<L, T, U>(layer: L) -> (L::Service, Handle<T, U>, CallCount)
+where
+    L: Layer<Counted<Mock<T, U>>>,
+{
+    let (mock, handle) = tower_test::mock::pair();
+    let (mock, calls) = counted(mock);
+    (layer.layer(mock), handle, calls)
+}
+
+/// Error injected by [`MockErrors`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct InjectedError;
+
+impl fmt::Display for InjectedError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("injected fault")
+    }
+}
+
+impl error::Error for InjectedError {}
+
+/// Generator of [`InjectedError`]s, with the error type of mock services.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct MockErrors;
+
+impl<R> Generator<R, Error> for MockErrors {
+    fn generate(&self, _req: &R) -> Error {
+        Box::new(InjectedError)
+    }
+}
+
+/// Returns whether the error was generated by [`MockErrors`].
+pub fn is_injected(err: &Error) -> bool {
+    err.is::<InjectedError>()
+}
+
+#[cfg(all(test, feature = "error"))]
+mod tests {
+    use super::*;
+    use crate::error::ErrorLayer;
+    use tower::ServiceExt;
+
+    #[tokio::test]
+    async fn mock_calls() {
+        let layer = ErrorLayer::new(0.0, MockErrors);
+        let (service, mut handle, calls) = mock_layer::<_, u64, u64>(layer);
+
+        let response = tokio::spawn(service.oneshot(1));
+        let (req, send) = handle.next_request().await.unwrap();
+        assert_eq!(req, 1);
+        send.send_error(String::from("inner error"));
+
+        let err = response.await.unwrap().unwrap_err();
+        assert!(!is_injected(&err));
+        assert_eq!(calls.get(), 1);
+    }
+}
@@ -0,0 +1,412 @@
+//! # Resilience testing helpers
+//!
+//! Helpers to drive a service with synthetic load and make assertions on
+//! the faults it returned, to write resilience tests against the layers of
+//! this crate without boilerplate.
+//!
+//! [`drive`] sends a sequence of requests to a service, one at a time, and
+//! returns a [`LoadReport`] with the number of errors and the latency of
+//! each request. [`assert_fault_rate`] checks that the rate of errors
+//! returned by the service is close to an expected rate.
+//!
+//! ```rust
+//! use tower::{service_fn, Layer};
+//! use tower_fault::{error::ErrorLayer, testing};
+//! # #[tokio::main(flavor = "current_thread")]
+//! # async fn main() {
+//!
+//! let layer = ErrorLayer::new(0.2, |_: &u64| String::from("error"));
+//! let service = layer.layer(service_fn(|_: u64| async { Ok::<_, String>(()) }));
+//!
+//! // Between 15% and 25% of the requests fail.
+//! testing::assert_fault_rate(service, 0..1000, 0.2, 0.05).await;
+//! # }
+//! ```
+//!
+//! ## Registry faults
+//!
+//! [`drive_with_fault`] enables a fault of a
+//! [`FaultRegistry`](crate::registry::FaultRegistry) while driving the
+//! service, and restores its previous state afterwards, to compare the
+//! behaviour of a service with and without the fault.
+//!
+//! ## Inner service calls
+//!
+//! [`counted`] wraps the inner service of a fault layer to count how many
+//! times it was called, for example to assert that the inner service is not
+//! called when an error is injected.
+//!
+//! ```rust
+//! use tower::{service_fn, Layer};
+//! use tower_fault::{error::ErrorLayer, testing};
+//! # #[tokio::main(flavor = "current_thread")]
+//! # async fn main() {
+//!
+//! let (inner, calls) = testing::counted(service_fn(|_: u64| async { Ok::<_, String>(()) }));
+//! let layer = ErrorLayer::new(1.0, |_: &u64| String::from("error"));
+//! testing::drive(layer.layer(inner), 0..10).await;
+//! assert_eq!(calls.get(), 0);
+//! # }
+//! ```
+//!
+//! ## One-off calls
+//!
+//! [`oneshot_with`] and [`call_all_with`] apply a fault layer to a service
+//! for a single [`ServiceExt::oneshot`] call or [`ServiceExt::call_all`]
+//! stream, for test code that doesn't build persistent layer stacks.
+//!
+//! ```rust
+//! use futures_util::{stream, StreamExt};
+//! use tower::service_fn;
+//! use tower_fault::{error::ErrorLayer, testing};
+//! # #[tokio::main(flavor = "current_thread")]
+//! # async fn main() {
+//!
+//! let service = service_fn(|_: u64| async { Ok::<_, String>(()) });
+//! let error_layer = ErrorLayer::new(1.0, |_: &u64| String::from("error"));
+//!
+//! let res = testing::oneshot_with(error_layer.clone(), service, 0).await;
+//! assert!(res.is_err());
+//!
+//! let results = testing::call_all_with(error_layer, service, stream::iter(0..10))
+//!     .collect::<Vec<_>>()
+//!     .await;
+//! assert!(results.iter().all(Result::is_err));
+//! # }
+//! ```
+//!
+//! ## Ordering
+//!
+//! [`OrderProbe`] records the order in which requests arrive and complete,
+//! and reports the inversions between the two. This quantifies how much
+//! reordering a fault actually achieved, for example with latency injected
+//! into some requests, and lets the downstream report with
+//! [`OrderProbe::detected`] when it noticed a request completing out of
+//! order.
+//!
+//! ```rust
+//! use std::time::Duration;
+//! use tower::{service_fn, Layer, ServiceExt};
+//! use tower_fault::testing::OrderProbe;
+//! # #[tokio::main(flavor = "current_thread")]
+//! # async fn main() {
+//!
+//! let probe = OrderProbe::new();
+//! let service = probe.layer().layer(service_fn(|delay: u64| async move {
+//!     tokio::time::sleep(Duration::from_millis(delay)).await;
+//!     Ok::<_, String>(())
+//! }));
+//!
+//! // The second request completes before the first one.
+//! let _ = tokio::join!(service.clone().oneshot(20), service.clone().oneshot(0));
+//! assert_eq!(probe.report().inversions(), 1);
+//! # }
+//! ```
+//!
+//! ## Mock services
+//!
+//! With the `tower-test` feature, the [`mock`] module combines fault layers
+//! with the mock services of the `tower-test` crate.
+
+use crate::registry::FaultHandle;
+use futures_core::Stream;
+use std::{
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+    task::{Context, Poll},
+    time::Duration,
+};
+use tokio::time::Instant;
+use tower::{
+    util::{CallAll, Oneshot},
+    BoxError, Layer, Service, ServiceExt,
+};
+
+#[cfg(feature = "tower-test")]
+#[cfg_attr(docsrs, doc(cfg(feature = "tower-test")))]
+pub mod mock;
+
+mod order;
+pub use order::{OrderProbe, OrderProbeFuture, OrderProbeLayer, OrderProbeService, OrderReport};
+
+/// Results of driving a service with [`drive`].
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct LoadReport {
+    errors: usize,
+    latencies: Vec<Duration>,
+}
+
+impl LoadReport {
+    /// Returns the number of requests sent to the service.
+    pub fn requests(&self) -> usize {
+        self.latencies.len()
+    }
+
+    /// Returns the number of requests that returned an error.
+    pub fn errors(&self) -> usize {
+        self.errors
+    }
+
+    /// Returns the ratio of requests that returned an error, between 0.0
+    /// and 1.0.
+    pub fn error_rate(&self) -> f64 {
+        match self.requests() {
+            0 => 0.0,
+            requests => self.errors as f64 / requests as f64,
+        }
+    }
+
+    /// Returns the latency of the requests at the given quantile, between
+    /// 0.0 and 1.0.
+    ///
+    /// This returns `None` if no requests were sent.
+    pub fn latency(&self, quantile: f64) -> Option<Duration> {
+        let mut latencies = self.latencies.clone();
+        latencies.sort_unstable();
+        let last = latencies.len().checked_sub(1)?;
+        let index = (quantile.clamp(0.0, 1.0) * last as f64).round() as usize;
+        latencies.get(index).copied()
+    }
+
+    /// Returns the mean latency of the requests.
+    ///
+    /// This returns `None` if no requests were sent.
+    pub fn mean_latency(&self) -> Option<Duration> {
+        let requests = u32::try_from(self.requests()).ok().filter(|n| *n > 0)?;
+        Some(self.latencies.iter().sum::<Duration>() / requests)
+    }
+}
+
+/// Send the requests to the service one at a time, and report the errors
+/// and latencies.
+///
+/// Errors returned while waiting for the service to be ready are counted as
+/// errors of the request.
+pub async fn drive<S, R, I>(mut service: S, requests: I) -> LoadReport
+where
+    S: Service<R>,
+    I: IntoIterator<Item = R>,
+{
+    let mut report = LoadReport::default();
+    for req in requests {
+        let start = Instant::now();
+        let result = match service.ready().await {
+            Ok(service) => service.call(req).await,
+            Err(err) => Err(err),
+        };
+        report.latencies.push(start.elapsed());
+        if result.is_err() {
+            report.errors += 1;
+        }
+    }
+    report
+}
+
+/// Enable a registry fault, send the requests to the service with
+/// [`drive`], then restore the previous state of the fault.
+pub async fn drive_with_fault<S, R, I>(fault: &FaultHandle, service: S, requests: I) -> LoadReport
+where
+    S: Service<R>,
+    I: IntoIterator<Item = R>,
+{
+    let enabled = fault.is_enabled();
+    fault.enable();
+    let report = drive(service, requests).await;
+    if !enabled {
+        fault.disable();
+    }
+    report
+}
+
+/// Send the requests to the service with [`drive`], and assert that the
+/// rate of errors is within `tolerance` of `expected_rate`.
+///
+/// ## Panics
+///
+/// Panics if the error rate is outside of the expected range.
+pub async fn assert_fault_rate<S, R, I>(
+    service: S,
+    requests: I,
+    expected_rate: f64,
+    tolerance: f64,
+) -> LoadReport
+where
+    S: Service<R>,
+    I: IntoIterator<Item = R>,
+{
+    let report = drive(service, requests).await;
+    let rate = report.error_rate();
+    assert!(
+        (rate - expected_rate).abs() <= tolerance,
+        "error rate {:.4} is not within {} of {} ({} errors over {} requests)",
+        rate,
+        tolerance,
+        expected_rate,
+        report.errors(),
+        report.requests(),
+    );
+    report
+}
+
+/// Apply the layer to the service, and send it a single request with
+/// [`ServiceExt::oneshot`].
+pub fn oneshot_with<L, S, R>(layer: L, service: S, req: R) -> Oneshot<L::Service, R>
+where
+    L: Layer<S>,
+    L::Service: Service<R>,
+{
+    layer.layer(service).oneshot(req)
+}
+
+/// Apply the layer to the service, and send it the requests of the stream
+/// with [`ServiceExt::call_all`].
+///
+/// The responses are returned in the order of the requests.
+pub fn call_all_with<L, S, St>(layer: L, service: S, requests: St) -> CallAll<L::Service, St>
+where
+    L: Layer<S>,
+    L::Service: Service<St::Item>,
+    <L::Service as Service<St::Item>>::Error: Into<BoxError>,
+    St: Stream,
+{
+    layer.layer(service).call_all(requests)
+}
+
+/// Wrap a service to count how many times it is called.
+///
+/// The returned [`CallCount`] is shared with the clones of the service.
+pub fn counted<S>(service: S) -> (Counted<S>, CallCount) {
+    let calls = CallCount::default();
+    let service = Counted {
+        inner: service,
+        calls: calls.clone(),
+    };
+    (service, calls)
+}
+
+/// Number of times a [`Counted`] service was called.
+#[derive(Clone, Debug, Default)]
+pub struct CallCount(Arc<AtomicUsize>);
+
+impl CallCount {
+    /// Returns the number of calls.
+    pub fn get(&self) -> usize {
+        self.0.load(Ordering::Relaxed)
+    }
+
+    /// Reset the number of calls to zero.
+    pub fn reset(&self) {
+        self.0.store(0, Ordering::Relaxed);
+    }
+}
+
+/// Service counting how many times the inner service is called.
+///
+/// See [`counted`] for more information.
+#[derive(Clone, Debug)]
+pub struct Counted<S> {
+    inner: S,
+    calls: CallCount,
+}
+
+impl<S> Counted<S> {
+    /// Returns the number of calls of this service.
+    pub fn calls(&self) -> &CallCount {
+        &self.calls
+    }
+}
+
+impl<S, R> Service<R> for Counted<S>
+where
+    S: Service<R>,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = S::Future;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, req: R) -> Self::Future {
+        self.calls.0.fetch_add(1, Ordering::Relaxed);
+        self.inner.call(req)
+    }
+}
+
+#[cfg(all(test, feature = "error"))]
+mod tests {
+    use super::*;
+    use crate::{error::ErrorLayer, registry::FaultRegistry, test_utils::DummyService};
+    use std::iter;
+    use tower::Layer;
+
+    #[tokio::test]
+    async fn fault_rate() {
+        let layer = ErrorLayer::new(0.3, |_: &()| String::from("error"));
+        let report = assert_fault_rate(
+            layer.layer(DummyService),
+            iter::repeat_n((), 2000),
+            0.3,
+            0.05,
+        )
+        .await;
+        assert_eq!(report.requests(), 2000);
+        assert!(report.latency(0.5).is_some());
+        assert_eq!(LoadReport::default().mean_latency(), None);
+    }
+
+    #[tokio::test]
+    #[should_panic(expected = "error rate")]
+    async fn fault_rate_outside_tolerance() {
+        let layer = ErrorLayer::new(1.0, |_: &()| String::from("error"));
+        assert_fault_rate(layer.layer(DummyService), iter::repeat_n((), 10), 0.0, 0.1).await;
+    }
+
+    #[tokio::test]
+    async fn with_fault() {
+        let registry = FaultRegistry::new();
+        let fault = registry.register("error", 1.0);
+        fault.disable();
+        let layer = ErrorLayer::new(fault.clone(), |_: &()| String::from("error"));
+
+        let report =
+            drive_with_fault(&fault, layer.layer(DummyService), iter::repeat_n((), 10)).await;
+        assert_eq!(report.error_rate(), 1.0);
+        assert!(!fault.is_enabled());
+
+        let report = drive(layer.layer(DummyService), iter::repeat_n((), 10)).await;
+        assert_eq!(report.errors(), 0);
+    }
+
+    #[tokio::test]
+    async fn oneshot_and_call_all() {
+        use futures_util::{stream, StreamExt};
+
+        let layer = ErrorLayer::new(|req: &u64| *req < 2, |_: &u64| String::from("error"));
+        let service = tower::service_fn(|_: u64| async { Ok::<_, String>(()) });
+
+        assert!(oneshot_with(layer.clone(), service, 0).await.is_err());
+        assert!(oneshot_with(layer.clone(), service, 2).await.is_ok());
+
+        let results = call_all_with(layer, service, stream::iter(0..4))
+            .collect::<Vec<_>>()
+            .await;
+        let errors = results.iter().map(Result::is_err).collect::<Vec<_>>();
+        assert_eq!(errors, [true, true, false, false]);
+    }
+
+    #[tokio::test]
+    async fn counted_calls() {
+        let (inner, calls) = counted(DummyService);
+        let layer = ErrorLayer::new(0.0, |_: &()| String::from("error"));
+        assert_eq!(inner.calls().get(), 0);
+        drive(layer.layer(inner), iter::repeat_n((), 3)).await;
+        assert_eq!(calls.get(), 3);
+
+        calls.reset();
+        assert_eq!(calls.get(), 0);
+    }
+}
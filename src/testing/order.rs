@@ -0,0 +1,292 @@
+use std::{
+    collections::BTreeSet,
+    future::Future,
+    pin::Pin,
+    sync::{Arc, Mutex, MutexGuard},
+    task::{Context, Poll},
+};
+use tower::{Layer, Service};
+
+/// Probe recording the order in which requests arrive and complete.
+///
+/// Each request is assigned an arrival sequence number when the service is
+/// called. When the request completes, every request that arrived earlier
+/// and is still pending counts as an inversion. Requests dropped before
+/// completing are not counted as completed.
+///
+/// The probe is shared with the layers and services created from it.
+///
+/// See the [module documentation](crate::testing#ordering) for more
+/// information.
+#[derive(Clone, Debug, Default)]
+pub struct OrderProbe {
+    state: Arc<Mutex<ProbeState>>,
+}
+
+#[derive(Debug, Default)]
+struct ProbeState {
+    next: u64,
+    pending: BTreeSet<u64>,
+    report: OrderReport,
+}
+
+impl OrderProbe {
+    /// Create a new `OrderProbe`.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns a layer recording the order of the requests into this probe.
+    pub fn layer(&self) -> OrderProbeLayer {
+        OrderProbeLayer {
+            probe: self.clone(),
+        }
+    }
+
+    /// Record that the downstream detected a request completing out of
+    /// order.
+    ///
+    /// This lets experiments compare the reordering that was actually
+    /// achieved with the reordering the downstream noticed.
+    pub fn detected(&self) {
+        self.lock().report.detected += 1;
+    }
+
+    /// Returns the order statistics recorded so far.
+    pub fn report(&self) -> OrderReport {
+        self.lock().report.clone()
+    }
+
+    /// Reset the recorded statistics.
+    ///
+    /// Requests still pending are forgotten, and don't count as inversions
+    /// when they complete.
+    pub fn reset(&self) {
+        let mut state = self.lock();
+        state.pending.clear();
+        state.report = OrderReport::default();
+    }
+
+    fn arrive(&self) -> u64 {
+        let mut state = self.lock();
+        let seq = state.next;
+        state.next += 1;
+        state.pending.insert(seq);
+        state.report.requests += 1;
+        seq
+    }
+
+    fn complete(&self, seq: u64) {
+        let mut state = self.lock();
+        if !state.pending.remove(&seq) {
+            return;
+        }
+        let overtaken = state.pending.range(..seq).count() as u64;
+        let report = &mut state.report;
+        report.completed += 1;
+        report.inversions += overtaken;
+        if overtaken > 0 {
+            report.reordered += 1;
+        }
+        report.max_displacement = report.max_displacement.max(overtaken);
+    }
+
+    fn cancel(&self, seq: u64) {
+        self.lock().pending.remove(&seq);
+    }
+
+    fn lock(&self) -> MutexGuard<'_, ProbeState> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+/// Order statistics recorded by an [`OrderProbe`].
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct OrderReport {
+    requests: u64,
+    completed: u64,
+    inversions: u64,
+    reordered: u64,
+    max_displacement: u64,
+    detected: u64,
+}
+
+impl OrderReport {
+    /// Returns the number of requests that arrived.
+    pub fn requests(&self) -> u64 {
+        self.requests
+    }
+
+    /// Returns the number of requests that completed, successfully or not.
+    pub fn completed(&self) -> u64 {
+        self.completed
+    }
+
+    /// Returns the number of pairs of requests that completed in the
+    /// opposite order of their arrival.
+    pub fn inversions(&self) -> u64 {
+        self.inversions
+    }
+
+    /// Returns the number of requests that completed before at least one
+    /// request that arrived earlier.
+    pub fn reordered(&self) -> u64 {
+        self.reordered
+    }
+
+    /// Returns the largest number of earlier requests overtaken by a single
+    /// request.
+    pub fn max_displacement(&self) -> u64 {
+        self.max_displacement
+    }
+
+    /// Returns the number of reorderings the downstream reported with
+    /// [`OrderProbe::detected`].
+    pub fn detected(&self) -> u64 {
+        self.detected
+    }
+
+    /// Returns the fraction of the completed requests that were reordered,
+    /// or 0.0 if no request completed.
+    pub fn reorder_rate(&self) -> f64 {
+        if self.completed == 0 {
+            return 0.0;
+        }
+        self.reordered as f64 / self.completed as f64
+    }
+}
+
+/// Layer recording the order of the requests into an [`OrderProbe`].
+///
+/// Created with [`OrderProbe::layer`].
+#[derive(Clone, Debug)]
+pub struct OrderProbeLayer {
+    probe: OrderProbe,
+}
+
+impl<S> Layer<S> for OrderProbeLayer {
+    type Service = OrderProbeService<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        OrderProbeService {
+            inner,
+            probe: self.probe.clone(),
+        }
+    }
+}
+
+/// Service recording the order of the requests into an [`OrderProbe`].
+#[derive(Clone, Debug)]
+pub struct OrderProbeService<S> {
+    inner: S,
+    probe: OrderProbe,
+}
+
+impl<S> OrderProbeService<S> {
+    /// Returns the probe of this service.
+    pub fn probe(&self) -> &OrderProbe {
+        &self.probe
+    }
+}
+
+impl<S, R> Service<R> for OrderProbeService<S>
+where
+    S: Service<R>,
+    S::Future: Send + 'static,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = OrderProbeFuture<S::Response, S::Error>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, req: R) -> Self::Future {
+        let pending = Pending {
+            seq: self.probe.arrive(),
+            probe: self.probe.clone(),
+        };
+        let fut = self.inner.call(req);
+        Box::pin(async move {
+            let res = fut.await;
+            pending.complete();
+            res
+        })
+    }
+}
+
+/// Future returned by [`OrderProbeService`].
+pub type OrderProbeFuture<T, E> = Pin<Box<dyn Future<Output = Result<T, E>> + Send>>;
+
+/// Request that arrived, but didn't complete yet.
+///
+/// Dropping it without completing it removes the request from the pending
+/// requests.
+struct Pending {
+    seq: u64,
+    probe: OrderProbe,
+}
+
+impl Pending {
+    fn complete(self) {
+        self.probe.complete(self.seq);
+    }
+}
+
+impl Drop for Pending {
+    fn drop(&mut self) {
+        // No-op if the request already completed.
+        self.probe.cancel(self.seq);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+    use tower::{service_fn, ServiceExt};
+
+    #[tokio::test(start_paused = true)]
+    async fn order_inversions() {
+        let probe = OrderProbe::new();
+        let service = probe.layer().layer(service_fn(|delay: u64| async move {
+            tokio::time::sleep(Duration::from_millis(delay)).await;
+            Ok::<_, String>(delay)
+        }));
+
+        let (a, b, c) = tokio::join!(
+            service.clone().oneshot(30),
+            service.clone().oneshot(10),
+            service.clone().oneshot(20),
+        );
+        assert_eq!((a.unwrap(), b.unwrap(), c.unwrap()), (30, 10, 20));
+
+        probe.detected();
+        let report = service.probe().report();
+        assert_eq!(report.requests(), 3);
+        assert_eq!(report.completed(), 3);
+        assert_eq!(report.inversions(), 2);
+        assert_eq!(report.reordered(), 2);
+        assert_eq!(report.max_displacement(), 1);
+        assert_eq!(report.detected(), 1);
+        assert!((report.reorder_rate() - 2.0 / 3.0).abs() < f64::EPSILON);
+
+        probe.reset();
+        assert_eq!(probe.report(), OrderReport::default());
+    }
+
+    #[tokio::test]
+    async fn order_dropped_request() {
+        let probe = OrderProbe::new();
+        let mut service = probe.layer().layer(service_fn(|_: ()| {
+            std::future::pending::<Result<(), String>>()
+        }));
+
+        drop(service.call(()));
+        let report = probe.report();
+        assert_eq!(report.requests(), 1);
+        assert_eq!(report.completed(), 0);
+        assert!(probe.state.lock().unwrap().pending.is_empty());
+    }
+}
@@ -0,0 +1,155 @@
+//! Timelines of values replayed over the elapsed time of an experiment.
+
+use crate::Error;
+use std::{
+    fs,
+    path::Path,
+    time::{Duration, Instant},
+};
+
+/// Step function of values over the elapsed time of an experiment.
+///
+/// Timelines are parsed from files with one `offset,value` point per line,
+/// where the offset is a number of seconds, or a `MM:SS` or `HH:MM:SS` time.
+/// Points can also be separated by whitespace. Empty lines and lines
+/// starting with `#` are ignored.
+///
+/// The value of a point applies from its offset until the offset of the
+/// next point, and the value of the last point applies until the end of the
+/// experiment.
+#[derive(Clone, Debug)]
+pub(crate) struct Timeline<T> {
+    points: Vec<(Duration, T)>,
+    start: Instant,
+}
+
+impl<T: Copy> Timeline<T> {
+    /// Parse a timeline, using the given function to parse the values.
+    pub(crate) fn parse<F>(input: &str, value: F) -> Result<Self, Error>
+    where
+        F: Fn(&str) -> Option<T>,
+    {
+        let mut points = Vec::new();
+        for (index, line) in input.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let invalid =
+                || Error::InvalidConfig(format!("invalid timeline line {}: {}", index + 1, line));
+            let (offset, raw) = line
+                .split_once(',')
+                .or_else(|| line.split_once(char::is_whitespace))
+                .ok_or_else(invalid)?;
+            let offset = parse_offset(offset.trim()).ok_or_else(invalid)?;
+            let value = value(raw.trim()).ok_or_else(invalid)?;
+            points.push((offset, value));
+        }
+        if points.is_empty() {
+            return Err(Error::InvalidConfig("timeline has no points".to_string()));
+        }
+        // Stable sort, so that the last of duplicate offsets wins.
+        points.sort_by_key(|(offset, _)| *offset);
+
+        Ok(Self {
+            points,
+            start: Instant::now(),
+        })
+    }
+
+    /// Read and parse a timeline file.
+    pub(crate) fn read<F>(path: &Path, value: F) -> Result<Self, Error>
+    where
+        F: Fn(&str) -> Option<T>,
+    {
+        let input = fs::read_to_string(path).map_err(|err| {
+            Error::InvalidConfig(format!(
+                "failed to read timeline {}: {}",
+                path.display(),
+                err
+            ))
+        })?;
+        Self::parse(&input, value)
+    }
+
+    /// Set the start of the experiment.
+    pub(crate) fn starting_at(mut self, start: Instant) -> Self {
+        self.start = start;
+        self
+    }
+
+    /// Returns the value at the given elapsed time, if the first point has
+    /// been reached.
+    pub(crate) fn at(&self, elapsed: Duration) -> Option<T> {
+        let index = self
+            .points
+            .partition_point(|(offset, _)| *offset <= elapsed);
+        index.checked_sub(1).map(|index| self.points[index].1)
+    }
+
+    /// Returns the value at the current elapsed time.
+    pub(crate) fn current(&self) -> Option<T> {
+        self.at(self.start.elapsed())
+    }
+
+    /// Returns the number of points of the timeline.
+    pub(crate) fn len(&self) -> usize {
+        self.points.len()
+    }
+
+    /// Returns the offset of the last point.
+    pub(crate) fn duration(&self) -> Duration {
+        self.points
+            .last()
+            .map_or(Duration::ZERO, |(offset, _)| *offset)
+    }
+}
+
+/// Parse an offset in seconds, or as a `MM:SS` or `HH:MM:SS` time.
+fn parse_offset(offset: &str) -> Option<Duration> {
+    if !offset.contains(':') {
+        let seconds = offset.parse::<f64>().ok()?;
+        return Duration::try_from_secs_f64(seconds).ok();
+    }
+    let parts = offset
+        .split(':')
+        .map(|part| part.parse::<u64>().ok())
+        .collect::<Option<Vec<_>>>()?;
+    let seconds = match parts.as_slice() {
+        [minutes, seconds] => minutes * 60 + seconds,
+        [hours, minutes, seconds] => hours * 3600 + minutes * 60 + seconds,
+        _ => return None,
+    };
+    Some(Duration::from_secs(seconds))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn timeline_parse() {
+        let input = "
+            # offset, value
+            0, 1
+            1:00, 2
+            90 3
+            1:00:00, 4
+        ";
+        let timeline = Timeline::parse(input, |v| v.parse::<u32>().ok()).unwrap();
+        assert_eq!(timeline.len(), 4);
+        assert_eq!(timeline.duration(), Duration::from_secs(3600));
+
+        assert_eq!(timeline.at(Duration::from_secs(59)), Some(1));
+        assert_eq!(timeline.at(Duration::from_secs(60)), Some(2));
+        assert_eq!(timeline.at(Duration::from_secs(100)), Some(3));
+        assert_eq!(timeline.at(Duration::from_secs(7200)), Some(4));
+
+        let timeline = Timeline::parse("10,1", |v| v.parse::<u32>().ok()).unwrap();
+        assert_eq!(timeline.at(Duration::from_secs(5)), None);
+
+        assert!(Timeline::parse("", |v| v.parse::<u32>().ok()).is_err());
+        assert!(Timeline::parse("1:2:3:4,1", |v| v.parse::<u32>().ok()).is_err());
+        assert!(Timeline::parse("10,abc", |v| v.parse::<u32>().ok()).is_err());
+    }
+}
@@ -0,0 +1,227 @@
+//! # `tonic` integration
+//!
+//! This module contains helpers to inject faults into [`tonic`] clients:
+//!
+//! * [`FaultChannel`] - wraps a [`Channel`] with latency and error layers,
+//!   and erases the resulting type so it can be used with generated clients.
+//! * [`GrpcMethod`] - decider that only injects faults for specific gRPC
+//!   services or methods.
+//! * [`status`] - error generator producing a [`Status`].
+//!
+//! ## Example
+//!
+//! ```rust,no_run
+//! use tonic::{transport::Channel, Code};
+//! use tower_fault::tonic::{status, FaultChannel, GrpcMethod};
+//!
+//! # async fn run() -> Result<(), Box<dyn std::error::Error>> {
+//! let channel = Channel::from_static("http://[::1]:50051").connect().await?;
+//!
+//! let channel = FaultChannel::builder(channel)
+//!     // Inject 200 to 500 milliseconds of latency 10% of the time.
+//!     .latency(0.1, 200..500)
+//!     // Fail 5% of the `GetUser` calls with an `UNAVAILABLE` status.
+//!     .error(
+//!         GrpcMethod::method("users.Users", "GetUser", 0.05),
+//!         status(Code::Unavailable, "injected fault"),
+//!     )
+//!     .build();
+//!
+//! // The channel can then be used with a generated client:
+//! // let client = UsersClient::new(channel);
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::{decider::Decider, error::ErrorLayer, latency::Distribution, latency::LatencyLayer};
+use http::{Request, Response};
+use std::task::{Context, Poll};
+use tonic::{
+    body::BoxBody,
+    transport::{Body, Channel},
+    Code, Status,
+};
+use tower::{util::BoxCloneService, BoxError, Layer, Service, ServiceExt};
+
+type Inner = BoxCloneService<Request<BoxBody>, Response<Body>, BoxError>;
+
+/// [`Channel`] with fault layers applied.
+///
+/// Errors from the underlying channel and injected errors are both boxed,
+/// and tonic clients convert them back into a [`Status`].
+#[derive(Clone)]
+pub struct FaultChannel {
+    inner: Inner,
+}
+
+impl FaultChannel {
+    /// Create a new [`FaultChannelBuilder`] for the given channel.
+    pub fn builder(channel: Channel) -> FaultChannelBuilder {
+        FaultChannelBuilder {
+            inner: Inner::new(channel.map_err(BoxError::from)),
+        }
+    }
+}
+
+impl std::fmt::Debug for FaultChannel {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("FaultChannel").finish_non_exhaustive()
+    }
+}
+
+impl Service<Request<BoxBody>> for FaultChannel {
+    type Response = Response<Body>;
+    type Error = BoxError;
+    type Future = <Inner as Service<Request<BoxBody>>>::Future;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, request: Request<BoxBody>) -> Self::Future {
+        self.inner.call(request)
+    }
+}
+
+/// Builder for a [`FaultChannel`].
+///
+/// Layers are applied in order: the first layer added is the closest to the
+/// channel.
+pub struct FaultChannelBuilder {
+    inner: Inner,
+}
+
+impl std::fmt::Debug for FaultChannelBuilder {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("FaultChannelBuilder")
+            .finish_non_exhaustive()
+    }
+}
+
+impl FaultChannelBuilder {
+    /// Add a [`LatencyLayer`] with the given decider and distribution.
+    pub fn latency<De, Di>(self, decider: De, distribution: Di) -> Self
+    where
+        De: Decider<Request<BoxBody>> + Clone + Send + 'static,
+        Di: Distribution<Request<BoxBody>> + Clone + Send + 'static,
+    {
+        let layer = LatencyLayer::new(decider, distribution);
+        Self {
+            inner: Inner::new(layer.layer(self.inner)),
+        }
+    }
+
+    /// Add an [`ErrorLayer`] with the given decider and error generator.
+    pub fn error<D, G>(self, decider: D, generator: G) -> Self
+    where
+        D: Decider<Request<BoxBody>> + Clone + Send + 'static,
+        G: Fn(&Request<BoxBody>) -> BoxError + Clone + Send + 'static,
+    {
+        let layer = ErrorLayer::new(decider, generator);
+        Self {
+            inner: Inner::new(layer.layer(self.inner)),
+        }
+    }
+
+    /// Build the [`FaultChannel`].
+    pub fn build(self) -> FaultChannel {
+        FaultChannel { inner: self.inner }
+    }
+}
+
+/// Error generator returning a [`Status`] with the given code and message.
+pub fn status<R>(code: Code, message: impl Into<String>) -> impl Fn(&R) -> BoxError + Clone {
+    let message = message.into();
+    move |_: &R| Box::new(Status::new(code, message.clone())) as BoxError
+}
+
+/// Decider that only injects faults for specific gRPC services or methods.
+///
+/// The service and method are matched against the request URI path, which
+/// has the `/{package}.{service}/{method}` format. When the request matches,
+/// the inner decider is used to decide if a fault should be injected.
+#[derive(Clone, Debug)]
+pub struct GrpcMethod<D> {
+    service: String,
+    method: Option<String>,
+    decider: D,
+}
+
+impl<D> GrpcMethod<D> {
+    /// Match all the methods of the given fully-qualified service name, such
+    /// as `helloworld.Greeter`.
+    pub fn service(service: impl Into<String>, decider: D) -> Self {
+        Self {
+            service: service.into(),
+            method: None,
+            decider,
+        }
+    }
+
+    /// Match a single method of the given fully-qualified service name.
+    pub fn method(service: impl Into<String>, method: impl Into<String>, decider: D) -> Self {
+        Self {
+            service: service.into(),
+            method: Some(method.into()),
+            decider,
+        }
+    }
+
+    fn matches(&self, path: &str) -> bool {
+        let mut parts = path.trim_start_matches('/').splitn(2, '/');
+        let service = parts.next().unwrap_or_default();
+        let method = parts.next().unwrap_or_default();
+
+        service == self.service
+            && self
+                .method
+                .as_ref()
+                .map(|expected| expected == method)
+                .unwrap_or(true)
+    }
+}
+
+impl<B, D> Decider<Request<B>> for GrpcMethod<D>
+where
+    D: Decider<Request<B>>,
+{
+    fn decide(&self, req: &Request<B>) -> bool {
+        self.matches(req.uri().path()) && self.decider.decide(req)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tonic::transport::Endpoint;
+
+    #[test]
+    fn grpc_method_matches() {
+        let service = GrpcMethod::service("users.Users", true);
+        let method = GrpcMethod::method("users.Users", "GetUser", true);
+
+        assert!(service.matches("/users.Users/GetUser"));
+        assert!(service.matches("/users.Users/ListUsers"));
+        assert!(!service.matches("/orders.Orders/GetOrder"));
+        assert!(method.matches("/users.Users/GetUser"));
+        assert!(!method.matches("/users.Users/ListUsers"));
+    }
+
+    #[tokio::test]
+    async fn fault_channel_returns_status() {
+        let channel = Endpoint::from_static("http://127.0.0.1:1").connect_lazy();
+        let mut channel = FaultChannel::builder(channel)
+            .error(true, status(Code::Unavailable, "injected"))
+            .build();
+
+        let req = Request::builder()
+            .uri("http://127.0.0.1:1/users.Users/GetUser")
+            .body(BoxBody::default())
+            .unwrap();
+        let err = channel.ready().await.unwrap().call(req).await.unwrap_err();
+
+        let status = Status::from_error(err);
+        assert_eq!(status.code(), Code::Unavailable);
+        assert_eq!(status.message(), "injected");
+    }
+}
@@ -0,0 +1,654 @@
+//! # Toxiproxy compatibility
+//!
+//! This module parses [Toxiproxy](https://github.com/Shopify/toxiproxy)
+//! "toxic" definitions and builds equivalent layers, so that existing
+//! Toxiproxy experiments can be reused in-process.
+//!
+//! The following toxics are supported:
+//!
+//! * `latency` - adds `latency` ± `jitter` milliseconds. `upstream` toxics
+//!   delay the request before calling the service, while `downstream` toxics
+//!   delay the response.
+//! * `timeout` - waits `timeout` milliseconds, then fails the request with
+//!   the generated error. A timeout of `0` waits forever, like Toxiproxy.
+//! * `reset_peer` - waits `timeout` milliseconds, then fails the request with
+//!   the generated error.
+//! * `slow_close` - delays the response by `delay` milliseconds.
+//! * `bandwidth` - delays the request or response by the time it takes to
+//!   transfer its payload at `rate` KB/s.
+//! * `slicer` - delays the request or response by `delay` microseconds for
+//!   every `average_size` bytes of its payload, like the pauses between the
+//!   slices of the byte stream.
+//!
+//! The `bandwidth` and `slicer` toxics depend on the size of the payloads,
+//! so their layers are built with [`Toxic::sized_layer`] and a
+//! [`PayloadSize`]. With the `connect` feature, upstream `bandwidth` and
+//! `slicer` toxics can also trickle the bytes written to the connections,
+//! with [`Toxic::trickle_layer`].
+//!
+//! The `limit_data` toxic closes the connection in the middle of the byte
+//! stream, which has no equivalent here, and returns an
+//! [`Error::InvalidConfig`] error.
+//!
+//! ## Example
+//!
+//! ```rust
+//! use tower_fault::toxiproxy::Toxic;
+//!
+//! let toxic = Toxic::from_json(r#"{
+//!     "name": "latency_downstream",
+//!     "type": "latency",
+//!     "stream": "downstream",
+//!     "toxicity": 0.5,
+//!     "attributes": { "latency": 1000, "jitter": 100 }
+//! }"#).unwrap();
+//!
+//! let layer = toxic.layer(|_: &()| String::from("connection reset")).unwrap();
+//!
+//! // Delay the responses by the time it takes to transfer their body.
+//! let toxic = Toxic::from_json(r#"{
+//!     "type": "bandwidth",
+//!     "stream": "downstream",
+//!     "attributes": { "rate": 100 }
+//! }"#).unwrap();
+//!
+//! let layer = toxic
+//!     .sized_layer(
+//!         |_: &()| String::from("connection reset"),
+//!         (|_: &()| 0, |res: &Vec<u8>| res.len()),
+//!     )
+//!     .unwrap();
+//! ```
+
+#[cfg(feature = "connect")]
+use crate::connect::{Trickle, TrickleLayer};
+use crate::{
+    decider::{Decider, SizeCurve},
+    latency::{Distribution, LatencyBySize},
+    veto::Veto,
+    Error,
+};
+use serde::Deserialize;
+use std::{
+    future::Future,
+    pin::Pin,
+    task::{Context, Poll},
+    time::Duration,
+};
+use tokio::time;
+use tower::{Layer, Service};
+
+/// Toxiproxy toxic definition.
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+pub struct Toxic {
+    /// Name of the toxic.
+    #[serde(default)]
+    pub name: String,
+    /// Direction the toxic applies to.
+    #[serde(default)]
+    pub stream: Stream,
+    /// Probability of the toxic being applied.
+    #[serde(default = "default_toxicity")]
+    pub toxicity: f64,
+    /// Type and attributes of the toxic.
+    #[serde(flatten)]
+    pub kind: ToxicKind,
+}
+
+fn default_toxicity() -> f64 {
+    1.0
+}
+
+/// Direction a toxic applies to.
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Stream {
+    /// From the client to the server, i.e. the request.
+    Upstream,
+    /// From the server to the client, i.e. the response.
+    #[default]
+    Downstream,
+}
+
+/// Type and attributes of a toxic.
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+#[serde(tag = "type", content = "attributes", rename_all = "snake_case")]
+pub enum ToxicKind {
+    /// Adds latency, in milliseconds.
+    Latency {
+        /// Base latency.
+        #[serde(default)]
+        latency: u64,
+        /// Maximum deviation from the base latency.
+        #[serde(default)]
+        jitter: u64,
+    },
+    /// Limits the bandwidth, in KB/s.
+    Bandwidth {
+        /// Rate in KB/s. `0` means the bandwidth isn't limited.
+        #[serde(default)]
+        rate: u64,
+    },
+    /// Delays closing the connection, in milliseconds.
+    SlowClose {
+        /// Delay before closing the connection.
+        #[serde(default)]
+        delay: u64,
+    },
+    /// Stops all data and closes the connection after a timeout, in
+    /// milliseconds.
+    Timeout {
+        /// Timeout. `0` means the connection is never closed.
+        #[serde(default)]
+        timeout: u64,
+    },
+    /// Resets the connection after a timeout, in milliseconds.
+    ResetPeer {
+        /// Timeout before resetting the connection.
+        #[serde(default)]
+        timeout: u64,
+    },
+    /// Slices the data into smaller chunks.
+    Slicer {
+        /// Average size of the chunks.
+        #[serde(default)]
+        average_size: u64,
+        /// Variation of the size of the chunks.
+        #[serde(default)]
+        size_variation: u64,
+        /// Delay between chunks, in microseconds.
+        #[serde(default)]
+        delay: u64,
+    },
+    /// Closes the connection after a number of bytes.
+    LimitData {
+        /// Number of bytes.
+        #[serde(default)]
+        bytes: u64,
+    },
+}
+
+impl ToxicKind {
+    fn name(&self) -> &'static str {
+        match self {
+            ToxicKind::Latency { .. } => "latency",
+            ToxicKind::Bandwidth { .. } => "bandwidth",
+            ToxicKind::SlowClose { .. } => "slow_close",
+            ToxicKind::Timeout { .. } => "timeout",
+            ToxicKind::ResetPeer { .. } => "reset_peer",
+            ToxicKind::Slicer { .. } => "slicer",
+            ToxicKind::LimitData { .. } => "limit_data",
+        }
+    }
+}
+
+impl Toxic {
+    /// Parse a single toxic from its JSON definition.
+    pub fn from_json(json: &str) -> Result<Self, Error> {
+        serde_json::from_str(json)
+            .map_err(|err| Error::InvalidConfig(format!("invalid toxic definition: {}", err)))
+    }
+
+    /// Parse a list of toxics, as returned by the Toxiproxy API.
+    pub fn list_from_json(json: &str) -> Result<Vec<Self>, Error> {
+        serde_json::from_str(json)
+            .map_err(|err| Error::InvalidConfig(format!("invalid toxic definition: {}", err)))
+    }
+
+    /// Build a [`ToxicLayer`] equivalent to this toxic.
+    ///
+    /// The generator is used to create errors for the `timeout` and
+    /// `reset_peer` toxics.
+    ///
+    /// The `bandwidth` and `slicer` toxics need the size of the payloads,
+    /// and return an [`Error::InvalidConfig`] error: use
+    /// [`sized_layer`](Toxic::sized_layer) for them instead.
+    pub fn layer<G>(&self, generator: G) -> Result<ToxicLayer<G>, Error> {
+        if let ToxicKind::Bandwidth { .. } | ToxicKind::Slicer { .. } = self.kind {
+            return Err(Error::InvalidConfig(format!(
+                "{} toxics need the size of the payloads, use `Toxic::sized_layer`",
+                self.kind.name()
+            )));
+        }
+        self.sized_layer(generator, ())
+    }
+
+    /// Build a [`ToxicLayer`] equivalent to this toxic, measuring the
+    /// payloads of the requests and responses with the given
+    /// [`PayloadSize`].
+    ///
+    /// The generator is used to create errors for the `timeout` and
+    /// `reset_peer` toxics.
+    pub fn sized_layer<G, Z>(
+        &self,
+        generator: G,
+        size: Z,
+    ) -> Result<ToxicLayer<G, bool, Z>, Error> {
+        let (delay, fail) = match self.kind {
+            ToxicKind::Latency { latency, jitter } => (
+                Delay {
+                    latency,
+                    jitter,
+                    per_kib: 0.0,
+                },
+                false,
+            ),
+            ToxicKind::Bandwidth { rate: 0 } => (Delay::NONE, false),
+            // A rate of 1 KB/s transfers a KiB in 1.024 seconds.
+            ToxicKind::Bandwidth { rate } => (Delay::per_kib(1024.0 / rate as f64), false),
+            ToxicKind::Slicer {
+                average_size: 0, ..
+            } => {
+                return Err(Error::InvalidConfig(String::from(
+                    "slicer toxics need a positive average_size",
+                )))
+            }
+            // The delay between slices is in microseconds, and the size
+            // variation averages out over the payload.
+            ToxicKind::Slicer {
+                average_size,
+                delay,
+                ..
+            } => (
+                Delay::per_kib(delay as f64 / 1000.0 * 1024.0 / average_size as f64),
+                false,
+            ),
+            ToxicKind::SlowClose { delay } => (Delay::fixed(delay), false),
+            ToxicKind::Timeout { timeout: 0 } => (Delay::FOREVER, true),
+            ToxicKind::Timeout { timeout } | ToxicKind::ResetPeer { timeout } => {
+                (Delay::fixed(timeout), true)
+            }
+            ToxicKind::LimitData { .. } => {
+                return Err(Error::InvalidConfig(format!(
+                    "unsupported toxic type: {}",
+                    self.kind.name()
+                )))
+            }
+        };
+
+        let upstream = match self.kind {
+            // Toxics that fail the request wait before failing, regardless of
+            // their direction.
+            ToxicKind::Timeout { .. } | ToxicKind::ResetPeer { .. } => true,
+            ToxicKind::SlowClose { .. } => false,
+            _ => self.stream == Stream::Upstream,
+        };
+        let (before, after) = if upstream {
+            (delay, Delay::NONE)
+        } else {
+            (Delay::NONE, delay)
+        };
+
+        Ok(ToxicLayer {
+            toxicity: self.toxicity.clamp(0.0, 1.0),
+            before,
+            after,
+            fail,
+            generator,
+            veto: false,
+            size,
+        })
+    }
+
+    /// Build a [`TrickleLayer`] that writes the bytes of the connections at
+    /// the rate of this toxic.
+    ///
+    /// Connections only trickle the bytes they write, so this supports the
+    /// upstream `bandwidth` and `slicer` toxics, and returns an
+    /// [`Error::InvalidConfig`] error for other toxics.
+    #[cfg(feature = "connect")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "connect")))]
+    pub fn trickle_layer(&self) -> Result<TrickleLayer<f64>, Error> {
+        let trickle = match (&self.kind, self.stream) {
+            // Write the bytes allowed by the rate every 10 milliseconds.
+            (ToxicKind::Bandwidth { rate }, Stream::Upstream) if *rate > 0 => Trickle::new(
+                usize::try_from(rate.saturating_mul(10)).unwrap_or(usize::MAX),
+                Duration::from_millis(10),
+            ),
+            (
+                ToxicKind::Slicer {
+                    average_size,
+                    delay,
+                    ..
+                },
+                Stream::Upstream,
+            ) if *average_size > 0 => Trickle::new(
+                usize::try_from(*average_size).unwrap_or(usize::MAX),
+                Duration::from_micros(*delay),
+            ),
+            _ => {
+                return Err(Error::InvalidConfig(format!(
+                    "{} toxics can't trickle connections",
+                    self.kind.name()
+                )))
+            }
+        };
+
+        Ok(TrickleLayer::new(self.toxicity.clamp(0.0, 1.0), trickle))
+    }
+}
+
+/// Size of the payloads of requests and responses, used by the
+/// `bandwidth` and `slicer` toxics.
+///
+/// This is implemented for pairs of closures measuring the request and the
+/// response respectively, such as the length of their body. `()` measures
+/// every payload as empty.
+pub trait PayloadSize<R, T> {
+    /// Size of the payload of the request, in bytes.
+    fn request_size(&self, req: &R) -> usize;
+
+    /// Size of the payload of the response, in bytes.
+    fn response_size(&self, res: &T) -> usize;
+}
+
+impl<R, T> PayloadSize<R, T> for () {
+    fn request_size(&self, _req: &R) -> usize {
+        0
+    }
+
+    fn response_size(&self, _res: &T) -> usize {
+        0
+    }
+}
+
+impl<Q, P, R, T> PayloadSize<R, T> for (Q, P)
+where
+    Q: Fn(&R) -> usize,
+    P: Fn(&T) -> usize,
+{
+    fn request_size(&self, req: &R) -> usize {
+        (self.0)(req)
+    }
+
+    fn response_size(&self, res: &T) -> usize {
+        (self.1)(res)
+    }
+}
+
+/// Latency distribution of `latency` ± `jitter` milliseconds, plus
+/// `per_kib` milliseconds for each KiB of payload.
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct Delay {
+    latency: u64,
+    jitter: u64,
+    per_kib: f64,
+}
+
+impl Delay {
+    const NONE: Delay = Delay::fixed(0);
+    // Roughly 30 years, which is what tokio uses for "never".
+    const FOREVER: Delay = Delay::fixed(86_400 * 365 * 30 * 1_000);
+
+    const fn fixed(latency: u64) -> Self {
+        Self {
+            latency,
+            jitter: 0,
+            per_kib: 0.0,
+        }
+    }
+
+    const fn per_kib(per_kib: f64) -> Self {
+        Self {
+            latency: 0,
+            jitter: 0,
+            per_kib,
+        }
+    }
+
+    /// Sample the delay for a payload, measured with the given closure.
+    fn sample_sized<T>(&self, payload: &T, size: impl Fn(&T) -> usize) -> Duration {
+        let delay = self.sample(payload);
+        if self.per_kib == 0.0 {
+            return delay;
+        }
+        delay + LatencyBySize::new(size, SizeCurve::linear(0.0, self.per_kib)).sample(payload)
+    }
+}
+
+impl<R> Distribution<R> for Delay {
+    fn sample(&self, req: &R) -> Duration {
+        if self.jitter == 0 {
+            return self.latency.sample(req);
+        }
+        let low = self.latency.saturating_sub(self.jitter);
+        let high = self.latency.saturating_add(self.jitter);
+        (low..=high).sample(req)
+    }
+}
+
+/// Layer equivalent to a Toxiproxy toxic.
+#[derive(Clone, Debug)]
+pub struct ToxicLayer<G, V = bool, Z = ()> {
+    toxicity: f64,
+    before: Delay,
+    after: Delay,
+    fail: bool,
+    generator: G,
+    veto: V,
+    size: Z,
+}
+
+impl<G, V, Z> ToxicLayer<G, V, Z> {
+    /// Never apply the toxic to the requests vetoed by the given veto.
+    ///
+    /// See the [`veto`](crate::veto) module for more information.
+    pub fn with_veto<NV>(self, veto: NV) -> ToxicLayer<G, NV, Z> {
+        ToxicLayer {
+            toxicity: self.toxicity,
+            before: self.before,
+            after: self.after,
+            fail: self.fail,
+            generator: self.generator,
+            veto,
+            size: self.size,
+        }
+    }
+}
+
+impl<G, V, Z, S> Layer<S> for ToxicLayer<G, V, Z>
+where
+    G: Clone,
+    V: Clone,
+    Z: Clone,
+{
+    type Service = ToxicService<G, S, V, Z>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        ToxicService {
+            inner,
+            layer: self.clone(),
+        }
+    }
+}
+
+/// Service that applies a Toxiproxy toxic.
+#[derive(Clone, Debug)]
+pub struct ToxicService<G, S, V = bool, Z = ()> {
+    inner: S,
+    layer: ToxicLayer<G, V, Z>,
+}
+
+impl<G, S, V, Z, R> Service<R> for ToxicService<G, S, V, Z>
+where
+    G: Fn(&R) -> S::Error,
+    V: Veto<R>,
+    Z: PayloadSize<R, S::Response> + Clone + Send + 'static,
+    S: Service<R>,
+    S::Future: Send + 'static,
+    S::Response: Send + 'static,
+    S::Error: Send + 'static,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = ToxicFuture<R, S>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, request: R) -> Self::Future {
+        if self.layer.veto.veto(&request) || !self.layer.toxicity.decide(&request) {
+            return Box::pin(self.inner.call(request));
+        }
+
+        let size = self.layer.size.clone();
+        let before = self
+            .layer
+            .before
+            .sample_sized(&request, |req| size.request_size(req));
+        let after = self.layer.after;
+
+        if self.layer.fail {
+            let error = (self.layer.generator)(&request);
+            return Box::pin(async move {
+                time::sleep(before).await;
+                Err(error)
+            });
+        }
+
+        let fut = self.inner.call(request);
+        Box::pin(async move {
+            time::sleep(before).await;
+            let res = fut.await;
+            let after = match &res {
+                Ok(res) => after.sample_sized(res, |res| size.response_size(res)),
+                Err(err) => after.sample(err),
+            };
+            time::sleep(after).await;
+            res
+        })
+    }
+}
+
+type ToxicFuture<R, S> = Pin<
+    Box<
+        dyn Future<Output = Result<<S as Service<R>>::Response, <S as Service<R>>::Error>>
+            + Send
+            + 'static,
+    >,
+>;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::*;
+
+    #[test]
+    fn toxic_parse_list() {
+        let toxics = Toxic::list_from_json(
+            r#"[
+                {"name": "lat", "type": "latency", "stream": "upstream", "toxicity": 0.5,
+                 "attributes": {"latency": 100, "jitter": 10}},
+                {"name": "to", "type": "timeout", "attributes": {"timeout": 500}}
+            ]"#,
+        )
+        .unwrap();
+
+        assert_eq!(toxics[0].stream, Stream::Upstream);
+        assert_eq!(
+            toxics[0].kind,
+            ToxicKind::Latency {
+                latency: 100,
+                jitter: 10
+            }
+        );
+        assert_eq!(toxics[1].toxicity, 1.0);
+        assert_eq!(toxics[1].kind, ToxicKind::Timeout { timeout: 500 });
+    }
+
+    #[test]
+    fn toxic_unsupported() {
+        let toxic =
+            Toxic::from_json(r#"{"type": "limit_data", "attributes": {"bytes": 10}}"#).unwrap();
+        assert_eq!(
+            toxic.sized_layer((), ()).err().unwrap(),
+            Error::InvalidConfig("unsupported toxic type: limit_data".to_string())
+        );
+
+        let toxic =
+            Toxic::from_json(r#"{"type": "bandwidth", "attributes": {"rate": 10}}"#).unwrap();
+        assert_eq!(
+            toxic.layer(()).err().unwrap(),
+            Error::InvalidConfig(
+                "bandwidth toxics need the size of the payloads, use `Toxic::sized_layer`"
+                    .to_string()
+            )
+        );
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn toxic_bandwidth() {
+        let toxic =
+            Toxic::from_json(r#"{"type": "bandwidth", "attributes": {"rate": 2}}"#).unwrap();
+        let mut service = toxic
+            .sized_layer(
+                |_: &usize| String::from("error"),
+                (|_: &usize| 0, |res: &Vec<u8>| res.len()),
+            )
+            .unwrap()
+            .layer(tower::service_fn(|len: usize| async move {
+                Ok::<_, String>(vec![0; len])
+            }));
+
+        // 2 KiB at 2 KB/s.
+        let start = time::Instant::now();
+        service.call(2048).await.unwrap();
+        assert_eq!(start.elapsed(), Duration::from_millis(1024));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn toxic_slicer() {
+        let toxic = Toxic::from_json(
+            r#"{"type": "slicer", "stream": "upstream",
+                "attributes": {"average_size": 512, "delay": 1000}}"#,
+        )
+        .unwrap();
+        let mut service = toxic
+            .sized_layer(
+                |_: &Vec<u8>| String::from("error"),
+                (|req: &Vec<u8>| req.len(), |_: &usize| 0),
+            )
+            .unwrap()
+            .layer(tower::service_fn(|req: Vec<u8>| async move {
+                Ok::<_, String>(req.len())
+            }));
+
+        // 8 slices of 512 bytes, 1 millisecond apart.
+        let start = time::Instant::now();
+        assert_eq!(service.call(vec![0; 4096]).await.unwrap(), 4096);
+        assert_eq!(start.elapsed(), Duration::from_millis(8));
+    }
+
+    #[cfg(feature = "connect")]
+    #[test]
+    fn toxic_trickle_layer() {
+        let toxic = Toxic::from_json(
+            r#"{"type": "slicer", "stream": "upstream",
+                "attributes": {"average_size": 512, "delay": 1000}}"#,
+        )
+        .unwrap();
+        assert!(toxic.trickle_layer().is_ok());
+
+        let toxic =
+            Toxic::from_json(r#"{"type": "slicer", "attributes": {"average_size": 512}}"#).unwrap();
+        assert_eq!(
+            toxic.trickle_layer().err().unwrap(),
+            Error::InvalidConfig("slicer toxics can't trickle connections".to_string())
+        );
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn toxic_timeout() {
+        let toxic =
+            Toxic::from_json(r#"{"type": "timeout", "attributes": {"timeout": 500}}"#).unwrap();
+        let mut service = toxic
+            .layer(|_: &()| String::from("timeout"))
+            .unwrap()
+            .layer(DummyService);
+
+        let start = time::Instant::now();
+        assert_eq!(service.call(()).await.unwrap_err(), "timeout");
+        assert_eq!(start.elapsed(), Duration::from_millis(500));
+    }
+}
@@ -0,0 +1,194 @@
+//! # Validation
+//!
+//! Layers can be validated with their `build()` method, which returns an
+//! [`Error`] if a component is misconfigured, instead of panicking at request
+//! time.
+//!
+//! Validation relies on the [`ValidateDecider`] and [`ValidateDistribution`]
+//! traits, which are implemented for the deciders and distributions of this
+//! crate. Closures cannot be validated, and layers using them as decider or
+//! distribution don't have a `build()` method.
+//!
+//! ```rust
+//! use tower_fault::{error::ErrorLayer, latency::LatencyLayer, Error};
+//! # struct MyRequest;
+//!
+//! let res = ErrorLayer::new(1.5, |_: &MyRequest| String::from("error")).build();
+//! assert_eq!(res.err(), Some(Error::InvalidProbability(1.5)));
+//!
+//! let res = LatencyLayer::new(0.5, 200..200).build();
+//! assert!(matches!(res, Err(Error::InvalidRange(_))));
+//! ```
+//!
+//! Components that were not set on a `builder()` are reported as missing,
+//! instead of failing with trait-bound errors when the layer is used.
+//!
+//! ```rust
+//! use tower_fault::{error::ErrorLayer, latency::LatencyLayer, Error};
+//!
+//! let latency_layer = LatencyLayer::builder().with_decider(0.5);
+//! # #[cfg(not(feature = "tokio"))]
+//! # let latency_layer = latency_layer.with_sleep(|_| async {});
+//! let res = latency_layer.build();
+//! assert_eq!(res.err(), Some(Error::Missing(vec!["distribution"])));
+//!
+//! let res = ErrorLayer::builder().with_decider(0.5).build();
+//! assert_eq!(res.err(), Some(Error::Missing(vec!["generator"])));
+//! ```
+
+use alloc::{format, string::String, vec, vec::Vec};
+use core::fmt;
+
+/// Error returned when a fault is misconfigured.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Error {
+    /// The probability is not between 0.0 and 1.0.
+    InvalidProbability(f64),
+    /// The range is empty.
+    InvalidRange(String),
+    /// The configuration is invalid.
+    InvalidConfig(String),
+    /// Components of a builder were not set.
+    Missing(Vec<&'static str>),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::InvalidProbability(p) => {
+                write!(f, "invalid probability {}: must be between 0.0 and 1.0", p)
+            }
+            Error::InvalidRange(range) => write!(f, "invalid range {}: range is empty", range),
+            Error::InvalidConfig(reason) => write!(f, "invalid configuration: {}", reason),
+            Error::Missing(components) => {
+                let setters = components
+                    .iter()
+                    .map(|component| format!("`with_{}()`", component))
+                    .collect::<Vec<_>>();
+                write!(
+                    f,
+                    "missing {}: set with {}",
+                    components.join(", "),
+                    setters.join(", ")
+                )
+            }
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for Error {}
+
+/// Trait for deciders that can be validated.
+pub trait ValidateDecider {
+    /// Returns an error if the decider is misconfigured.
+    fn validate_decider(&self) -> Result<(), Error>;
+}
+
+/// Trait for latency distributions that can be validated.
+pub trait ValidateDistribution {
+    /// Returns an error if the distribution is misconfigured.
+    fn validate_distribution(&self) -> Result<(), Error>;
+}
+
+/// Trait for error generators that can be validated.
+///
+/// Generators are usually closures, so this is implemented for all the
+/// generators that can be used by a layer, which must be `Clone`. The
+/// [`Unset`] placeholder of a builder is reported as missing.
+pub trait ValidateGenerator {
+    /// Returns an error if the generator is misconfigured.
+    fn validate_generator(&self) -> Result<(), Error>;
+}
+
+/// Placeholder for a generator that was not set on a builder.
+///
+/// This doesn't implement `Clone`, so layers using it can't be built or
+/// used as a layer.
+#[derive(Debug)]
+pub struct Unset;
+
+impl<G: Clone> ValidateGenerator for G {
+    fn validate_generator(&self) -> Result<(), Error> {
+        Ok(())
+    }
+}
+
+impl ValidateGenerator for Unset {
+    fn validate_generator(&self) -> Result<(), Error> {
+        Err(Error::Missing(vec!["generator"]))
+    }
+}
+
+impl ValidateDecider for () {
+    fn validate_decider(&self) -> Result<(), Error> {
+        Err(Error::Missing(vec!["decider"]))
+    }
+}
+
+impl ValidateDistribution for () {
+    fn validate_distribution(&self) -> Result<(), Error> {
+        Err(Error::Missing(vec!["distribution"]))
+    }
+}
+
+/// Returns the first error of the results, or all the missing components if
+/// they are all missing.
+#[cfg_attr(not(any(feature = "error", feature = "latency")), allow(dead_code))]
+pub(crate) fn all<const N: usize>(results: [Result<(), Error>; N]) -> Result<(), Error> {
+    let errors = results
+        .into_iter()
+        .filter_map(Result::err)
+        .collect::<Vec<_>>();
+    if errors
+        .iter()
+        .all(|error| matches!(error, Error::Missing(_)))
+    {
+        let missing = errors
+            .into_iter()
+            .flat_map(|error| match error {
+                Error::Missing(components) => components,
+                _ => Vec::new(),
+            })
+            .collect::<Vec<_>>();
+        return if missing.is_empty() {
+            Ok(())
+        } else {
+            Err(Error::Missing(missing))
+        };
+    }
+    Err(errors
+        .into_iter()
+        .find(|error| !matches!(error, Error::Missing(_)))
+        .expect("a non-missing error exists"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn validate_missing() {
+        let res = all([().validate_decider(), ().validate_distribution()]);
+        let err = res.unwrap_err();
+        assert_eq!(err, Error::Missing(vec!["decider", "distribution"]));
+        assert_eq!(
+            err.to_string(),
+            "missing decider, distribution: set with `with_decider()`, `with_distribution()`"
+        );
+
+        let res = all([().validate_decider(), 1.5.validate_decider()]);
+        assert_eq!(res, Err(Error::InvalidProbability(1.5)));
+        assert_eq!(all([Ok(()), Ok(())]), Ok(()));
+    }
+
+    #[test]
+    fn validate_unset() {
+        assert_eq!(
+            Unset.validate_generator(),
+            Err(Error::Missing(vec!["generator"]))
+        );
+        assert_eq!((|_: &()| ()).validate_generator(), Ok(()));
+        assert_eq!(().validate_generator(), Ok(()));
+    }
+}
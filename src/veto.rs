@@ -0,0 +1,214 @@
+//! # Vetoes
+//!
+//! A [`Veto`] prevents faults from being injected into specific requests,
+//! such as payment captures, health checks or admin traffic, regardless of
+//! the decider. Vetoes are checked before the decider, so protected requests
+//! don't affect stateful deciders such as rates or bursts.
+//!
+//! The fault layers of this crate have a `with_veto()` method. Layers with a
+//! decider wrap it in a [`Vetoed`] decider, while the others, such as the
+//! Envoy, Toxiproxy and Chaos Mesh layers, call the inner service directly
+//! for the vetoed requests. For other layers, wrap the decider in a
+//! [`Vetoed`] decider.
+//!
+//! When a layer has an observer, vetoed requests are reported to its
+//! observer as suppressed. The decider still isn't evaluated for them, so
+//! the report includes the vetoed requests that the decider wouldn't have
+//! faulted.
+//!
+//! A [`DenyList`] combines several vetoes, and can be shared by all the
+//! layers of a service to maintain a single deny list.
+//!
+//! ## Example
+//!
+//! ```rust
+//! use tower_fault::{error::ErrorLayer, latency::LatencyLayer, veto::DenyList};
+//! # struct MyRequest { path: String, admin: bool };
+//!
+//! let deny_list = DenyList::new()
+//!     .deny(|req: &MyRequest| req.path.starts_with("/payments/"))
+//!     .deny(|req: &MyRequest| req.admin);
+//!
+//! let latency_layer = LatencyLayer::new(0.1, 200..500).with_veto(deny_list.clone());
+//! let error_layer =
+//!     ErrorLayer::new(0.1, |_: &MyRequest| String::from("error")).with_veto(deny_list);
+//! ```
+
+use crate::{
+    decider::{Decider, ExplainStep, Explanation, Suppression, Verdict},
+    describe::{DeciderDescription, DescribeDecider},
+    validate::ValidateDecider,
+    Error,
+};
+use alloc::{format, sync::Arc, vec::Vec};
+use core::fmt;
+
+/// Trait to prevent faults from being injected into a request.
+///
+/// This is implemented for closures returning `true` for the requests that
+/// must never be faulted.
+pub trait Veto<R> {
+    /// Returns `true` if no fault must be injected into the request.
+    fn veto(&self, req: &R) -> bool;
+}
+
+/// `false` never vetoes requests, and `true` vetoes all of them.
+impl<R> Veto<R> for bool {
+    fn veto(&self, _req: &R) -> bool {
+        *self
+    }
+}
+
+impl<F, R> Veto<R> for F
+where
+    F: Fn(&R) -> bool,
+{
+    fn veto(&self, req: &R) -> bool {
+        self(req)
+    }
+}
+
+/// Decider that only decides to inject a fault if the request isn't vetoed.
+#[derive(Clone, Debug)]
+pub struct Vetoed<D, V> {
+    decider: D,
+    veto: V,
+}
+
+impl<D, V> Vetoed<D, V> {
+    /// Create a new `Vetoed` decider.
+    pub fn new(decider: D, veto: V) -> Self {
+        Self { decider, veto }
+    }
+}
+
+impl<D, V, R> Decider<R> for Vetoed<D, V>
+where
+    D: Decider<R>,
+    V: Veto<R>,
+{
+    fn decide(&self, req: &R) -> bool {
+        !self.veto.veto(req) && self.decider.decide(req)
+    }
+
+    /// Vetoed requests are reported as suppressed, without evaluating the
+    /// decider.
+    fn verdict(&self, req: &R) -> Verdict {
+        if self.veto.veto(req) {
+            Verdict::Suppressed(Suppression::Veto)
+        } else {
+            self.decider.verdict(req)
+        }
+    }
+
+    fn explain(&self, req: &R, explanation: &mut Explanation) -> Verdict {
+        let vetoed = self.veto.veto(req);
+        let verdict = if vetoed {
+            Verdict::Suppressed(Suppression::Veto)
+        } else {
+            self.decider.explain(req, explanation)
+        };
+        let detail = if vetoed { "vetoed" } else { "not vetoed" };
+        explanation.record(ExplainStep::new("veto", verdict).with_detail(detail));
+        verdict
+    }
+}
+
+impl<D, V> ValidateDecider for Vetoed<D, V>
+where
+    D: ValidateDecider,
+{
+    fn validate_decider(&self) -> Result<(), Error> {
+        self.decider.validate_decider()
+    }
+}
+
+impl<D, V> DescribeDecider for Vetoed<D, V>
+where
+    D: DescribeDecider,
+{
+    fn describe_decider(&self) -> DeciderDescription {
+        let inner = self.decider.describe_decider();
+        DeciderDescription::new(format!("{} (with veto)", inner.kind), inner.probability)
+    }
+}
+
+/// List of vetoes.
+///
+/// A request is vetoed if any of the vetoes of the list vetoes it. Cloning
+/// the list is cheap.
+pub struct DenyList<R> {
+    vetoes: Vec<Arc<dyn Veto<R> + Send + Sync>>,
+}
+
+impl<R> DenyList<R> {
+    /// Create a new empty `DenyList`.
+    pub fn new() -> Self {
+        Self { vetoes: Vec::new() }
+    }
+
+    /// Add a veto to the list.
+    pub fn deny<V>(mut self, veto: V) -> Self
+    where
+        V: Veto<R> + Send + Sync + 'static,
+    {
+        self.vetoes.push(Arc::new(veto));
+        self
+    }
+}
+
+impl<R> Default for DenyList<R> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<R> Clone for DenyList<R> {
+    fn clone(&self) -> Self {
+        Self {
+            vetoes: self.vetoes.clone(),
+        }
+    }
+}
+
+impl<R> fmt::Debug for DenyList<R> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("DenyList")
+            .field("vetoes", &self.vetoes.len())
+            .finish()
+    }
+}
+
+impl<R> Veto<R> for DenyList<R> {
+    fn veto(&self, req: &R) -> bool {
+        self.vetoes.iter().any(|veto| veto.veto(req))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn vetoed_decider() {
+        let deny_list = DenyList::new()
+            .deny(|req: &u32| *req == 1)
+            .deny(|req: &u32| *req == 2);
+        let decider = Vetoed::new(true, deny_list.clone());
+
+        assert!(!decider.decide(&1));
+        assert!(!decider.decide(&2));
+        assert!(decider.decide(&3));
+
+        let extended = deny_list.deny(|req: &u32| *req == 3);
+        assert!(extended.veto(&1));
+        assert!(extended.veto(&3));
+    }
+
+    #[test]
+    fn vetoed_verdict() {
+        let decider = Vetoed::new(false, |req: &u32| *req == 1);
+        assert_eq!(decider.verdict(&1), Verdict::Suppressed(Suppression::Veto));
+        assert_eq!(decider.verdict(&2), Verdict::Pass);
+    }
+}
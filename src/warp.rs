@@ -0,0 +1,171 @@
+//! # `warp` integration
+//!
+//! This module exposes the fault layers as [`warp`] filters, so that warp
+//! services can use the same deciders and distributions as tower services.
+//!
+//! Deciders and distributions receive an [`http::Request<()>`](http::Request)
+//! containing the method, URI and headers of the incoming request. This
+//! means that the deciders from the [`http`](crate::http) module can be used
+//! with these filters as well.
+//!
+//! ## Example
+//!
+//! ```rust
+//! use tower_fault::{http::RouteFaults, warp as fault};
+//! use warp::Filter;
+//!
+//! #[derive(Debug)]
+//! struct Unavailable;
+//! impl warp::reject::Reject for Unavailable {}
+//!
+//! let route = warp::path("hello")
+//!     // Inject 200 to 500 milliseconds of latency 10% of the time.
+//!     .and(fault::latency(0.1, 200..500))
+//!     // Reject requests for `/hello` 5% of the time.
+//!     .and(fault::error(
+//!         RouteFaults::new().route("/hello", 0.05),
+//!         |_: &http::Request<()>| Unavailable,
+//!     ))
+//!     .map(|| "Hello, world!");
+//! ```
+
+use crate::{decider::Decider, latency::Distribution};
+use http::{HeaderMap, Method, Request, Uri};
+use std::convert::Infallible;
+use tokio::time;
+use warp::{
+    filters::path::FullPath,
+    reject::{self, Reject},
+    Filter, Rejection,
+};
+
+/// Filter that extracts the method, URI and headers of the incoming request
+/// as a body-less [`http::Request`].
+pub fn request() -> impl Filter<Extract = (Request<()>,), Error = Infallible> + Clone {
+    let query = warp::query::raw().or(warp::any().map(String::new)).unify();
+
+    warp::method()
+        .and(warp::path::full())
+        .and(query)
+        .and(warp::header::headers_cloned())
+        .map(
+            |method: Method, path: FullPath, query: String, headers: HeaderMap| {
+                let uri = if query.is_empty() {
+                    path.as_str().parse::<Uri>()
+                } else {
+                    format!("{}?{}", path.as_str(), query).parse::<Uri>()
+                };
+
+                let mut req = Request::new(());
+                *req.method_mut() = method;
+                *req.uri_mut() = uri.unwrap_or_default();
+                *req.headers_mut() = headers;
+                req
+            },
+        )
+}
+
+/// Filter that randomly adds latency before the rest of the filter chain.
+///
+/// See the [`latency`](crate::latency) module for more information about
+/// deciders and distributions.
+pub fn latency<De, Di>(
+    decider: De,
+    distribution: Di,
+) -> impl Filter<Extract = (), Error = Infallible> + Clone
+where
+    De: Decider<Request<()>> + Clone + Send + Sync + 'static,
+    Di: Distribution<Request<()>> + Clone + Send + Sync + 'static,
+{
+    request()
+        .and_then(move |req: Request<()>| {
+            let latency = if decider.decide(&req) {
+                Some(distribution.sample(&req))
+            } else {
+                None
+            };
+
+            async move {
+                if let Some(latency) = latency {
+                    time::sleep(latency).await;
+                }
+                Ok::<_, Infallible>(())
+            }
+        })
+        .untuple_one()
+}
+
+/// Filter that randomly rejects requests with a custom rejection produced by
+/// the generator.
+///
+/// See the [`error`](crate::error) module for more information about
+/// deciders and generators.
+pub fn error<D, G, E>(
+    decider: D,
+    generator: G,
+) -> impl Filter<Extract = (), Error = Rejection> + Clone
+where
+    D: Decider<Request<()>> + Clone + Send + Sync + 'static,
+    G: Fn(&Request<()>) -> E + Clone + Send + Sync + 'static,
+    E: Reject,
+{
+    request()
+        .and_then(move |req: Request<()>| {
+            let result = if decider.decide(&req) {
+                Err(reject::custom(generator(&req)))
+            } else {
+                Ok(())
+            };
+
+            async move { result }
+        })
+        .untuple_one()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Debug)]
+    struct Injected;
+    impl Reject for Injected {}
+
+    #[tokio::test]
+    async fn warp_request_contains_uri() {
+        let req = warp::test::request()
+            .method("POST")
+            .path("/users/42?verbose=1")
+            .header("baggage", "chaos=error")
+            .filter(&request())
+            .await
+            .unwrap();
+
+        assert_eq!(req.method(), Method::POST);
+        assert_eq!(req.uri().path(), "/users/42");
+        assert_eq!(req.uri().query(), Some("verbose=1"));
+        assert_eq!(req.headers()["baggage"], "chaos=error");
+    }
+
+    #[tokio::test]
+    async fn warp_error_rejects() {
+        let filter = error(
+            |req: &Request<()>| req.uri().path() == "/faulty",
+            |_: &Request<()>| Injected,
+        )
+        .map(|| "ok");
+
+        let rejection = warp::test::request()
+            .path("/faulty")
+            .filter(&filter)
+            .await
+            .unwrap_err();
+        assert!(rejection.find::<Injected>().is_some());
+
+        let res = warp::test::request()
+            .path("/healthy")
+            .filter(&filter)
+            .await
+            .unwrap();
+        assert_eq!(res, "ok");
+    }
+}